@@ -2,6 +2,9 @@ use std::path::PathBuf;
 
 use serde::Deserialize;
 
+use crate::model::group::GroupId;
+use crate::model::password::PasswordPolicy;
+
 #[derive(Default)]
 pub struct Config {
     pub database: Database,
@@ -25,6 +28,10 @@ fn default_groups_path() -> PathBuf {
     "/var/local/lib/fence/groups.json".into()
 }
 
+fn default_authorization_codes_path() -> PathBuf {
+    "/var/local/lib/fence/authorization_codes.json".into()
+}
+
 fn default_clients_path() -> PathBuf {
     "/var/local/lib/fence/clients.json".into()
 }
@@ -33,10 +40,94 @@ fn default_ro_clients_path() -> PathBuf {
     "/var/local/lib/fence/ro_clients.json".into()
 }
 
+fn default_consents_path() -> PathBuf {
+    "/var/local/lib/fence/consents.json".into()
+}
+
+fn default_oauth_clients_path() -> PathBuf {
+    "/var/local/lib/fence/oauth_clients.json".into()
+}
+
+fn default_initial_access_tokens_path() -> PathBuf {
+    "/var/local/lib/fence/initial_access_tokens.json".into()
+}
+
+fn default_refresh_tokens_path() -> PathBuf {
+    "/var/local/lib/fence/refresh_tokens.json".into()
+}
+
+fn default_revoked_tokens_path() -> PathBuf {
+    "/var/local/lib/fence/revoked_tokens.json".into()
+}
+
+fn default_sessions_path() -> PathBuf {
+    "/var/local/lib/fence/sessions.json".into()
+}
+
+fn default_failed_attempts_path() -> PathBuf {
+    "/var/local/lib/fence/failed_attempts.json".into()
+}
+
+fn default_policy_path() -> PathBuf {
+    "/var/local/lib/fence/policy.json".into()
+}
+
 fn default_issuer_url() -> url::Url {
     url::Url::parse("http://fence.flecs.local").unwrap()
 }
 
+fn default_flecs_ui_hosts() -> Vec<String> {
+    vec!["flecs.local".to_string()]
+}
+
+fn default_signing_key_path() -> PathBuf {
+    "/var/local/lib/fence/signing_key.json".into()
+}
+
+fn default_signing_key_rotation_days() -> u32 {
+    30
+}
+
+fn default_refresh_token_lifetime_days() -> u32 {
+    30
+}
+
+fn default_session_idle_timeout_minutes() -> u32 {
+    60
+}
+
+fn default_session_max_age_hours() -> u32 {
+    12
+}
+
+fn default_lockout_threshold() -> u32 {
+    5
+}
+
+fn default_ip_lockout_threshold() -> u32 {
+    20
+}
+
+fn default_lockout_minutes() -> u32 {
+    15
+}
+
+fn default_login_backoff_seconds() -> u32 {
+    1
+}
+
+fn default_password_min_length() -> u32 {
+    PasswordPolicy::MIN_LENGTH
+}
+
+fn default_password_max_length() -> u32 {
+    PasswordPolicy::MAX_LENGTH
+}
+
+fn default_true() -> bool {
+    true
+}
+
 fn default_casbin_model_path() -> PathBuf {
     "/usr/local/share/fence/casbin_model.conf".into()
 }
@@ -51,10 +142,30 @@ pub struct Database {
     pub users_path: PathBuf,
     #[serde(default = "default_groups_path")]
     pub groups_path: PathBuf,
+    #[serde(default = "default_authorization_codes_path")]
+    pub authorization_codes_path: PathBuf,
     #[serde(default = "default_clients_path")]
     pub clients_path: PathBuf,
     #[serde(default = "default_ro_clients_path")]
     pub ro_clients_path: PathBuf,
+    #[serde(default = "default_consents_path")]
+    pub consents_path: PathBuf,
+    #[serde(default = "default_oauth_clients_path")]
+    pub oauth_clients_path: PathBuf,
+    #[serde(default = "default_initial_access_tokens_path")]
+    pub initial_access_tokens_path: PathBuf,
+    #[serde(default = "default_refresh_tokens_path")]
+    pub refresh_tokens_path: PathBuf,
+    #[serde(default = "default_revoked_tokens_path")]
+    pub revoked_tokens_path: PathBuf,
+    #[serde(default = "default_sessions_path")]
+    pub sessions_path: PathBuf,
+    #[serde(default = "default_failed_attempts_path")]
+    pub failed_attempts_path: PathBuf,
+    /// Changes to the casbin policy made at runtime, layered over the shipped
+    /// `casbin_policy_path`
+    #[serde(default = "default_policy_path")]
+    pub policy_path: PathBuf,
 }
 
 impl Default for Database {
@@ -62,8 +173,17 @@ impl Default for Database {
         Self {
             users_path: default_users_path(),
             groups_path: default_groups_path(),
+            authorization_codes_path: default_authorization_codes_path(),
             clients_path: default_clients_path(),
             ro_clients_path: default_ro_clients_path(),
+            consents_path: default_consents_path(),
+            oauth_clients_path: default_oauth_clients_path(),
+            initial_access_tokens_path: default_initial_access_tokens_path(),
+            refresh_tokens_path: default_refresh_tokens_path(),
+            revoked_tokens_path: default_revoked_tokens_path(),
+            sessions_path: default_sessions_path(),
+            failed_attempts_path: default_failed_attempts_path(),
+            policy_path: default_policy_path(),
         }
     }
 }
@@ -72,6 +192,71 @@ impl Default for Database {
 pub struct Auth {
     #[serde(default = "default_issuer_url")]
     pub issuer_url: url::Url,
+    #[serde(default = "default_signing_key_path")]
+    pub signing_key_path: PathBuf,
+    /// Rotate the signing key once it is older than this, 0 disables rotation
+    #[serde(default = "default_signing_key_rotation_days")]
+    pub signing_key_rotation_days: u32,
+    /// Refresh tokens expire if they are not used within this time
+    #[serde(default = "default_refresh_token_lifetime_days")]
+    pub refresh_token_lifetime_days: u32,
+    /// Users have to log in again after being inactive for this long
+    #[serde(default = "default_session_idle_timeout_minutes")]
+    pub session_idle_timeout_minutes: u32,
+    /// Users have to log in again this long after they logged in
+    #[serde(default = "default_session_max_age_hours")]
+    pub session_max_age_hours: u32,
+    /// Take the address of clients from the `X-Forwarded-For` header, which
+    /// is only safe behind a reverse proxy that sets it. Without it failed
+    /// attempts are not throttled per address.
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+    /// Users and clients are locked after this many failed attempts in a
+    /// row, 0 disables lockouts
+    #[serde(default = "default_lockout_threshold")]
+    pub lockout_threshold: u32,
+    /// Addresses are locked after this many failed attempts in a row, across
+    /// all accounts, 0 disables lockouts. Only applies to addresses taken
+    /// from `X-Forwarded-For`, see `trust_forwarded_for`, as the peer may be
+    /// the reverse proxy in front of fence that all clients share.
+    #[serde(default = "default_ip_lockout_threshold")]
+    pub ip_lockout_threshold: u32,
+    /// How long lockouts last and how long failed attempts are remembered
+    #[serde(default = "default_lockout_minutes")]
+    pub lockout_minutes: u32,
+    /// Delay after a failed attempt, doubled with every further failure, 0
+    /// disables the backoff
+    #[serde(default = "default_login_backoff_seconds")]
+    pub login_backoff_seconds: u32,
+    /// Passwords of users must have at least this many characters
+    #[serde(default = "default_password_min_length")]
+    pub password_min_length: u32,
+    /// Passwords of users must have at most this many characters
+    #[serde(default = "default_password_max_length")]
+    pub password_max_length: u32,
+    /// Passwords of users must contain a lowercase letter
+    #[serde(default = "default_true")]
+    pub password_need_lower: bool,
+    /// Passwords of users must contain an uppercase letter
+    #[serde(default = "default_true")]
+    pub password_need_upper: bool,
+    /// Passwords of users must contain a digit
+    #[serde(default = "default_true")]
+    pub password_need_digit: bool,
+    /// Passwords of users must contain a character that is neither a letter,
+    /// a digit nor whitespace
+    #[serde(default)]
+    pub password_need_special: bool,
+    /// Members of these groups have to log in with two-factor
+    /// authentication, comma separated
+    #[serde(default)]
+    pub totp_required_groups: Vec<GroupId>,
+    /// Hosts the FLECS web UI is served under, the built-in `flecs` client
+    /// only redirects back to these after login and logout, comma separated.
+    /// Hosts have to be exact, every other name or address the UI is opened
+    /// under has to be listed as well.
+    #[serde(default = "default_flecs_ui_hosts")]
+    pub flecs_ui_hosts: Vec<String>,
     #[serde(default = "default_casbin_model_path")]
     pub casbin_model_path: PathBuf,
     #[serde(default = "default_casbin_policy_path")]
@@ -82,6 +267,24 @@ impl Default for Auth {
     fn default() -> Self {
         Self {
             issuer_url: default_issuer_url(),
+            signing_key_path: default_signing_key_path(),
+            signing_key_rotation_days: default_signing_key_rotation_days(),
+            refresh_token_lifetime_days: default_refresh_token_lifetime_days(),
+            session_idle_timeout_minutes: default_session_idle_timeout_minutes(),
+            session_max_age_hours: default_session_max_age_hours(),
+            trust_forwarded_for: false,
+            lockout_threshold: default_lockout_threshold(),
+            ip_lockout_threshold: default_ip_lockout_threshold(),
+            lockout_minutes: default_lockout_minutes(),
+            login_backoff_seconds: default_login_backoff_seconds(),
+            password_min_length: default_password_min_length(),
+            password_max_length: default_password_max_length(),
+            password_need_lower: true,
+            password_need_upper: true,
+            password_need_digit: true,
+            password_need_special: false,
+            totp_required_groups: Vec::new(),
+            flecs_ui_hosts: default_flecs_ui_hosts(),
             casbin_model_path: default_casbin_model_path(),
             casbin_policy_path: default_casbin_policy_path(),
         }
@@ -35,18 +35,71 @@ impl Modify for Security {
         rest::users::uid::delete,
         rest::users::self_::patch,
         rest::users::self_::delete,
+        rest::users::self_::consents::get,
+        rest::users::self_::effective_roles::get,
+        rest::users::self_::consents::client_id::delete,
+        rest::users::self_::sessions::get,
+        rest::users::self_::sessions::sid::delete,
+        rest::users::self_::totp::get,
+        rest::users::self_::totp::post,
+        rest::users::self_::totp::delete,
+        rest::users::self_::totp::confirm::post,
+        rest::users::self_::totp::recovery_codes::post,
+        rest::users::uid::effective_roles::get,
+        rest::users::uid::lockout::get,
+        rest::users::uid::lockout::delete,
         rest::users::uid::roles::get,
         rest::users::uid::roles::put,
         rest::users::uid::roles::role::put,
         rest::users::uid::roles::role::delete,
+        rest::users::uid::sessions::get,
+        rest::users::uid::sessions::sid::delete,
+        rest::users::uid::totp::delete,
         rest::users::super_admin::get,
         rest::users::super_admin::post,
         rest::meta::jwk::get,
+        rest::meta::jwks::get,
         rest::meta::issuer::get,
+        rest::meta::password_policy::get,
         rest::clients::get,
         rest::clients::post,
         rest::clients::cid::get,
         rest::clients::cid::delete,
+        rest::clients::cid::effective_roles::get,
+        rest::clients::initial_access_tokens::post,
+        rest::failed_attempts::get,
+        rest::groups::get,
+        rest::groups::post,
+        rest::groups::gid::get,
+        rest::groups::gid::patch,
+        rest::groups::gid::delete,
+        rest::groups::gid::sub_groups::get,
+        rest::groups::gid::sub_groups::sub_gid::put,
+        rest::groups::gid::sub_groups::sub_gid::delete,
+        rest::oauth_clients::get,
+        rest::oauth_clients::post,
+        rest::oauth_clients::client_id::get,
+        rest::oauth_clients::client_id::patch,
+        rest::oauth_clients::client_id::delete,
+        rest::policy::check::post,
+        rest::policy::rules::get,
+        rest::policy::rules::post,
+        rest::policy::rules::delete,
+        rest::policy::inheritances::get,
+        rest::policy::inheritances::post,
+        rest::policy::inheritances::delete,
+        rest::policy::reload::post,
+        rest::oauth::register::post,
+        rest::oauth::register::cid::get,
+        rest::oauth::register::cid::put,
+        rest::oauth::register::cid::delete,
+        rest::keys::get,
+        rest::keys::rotate::post,
+        rest::well_known::openid_configuration::get,
+        rest::well_known::oauth_authorization_server::get,
+        rest::well_known::jwks::get,
+        rest::userinfo::get,
+        rest::userinfo::post,
     ),
     // Top-level security requirement (applies to every operation by default)
     security(
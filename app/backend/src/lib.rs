@@ -3,6 +3,7 @@ pub mod middleware;
 pub mod model;
 pub mod oauth;
 pub mod persist;
+pub mod policy;
 pub mod rest;
 pub mod router;
 pub mod state;
@@ -3,7 +3,9 @@ use user_manager::router::build_router;
 
 use async_signal::{Signal, Signals};
 use futures_util::StreamExt;
+use std::net::SocketAddr;
 use tower_http::services::ServeDir;
+use tracing::error;
 use user_manager::state;
 
 #[cfg(debug_assertions)]
@@ -21,6 +23,20 @@ async fn signal_handler() {
     }
 }
 
+/// Reload the casbin policy whenever SIGHUP is received, e.g. after the
+/// changes in the data volume were edited by hand
+async fn reload_policy_on_hangup(state: state::AppState) {
+    let mut signals = Signals::new([Signal::Hup]).unwrap();
+
+    while let Some(signal) = signals.next().await {
+        if matches!(signal, Ok(Signal::Hup))
+            && let Err(e) = state.reload_policy().await
+        {
+            error!("Could not reload casbin policy: {e:#}");
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     let filter =
@@ -34,14 +50,22 @@ async fn main() {
     )
     .await
     .unwrap();
-    let app_state = state::AppState::new(enforcer, &config);
+    let app_state = state::AppState::new(enforcer, &config).unwrap_or_else(|e| {
+        error!("Could not start fence: {e:#}");
+        std::process::exit(1)
+    });
+    tokio::spawn(state::collect_expired_sessions(app_state.clone()));
+    tokio::spawn(reload_policy_on_hangup(app_state.clone()));
     let router = build_router(app_state).fallback_service(ServeDir::new("./static"));
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:27000")
         .await
         .unwrap();
-    axum::serve(listener, router)
-        .with_graceful_shutdown(signal_handler())
-        .await
-        .unwrap();
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(signal_handler())
+    .await
+    .unwrap();
 }
@@ -1,2 +1,3 @@
+pub mod client_info;
 pub mod role;
 pub mod token;
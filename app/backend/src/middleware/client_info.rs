@@ -0,0 +1,78 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+
+use axum::extract::ConnectInfo;
+use http::header;
+
+use crate::model::failed_attempt::{Account, FailedAttempt};
+use crate::persist::failed_attempt_db::{FailedAttemptDB, PendingAttempt};
+use crate::state;
+
+/// Where a request comes from, as far as fence can tell
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<IpAddr>,
+    /// Whether `ip_address` was taken from a trusted `X-Forwarded-For` header
+    forwarded: bool,
+}
+
+impl ClientInfo {
+    /// Address failed attempts are throttled by. The peer address is not
+    /// used, behind the reverse proxy in front of fence every client shares
+    /// it and one could lock out everybody else.
+    pub fn throttled_address(&self) -> Option<IpAddr> {
+        self.ip_address.filter(|_| self.forwarded)
+    }
+
+    /// Reserve an attempt of the account from this client, see
+    /// [`PendingAttempt::begin`]
+    pub fn begin_attempt<'a>(
+        &self,
+        failed_attempts: &'a Mutex<FailedAttemptDB>,
+        account: Account,
+    ) -> Result<PendingAttempt<'a>, chrono::Duration> {
+        let attempt = FailedAttempt::new(account, self.ip_address, self.user_agent.clone());
+        PendingAttempt::begin(failed_attempts, attempt, self.throttled_address())
+    }
+}
+
+impl axum::extract::FromRequestParts<state::AppState> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut http::request::Parts,
+        state: &state::AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        // The right-most address was added by the proxy in front of fence,
+        // everything before it may be made up by the client
+        let forwarded_for = state
+            .trust_forwarded_for
+            .then(|| {
+                parts
+                    .headers
+                    .get_all("x-forwarded-for")
+                    .iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .flat_map(|v| v.split(','))
+                    .next_back()
+                    .and_then(|ip| ip.trim().parse().ok())
+            })
+            .flatten();
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        Ok(Self {
+            user_agent,
+            forwarded: forwarded_for.is_some(),
+            ip_address: forwarded_for.or(peer),
+        })
+    }
+}
@@ -1,3 +1,4 @@
+use crate::policy::PUBLIC_ROLE;
 use crate::state;
 use crate::token::Roles;
 use axum::response::{IntoResponse, Response};
@@ -45,8 +46,6 @@ fn verify_roles(
     roles: &HashSet<String>,
     method: &http::method::Method,
 ) -> Result<(), VerifyRolesError> {
-    /// The casbin policy treats '*' as the anonymous/public role everybody has
-    const PUBLIC_ROLE: &str = "*";
     for role in std::iter::once(PUBLIC_ROLE).chain(roles.iter().map(|r| r.as_str())) {
         if enforcer
             .enforce((role, path, method.as_str()))
@@ -37,25 +37,27 @@ pub async fn middleware(
     if let Some(token) = auth_token.as_deref() {
         let (jwks, issuer) = {
             let issuer = state.issuer.lock().unwrap();
-            (
-                jsonwebtoken::jwk::JwkSet {
-                    keys: vec![issuer.jwk.clone()],
-                },
-                issuer.url.clone(),
-            )
+            (issuer.jwks(), issuer.url.clone())
         };
-        match crate::token::verify(token, &jwks, &issuer) {
+        let verified = {
+            let db = state.db.lock().unwrap();
+            crate::token::verify(token, &jwks, &issuer, &db.revoked_tokens)
+        };
+        match verified {
             Err(e) => {
                 error!("Failed to verify token: {e}");
                 return http::StatusCode::UNAUTHORIZED.into_response();
             }
-            Ok((roles, subject)) => {
+            Ok(verified) => {
                 debug!(
                     "Successfully verified token of {}, roles: {:?}",
-                    subject, roles.0
+                    verified.subject, verified.roles.0
                 );
-                request.extensions_mut().insert(roles);
-                request.extensions_mut().insert(subject);
+                request
+                    .extensions_mut()
+                    .insert(crate::token::GrantedScope(verified.scope));
+                request.extensions_mut().insert(verified.roles);
+                request.extensions_mut().insert(verified.subject);
             }
         }
     } else {
@@ -1,5 +1,18 @@
+pub mod authorization_code;
 pub mod client;
+pub mod client_registration;
+pub mod consent;
+pub mod failed_attempt;
 pub mod group;
+pub mod initial_access_token;
+pub mod oauth_client;
+pub mod opaque_token;
 pub mod password;
+pub mod permission;
+pub mod policy;
+pub mod redirect_uri;
+pub mod refresh_token;
 pub mod session;
+pub mod signing_key;
+pub mod totp;
 pub mod user;
@@ -0,0 +1,103 @@
+use oxide_auth::primitives::grant::{Extensions, Grant, Value};
+use oxide_auth::primitives::scope::Scope;
+use serde::{Deserialize, Serialize};
+
+use super::oauth_client::scope_string;
+
+/// Grant of an authorization code that was not yet exchanged for tokens,
+/// stored by the digest of the code
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingGrant {
+    owner_id: String,
+    client_id: String,
+    #[serde(with = "scope_string")]
+    scope: Scope,
+    redirect_uri: url::Url,
+    until: chrono::DateTime<chrono::Utc>,
+    extensions: Vec<GrantExtension>,
+}
+
+/// Data that addons such as PKCE attached to a grant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct GrantExtension {
+    id: String,
+    value: Option<String>,
+    private: bool,
+}
+
+impl PendingGrant {
+    pub fn is_expired(&self) -> bool {
+        self.until < chrono::Utc::now()
+    }
+}
+
+impl From<Grant> for PendingGrant {
+    fn from(grant: Grant) -> Self {
+        let extension = |private: bool| {
+            move |(id, value): (&str, Option<&str>)| GrantExtension {
+                id: id.to_string(),
+                value: value.map(str::to_string),
+                private,
+            }
+        };
+        let extensions = grant
+            .extensions
+            .public()
+            .map(extension(false))
+            .chain(grant.extensions.private().map(extension(true)))
+            .collect();
+        Self {
+            owner_id: grant.owner_id,
+            client_id: grant.client_id,
+            scope: grant.scope,
+            redirect_uri: grant.redirect_uri,
+            until: grant.until,
+            extensions,
+        }
+    }
+}
+
+impl From<PendingGrant> for Grant {
+    fn from(grant: PendingGrant) -> Self {
+        let mut extensions = Extensions::new();
+        for extension in grant.extensions {
+            let value = match extension.private {
+                true => Value::private(extension.value),
+                false => Value::public(extension.value),
+            };
+            extensions.set_raw(extension.id, value);
+        }
+        Self {
+            owner_id: grant.owner_id,
+            client_id: grant.client_id,
+            scope: grant.scope,
+            redirect_uri: grant.redirect_uri,
+            until: grant.until,
+            extensions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grant_roundtrip_keeps_extensions() {
+        let mut extensions = Extensions::new();
+        extensions.set_raw("pkce".to_string(), Value::private(Some("challenge".into())));
+        extensions.set_raw("oidc".to_string(), Value::public(None));
+        let grant = Grant {
+            owner_id: "1".to_string(),
+            client_id: "flecs".to_string(),
+            scope: "admin".parse().unwrap(),
+            redirect_uri: url::Url::parse("https://localhost/").unwrap(),
+            until: chrono::Utc::now(),
+            extensions,
+        };
+
+        let json = serde_json::to_value(PendingGrant::from(grant.clone())).unwrap();
+        let pending: PendingGrant = serde_json::from_value(json).unwrap();
+        assert_eq!(Grant::from(pending), grant);
+    }
+}
@@ -15,6 +15,17 @@ pub enum AuthMethod {
     Certificate { pem: String },
 }
 
+/// Set for clients that registered themselves, see RFC 7591
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Registration {
+    /// Digest of the registration access token, which authorizes the client
+    /// to manage its registration (RFC 7592)
+    pub access_token: String,
+    /// Groups of the initial access token the client registered with, it can
+    /// never request more
+    pub allowed_groups: HashSet<GroupId>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Client {
     pub id: ClientId,
@@ -22,6 +33,8 @@ pub struct Client {
     pub auth_method: AuthMethod,
     pub groups: HashSet<GroupId>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registration: Option<Registration>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -0,0 +1,263 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use super::client::{AuthMethod, Client, CreateAuthMethod};
+use super::group::GroupId;
+
+/// The only grant type available to dynamically registered clients
+pub const GRANT_TYPE: &str = "client_credentials";
+
+#[derive(Debug, Error)]
+pub enum InvalidClientMetadata {
+    #[error("client_name is required")]
+    MissingClientName,
+    #[error("Unsupported token_endpoint_auth_method '{0}'")]
+    UnsupportedAuthMethod(String),
+    #[error("Unsupported grant type '{0}', only client_credentials is available")]
+    UnsupportedGrantType(String),
+    #[error("Scope exceeds the groups of the registration: {0}")]
+    ScopeNotAllowed(String),
+    #[error("A certificate is only valid with private_key_jwt")]
+    UnexpectedCertificate,
+    #[error("The token_endpoint_auth_method of a client cannot be changed")]
+    AuthMethodChange,
+    #[error("client_id does not match the registered client")]
+    ClientIdMismatch,
+}
+
+/// Client metadata of a registration request (RFC 7591, section 2). Metadata
+/// fence does not understand is ignored.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ClientMetadata {
+    /// Only checked when updating a registration, must match the client
+    pub client_id: Option<String>,
+    /// Has to be unique among all clients
+    pub client_name: Option<String>,
+    /// `client_secret_basic` (default), `client_secret_post` or
+    /// `private_key_jwt`
+    pub token_endpoint_auth_method: Option<String>,
+    pub grant_types: Option<Vec<String>>,
+    /// Space separated groups of the client, defaults to all groups the
+    /// registration allows
+    pub scope: Option<String>,
+    /// PEM certificate of a `private_key_jwt` client, fence generates a
+    /// certificate and private key if omitted
+    pub certificate: Option<String>,
+}
+
+impl ClientMetadata {
+    pub fn client_name(&self) -> Result<String, InvalidClientMetadata> {
+        self.client_name
+            .clone()
+            .filter(|name| !name.is_empty())
+            .ok_or(InvalidClientMetadata::MissingClientName)
+    }
+
+    pub fn auth_method(&self) -> Result<CreateAuthMethod, InvalidClientMetadata> {
+        match self.token_endpoint_auth_method.as_deref() {
+            None | Some("client_secret_basic") | Some("client_secret_post") => {
+                if self.certificate.is_some() {
+                    return Err(InvalidClientMetadata::UnexpectedCertificate);
+                }
+                Ok(CreateAuthMethod::Secret)
+            }
+            Some("private_key_jwt") => Ok(CreateAuthMethod::Certificate {
+                pem: self.certificate.clone(),
+            }),
+            Some(other) => Err(InvalidClientMetadata::UnsupportedAuthMethod(
+                other.to_string(),
+            )),
+        }
+    }
+
+    pub fn check_grant_types(&self) -> Result<(), InvalidClientMetadata> {
+        match self
+            .grant_types
+            .iter()
+            .flatten()
+            .find(|grant_type| *grant_type != GRANT_TYPE)
+        {
+            Some(unsupported) => Err(InvalidClientMetadata::UnsupportedGrantType(
+                unsupported.clone(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Groups requested by the scope, which have to be a subset of `allowed`
+    pub fn groups(
+        &self,
+        allowed: &HashSet<GroupId>,
+    ) -> Result<HashSet<GroupId>, InvalidClientMetadata> {
+        let Some(scope) = &self.scope else {
+            return Ok(allowed.clone());
+        };
+        let groups: HashSet<GroupId> = scope
+            .split_whitespace()
+            .map(|group| GroupId::from(group.to_string()))
+            .collect();
+        let mut not_allowed: Vec<_> = groups
+            .difference(allowed)
+            .map(ToString::to_string)
+            .collect();
+        if !not_allowed.is_empty() {
+            not_allowed.sort();
+            return Err(InvalidClientMetadata::ScopeNotAllowed(
+                not_allowed.join(" "),
+            ));
+        }
+        Ok(groups)
+    }
+
+    /// Check the parts of an update that fence does not allow to change
+    pub fn check_update_of(&self, client: &Client) -> Result<(), InvalidClientMetadata> {
+        if self
+            .client_id
+            .as_ref()
+            .is_some_and(|id| *id != client.id.to_string())
+        {
+            return Err(InvalidClientMetadata::ClientIdMismatch);
+        }
+        let unchanged = match (self.auth_method()?, &client.auth_method) {
+            (CreateAuthMethod::Secret, AuthMethod::Secret { .. }) => true,
+            (CreateAuthMethod::Certificate { pem: None }, AuthMethod::Certificate { .. }) => true,
+            (CreateAuthMethod::Certificate { pem: Some(new) }, AuthMethod::Certificate { pem }) => {
+                new.trim() == pem.trim()
+            }
+            _ => false,
+        };
+        if !unchanged {
+            return Err(InvalidClientMetadata::AuthMethodChange);
+        }
+        self.check_grant_types()
+    }
+}
+
+/// Client information response (RFC 7591, section 3.2.1 and RFC 7592)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ClientInformation {
+    pub client_id: String,
+    /// Only returned on registration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    pub client_id_issued_at: i64,
+    /// Secrets do not expire, always 0 for secret clients
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret_expires_at: Option<i64>,
+    pub client_name: String,
+    pub token_endpoint_auth_method: String,
+    pub grant_types: Vec<String>,
+    pub scope: String,
+    /// Only returned on registration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registration_access_token: Option<String>,
+    #[schema(value_type = String, format = Uri)]
+    pub registration_client_uri: url::Url,
+    /// Generated certificate of a `private_key_jwt` client
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<String>,
+    /// Generated private key of a `private_key_jwt` client, only returned on
+    /// registration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private_key: Option<String>,
+}
+
+impl ClientInformation {
+    pub fn new(client: &Client, registration_client_uri: url::Url) -> Self {
+        let (token_endpoint_auth_method, client_secret_expires_at) = match &client.auth_method {
+            AuthMethod::Secret { .. } => ("client_secret_basic", Some(0)),
+            AuthMethod::Certificate { .. } => ("private_key_jwt", None),
+        };
+        let mut groups: Vec<_> = client.groups.iter().map(ToString::to_string).collect();
+        groups.sort();
+        Self {
+            client_id: client.id.to_string(),
+            client_secret: None,
+            client_id_issued_at: client.created_at.timestamp(),
+            client_secret_expires_at,
+            client_name: client.name.clone(),
+            token_endpoint_auth_method: token_endpoint_auth_method.to_string(),
+            grant_types: vec![GRANT_TYPE.to_string()],
+            scope: groups.join(" "),
+            registration_access_token: None,
+            registration_client_uri,
+            certificate: None,
+            private_key: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(json: serde_json::Value) -> ClientMetadata {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn groups(groups: &[&str]) -> HashSet<GroupId> {
+        groups
+            .iter()
+            .map(|group| GroupId::from(group.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn scope_defaults_to_allowed_groups() {
+        let allowed = groups(&["tech.flecs.operator", "tech.flecs.viewer"]);
+        let request = metadata(serde_json::json!({"client_name": "app"}));
+        assert_eq!(request.groups(&allowed).unwrap(), allowed);
+    }
+
+    #[test]
+    fn scope_must_not_exceed_allowed_groups() {
+        let allowed = groups(&["tech.flecs.operator"]);
+        let request = metadata(serde_json::json!({
+            "client_name": "app",
+            "scope": "tech.flecs.operator tech.flecs.admin"
+        }));
+        assert!(matches!(
+            request.groups(&allowed),
+            Err(InvalidClientMetadata::ScopeNotAllowed(scope)) if scope == "tech.flecs.admin"
+        ));
+    }
+
+    #[test]
+    fn only_client_credentials_grant_is_supported() {
+        let request = metadata(serde_json::json!({"grant_types": ["client_credentials"]}));
+        assert!(request.check_grant_types().is_ok());
+        let request = metadata(serde_json::json!({"grant_types": ["authorization_code"]}));
+        assert!(matches!(
+            request.check_grant_types(),
+            Err(InvalidClientMetadata::UnsupportedGrantType(_))
+        ));
+    }
+
+    #[test]
+    fn auth_methods() {
+        let request = metadata(serde_json::json!({}));
+        assert!(matches!(
+            request.auth_method(),
+            Ok(CreateAuthMethod::Secret)
+        ));
+        let request =
+            metadata(serde_json::json!({"token_endpoint_auth_method": "private_key_jwt"}));
+        assert!(matches!(
+            request.auth_method(),
+            Ok(CreateAuthMethod::Certificate { pem: None })
+        ));
+        let request = metadata(serde_json::json!({"certificate": "pem"}));
+        assert!(matches!(
+            request.auth_method(),
+            Err(InvalidClientMetadata::UnexpectedCertificate)
+        ));
+        let request = metadata(serde_json::json!({"token_endpoint_auth_method": "none"}));
+        assert!(matches!(
+            request.auth_method(),
+            Err(InvalidClientMetadata::UnsupportedAuthMethod(_))
+        ));
+    }
+}
@@ -0,0 +1,51 @@
+use oxide_auth::primitives::scope::Scope;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::oauth_client::{OAuthClientId, scope_string};
+use super::user::UserId;
+
+/// Remembered consent of a user for a client to act on their behalf
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Consent {
+    pub uid: UserId,
+    pub client_id: OAuthClientId,
+    /// Everything the user consented to so far
+    #[serde(with = "scope_string")]
+    pub scope: Scope,
+    pub granted_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Consent {
+    pub fn covers(&self, scope: &Scope) -> bool {
+        scope.allow_access(&self.scope)
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConsentSummary {
+    pub client_id: OAuthClientId,
+    /// Name of the client, `None` if it no longer exists
+    pub client_name: Option<String>,
+    pub scope: String,
+    #[schema(value_type = String)]
+    pub granted_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consent_covers_subsets_of_its_scope() {
+        let consent = Consent {
+            uid: 1,
+            client_id: "app".to_string(),
+            scope: "openid tech.flecs.operator".parse().unwrap(),
+            granted_at: chrono::Utc::now(),
+        };
+        assert!(consent.covers(&"openid".parse().unwrap()));
+        assert!(consent.covers(&"openid tech.flecs.operator".parse().unwrap()));
+        assert!(!consent.covers(&"openid admin".parse().unwrap()));
+    }
+}
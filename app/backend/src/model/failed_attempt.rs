@@ -0,0 +1,267 @@
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::config;
+
+/// Account whose credentials were guessed. Users are identified by the name
+/// they entered, so that unknown and known names behave the same.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Account {
+    User { username: String },
+    Client { client_id: String },
+}
+
+impl Account {
+    /// Username or client id
+    pub fn id(&self) -> &str {
+        match self {
+            Self::User { username } => username,
+            Self::Client { client_id } => client_id,
+        }
+    }
+}
+
+/// How hard fence makes guessing passwords and client secrets
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottlePolicy {
+    /// Accounts are locked after this many failed attempts in a row, 0
+    /// disables lockouts
+    pub account_threshold: u32,
+    /// Addresses are locked after this many failed attempts in a row, 0
+    /// disables lockouts
+    pub address_threshold: u32,
+    /// How long lockouts last, failures are forgotten after this long as well
+    pub lockout: chrono::Duration,
+    /// Delay after the first failed attempt, which doubles with every further
+    /// failure until the threshold is reached
+    pub backoff: chrono::Duration,
+}
+
+impl From<&config::Auth> for ThrottlePolicy {
+    fn from(config: &config::Auth) -> Self {
+        Self {
+            account_threshold: config.lockout_threshold,
+            address_threshold: config.ip_lockout_threshold,
+            lockout: chrono::Duration::minutes(config.lockout_minutes.into()),
+            backoff: chrono::Duration::seconds(config.login_backoff_seconds.into()),
+        }
+    }
+}
+
+/// Whole seconds for the `Retry-After` header, rounded up
+pub fn retry_after_seconds(delay: chrono::Duration) -> u64 {
+    let millis = delay.num_milliseconds().max(0) as u64;
+    millis.div_ceil(1000)
+}
+
+/// Consecutive failed attempts of an account or address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Throttle {
+    failures: u32,
+    last_failure: chrono::DateTime<chrono::Utc>,
+    blocked_until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Throttle {
+    pub fn new() -> Self {
+        Self {
+            failures: 0,
+            last_failure: chrono::Utc::now(),
+            blocked_until: None,
+        }
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Count another failure and block further attempts for an exponentially
+    /// growing delay, or for the whole lockout once `threshold` is reached
+    pub fn fail(&mut self, policy: &ThrottlePolicy, threshold: u32) {
+        if self.is_expired(policy) {
+            self.failures = 0;
+        }
+        let now = chrono::Utc::now();
+        self.failures = self.failures.saturating_add(1);
+        self.last_failure = now;
+        let delay = if threshold > 0 && self.failures >= threshold {
+            policy.lockout
+        } else {
+            // Saturate instead of overflowing, the delay is capped anyway
+            let factor = 2_i32.checked_pow(self.failures - 1).unwrap_or(i32::MAX);
+            policy
+                .backoff
+                .checked_mul(factor)
+                .map_or(policy.lockout, |delay| delay.min(policy.lockout))
+        };
+        self.blocked_until = (delay > chrono::Duration::zero()).then(|| now + delay);
+    }
+
+    /// Time until the next attempt is allowed, if it is not allowed yet
+    pub fn retry_after(&self) -> Option<chrono::Duration> {
+        self.blocked_until
+            .map(|until| until - chrono::Utc::now())
+            .filter(|delay| *delay > chrono::Duration::zero())
+    }
+
+    /// Time until the next attempt is allowed once `pending` attempts, whose
+    /// credentials are still being verified, failed as well
+    pub fn retry_after_pending(
+        &self,
+        policy: &ThrottlePolicy,
+        threshold: u32,
+        pending: u32,
+    ) -> Option<chrono::Duration> {
+        let mut throttle = self.clone();
+        for _ in 0..pending {
+            throttle.fail(policy, threshold);
+        }
+        throttle.retry_after()
+    }
+
+    /// Whether the whole lockout applies, as opposed to a backoff delay
+    pub fn is_locked(&self, threshold: u32) -> bool {
+        threshold > 0 && self.failures >= threshold && self.retry_after().is_some()
+    }
+
+    pub fn locked_until(&self, threshold: u32) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.blocked_until.filter(|_| self.is_locked(threshold))
+    }
+
+    /// Failures are forgotten once the last one is older than the lockout
+    pub fn is_expired(&self, policy: &ThrottlePolicy) -> bool {
+        chrono::Utc::now() - self.last_failure > policy.lockout
+    }
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lockout state of an account as seen by admins
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct Lockout {
+    /// Consecutive failed attempts that were not forgotten yet
+    pub failures: u32,
+    #[schema(value_type = Option<String>)]
+    pub locked_until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Lockout {
+    pub fn new(throttle: &Throttle, threshold: u32) -> Self {
+        Self {
+            failures: throttle.failures(),
+            locked_until: throttle.locked_until(threshold),
+        }
+    }
+}
+
+/// Failed attempt to authenticate, kept for admins to review
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FailedAttempt {
+    #[schema(value_type = String)]
+    pub at: chrono::DateTime<chrono::Utc>,
+    pub account: Account,
+    #[schema(value_type = Option<String>)]
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl FailedAttempt {
+    pub fn new(account: Account, ip_address: Option<IpAddr>, user_agent: Option<String>) -> Self {
+        Self {
+            at: chrono::Utc::now(),
+            account,
+            ip_address,
+            user_agent,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: ThrottlePolicy = ThrottlePolicy {
+        account_threshold: 3,
+        address_threshold: 10,
+        lockout: chrono::Duration::minutes(15),
+        backoff: chrono::Duration::seconds(1),
+    };
+
+    #[test]
+    fn backoff_doubles_until_lockout() {
+        let mut throttle = Throttle::new();
+        assert!(throttle.retry_after().is_none());
+
+        throttle.fail(&POLICY, 3);
+        let first = throttle.retry_after().unwrap();
+        assert!(first <= chrono::Duration::seconds(1));
+        assert!(!throttle.is_locked(3));
+
+        throttle.fail(&POLICY, 3);
+        let second = throttle.retry_after().unwrap();
+        assert!(second > chrono::Duration::seconds(1));
+        assert!(second <= chrono::Duration::seconds(2));
+
+        throttle.fail(&POLICY, 3);
+        assert!(throttle.is_locked(3));
+        assert!(throttle.retry_after().unwrap() > chrono::Duration::minutes(14));
+        assert!(throttle.locked_until(3).is_some());
+    }
+
+    #[test]
+    fn backoff_is_capped_by_lockout() {
+        let mut throttle = Throttle::new();
+        for _ in 0..64 {
+            throttle.fail(&POLICY, 0);
+        }
+        assert!(!throttle.is_locked(0));
+        assert!(throttle.retry_after().unwrap() <= POLICY.lockout);
+    }
+
+    #[test]
+    fn zero_backoff_only_blocks_at_threshold() {
+        let policy = ThrottlePolicy {
+            backoff: chrono::Duration::zero(),
+            ..POLICY
+        };
+        let mut throttle = Throttle::new();
+        throttle.fail(&policy, 2);
+        assert!(throttle.retry_after().is_none());
+        throttle.fail(&policy, 2);
+        assert!(throttle.is_locked(2));
+    }
+
+    #[test]
+    fn pending_attempts_count_as_failures() {
+        let throttle = Throttle::new();
+        assert!(throttle.retry_after_pending(&POLICY, 3, 0).is_none());
+        assert!(throttle.retry_after_pending(&POLICY, 3, 1).is_some());
+
+        let policy = ThrottlePolicy {
+            backoff: chrono::Duration::zero(),
+            ..POLICY
+        };
+        assert!(throttle.retry_after_pending(&policy, 3, 2).is_none());
+        assert!(throttle.retry_after_pending(&policy, 3, 3).is_some());
+    }
+
+    #[test]
+    fn old_failures_are_forgotten() {
+        let mut throttle = Throttle {
+            failures: 2,
+            last_failure: chrono::Utc::now() - chrono::Duration::minutes(16),
+            blocked_until: None,
+        };
+        assert!(throttle.is_expired(&POLICY));
+        throttle.fail(&POLICY, 3);
+        assert_eq!(throttle.failures(), 1);
+        assert!(!throttle.is_locked(3));
+    }
+}
@@ -1,9 +1,10 @@
 pub use id::GroupId;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use utoipa::ToSchema;
 mod id;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 pub struct Group {
     pub id: GroupId,
     pub name: String,
@@ -12,3 +13,33 @@ pub struct Group {
     #[serde(default)]
     pub sub_groups: HashSet<GroupId>,
 }
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateGroup {
+    pub id: GroupId,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub sub_groups: HashSet<GroupId>,
+}
+
+impl From<CreateGroup> for Group {
+    fn from(create: CreateGroup) -> Self {
+        Self {
+            id: create.id,
+            name: create.name,
+            description: create.description.filter(|d| !d.is_empty()),
+            sub_groups: create.sub_groups,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateGroup {
+    pub name: Option<String>,
+    /// An empty description removes it
+    pub description: Option<String>,
+    /// Replaces all sub groups
+    pub sub_groups: Option<HashSet<GroupId>>,
+}
@@ -17,6 +17,12 @@ impl AsRef<str> for GroupId {
 }
 
 impl GroupId {
+    /// Groups in the `tech.flecs.` namespace are shipped with fence and
+    /// referenced by the casbin policy, so they cannot be changed at runtime
+    pub fn is_builtin(&self) -> bool {
+        self.0.starts_with("tech.flecs.")
+    }
+
     pub fn admin() -> Self {
         Self("tech.flecs.admin".to_string())
     }
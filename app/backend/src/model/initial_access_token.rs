@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::group::GroupId;
+use super::opaque_token::{self, digest};
+
+/// Lifetime of initial access tokens without explicit `expires_in`
+pub const DEFAULT_LIFETIME_SECONDS: u32 = 3600;
+
+/// Authorizes a single dynamic client registration (RFC 7591). The registered
+/// client may request at most the groups of the token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InitialAccessToken {
+    pub digest: String,
+    pub groups: HashSet<GroupId>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl InitialAccessToken {
+    /// Create a new token and return it together with its plaintext value
+    pub fn new(groups: HashSet<GroupId>, lifetime: chrono::Duration) -> (Self, String) {
+        let token = opaque_token::generate();
+        let now = chrono::Utc::now();
+        let initial_access_token = Self {
+            digest: digest(&token),
+            groups,
+            created_at: now,
+            expires_at: now + lifetime,
+        };
+        (initial_access_token, token)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now()
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateInitialAccessToken {
+    /// Groups that clients registering with the token may request
+    pub groups: HashSet<GroupId>,
+    /// Lifetime in seconds, defaults to one hour
+    pub expires_in: Option<u32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateInitialAccessTokenResponse {
+    /// Plaintext token, only returned once
+    pub token: String,
+    pub groups: HashSet<GroupId>,
+    #[schema(value_type = String)]
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_is_not_stored_in_plaintext() {
+        let (initial_access_token, token) =
+            InitialAccessToken::new(HashSet::new(), chrono::Duration::hours(1));
+        let json = serde_json::to_string(&initial_access_token).unwrap();
+        assert!(!json.contains(&token));
+        assert_eq!(initial_access_token.digest, digest(&token));
+        assert!(!initial_access_token.is_expired());
+    }
+}
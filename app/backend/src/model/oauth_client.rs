@@ -0,0 +1,398 @@
+use oxide_auth::primitives::scope::Scope;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::password::Password;
+use super::redirect_uri::{RedirectUri, RedirectUriError};
+
+pub type OAuthClientId = String;
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidOAuthClient {
+    #[error(transparent)]
+    RedirectUri(#[from] RedirectUriError),
+    #[error("At least one redirect uri is required")]
+    NoRedirectUri,
+    #[error("Invalid scope '{0}'")]
+    Scope(String),
+    #[error("Public clients cannot opt out of PKCE")]
+    PublicClientPkce,
+    #[error(
+        "Invalid back-channel logout uri '{0}', it must be an http or https url without fragment"
+    )]
+    BackchannelLogoutUri(String),
+}
+
+pub fn parse_redirect_uris(uris: &[String]) -> Result<Vec<RedirectUri>, InvalidOAuthClient> {
+    if uris.is_empty() {
+        return Err(InvalidOAuthClient::NoRedirectUri);
+    }
+    Ok(uris
+        .iter()
+        .map(|uri| uri.parse())
+        .collect::<Result<_, _>>()?)
+}
+
+/// Unlike redirect uris, post logout redirect uris are optional
+pub fn parse_post_logout_redirect_uris(
+    uris: &[String],
+) -> Result<Vec<RedirectUri>, InvalidOAuthClient> {
+    Ok(uris
+        .iter()
+        .map(|uri| uri.parse())
+        .collect::<Result<_, _>>()?)
+}
+
+pub fn parse_backchannel_logout_uri(uri: &str) -> Result<url::Url, InvalidOAuthClient> {
+    url::Url::parse(uri)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https") && url.fragment().is_none())
+        .ok_or_else(|| InvalidOAuthClient::BackchannelLogoutUri(uri.to_string()))
+}
+
+pub fn parse_scope(scope: &str) -> Result<Scope, InvalidOAuthClient> {
+    scope
+        .parse::<Scope>()
+        .ok()
+        .filter(|scope| scope.iter().next().is_some())
+        .ok_or_else(|| InvalidOAuthClient::Scope(scope.to_string()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ClientType {
+    /// Client that cannot keep a secret, like a browser app. It has to use
+    /// PKCE for every authorization.
+    Public,
+    Confidential {
+        secret: Password,
+        require_pkce: bool,
+    },
+}
+
+/// Client of the authorization code flow, in contrast to the machine clients
+/// of the client credentials flow
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthClient {
+    pub id: OAuthClientId,
+    pub name: String,
+    pub client_type: ClientType,
+    pub redirect_uris: Vec<RedirectUri>,
+    /// Scope the client may request, also granted if it requests none. Each
+    /// entry is a group whose roles tokens of the client may carry, or
+    /// [`ALL_ROLES`](crate::oauth::scope::ALL_ROLES) for every role the user
+    /// holds.
+    #[serde(with = "scope_string")]
+    pub scope: Scope,
+    /// Client that is part of FLECS itself, users are not asked for consent
+    #[serde(default)]
+    pub first_party: bool,
+    /// Where the client may send users after they logged out
+    #[serde(default)]
+    pub post_logout_redirect_uris: Vec<RedirectUri>,
+    /// Receives logout tokens when a user that signed in to the client logs out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backchannel_logout_uri: Option<url::Url>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// [`Scope`] only deserializes from borrowed strings, which a reader cannot
+/// provide
+pub(crate) mod scope_string {
+    use oxide_auth::primitives::scope::Scope;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(scope: &Scope, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&scope.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Scope, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl OAuthClient {
+    pub fn requires_pkce(&self) -> bool {
+        match &self.client_type {
+            ClientType::Public => true,
+            ClientType::Confidential { require_pkce, .. } => *require_pkce,
+        }
+    }
+
+    pub fn allows_redirect(&self, requested: &url::Url) -> bool {
+        self.redirect_uris.iter().any(|uri| uri.matches(requested))
+    }
+
+    pub fn allows_post_logout_redirect(&self, requested: &url::Url) -> bool {
+        self.post_logout_redirect_uris
+            .iter()
+            .any(|uri| uri.matches(requested))
+    }
+
+    /// Apply an update, nothing is changed if any part of it is invalid
+    pub fn update(&mut self, update: UpdateOAuthClient) -> Result<(), InvalidOAuthClient> {
+        let redirect_uris = update
+            .redirect_uris
+            .as_deref()
+            .map(parse_redirect_uris)
+            .transpose()?;
+        let scope = update.scope.as_deref().map(parse_scope).transpose()?;
+        let post_logout_redirect_uris = update
+            .post_logout_redirect_uris
+            .as_deref()
+            .map(parse_post_logout_redirect_uris)
+            .transpose()?;
+        let backchannel_logout_uri = match update.backchannel_logout_uri.as_deref() {
+            Some("") => Some(None),
+            Some(uri) => Some(Some(parse_backchannel_logout_uri(uri)?)),
+            None => None,
+        };
+        if let Some(require) = update.require_pkce {
+            match &mut self.client_type {
+                ClientType::Public if !require => return Err(InvalidOAuthClient::PublicClientPkce),
+                ClientType::Public => {}
+                ClientType::Confidential { require_pkce, .. } => *require_pkce = require,
+            }
+        }
+        if let Some(name) = update.name {
+            self.name = name;
+        }
+        if let Some(redirect_uris) = redirect_uris {
+            self.redirect_uris = redirect_uris;
+        }
+        if let Some(scope) = scope {
+            self.scope = scope;
+        }
+        if let Some(first_party) = update.first_party {
+            self.first_party = first_party;
+        }
+        if let Some(post_logout_redirect_uris) = post_logout_redirect_uris {
+            self.post_logout_redirect_uris = post_logout_redirect_uris;
+        }
+        if let Some(backchannel_logout_uri) = backchannel_logout_uri {
+            self.backchannel_logout_uri = backchannel_logout_uri;
+        }
+        Ok(())
+    }
+
+    /// Redirect uri of authorization requests without one, only clients with
+    /// a single exact redirect uri have a default
+    pub fn default_redirect_uri(&self) -> Option<&RedirectUri> {
+        match self.redirect_uris.as_slice() {
+            [uri] if !uri.is_pattern() => Some(uri),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OAuthClientSummary {
+    pub id: OAuthClientId,
+    pub name: String,
+    /// Either `public` or `confidential`
+    pub client_type: String,
+    pub require_pkce: bool,
+    pub redirect_uris: Vec<String>,
+    /// Space separated group ids the client may request, `admin` stands for
+    /// all roles of the user
+    pub scope: String,
+    pub first_party: bool,
+    pub post_logout_redirect_uris: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, format = Uri)]
+    pub backchannel_logout_uri: Option<url::Url>,
+    #[schema(value_type = String)]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<&OAuthClient> for OAuthClientSummary {
+    fn from(client: &OAuthClient) -> Self {
+        Self {
+            id: client.id.clone(),
+            name: client.name.clone(),
+            client_type: match &client.client_type {
+                ClientType::Public => "public".to_string(),
+                ClientType::Confidential { .. } => "confidential".to_string(),
+            },
+            require_pkce: client.requires_pkce(),
+            redirect_uris: client
+                .redirect_uris
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            scope: client.scope.to_string(),
+            first_party: client.first_party,
+            post_logout_redirect_uris: client
+                .post_logout_redirect_uris
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            backchannel_logout_uri: client.backchannel_logout_uri.clone(),
+            created_at: client.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateOAuthClient {
+    pub id: OAuthClientId,
+    pub name: String,
+    pub client_type: CreateClientType,
+    pub redirect_uris: Vec<String>,
+    /// Space separated group ids the client may request, `admin` stands for
+    /// all roles of the user
+    pub scope: String,
+    /// Skip the consent page for this client
+    #[serde(default)]
+    pub first_party: bool,
+    #[serde(default)]
+    pub post_logout_redirect_uris: Vec<String>,
+    pub backchannel_logout_uri: Option<String>,
+}
+
+fn default_require_pkce() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "type")]
+pub enum CreateClientType {
+    Public,
+    Confidential {
+        #[serde(default = "default_require_pkce")]
+        require_pkce: bool,
+    },
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateOAuthClientResponse {
+    #[serde(flatten)]
+    pub client: OAuthClientSummary,
+    /// Secret of a confidential client, only returned once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateOAuthClient {
+    pub name: Option<String>,
+    pub redirect_uris: Option<Vec<String>>,
+    /// Space separated group ids the client may request, `admin` stands for
+    /// all roles of the user
+    pub scope: Option<String>,
+    /// Only confidential clients may opt out of PKCE
+    pub require_pkce: Option<bool>,
+    pub first_party: Option<bool>,
+    pub post_logout_redirect_uris: Option<Vec<String>>,
+    /// An empty string removes the back-channel logout uri
+    pub backchannel_logout_uri: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_client(redirect_uris: &[&str]) -> OAuthClient {
+        OAuthClient {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            client_type: ClientType::Public,
+            redirect_uris: redirect_uris
+                .iter()
+                .map(|uri| uri.parse().unwrap())
+                .collect(),
+            scope: "admin".parse().unwrap(),
+            first_party: false,
+            post_logout_redirect_uris: vec![],
+            backchannel_logout_uri: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn requested_redirect_uri_must_be_registered() {
+        let client = make_client(&["https://a.local/cb", "https://*.flecs.local/cb"]);
+        assert!(client.allows_redirect(&url::Url::parse("https://ui.flecs.local/cb").unwrap()));
+        assert!(client.allows_redirect(&url::Url::parse("https://a.local/cb").unwrap()));
+        assert!(!client.allows_redirect(&url::Url::parse("https://b.local/cb").unwrap()));
+    }
+
+    #[test]
+    fn invalid_update_changes_nothing() {
+        let mut client = make_client(&["https://a.local/cb"]);
+        let update = UpdateOAuthClient {
+            name: Some("Other".to_string()),
+            redirect_uris: None,
+            scope: None,
+            require_pkce: Some(false),
+            first_party: None,
+            post_logout_redirect_uris: None,
+            backchannel_logout_uri: None,
+        };
+        assert!(matches!(
+            client.update(update),
+            Err(InvalidOAuthClient::PublicClientPkce)
+        ));
+        assert_eq!(client.name, "Test");
+
+        let update = UpdateOAuthClient {
+            name: Some("Other".to_string()),
+            redirect_uris: Some(vec![]),
+            scope: Some("tech.flecs.operator".to_string()),
+            require_pkce: None,
+            first_party: None,
+            post_logout_redirect_uris: None,
+            backchannel_logout_uri: None,
+        };
+        assert!(matches!(
+            client.update(update),
+            Err(InvalidOAuthClient::NoRedirectUri)
+        ));
+        assert_eq!(client.name, "Test");
+        assert_eq!(client.scope.to_string(), "admin");
+    }
+
+    #[test]
+    fn empty_scope_is_invalid() {
+        assert!(parse_scope("").is_err());
+        assert!(parse_scope("admin openid").is_ok());
+    }
+
+    #[test]
+    fn single_exact_redirect_uri_is_default() {
+        let client = make_client(&["https://a.local/cb"]);
+        assert_eq!(
+            client.default_redirect_uri().map(ToString::to_string),
+            Some("https://a.local/cb".to_string())
+        );
+        assert!(
+            make_client(&["https://*.local/cb"])
+                .default_redirect_uri()
+                .is_none()
+        );
+        assert!(
+            make_client(&["https://a.local/cb", "https://b.local/cb"])
+                .default_redirect_uri()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn backchannel_logout_uri_must_be_http_without_fragment() {
+        assert!(parse_backchannel_logout_uri("https://app.local/logout").is_ok());
+        assert!(parse_backchannel_logout_uri("ftp://app.local/logout").is_err());
+        assert!(parse_backchannel_logout_uri("https://app.local/logout#now").is_err());
+        assert!(parse_backchannel_logout_uri("logout").is_err());
+    }
+
+    #[test]
+    fn post_logout_redirect_uri_must_be_registered() {
+        let mut client = make_client(&["https://a.local/cb"]);
+        let url = url::Url::parse("https://a.local/").unwrap();
+        assert!(!client.allows_post_logout_redirect(&url));
+        client.post_logout_redirect_uris = vec!["https://a.local/".parse().unwrap()];
+        assert!(client.allows_post_logout_redirect(&url));
+    }
+}
@@ -0,0 +1,16 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand_core::TryRngCore;
+
+/// Random bearer token, which fence only stores as its [`digest`]
+pub fn generate() -> String {
+    let mut bytes = [0u8; 32];
+    rand_core::OsRng
+        .try_fill_bytes(&mut bytes)
+        .expect("OS RNG should work");
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn digest(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(openssl::sha::sha256(token.as_bytes()))
+}
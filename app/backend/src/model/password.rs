@@ -1,5 +1,8 @@
 use std::fmt;
 
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
 use argon2::{
     Argon2, PasswordVerifier,
     password_hash::{PasswordHash, PasswordHasher, SaltString, rand_core::OsRng},
@@ -13,6 +16,8 @@ use std::sync::LazyLock;
 use utoipa::ToSchema;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+use crate::config;
+
 #[derive(Debug, thiserror::Error)]
 #[error("Failed to hash password: {0}")]
 pub struct HashError(#[from] argon2::password_hash::Error);
@@ -95,6 +100,15 @@ impl Password {
         Ok(Password::Hashed(PasswordHash::new(phc)?.to_string()))
     }
 
+    /// Digest of the stored hash that changes whenever the password does,
+    /// `None` for plaintext passwords
+    pub fn fingerprint(&self) -> Option<String> {
+        match self {
+            Password::Hashed(s) => Some(URL_SAFE_NO_PAD.encode(openssl::sha::sha256(s.as_bytes()))),
+            _ => None,
+        }
+    }
+
     pub fn verify(&self, plain: &str) -> Result<(), VerifyError> {
         match self {
             Password::Hashed(s) => {
@@ -105,8 +119,104 @@ impl Password {
     }
 }
 
+/// Rule of the [`PasswordPolicy`] that a password can break
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordRule {
+    MinLength,
+    MaxLength,
+    Lower,
+    Upper,
+    Digit,
+    Special,
+}
+
+#[derive(Debug, thiserror::Error, Serialize, ToSchema)]
+#[error("Password does not meet the password policy: {violations:?}")]
+pub struct PolicyError {
+    /// Rules the password breaks
+    pub violations: Vec<PasswordRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PasswordPolicy {
+    len_min: u32,
+    len_max: u32,
+    need_lower: bool,
+    need_upper: bool,
+    need_digit: bool,
+    need_special: bool,
+}
+
+impl PasswordPolicy {
+    pub const MIN_LENGTH: u32 = 12;
+    pub const MAX_LENGTH: u32 = 63;
+
+    /// Check the password against all rules at once, so that users learn
+    /// about every rule they break
+    pub fn validate(&self, plain: &str) -> Result<(), PolicyError> {
+        let len = plain.chars().count();
+        let has = |predicate: fn(char) -> bool| plain.chars().any(predicate);
+        let violations: Vec<_> = [
+            (len < self.len_min as usize, PasswordRule::MinLength),
+            (len > self.len_max as usize, PasswordRule::MaxLength),
+            (
+                self.need_lower && !has(char::is_lowercase),
+                PasswordRule::Lower,
+            ),
+            (
+                self.need_upper && !has(char::is_uppercase),
+                PasswordRule::Upper,
+            ),
+            (
+                self.need_digit && !has(|c| c.is_ascii_digit()),
+                PasswordRule::Digit,
+            ),
+            (
+                self.need_special && !has(|c| !c.is_alphanumeric() && !c.is_whitespace()),
+                PasswordRule::Special,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(broken, rule)| broken.then_some(rule))
+        .collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(PolicyError { violations })
+        }
+    }
+}
+
+impl From<&config::Auth> for PasswordPolicy {
+    fn from(config: &config::Auth) -> Self {
+        Self {
+            len_min: config.password_min_length,
+            len_max: config.password_max_length,
+            need_lower: config.password_need_lower,
+            need_upper: config.password_need_upper,
+            need_digit: config.password_need_digit,
+            need_special: config.password_need_special,
+        }
+    }
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            len_min: PasswordPolicy::MIN_LENGTH,
+            len_max: PasswordPolicy::MAX_LENGTH,
+            need_lower: true,
+            need_upper: true,
+            need_digit: true,
+            need_special: false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::{PasswordPolicy, PasswordRule};
 
     #[test]
     fn serialize_deserialize_password_ok() {
@@ -135,32 +245,60 @@ mod tests {
         serde_json::from_str::<super::Password>("password")
             .expect_err("Plaintext password should not be deserializable");
     }
-}
 
-#[derive(Serialize, Deserialize)]
-pub struct PasswordPolicy {
-    len_min: u32,
-    len_max: u32,
-    need_lower: bool,
-    need_upper: bool,
-    need_digit: bool,
-    need_special: bool,
-}
+    #[test]
+    fn default_policy_accepts_strong_password() {
+        PasswordPolicy::default()
+            .validate("TestPassword123")
+            .expect("Password should meet the default policy");
+    }
 
-impl PasswordPolicy {
-    pub const MIN_LENGTH: u32 = 12;
-    pub const MAX_LENGTH: u32 = 63;
-}
+    #[test]
+    fn all_broken_rules_are_reported() {
+        let policy = PasswordPolicy {
+            need_special: true,
+            ..Default::default()
+        };
+        let error = policy.validate("").unwrap_err();
+        assert_eq!(
+            error.violations,
+            vec![
+                PasswordRule::MinLength,
+                PasswordRule::Lower,
+                PasswordRule::Upper,
+                PasswordRule::Digit,
+                PasswordRule::Special,
+            ]
+        );
+    }
 
-impl Default for PasswordPolicy {
-    fn default() -> Self {
-        Self {
-            len_min: PasswordPolicy::MIN_LENGTH,
-            len_max: PasswordPolicy::MAX_LENGTH,
-            need_lower: true,
-            need_upper: true,
-            need_digit: true,
+    #[test]
+    fn length_counts_characters_not_bytes() {
+        let policy = PasswordPolicy {
+            len_min: 4,
+            len_max: 4,
+            need_lower: false,
+            need_upper: false,
+            need_digit: false,
             need_special: false,
-        }
+        };
+        policy.validate("äöüß").unwrap();
+        assert_eq!(
+            policy.validate("äöüßx").unwrap_err().violations,
+            vec![PasswordRule::MaxLength]
+        );
+    }
+
+    #[test]
+    fn special_characters_are_recognized() {
+        let policy = PasswordPolicy {
+            need_special: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.validate("TestPassword123").unwrap_err().violations,
+            vec![PasswordRule::Special]
+        );
+        policy.validate("TestPassword123!").unwrap();
     }
 }
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::group::GroupId;
+
+/// Access to the endpoints matching a casbin path pattern with a method
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug, ToSchema)]
+pub struct Permission {
+    pub path: String,
+    pub method: String,
+}
+
+/// Everything a set of groups grants, combining the sub groups of the
+/// `GroupDB` with the role inheritance of the casbin policy
+#[derive(Serialize, ToSchema)]
+pub struct EffectiveRoles {
+    /// Groups held directly or through sub groups
+    pub groups: Vec<GroupId>,
+    /// Roles inherited from these groups through the casbin policy
+    pub roles: Vec<String>,
+    /// Endpoints the groups and roles grant access to, in addition to the
+    /// public ones
+    pub permissions: Vec<Permission>,
+}
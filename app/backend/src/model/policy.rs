@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::model::client::ClientId;
+use crate::model::user::UserId;
+
+/// A `p` line of the casbin policy, granting a role access to the endpoints
+/// matching a path pattern with a method
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug, ToSchema)]
+pub struct PolicyRule {
+    pub role: String,
+    pub path: String,
+    pub method: String,
+}
+
+impl PolicyRule {
+    pub fn from_casbin(rule: &[String]) -> Option<Self> {
+        match rule {
+            [role, path, method, ..] => Some(Self {
+                role: role.clone(),
+                path: path.clone(),
+                method: method.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_casbin(&self) -> Vec<String> {
+        vec![self.role.clone(), self.path.clone(), self.method.clone()]
+    }
+}
+
+/// A `g` line of the casbin policy, letting a role inherit everything another
+/// role is granted
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug, ToSchema)]
+pub struct RoleInheritance {
+    pub role: String,
+    pub inherited_role: String,
+}
+
+impl RoleInheritance {
+    pub fn from_casbin(inheritance: &[String]) -> Option<Self> {
+        match inheritance {
+            [role, inherited_role, ..] => Some(Self {
+                role: role.clone(),
+                inherited_role: inherited_role.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_casbin(&self) -> Vec<String> {
+        vec![self.role.clone(), self.inherited_role.clone()]
+    }
+}
+
+/// Whose permissions are checked, either of a user or client as stored or of
+/// the roles a token carries
+#[derive(Deserialize, ToSchema)]
+#[serde(tag = "type")]
+pub enum PolicySubject {
+    User {
+        uid: UserId,
+    },
+    Client {
+        #[schema(value_type = String)]
+        cid: ClientId,
+    },
+    Roles {
+        roles: HashSet<String>,
+    },
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct PolicyCheck {
+    pub subject: PolicySubject,
+    pub path: String,
+    pub method: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PolicyDecision {
+    pub allowed: bool,
+    /// Roles the request was checked with, as a token of the subject carries
+    /// them
+    pub roles: Vec<String>,
+    /// Policy line that allowed the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<PolicyRule>,
+    /// Path from a role the subject holds directly to the role of the rule,
+    /// through sub groups and inherited roles
+    pub role_chain: Vec<String>,
+}
@@ -0,0 +1,244 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Host used in place of a wildcard so that patterns can be parsed as urls
+const WILDCARD_HOST: &str = "wildcard.invalid";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RedirectUriError {
+    #[error("Invalid redirect uri '{0}'")]
+    Invalid(String),
+    #[error("Redirect uri '{0}' must use http or https")]
+    UnsupportedScheme(String),
+    #[error("Redirect uri '{0}' must not contain a fragment")]
+    Fragment(String),
+    #[error("Redirect uri '{0}' must not contain user info")]
+    UserInfo(String),
+    #[error("Invalid host pattern in redirect uri '{0}', use '*' or '*.<domain>'")]
+    HostPattern(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum HostPattern {
+    Exact,
+    Any,
+    SubdomainOf(String),
+}
+
+/// Redirect uri registered for an OAuth client. Besides exact uris, the host
+/// may be a pattern: `*` matches any host and `*.flecs.local` any subdomain of
+/// `flecs.local`. A port of `*` matches any port. Scheme, path and query always
+/// have to match exactly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RedirectUri {
+    raw: String,
+    template: url::Url,
+    host: HostPattern,
+    any_port: bool,
+}
+
+impl RedirectUri {
+    pub fn is_pattern(&self) -> bool {
+        self.host != HostPattern::Exact || self.any_port
+    }
+
+    pub fn matches(&self, url: &url::Url) -> bool {
+        if url.scheme() != self.template.scheme()
+            || !url.username().is_empty()
+            || url.password().is_some()
+            || url.fragment().is_some()
+            || url.path() != self.template.path()
+            || url.query() != self.template.query()
+        {
+            return false;
+        }
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host_matches = match &self.host {
+            HostPattern::Exact => Some(host) == self.template.host_str(),
+            HostPattern::Any => true,
+            HostPattern::SubdomainOf(domain) => host
+                .strip_suffix(domain.as_str())
+                .and_then(|subdomain| subdomain.strip_suffix('.'))
+                .is_some_and(|subdomain| !subdomain.is_empty()),
+        };
+        host_matches
+            && (self.any_port
+                || url.port_or_known_default() == self.template.port_or_known_default())
+    }
+}
+
+impl FromStr for RedirectUri {
+    type Err = RedirectUriError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let invalid = || RedirectUriError::Invalid(raw.to_string());
+        let (scheme, rest) = raw.split_once("://").ok_or_else(invalid)?;
+        if scheme != "http" && scheme != "https" {
+            return Err(RedirectUriError::UnsupportedScheme(raw.to_string()));
+        }
+        if raw.contains('#') {
+            return Err(RedirectUriError::Fragment(raw.to_string()));
+        }
+        let (authority, path) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+        if authority.contains('@') {
+            return Err(RedirectUriError::UserInfo(raw.to_string()));
+        }
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => (host, Some(port)),
+            _ => (authority, None),
+        };
+        let (host, host_pattern) = if host == "*" {
+            (WILDCARD_HOST.to_string(), HostPattern::Any)
+        } else if let Some(domain) = host.strip_prefix("*.") {
+            let domain = domain.to_ascii_lowercase();
+            (
+                format!("{WILDCARD_HOST}.{domain}"),
+                HostPattern::SubdomainOf(domain),
+            )
+        } else {
+            (host.to_string(), HostPattern::Exact)
+        };
+        if host.contains('*') {
+            return Err(RedirectUriError::HostPattern(raw.to_string()));
+        }
+        let (port, any_port) = match port {
+            Some("*") => (String::new(), true),
+            Some(port) => (format!(":{port}"), false),
+            None => (String::new(), false),
+        };
+        let template =
+            url::Url::parse(&format!("{scheme}://{host}{port}{path}")).map_err(|_| invalid())?;
+        let host_pattern = match host_pattern {
+            // The url crate normalizes the domain, e.g. to punycode
+            HostPattern::SubdomainOf(_) => HostPattern::SubdomainOf(
+                template
+                    .host_str()
+                    .and_then(|host| host.strip_prefix(WILDCARD_HOST))
+                    .and_then(|domain| domain.strip_prefix('.'))
+                    .ok_or_else(invalid)?
+                    .to_string(),
+            ),
+            pattern => pattern,
+        };
+        Ok(Self {
+            raw: raw.to_string(),
+            template,
+            host: host_pattern,
+            any_port,
+        })
+    }
+}
+
+impl TryFrom<String> for RedirectUri {
+    type Error = RedirectUriError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<RedirectUri> for String {
+    fn from(value: RedirectUri) -> Self {
+        value.raw
+    }
+}
+
+impl fmt::Display for RedirectUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl PartialEq for RedirectUri {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> url::Url {
+        url::Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn exact_uri_matches_only_itself() {
+        let uri: RedirectUri = "https://flecs.local/callback".parse().unwrap();
+        assert!(!uri.is_pattern());
+        assert!(uri.matches(&url("https://flecs.local/callback")));
+        assert!(uri.matches(&url("https://flecs.local:443/callback")));
+        assert!(!uri.matches(&url("http://flecs.local/callback")));
+        assert!(!uri.matches(&url("https://flecs.local/callback/")));
+        assert!(!uri.matches(&url("https://flecs.local/callback?x=1")));
+        assert!(!uri.matches(&url("https://flecs.local:8443/callback")));
+        assert!(!uri.matches(&url("https://evil.local/callback")));
+        assert!(!uri.matches(&url("https://user@flecs.local/callback")));
+        assert!(!uri.matches(&url("https://flecs.local/callback#fragment")));
+    }
+
+    #[test]
+    fn subdomain_pattern_matches_subdomains_only() {
+        let uri: RedirectUri = "https://*.flecs.local/callback".parse().unwrap();
+        assert!(uri.is_pattern());
+        assert!(uri.matches(&url("https://ui.flecs.local/callback")));
+        assert!(uri.matches(&url("https://a.b.flecs.local/callback")));
+        assert!(!uri.matches(&url("https://flecs.local/callback")));
+        assert!(!uri.matches(&url("https://evilflecs.local/callback")));
+        assert!(!uri.matches(&url("https://ui.flecs.local.evil/callback")));
+        assert!(!uri.matches(&url("https://ui.flecs.local/other")));
+    }
+
+    #[test]
+    fn wildcard_host_and_port() {
+        let uri: RedirectUri = "http://*:*/oauth/callback".parse().unwrap();
+        assert!(uri.matches(&url("http://192.168.1.10:8080/oauth/callback")));
+        assert!(uri.matches(&url("http://localhost/oauth/callback")));
+        assert!(!uri.matches(&url("https://localhost/oauth/callback")));
+        assert!(!uri.matches(&url("http://localhost/callback")));
+
+        let uri: RedirectUri = "https://*/callback".parse().unwrap();
+        assert!(uri.matches(&url("https://localhost/callback")));
+        assert!(!uri.matches(&url("https://localhost:8443/callback")));
+    }
+
+    #[test]
+    fn invalid_uris_are_rejected() {
+        assert!(matches!(
+            "ftp://flecs.local/".parse::<RedirectUri>(),
+            Err(RedirectUriError::UnsupportedScheme(_))
+        ));
+        assert!(matches!(
+            "https://flecs.local/#x".parse::<RedirectUri>(),
+            Err(RedirectUriError::Fragment(_))
+        ));
+        assert!(matches!(
+            "https://user@flecs.local/".parse::<RedirectUri>(),
+            Err(RedirectUriError::UserInfo(_))
+        ));
+        assert!(matches!(
+            "https://ui.*.local/".parse::<RedirectUri>(),
+            Err(RedirectUriError::HostPattern(_))
+        ));
+        assert!(matches!(
+            "flecs.local/callback".parse::<RedirectUri>(),
+            Err(RedirectUriError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn serializes_as_string() {
+        let uri: RedirectUri = "https://*.flecs.local/callback".parse().unwrap();
+        let json = serde_json::to_value(&uri).unwrap();
+        assert_eq!(json, "https://*.flecs.local/callback");
+        let back: RedirectUri = serde_json::from_value(json).unwrap();
+        assert_eq!(back, uri);
+    }
+}
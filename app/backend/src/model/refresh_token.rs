@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::opaque_token::{self, digest};
+use crate::model::user::UserId;
+
+pub type FamilyId = uuid::Uuid;
+
+/// A chain of refresh tokens descending from a single authorization. Every
+/// refresh replaces the current token, previously used tokens are remembered
+/// so that their reuse can be detected. Tokens are only stored as digests.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenFamily {
+    pub id: FamilyId,
+    pub uid: UserId,
+    pub client_id: String,
+    pub scope: String,
+    pub redirect_uri: url::Url,
+    /// Fingerprint of the user's password at the time of the authorization,
+    /// the family is void once the password changes
+    pub credential: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    current: String,
+    #[serde(default)]
+    used: HashSet<String>,
+}
+
+impl TokenFamily {
+    /// Create a new family and return it together with its first plaintext token
+    pub fn new(
+        uid: UserId,
+        client_id: String,
+        scope: String,
+        redirect_uri: url::Url,
+        credential: String,
+        lifetime: chrono::Duration,
+    ) -> (Self, String) {
+        let token = opaque_token::generate();
+        let now = chrono::Utc::now();
+        let family = Self {
+            id: uuid::Uuid::new_v4(),
+            uid,
+            client_id,
+            scope,
+            redirect_uri,
+            credential,
+            created_at: now,
+            expires_at: now + lifetime,
+            current: digest(&token),
+            used: HashSet::new(),
+        };
+        (family, token)
+    }
+
+    pub fn is_current(&self, token: &str) -> bool {
+        self.current == digest(token)
+    }
+
+    pub fn was_used(&self, token: &str) -> bool {
+        self.used.contains(&digest(token))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now()
+    }
+
+    /// Replace the current token by a new one and return it in plaintext
+    pub fn rotate(&mut self, lifetime: chrono::Duration) -> String {
+        let token = opaque_token::generate();
+        let previous = std::mem::replace(&mut self.current, digest(&token));
+        self.used.insert(previous);
+        self.expires_at = chrono::Utc::now() + lifetime;
+        token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_family() -> (TokenFamily, String) {
+        TokenFamily::new(
+            1,
+            "flecs".to_string(),
+            "admin".to_string(),
+            url::Url::parse("https://localhost/").unwrap(),
+            "credential".to_string(),
+            chrono::Duration::days(1),
+        )
+    }
+
+    #[test]
+    fn token_is_not_stored_in_plaintext() {
+        let (family, token) = make_family();
+        let json = serde_json::to_string(&family).unwrap();
+        assert!(!json.contains(&token));
+        assert!(family.is_current(&token));
+    }
+
+    #[test]
+    fn rotate_replaces_current_token() {
+        let (mut family, first) = make_family();
+        let second = family.rotate(chrono::Duration::days(1));
+        assert_ne!(first, second);
+        assert!(!family.is_current(&first));
+        assert!(family.was_used(&first));
+        assert!(family.is_current(&second));
+        assert!(!family.was_used(&second));
+    }
+
+    #[test]
+    fn family_with_past_expiry_is_expired() {
+        let (mut family, _) = make_family();
+        assert!(!family.is_expired());
+        family.expires_at = chrono::Utc::now() - chrono::Duration::seconds(1);
+        assert!(family.is_expired());
+    }
+}
@@ -1,101 +1,210 @@
-use std::{
-    borrow::Borrow,
-    hash::{Hash, Hasher},
-    time::{Duration, Instant},
-};
+use std::collections::HashSet;
+use std::net::IpAddr;
 
-use uuid::Uuid;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
+use crate::config;
+use crate::model::oauth_client::OAuthClientId;
+use crate::model::totp::TotpSecret;
 use crate::model::user::UserId;
 
-const LOGIN_SESSION_EXPIRY: Duration = Duration::from_secs(5 * 60);
+const LOGIN_SESSION_EXPIRY: chrono::Duration = chrono::Duration::minutes(5);
+pub const TWO_FACTOR_SESSION_EXPIRY: chrono::Duration = chrono::Duration::minutes(5);
 
-#[derive(Eq)]
+/// Pending login of a user who started an authorization request. The sid is
+/// not part of the session, sessions are stored by its digest.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct LoginSession {
-    sid: String,
     q: String,
-    expire_at: Instant,
+    expire_at: chrono::DateTime<chrono::Utc>,
 }
 
-impl PartialEq for LoginSession {
-    fn eq(&self, other: &Self) -> bool {
-        self.sid.eq(&other.sid)
+impl LoginSession {
+    pub fn new(q: String) -> Self {
+        Self {
+            q,
+            expire_at: chrono::Utc::now() + LOGIN_SESSION_EXPIRY,
+        }
+    }
+
+    pub fn get_q(&self) -> &str {
+        &self.q
     }
-}
 
-impl Hash for LoginSession {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.sid.hash(state);
+    pub fn is_expired(&self) -> bool {
+        self.expire_at < chrono::Utc::now()
     }
 }
 
-impl Borrow<str> for LoginSession {
-    fn borrow(&self) -> &str {
-        &self.sid
-    }
+/// Login of a user who entered their password, but not the code of their
+/// authenticator yet
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TwoFactorSession {
+    uid: UserId,
+    /// Query of the authorization request to continue with, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    q: Option<String>,
+    /// Secret of the authenticator the user has to set up first, because
+    /// their groups require two-factor authentication
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    enrollment: Option<TotpSecret>,
+    expire_at: chrono::DateTime<chrono::Utc>,
 }
 
-impl LoginSession {
-    pub fn new(q: String) -> Self {
+impl TwoFactorSession {
+    pub fn new(uid: UserId, q: Option<String>, enrollment: Option<TotpSecret>) -> Self {
         Self {
-            sid: new_sid(),
+            uid,
             q,
-            expire_at: Instant::now() + LOGIN_SESSION_EXPIRY,
+            enrollment,
+            expire_at: chrono::Utc::now() + TWO_FACTOR_SESSION_EXPIRY,
         }
     }
 
-    pub fn get_sid(&self) -> &str {
-        &self.sid
+    pub fn get_uid(&self) -> UserId {
+        self.uid
     }
 
-    pub fn get_q(&self) -> &str {
-        &self.q
+    pub fn get_q(&self) -> Option<&str> {
+        self.q.as_deref()
+    }
+
+    pub fn enrollment(&self) -> Option<&TotpSecret> {
+        self.enrollment.as_ref()
     }
 
     pub fn is_expired(&self) -> bool {
-        self.expire_at < Instant::now()
+        self.expire_at < chrono::Utc::now()
     }
 }
 
-#[derive(Eq)]
-pub struct UserSession {
-    sid: String,
-    uid: UserId,
+/// How long single sign-on sessions last
+#[derive(Debug, Clone, Copy)]
+pub struct SessionTimeouts {
+    /// Sessions end if they are not used for this long
+    pub idle: chrono::Duration,
+    /// Sessions end this long after the user logged in, however active they are
+    pub absolute: chrono::Duration,
 }
 
-impl PartialEq for UserSession {
-    fn eq(&self, other: &Self) -> bool {
-        self.sid.eq(&other.sid)
+impl From<&config::Auth> for SessionTimeouts {
+    fn from(config: &config::Auth) -> Self {
+        Self {
+            idle: chrono::Duration::minutes(config.session_idle_timeout_minutes.into()),
+            absolute: chrono::Duration::hours(config.session_max_age_hours.into()),
+        }
     }
 }
 
-impl Hash for UserSession {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.sid.hash(state);
-    }
-}
+/// Public id of a user session, unlike the sid it does not grant access
+pub type SessionId = uuid::Uuid;
 
-impl Borrow<str> for UserSession {
-    fn borrow(&self) -> &str {
-        &self.sid
-    }
+/// Single sign-on session of a logged in user, shared by all clients
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UserSession {
+    #[serde(default = "SessionId::new_v4")]
+    id: SessionId,
+    uid: UserId,
+    auth_time: chrono::DateTime<chrono::Utc>,
+    last_active: chrono::DateTime<chrono::Utc>,
+    /// Clients the user signed in to during this session
+    #[serde(default)]
+    clients: HashSet<OAuthClientId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ip_address: Option<IpAddr>,
 }
 
 impl UserSession {
     pub fn new(uid: UserId) -> Self {
+        let now = chrono::Utc::now();
         Self {
-            sid: new_sid(),
+            id: SessionId::new_v4(),
             uid,
+            auth_time: now,
+            last_active: now,
+            clients: HashSet::new(),
+            user_agent: None,
+            ip_address: None,
         }
     }
 
-    pub fn get_sid(&self) -> &str {
-        &self.sid
+    /// Remember where the user logged in from, so that they can recognize
+    /// the session when reviewing their sessions
+    pub fn with_client_info(
+        mut self,
+        user_agent: Option<String>,
+        ip_address: Option<IpAddr>,
+    ) -> Self {
+        self.user_agent = user_agent;
+        self.ip_address = ip_address;
+        self
+    }
+
+    pub fn get_id(&self) -> SessionId {
+        self.id
     }
 
     pub fn get_uid(&self) -> UserId {
         self.uid
     }
+
+    /// Time the user authenticated with their credentials
+    pub fn get_auth_time(&self) -> chrono::DateTime<chrono::Utc> {
+        self.auth_time
+    }
+
+    /// Returns whether the client is new to the session
+    pub fn add_client(&mut self, client_id: OAuthClientId) -> bool {
+        self.clients.insert(client_id)
+    }
+
+    pub fn clients(&self) -> impl Iterator<Item = &OAuthClientId> {
+        self.clients.iter()
+    }
+
+    /// Record that the session was just used, which postpones its idle timeout
+    pub fn touch(&mut self) {
+        self.last_active = chrono::Utc::now();
+    }
+
+    pub fn is_expired(&self, timeouts: &SessionTimeouts) -> bool {
+        let now = chrono::Utc::now();
+        now - self.last_active > timeouts.idle || now - self.auth_time > timeouts.absolute
+    }
+
+    /// Whether the user authenticated longer ago than `max_age` allows
+    pub fn is_older_than(&self, max_age: chrono::Duration) -> bool {
+        chrono::Utc::now() - self.auth_time > max_age
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionSummary {
+    /// Public id of the session
+    #[schema(value_type = String)]
+    pub sid: SessionId,
+    #[schema(value_type = String)]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    #[schema(value_type = String)]
+    pub last_active: chrono::DateTime<chrono::Utc>,
+    pub user_agent: Option<String>,
+    #[schema(value_type = Option<String>)]
+    pub ip_address: Option<IpAddr>,
+}
+
+impl From<&UserSession> for SessionSummary {
+    fn from(session: &UserSession) -> Self {
+        Self {
+            sid: session.id,
+            created_at: session.auth_time,
+            last_active: session.last_active,
+            user_agent: session.user_agent.clone(),
+            ip_address: session.ip_address,
+        }
+    }
 }
 
 impl Default for UserSession {
@@ -104,10 +213,6 @@ impl Default for UserSession {
     }
 }
 
-fn new_sid() -> String {
-    Uuid::new_v4().as_simple().to_string()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,9 +220,8 @@ mod tests {
     #[test]
     fn login_session_with_future_expiry_is_not_expired() {
         let session = LoginSession {
-            sid: new_sid(),
             q: "q=test".into(),
-            expire_at: Instant::now() + Duration::from_secs(60),
+            expire_at: chrono::Utc::now() + chrono::Duration::seconds(60),
         };
         assert!(!session.is_expired());
     }
@@ -125,10 +229,33 @@ mod tests {
     #[test]
     fn login_session_with_past_expiry_is_expired() {
         let session = LoginSession {
-            sid: new_sid(),
             q: "q=test".into(),
-            expire_at: Instant::now() - Duration::from_secs(1),
+            expire_at: chrono::Utc::now() - chrono::Duration::seconds(1),
         };
         assert!(session.is_expired());
     }
+
+    const TIMEOUTS: SessionTimeouts = SessionTimeouts {
+        idle: chrono::Duration::minutes(30),
+        absolute: chrono::Duration::hours(8),
+    };
+
+    #[test]
+    fn user_session_expires_when_idle() {
+        let mut session = UserSession::new(1);
+        assert!(!session.is_expired(&TIMEOUTS));
+        session.last_active = chrono::Utc::now() - chrono::Duration::minutes(31);
+        assert!(session.is_expired(&TIMEOUTS));
+        session.touch();
+        assert!(!session.is_expired(&TIMEOUTS));
+    }
+
+    #[test]
+    fn user_session_expires_after_absolute_timeout_despite_activity() {
+        let mut session = UserSession::new(1);
+        session.auth_time = chrono::Utc::now() - chrono::Duration::hours(9);
+        session.touch();
+        assert!(session.is_expired(&TIMEOUTS));
+        assert!(session.is_older_than(chrono::Duration::hours(1)));
+    }
 }
@@ -0,0 +1,399 @@
+use std::fmt;
+
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use rand_core::TryRngCore;
+use serde::{
+    Deserialize, Serialize,
+    de::{self, Visitor},
+};
+use utoipa::ToSchema;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::model::opaque_token::digest;
+
+/// Seconds each code is valid for
+const PERIOD: i64 = 30;
+const DIGITS: u32 = 6;
+/// Codes of this many time steps before and after the current one are
+/// accepted as well, to allow for clocks that drift apart
+const SKEW: i64 = 1;
+const SECRET_LENGTH: usize = 20;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Name of fence in authenticator apps
+const ISSUER: &str = "FLECS";
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | u16::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[usize::from((buffer >> bits) & 31)].into());
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[usize::from((buffer << (5 - bits)) & 31)].into());
+    }
+    encoded
+}
+
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for c in encoded.trim_end_matches('=').bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    rand_core::OsRng
+        .try_fill_bytes(&mut bytes)
+        .expect("OS RNG should work");
+    bytes
+}
+
+/// Shared secret of a user and their authenticator, see RFC 6238
+#[derive(Clone, PartialEq, Zeroize, ZeroizeOnDrop)]
+pub struct TotpSecret(Vec<u8>);
+
+impl TotpSecret {
+    pub fn generate() -> Self {
+        Self(random_bytes::<SECRET_LENGTH>().to_vec())
+    }
+
+    /// The secret as users type it into their authenticator
+    pub fn to_base32(&self) -> String {
+        base32_encode(&self.0)
+    }
+
+    /// URI to be shown as QR code, which authenticator apps can scan
+    pub fn otpauth_uri(&self, account: &str) -> String {
+        let mut uri = url::Url::parse("otpauth://totp/").expect("URI should be valid");
+        uri.set_path(&format!("{ISSUER}:{account}"));
+        uri.query_pairs_mut()
+            .append_pair("secret", &self.to_base32())
+            .append_pair("issuer", ISSUER)
+            .append_pair("algorithm", "SHA1")
+            .append_pair("digits", &DIGITS.to_string())
+            .append_pair("period", &PERIOD.to_string());
+        uri.into()
+    }
+
+    /// Code the authenticator shows at the time
+    pub fn code_at(&self, time: chrono::DateTime<chrono::Utc>) -> String {
+        self.code(time.timestamp().div_euclid(PERIOD))
+    }
+
+    /// HOTP value of the time step, see RFC 4226
+    fn code(&self, step: i64) -> String {
+        let key = PKey::hmac(&self.0).expect("HMAC key should be valid");
+        let mut signer =
+            Signer::new(MessageDigest::sha1(), &key).expect("HMAC-SHA1 should be available");
+        let mac = signer
+            .sign_oneshot_to_vec(&step.to_be_bytes())
+            .expect("HMAC should not fail");
+        let offset = usize::from(mac[mac.len() - 1] & 0x0f);
+        let truncated = u32::from_be_bytes([
+            mac[offset] & 0x7f,
+            mac[offset + 1],
+            mac[offset + 2],
+            mac[offset + 3],
+        ]);
+        format!(
+            "{:0width$}",
+            truncated % 10u32.pow(DIGITS),
+            width = DIGITS as usize
+        )
+    }
+
+    /// Time step whose code matches, if any
+    fn matching_step(&self, code: &str, now: chrono::DateTime<chrono::Utc>) -> Option<i64> {
+        let code = code.trim();
+        let current = now.timestamp().div_euclid(PERIOD);
+        (current - SKEW..=current + SKEW).find(|step| {
+            let expected = self.code(*step);
+            expected.len() == code.len()
+                && openssl::memcmp::eq(expected.as_bytes(), code.as_bytes())
+        })
+    }
+}
+
+impl fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TotpSecret(***)")
+    }
+}
+
+impl Serialize for TotpSecret {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_base32())
+    }
+}
+
+struct TotpSecretVisitor;
+
+impl Visitor<'_> for TotpSecretVisitor {
+    type Value = TotpSecret;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a base32 encoded secret")
+    }
+
+    fn visit_str<E>(self, encoded: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match base32_decode(encoded) {
+            Some(secret) if !secret.is_empty() => Ok(TotpSecret(secret)),
+            _ => Err(E::custom("invalid base32 encoded secret")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TotpSecret {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(TotpSecretVisitor)
+    }
+}
+
+/// Recovery codes are compared without dashes, whitespace and case
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Time-based one-time password of a user, the second factor of their login
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Totp {
+    #[schema(value_type = String)]
+    secret: TotpSecret,
+    /// Set once the user proved that their authenticator works, only then
+    /// the second factor is required
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    confirmed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Time step of the last accepted code, codes cannot be used twice
+    #[serde(default)]
+    last_step: i64,
+    /// Digests of the recovery codes that were not used yet
+    #[serde(default)]
+    recovery_codes: Vec<String>,
+}
+
+impl Totp {
+    pub fn new(secret: TotpSecret) -> Self {
+        Self {
+            secret,
+            confirmed_at: None,
+            last_step: 0,
+            recovery_codes: Vec::new(),
+        }
+    }
+
+    pub fn secret(&self) -> &TotpSecret {
+        &self.secret
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+
+    pub fn confirm(&mut self) {
+        self.confirmed_at = Some(chrono::Utc::now());
+    }
+
+    /// Check a code of the authenticator, every code is accepted only once
+    pub fn verify_code(&mut self, code: &str) -> bool {
+        self.verify_code_at(code, chrono::Utc::now())
+    }
+
+    fn verify_code_at(&mut self, code: &str, now: chrono::DateTime<chrono::Utc>) -> bool {
+        match self.secret.matching_step(code, now) {
+            Some(step) if step > self.last_step => {
+                self.last_step = step;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Check and use up a recovery code
+    pub fn use_recovery_code(&mut self, code: &str) -> bool {
+        let code = normalize_recovery_code(code);
+        if code.is_empty() {
+            return false;
+        }
+        let code = digest(&code);
+        let count = self.recovery_codes.len();
+        self.recovery_codes
+            .retain(|recovery_code| *recovery_code != code);
+        self.recovery_codes.len() < count
+    }
+
+    /// Accepts codes of the authenticator as well as recovery codes
+    pub fn verify(&mut self, code: &str) -> bool {
+        self.verify_code(code) || self.use_recovery_code(code)
+    }
+
+    /// Replace all recovery codes, the new codes are returned and only their
+    /// digests are kept
+    pub fn generate_recovery_codes(&mut self) -> Vec<String> {
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let code = base32_encode(&random_bytes::<5>()).to_ascii_lowercase();
+                format!("{}-{}", &code[..4], &code[4..])
+            })
+            .collect();
+        self.recovery_codes = codes
+            .iter()
+            .map(|code| digest(&normalize_recovery_code(code)))
+            .collect();
+        codes
+    }
+
+    pub fn recovery_codes_left(&self) -> usize {
+        self.recovery_codes.len()
+    }
+}
+
+/// Secret of a new authenticator, the authenticator is only used once it is
+/// confirmed with one of its codes
+#[derive(Serialize, ToSchema)]
+pub struct TotpEnrollment {
+    /// Base32 encoded secret, for authenticators that cannot scan QR codes
+    pub secret: String,
+    /// `otpauth://` URI to be shown as QR code
+    pub otpauth_uri: String,
+}
+
+impl TotpEnrollment {
+    pub fn new(secret: &TotpSecret, account: &str) -> Self {
+        Self {
+            secret: secret.to_base32(),
+            otpauth_uri: secret.otpauth_uri(account),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TotpStatus {
+    /// Whether the user has to enter a code when logging in
+    pub enabled: bool,
+    /// Whether the groups of the user require two-factor authentication
+    pub required: bool,
+    pub recovery_codes_left: usize,
+}
+
+/// Codes that can be used once each instead of a code of the authenticator
+#[derive(Serialize, ToSchema)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct TotpCode {
+    /// Current code of the authenticator
+    pub code: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Secret of the SHA1 test vectors of RFC 6238
+    fn rfc_secret() -> TotpSecret {
+        TotpSecret(b"12345678901234567890".to_vec())
+    }
+
+    fn at(timestamp: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(timestamp, 0).unwrap()
+    }
+
+    #[test]
+    fn codes_match_rfc_6238() {
+        let secret = rfc_secret();
+        assert_eq!(secret.code(59 / PERIOD), "287082");
+        assert_eq!(secret.code(1111111109 / PERIOD), "081804");
+        assert_eq!(secret.code(1234567890 / PERIOD), "005924");
+        assert_eq!(secret.code(2000000000 / PERIOD), "279037");
+    }
+
+    #[test]
+    fn base32_roundtrip() {
+        assert_eq!(
+            base32_encode(b"12345678901234567890"),
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+        );
+        assert_eq!(base32_encode(b"f"), "MY");
+        assert_eq!(base32_decode("MY").unwrap(), b"f");
+        assert_eq!(base32_decode("my======").unwrap(), b"f");
+        assert!(base32_decode("M1").is_none());
+        let secret = TotpSecret::generate();
+        assert_eq!(base32_decode(&secret.to_base32()).unwrap(), secret.0);
+    }
+
+    #[test]
+    fn neighbouring_codes_are_accepted_once() {
+        let mut totp = Totp::new(rfc_secret());
+        assert!(!totp.verify_code_at("000000", at(59)));
+        assert!(totp.verify_code_at("287082", at(75)));
+        assert!(!totp.verify_code_at("287082", at(75)));
+        assert!(!totp.verify_code_at("287082", at(120)));
+    }
+
+    #[test]
+    fn recovery_codes_are_used_up() {
+        let mut totp = Totp::new(TotpSecret::generate());
+        let codes = totp.generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(totp.use_recovery_code(&codes[0].to_uppercase().replace('-', " ")));
+        assert!(!totp.use_recovery_code(&codes[0]));
+        assert!(!totp.use_recovery_code(""));
+        assert_eq!(totp.recovery_codes_left(), RECOVERY_CODE_COUNT - 1);
+    }
+
+    #[test]
+    fn otpauth_uri_contains_secret_and_issuer() {
+        let uri = rfc_secret().otpauth_uri("jane doe");
+        assert!(uri.starts_with("otpauth://totp/FLECS:jane%20doe?"));
+        assert!(uri.contains("secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"));
+        assert!(uri.contains("issuer=FLECS"));
+    }
+
+    #[test]
+    fn secret_is_stored_as_base32() {
+        let json = serde_json::to_value(rfc_secret()).unwrap();
+        assert_eq!(json, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        let secret: TotpSecret = serde_json::from_value(json).unwrap();
+        assert!(secret == rfc_secret());
+        assert!(serde_json::from_value::<TotpSecret>("not base32!".into()).is_err());
+    }
+}
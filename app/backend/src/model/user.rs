@@ -1,9 +1,11 @@
 use std::collections::HashSet;
 
+use oxide_auth::primitives::scope::Scope;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::model::{group::GroupId, password};
+use crate::model::{group::GroupId, password, totp::Totp};
+use crate::oauth::oidc;
 
 pub type UserId = u16;
 
@@ -16,6 +18,23 @@ pub struct User {
     pub full_name: String,
     pub password: password::Password,
     pub groups: HashSet<GroupId>,
+    /// Authenticator of the user, which is only asked for once confirmed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp: Option<Totp>,
+}
+
+impl User {
+    /// Whether the user has to enter a code of their authenticator to log in
+    pub fn has_totp(&self) -> bool {
+        self.totp.as_ref().is_some_and(Totp::is_confirmed)
+    }
+
+    /// Whether any group of the user requires two-factor authentication
+    pub fn requires_totp(&self, required_groups: &[GroupId]) -> bool {
+        self.groups
+            .iter()
+            .any(|group| required_groups.contains(group))
+    }
 }
 
 #[derive(Serialize, ToSchema)]
@@ -35,6 +54,30 @@ impl From<&User> for UserSummary {
     }
 }
 
+/// Standard OpenID Connect claims about a user, limited to the granted scope.
+/// Users have no email address, so there is neither an `email` scope nor claim.
+#[derive(Serialize, ToSchema)]
+pub struct UserInfo {
+    pub sub: String,
+    /// Only with the `profile` scope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Only with the `profile` scope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+}
+
+impl UserInfo {
+    pub fn new(user: &User, scope: &Scope) -> Self {
+        let profile = oidc::includes_profile(scope);
+        Self {
+            sub: user.id.to_string(),
+            name: profile.then(|| user.full_name.clone()),
+            preferred_username: profile.then(|| user.name.clone()),
+        }
+    }
+}
+
 #[derive(Deserialize, ToSchema)]
 pub struct CreateUser {
     pub name: String,
@@ -47,6 +90,15 @@ pub struct UpdateUser {
     pub name: Option<String>,
     pub full_name: Option<String>,
     pub password: Option<String>,
+    /// Required by `/users/self` to change the password or the name
+    pub current_password: Option<String>,
+}
+
+/// Proof that the user knows their password, required to change their
+/// second factor
+#[derive(Deserialize, ToSchema)]
+pub struct CurrentPassword {
+    pub current_password: String,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -66,6 +118,7 @@ impl TryFrom<SuperAdmin> for User {
             full_name: value.full_name,
             password: password::Password::new(&value.password)?,
             groups: [GroupId::admin()].into(),
+            totp: None,
         })
     }
 }
@@ -1,2 +1,7 @@
+pub mod client_auth;
+pub mod discovery;
 pub mod endpoint;
+pub mod logout;
+pub mod oidc;
 pub mod registrar;
+pub mod scope;
@@ -0,0 +1,226 @@
+use std::sync::Mutex;
+
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use thiserror::Error;
+
+use crate::middleware::client_info::ClientInfo;
+use crate::model::client::{AuthMethod, Client};
+use crate::model::failed_attempt::{Account, retry_after_seconds};
+use crate::persist::client_db::ClientDB;
+use crate::persist::failed_attempt_db::{FailedAttemptDB, PendingAttempt};
+
+pub const JWT_BEARER_ASSERTION_TYPE: &str =
+    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+#[derive(Debug, Error)]
+pub enum ClientAuthError {
+    #[error("Missing client_id")]
+    MissingClientId,
+    #[error("Missing client_secret")]
+    MissingClientSecret,
+    #[error("Missing client_assertion")]
+    MissingClientAssertion,
+    #[error("Unknown client")]
+    UnknownClient,
+    #[error("Invalid client secret")]
+    InvalidClientSecret,
+    #[error("Client uses certificate authentication, not secret")]
+    NotSecretClient,
+    #[error("Client uses secret authentication, not certificate")]
+    NotCertificateClient,
+    #[error("Failed to use stored certificate: {0}")]
+    Certificate(#[from] openssl::error::ErrorStack),
+    #[error("Failed to create decoding key: {0}")]
+    DecodingKey(jsonwebtoken::errors::Error),
+    #[error("Invalid client assertion: {0}")]
+    InvalidAssertion(jsonwebtoken::errors::Error),
+    #[error("Assertion sub must match client_id")]
+    AssertionSubjectMismatch,
+    #[error("Too many failed attempts, please try again later")]
+    Throttled(chrono::Duration),
+}
+
+impl IntoResponse for ClientAuthError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::MissingClientId
+            | Self::MissingClientSecret
+            | Self::MissingClientAssertion
+            | Self::NotSecretClient
+            | Self::NotCertificateClient => StatusCode::BAD_REQUEST,
+            Self::UnknownClient
+            | Self::InvalidClientSecret
+            | Self::InvalidAssertion(_)
+            | Self::AssertionSubjectMismatch => StatusCode::UNAUTHORIZED,
+            Self::Certificate(_) | Self::DecodingKey(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Throttled(retry_after) => {
+                let retry_after = [(header::RETRY_AFTER, retry_after_seconds(retry_after))];
+                return (StatusCode::TOO_MANY_REQUESTS, retry_after, self.to_string())
+                    .into_response();
+            }
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+pub fn form_value<'a>(form: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    form.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+/// Authenticate a client of the [`ClientDB`] by its secret, passed via HTTP
+/// Basic auth or in the form, or by a JWT assertion signed with the private
+/// key of its certificate
+pub fn authenticate<'a>(
+    clients: &'a ClientDB,
+    headers: &HeaderMap,
+    form: &[(String, String)],
+) -> Result<&'a Client, ClientAuthError> {
+    if form_value(form, "client_assertion_type") == Some(JWT_BEARER_ASSERTION_TYPE) {
+        authenticate_certificate(clients, form)
+    } else {
+        authenticate_secret(clients, headers, form)
+    }
+}
+
+/// Like [`authenticate`], but refuses clients and addresses that failed to
+/// authenticate too often and records the outcome
+pub fn authenticate_throttled<'a>(
+    clients: &'a ClientDB,
+    failed_attempts: &Mutex<FailedAttemptDB>,
+    client_info: &ClientInfo,
+    headers: &HeaderMap,
+    form: &[(String, String)],
+) -> Result<&'a Client, ClientAuthError> {
+    let Some(client_id) = client_id(headers, form) else {
+        return authenticate(clients, headers, form);
+    };
+    let attempt = check_throttle(failed_attempts, client_id, client_info)?;
+    let result = authenticate(clients, headers, form);
+    let failed = matches!(
+        result,
+        Err(ClientAuthError::UnknownClient
+            | ClientAuthError::InvalidClientSecret
+            | ClientAuthError::InvalidAssertion(_)
+            | ClientAuthError::AssertionSubjectMismatch)
+    );
+    record_attempt(attempt, !failed);
+    result
+}
+
+/// Refuse the client if it or the address failed to authenticate too often,
+/// otherwise its attempt is pending until it is recorded
+pub fn check_throttle<'a>(
+    failed_attempts: &'a Mutex<FailedAttemptDB>,
+    client_id: String,
+    client_info: &ClientInfo,
+) -> Result<PendingAttempt<'a>, ClientAuthError> {
+    client_info
+        .begin_attempt(failed_attempts, Account::Client { client_id })
+        .map_err(ClientAuthError::Throttled)
+}
+
+/// Count a failed attempt of the client, or forget its failures on success
+pub fn record_attempt(attempt: PendingAttempt<'_>, success: bool) {
+    if success {
+        attempt.succeed();
+    } else {
+        attempt.fail();
+    }
+}
+
+/// Id of the client that tries to authenticate, from HTTP Basic auth or the
+/// form
+pub fn client_id(headers: &HeaderMap, form: &[(String, String)]) -> Option<String> {
+    extract_basic_auth(headers)
+        .map(|(id, _)| id)
+        .or_else(|| form_value(form, "client_id").map(str::to_string))
+}
+
+fn authenticate_secret<'a>(
+    clients: &'a ClientDB,
+    headers: &HeaderMap,
+    form: &[(String, String)],
+) -> Result<&'a Client, ClientAuthError> {
+    // Try HTTP Basic Auth first, then fall back to body params
+    let (client_id, client_secret) = match extract_basic_auth(headers) {
+        Some((id, secret)) => (id, secret),
+        None => {
+            let id = form_value(form, "client_id").ok_or(ClientAuthError::MissingClientId)?;
+            let secret =
+                form_value(form, "client_secret").ok_or(ClientAuthError::MissingClientSecret)?;
+            (id.to_string(), secret.to_string())
+        }
+    };
+
+    let client_id = client_id
+        .parse()
+        .map_err(|_| ClientAuthError::UnknownClient)?;
+    let client = clients
+        .query_by_id(client_id)
+        .ok_or(ClientAuthError::UnknownClient)?;
+
+    match &client.auth_method {
+        AuthMethod::Secret { secret } => {
+            if secret.verify(&client_secret).is_err() {
+                return Err(ClientAuthError::InvalidClientSecret);
+            }
+        }
+        AuthMethod::Certificate { .. } => return Err(ClientAuthError::NotSecretClient),
+    }
+    Ok(client)
+}
+
+fn authenticate_certificate<'a>(
+    clients: &'a ClientDB,
+    form: &[(String, String)],
+) -> Result<&'a Client, ClientAuthError> {
+    let client_id_str = form_value(form, "client_id").ok_or(ClientAuthError::MissingClientId)?;
+    let assertion =
+        form_value(form, "client_assertion").ok_or(ClientAuthError::MissingClientAssertion)?;
+
+    let client_id = client_id_str
+        .parse::<uuid::Uuid>()
+        .map_err(|_| ClientAuthError::UnknownClient)?;
+    let client = clients
+        .query_by_id(client_id)
+        .ok_or(ClientAuthError::UnknownClient)?;
+
+    let AuthMethod::Certificate { pem: cert_pem } = &client.auth_method else {
+        return Err(ClientAuthError::NotCertificateClient);
+    };
+
+    // Extract public key from stored certificate
+    let x509 = openssl::x509::X509::from_pem(cert_pem.as_bytes())?;
+    let pub_key_pem = x509.public_key()?.public_key_to_pem()?;
+
+    // Verify the JWT assertion
+    let decoding_key = jsonwebtoken::DecodingKey::from_rsa_pem(&pub_key_pem)
+        .map_err(ClientAuthError::DecodingKey)?;
+    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::RS256);
+    validation.set_audience(&["flecs-core-api", "fence-api"]);
+    validation.set_issuer(&[client_id_str]);
+    validation.set_required_spec_claims(&["exp", "aud", "iss", "sub"]);
+
+    let assertion_claims =
+        jsonwebtoken::decode::<serde_json::Value>(assertion, &decoding_key, &validation)
+            .map_err(ClientAuthError::InvalidAssertion)?
+            .claims;
+
+    // Verify sub == client_id
+    if assertion_claims.get("sub").and_then(|v| v.as_str()) != Some(client_id_str) {
+        return Err(ClientAuthError::AssertionSubjectMismatch);
+    }
+    Ok(client)
+}
+
+pub fn extract_basic_auth(headers: &HeaderMap) -> Option<(String, String)> {
+    let auth = headers.get("authorization")?.to_str().ok()?;
+    let encoded = auth.strip_prefix("Basic ")?;
+    let decoded = STANDARD.decode(encoded).ok()?;
+    let decoded_str = String::from_utf8(decoded).ok()?;
+    let (id, secret) = decoded_str.split_once(':')?;
+    Some((id.to_string(), secret.to_string()))
+}
@@ -0,0 +1,129 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::oauth::oidc;
+
+/// Authorization server metadata as defined by OpenID Connect Discovery 1.0 and
+/// RFC 8414, so that clients can locate all endpoints from the issuer url alone.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProviderMetadata {
+    #[schema(value_type = String, format = Uri)]
+    pub issuer: url::Url,
+    #[schema(value_type = String, format = Uri)]
+    pub authorization_endpoint: url::Url,
+    #[schema(value_type = String, format = Uri)]
+    pub token_endpoint: url::Url,
+    #[schema(value_type = String, format = Uri)]
+    pub revocation_endpoint: url::Url,
+    #[schema(value_type = String, format = Uri)]
+    pub introspection_endpoint: url::Url,
+    #[schema(value_type = String, format = Uri)]
+    pub registration_endpoint: url::Url,
+    #[schema(value_type = String, format = Uri)]
+    pub userinfo_endpoint: url::Url,
+    #[schema(value_type = String, format = Uri)]
+    pub end_session_endpoint: url::Url,
+    #[schema(value_type = String, format = Uri)]
+    pub jwks_uri: url::Url,
+    pub scopes_supported: Vec<&'static str>,
+    pub response_types_supported: Vec<&'static str>,
+    pub subject_types_supported: Vec<&'static str>,
+    pub grant_types_supported: Vec<&'static str>,
+    pub token_endpoint_auth_methods_supported: Vec<&'static str>,
+    pub id_token_signing_alg_values_supported: Vec<&'static str>,
+    pub code_challenge_methods_supported: Vec<&'static str>,
+    pub claims_supported: Vec<&'static str>,
+    pub backchannel_logout_supported: bool,
+    pub backchannel_logout_session_supported: bool,
+}
+
+impl ProviderMetadata {
+    pub fn new(issuer: &url::Url) -> Self {
+        Self {
+            issuer: issuer.clone(),
+            authorization_endpoint: endpoint(issuer, "/oauth/authorize"),
+            token_endpoint: endpoint(issuer, "/oauth/token"),
+            revocation_endpoint: endpoint(issuer, "/oauth/revoke"),
+            introspection_endpoint: endpoint(issuer, "/oauth/introspect"),
+            registration_endpoint: endpoint(issuer, "/oauth/register"),
+            userinfo_endpoint: endpoint(issuer, "/userinfo"),
+            end_session_endpoint: endpoint(issuer, "/logout"),
+            jwks_uri: endpoint(issuer, "/.well-known/jwks.json"),
+            scopes_supported: oidc::SCOPES.to_vec(),
+            response_types_supported: vec!["code"],
+            subject_types_supported: vec!["public"],
+            grant_types_supported: vec![
+                "authorization_code",
+                "client_credentials",
+                "refresh_token",
+            ],
+            // Methods every client of the authorization code flow can use,
+            // private_key_jwt is left out as only machine clients have keys
+            token_endpoint_auth_methods_supported: vec![
+                "none",
+                "client_secret_basic",
+                "client_secret_post",
+            ],
+            id_token_signing_alg_values_supported: vec!["RS256"],
+            code_challenge_methods_supported: vec!["S256"],
+            claims_supported: vec![
+                "iss",
+                "sub",
+                "aud",
+                "exp",
+                "iat",
+                "auth_time",
+                "nonce",
+                "azp",
+                "at_hash",
+                "name",
+                "preferred_username",
+            ],
+            backchannel_logout_supported: true,
+            backchannel_logout_session_supported: false,
+        }
+    }
+}
+
+/// Endpoints are located below the issuer, which may itself contain a path
+/// if fence is served behind a reverse proxy.
+pub fn endpoint(issuer: &url::Url, path: &str) -> url::Url {
+    let mut url = issuer.clone();
+    url.set_path(&format!("{}{path}", issuer.path().trim_end_matches('/')));
+    url.set_query(None);
+    url.set_fragment(None);
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_are_located_below_issuer() {
+        let issuer = url::Url::parse("http://fence.flecs.local:27000").unwrap();
+        let metadata = ProviderMetadata::new(&issuer);
+        assert_eq!(
+            metadata.token_endpoint.as_str(),
+            "http://fence.flecs.local:27000/oauth/token"
+        );
+        assert_eq!(
+            metadata.jwks_uri.as_str(),
+            "http://fence.flecs.local:27000/.well-known/jwks.json"
+        );
+    }
+
+    #[test]
+    fn endpoints_keep_issuer_path() {
+        let issuer = url::Url::parse("https://flecs.local/fence/").unwrap();
+        assert_eq!(
+            endpoint(&issuer, "/oauth/authorize").as_str(),
+            "https://flecs.local/fence/oauth/authorize"
+        );
+        let issuer = url::Url::parse("https://flecs.local/fence").unwrap();
+        assert_eq!(
+            endpoint(&issuer, "/oauth/authorize").as_str(),
+            "https://flecs.local/fence/oauth/authorize"
+        );
+    }
+}
@@ -1,73 +1,234 @@
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
-use base64::Engine;
-use base64::engine::general_purpose::URL_SAFE_NO_PAD as b64url;
-use jsonwebtoken::jwk::{
-    AlgorithmParameters, CommonParameters, KeyAlgorithm, PublicKeyUse, RSAKeyParameters, RSAKeyType,
-};
-use openssl::pkey::PKey;
-use openssl::rsa::Rsa;
-use oxide_auth::primitives::authorizer::AuthMap;
+use anyhow::Context;
+use jsonwebtoken::jwk::JwkSet;
 use oxide_auth::primitives::grant::Grant;
 use oxide_auth::primitives::issuer::{IssuedToken, RefreshedToken};
-use oxide_auth::primitives::prelude::RandomGenerator;
-use tracing::{debug, error, warn};
+use tracing::{debug, error, info, warn};
 
+use crate::config;
+use crate::model::refresh_token::TokenFamily;
+use crate::model::signing_key::{KeySummary, SigningKey};
+use crate::model::user::UserId;
 use crate::persist;
+use crate::persist::key_db::KeyDB;
+use crate::persist::refresh_token_db::Lookup;
 
-pub type Authorizer = AuthMap<RandomGenerator>;
+/// Authorizer backed by the
+/// [`AuthorizationCodeDB`](persist::authorization_code_db::AuthorizationCodeDB),
+/// so that codes can still be redeemed after a restart
+pub struct Authorizer {
+    db: Arc<Mutex<persist::Db>>,
+}
+
+impl Authorizer {
+    pub fn new(db: Arc<Mutex<persist::Db>>) -> Self {
+        Self { db }
+    }
+}
+
+impl oxide_auth::primitives::authorizer::Authorizer for Authorizer {
+    fn authorize(&mut self, grant: Grant) -> Result<String, ()> {
+        let mut db = self.db.lock().unwrap();
+        let code = db.authorization_codes.insert(grant);
+        db.authorization_codes.save().map_err(|e| {
+            error!("Could not persist authorization code database: {e}");
+        })?;
+        Ok(code)
+    }
+
+    fn extract(&mut self, code: &str) -> Result<Option<Grant>, ()> {
+        let mut db = self.db.lock().unwrap();
+        let grant = db.authorization_codes.take(code);
+        if grant.is_some()
+            && let Err(e) = db.authorization_codes.save()
+        {
+            error!("Could not persist authorization code database: {e}");
+        }
+        Ok(grant)
+    }
+}
 
 pub struct Issuer {
-    pub jwk: jsonwebtoken::jwk::Jwk,
-    pub encoding_key: jsonwebtoken::EncodingKey,
     pub url: url::Url,
+    keys: KeyDB,
+    rotation_interval: Option<chrono::Duration>,
+    refresh_token_lifetime: chrono::Duration,
+    /// Id tokens issued alongside an access token, keyed by that access token,
+    /// until the token endpoint adds them to its response or the access token
+    /// expires
+    id_tokens: HashMap<String, (String, chrono::DateTime<chrono::Utc>)>,
     db: Arc<Mutex<persist::Db>>,
 }
 
 impl Issuer {
-    pub fn new(db: Arc<Mutex<persist::Db>>, issuer_url: url::Url) -> Self {
-        let rsa = Rsa::generate(2048).unwrap();
-        let pkey = PKey::from_rsa(rsa).unwrap();
-        let private_key: Vec<u8> = pkey.private_key_to_pem_pkcs8().unwrap();
-        let encoding_key = jsonwebtoken::EncodingKey::from_rsa_pem(&private_key).unwrap();
-        let n = pkey.rsa().unwrap().n().to_vec();
-        let n = b64url.encode(&n);
-        let e = pkey.rsa().unwrap().e().to_vec();
-        let e = b64url.encode(&e);
-        let jwk = jsonwebtoken::jwk::Jwk {
-            common: CommonParameters {
-                key_id: Some(uuid::Uuid::new_v4().to_string()),
-                public_key_use: Some(PublicKeyUse::Signature),
-                key_algorithm: Some(KeyAlgorithm::RS256),
-                ..Default::default()
-            },
-            algorithm: AlgorithmParameters::RSA(RSAKeyParameters {
-                n,
-                e,
-                key_type: RSAKeyType::RSA,
-            }),
+    pub fn new(db: Arc<Mutex<persist::Db>>, auth: &config::Auth) -> anyhow::Result<Self> {
+        let rotation_interval = match auth.signing_key_rotation_days {
+            0 => None,
+            days => Some(chrono::Duration::days(days.into())),
         };
-        Self {
-            url: issuer_url,
-            jwk,
-            encoding_key,
+        Ok(Self {
+            url: auth.issuer_url.clone(),
+            keys: KeyDB::new(auth.signing_key_path.clone(), crate::token::TOKEN_DURATION)?,
+            rotation_interval,
+            refresh_token_lifetime: chrono::Duration::days(auth.refresh_token_lifetime_days.into()),
+            id_tokens: HashMap::new(),
             db,
+        })
+    }
+
+    pub fn signing_key(&self) -> &SigningKey {
+        self.keys.active()
+    }
+
+    /// Keys that tokens issued by this issuer can be verified with
+    pub fn jwks(&self) -> JwkSet {
+        self.keys.jwks()
+    }
+
+    pub fn key_summaries(&self) -> Vec<KeySummary> {
+        std::iter::once(KeySummary::from(self.keys.active()))
+            .chain(self.keys.retired().map(KeySummary::from))
+            .collect()
+    }
+
+    pub fn rotate(&mut self) -> anyhow::Result<&SigningKey> {
+        self.keys.rotate()
+    }
+
+    /// Rotate the signing key if it is older than the configured interval. A
+    /// failed rotation is logged and the current key stays active.
+    pub fn rotate_if_due(&mut self) {
+        let Some(interval) = self.rotation_interval else {
+            return;
+        };
+        if self.keys.active().created_at() + interval > chrono::Utc::now() {
+            return;
+        }
+        if let Err(e) = self.keys.rotate() {
+            error!("Could not rotate signing key: {e:#}");
         }
     }
-}
 
-impl oxide_auth::primitives::issuer::Issuer for Issuer {
-    fn issue(&mut self, grant: Grant) -> Result<IssuedToken, ()> {
-        match crate::token::issue(
+    fn issue_access_token(&self, grant: Grant) -> anyhow::Result<IssuedToken> {
+        let key = self.keys.active();
+        crate::token::issue(
             grant,
             self.url.clone(),
-            self.jwk.common.key_id.clone(),
-            &self.encoding_key,
+            Some(key.kid().to_string()),
+            key.encoding_key(),
             self.db.clone(),
-        ) {
+        )
+    }
+
+    fn issue_id_token(&self, grant: &Grant, access_token: &str) -> anyhow::Result<Option<String>> {
+        let uid: UserId = grant
+            .owner_id
+            .parse()
+            .with_context(|| format!("owner_id = {}", grant.owner_id))?;
+        let db = self.db.lock().unwrap();
+        let user = db
+            .users
+            .query_by_uid(uid)
+            .ok_or_else(|| anyhow::anyhow!("Unknown user id {uid}"))?;
+        let key = self.keys.active();
+        crate::token::issue_id_token(
+            grant,
+            user,
+            access_token,
+            self.url.clone(),
+            Some(key.kid().to_string()),
+            key.encoding_key(),
+        )
+    }
+
+    /// Take the id token that was issued together with `access_token`, if any
+    pub fn take_id_token(&mut self, access_token: &str) -> Option<String> {
+        self.id_tokens
+            .remove(access_token)
+            .map(|(id_token, _)| id_token)
+    }
+
+    /// Start a new refresh token family for the grant's owner and client
+    fn issue_refresh_token(&self, grant: &Grant) -> anyhow::Result<String> {
+        let uid: UserId = grant
+            .owner_id
+            .parse()
+            .with_context(|| format!("owner_id = {}", grant.owner_id))?;
+        let mut db = self.db.lock().unwrap();
+        let credential = db
+            .users
+            .query_by_uid(uid)
+            .ok_or_else(|| anyhow::anyhow!("Unknown user id {uid}"))?
+            .password
+            .fingerprint()
+            .ok_or_else(|| anyhow::anyhow!("User {uid} has no hashed password"))?;
+        let (family, token) = TokenFamily::new(
+            uid,
+            grant.client_id.clone(),
+            grant.scope.to_string(),
+            grant.redirect_uri.clone(),
+            credential,
+            self.refresh_token_lifetime,
+        );
+        db.refresh_tokens.insert(family);
+        db.refresh_tokens.save()?;
+        Ok(token)
+    }
+}
+
+impl oxide_auth::primitives::issuer::Issuer for Issuer {
+    fn issue(&mut self, grant: Grant) -> Result<IssuedToken, ()> {
+        self.rotate_if_due();
+        let refresh = self.issue_refresh_token(&grant).map_err(|e| {
+            error!("Error creating refresh token: {e:#}");
+        })?;
+        let token = self.issue_access_token(grant.clone()).map_err(|e| {
+            error!("Error encoding token: {e}");
+        })?;
+        match self.issue_id_token(&grant, &token.token) {
+            Ok(Some(id_token)) => {
+                let now = chrono::Utc::now();
+                self.id_tokens.retain(|_, (_, until)| *until > now);
+                self.id_tokens
+                    .insert(token.token.clone(), (id_token, token.until));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!("Error encoding id token: {e:#}");
+                return Err(());
+            }
+        }
+        debug!("Created token");
+        Ok(IssuedToken {
+            refresh: Some(refresh),
+            ..token
+        })
+    }
+
+    fn refresh(&mut self, refresh: &str, grant: Grant) -> Result<RefreshedToken, ()> {
+        self.rotate_if_due();
+        let refresh = {
+            let mut db = self.db.lock().unwrap();
+            let refresh = db
+                .refresh_tokens
+                .rotate(refresh, self.refresh_token_lifetime)
+                .ok_or(())?;
+            db.refresh_tokens.save().map_err(|e| {
+                error!("Could not persist refresh token database: {e}");
+            })?;
+            refresh
+        };
+        match self.issue_access_token(grant) {
             Ok(token) => {
-                debug!("Created token");
-                Ok(token)
+                debug!("Refreshed token");
+                Ok(RefreshedToken {
+                    token: token.token,
+                    refresh: Some(refresh),
+                    until: token.until,
+                    token_type: token.token_type,
+                })
             }
             Err(e) => {
                 error!("Error encoding token: {e}");
@@ -76,18 +237,47 @@ impl oxide_auth::primitives::issuer::Issuer for Issuer {
         }
     }
 
-    fn refresh(&mut self, _refresh: &str, _grant: Grant) -> Result<RefreshedToken, ()> {
-        warn!("fn refresh unimplemented");
-        Err(())
-    }
-
     fn recover_token<'a>(&'a self, _: &'a str) -> Result<Option<Grant>, ()> {
         warn!("fn recover_token unimplemented");
         Err(())
     }
 
-    fn recover_refresh<'a>(&'a self, _: &'a str) -> Result<Option<Grant>, ()> {
-        warn!("fn recover_refresh unimplemented");
-        Err(())
+    fn recover_refresh<'a>(&'a self, refresh: &'a str) -> Result<Option<Grant>, ()> {
+        let mut db = self.db.lock().unwrap();
+        let family = match db.refresh_tokens.find(refresh) {
+            Lookup::Valid(family) => family,
+            Lookup::Reused(id) => {
+                warn!("Reuse of refresh token detected, revoking token family {id}");
+                db.refresh_tokens.revoke(id);
+                db.refresh_tokens.save().map_err(|e| {
+                    error!("Could not persist refresh token database: {e}");
+                })?;
+                return Ok(None);
+            }
+            Lookup::Unknown => return Ok(None),
+        };
+        // The family is bound to the user's password at the time it was issued
+        let credential_valid = db
+            .users
+            .query_by_uid(family.uid)
+            .and_then(|user| user.password.fingerprint())
+            .is_some_and(|credential| credential == family.credential);
+        if !credential_valid {
+            let id = family.id;
+            info!("User of token family {id} was deleted or changed password, revoking family");
+            db.refresh_tokens.revoke(id);
+            db.refresh_tokens.save().map_err(|e| {
+                error!("Could not persist refresh token database: {e}");
+            })?;
+            return Ok(None);
+        }
+        Ok(Some(Grant {
+            owner_id: family.uid.to_string(),
+            client_id: family.client_id.clone(),
+            scope: family.scope.parse().map_err(|_| ())?,
+            redirect_uri: family.redirect_uri.clone(),
+            until: family.expires_at,
+            extensions: Default::default(),
+        }))
     }
 }
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+/// Clients that do not answer in time are not waited for any longer
+const BACKCHANNEL_LOGOUT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Logout token for the back-channel logout uri of a client
+pub struct Notification {
+    pub client_id: String,
+    pub uri: url::Url,
+    pub logout_token: String,
+}
+
+/// Post the logout tokens to the clients, see OpenID Connect Back-Channel
+/// Logout 1.0, section 2.5. Failures are only logged, the user is logged out
+/// of fence regardless.
+pub async fn notify(notifications: Vec<Notification>) {
+    if notifications.is_empty() {
+        return;
+    }
+    let client = match reqwest::Client::builder()
+        .timeout(BACKCHANNEL_LOGOUT_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Could not create client for back-channel logout: {e}");
+            return;
+        }
+    };
+    let requests = notifications.into_iter().map(|notification| {
+        let request = client
+            .post(notification.uri)
+            .form(&[("logout_token", notification.logout_token)]);
+        async move {
+            match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    debug!("Client {} was notified of logout", notification.client_id);
+                }
+                Ok(response) => warn!(
+                    "Back-channel logout of client {} failed with status {}",
+                    notification.client_id,
+                    response.status()
+                ),
+                Err(e) => warn!(
+                    "Back-channel logout of client {} failed: {e}",
+                    notification.client_id
+                ),
+            }
+        }
+    });
+    futures_util::future::join_all(requests).await;
+}
@@ -0,0 +1,121 @@
+use oxide_auth::frontends::simple::extensions::{
+    AccessTokenAddon, AccessTokenRequest, AddonResult, AuthorizationAddon, AuthorizationRequest,
+};
+use oxide_auth::primitives::grant::{Grant, GrantExtension, Value};
+use oxide_auth::primitives::scope::Scope;
+use serde::{Deserialize, Serialize};
+
+pub const OPENID_SCOPE: &str = "openid";
+pub const PROFILE_SCOPE: &str = "profile";
+
+/// Scopes defined by OpenID Connect, which every client may request in
+/// addition to its own scope
+pub const SCOPES: [&str; 2] = [OPENID_SCOPE, PROFILE_SCOPE];
+
+const EXTENSION_ID: &str = "oidc";
+
+pub fn is_openid_request(scope: &Scope) -> bool {
+    scope.iter().any(|s| s == OPENID_SCOPE)
+}
+
+pub fn includes_profile(scope: &Scope) -> bool {
+    scope.iter().any(|s| s == PROFILE_SCOPE)
+}
+
+/// Parameters of the authorization request that end up in the id token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthContext {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    pub auth_time: chrono::DateTime<chrono::Utc>,
+}
+
+impl AuthContext {
+    pub fn from_grant(grant: &Grant) -> Option<Self> {
+        grant
+            .extensions
+            .private()
+            .find(|(id, _)| *id == EXTENSION_ID)
+            .and_then(|(_, value)| value)
+            .and_then(|value| serde_json::from_str(value).ok())
+    }
+}
+
+/// Addon carrying the [`AuthContext`] from the authorization request through
+/// the authorization code to the issuer. The `auth_time` is only known to the
+/// authorization endpoint, the token endpoint passes the stored data on.
+pub struct Addon {
+    auth_time: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Addon {
+    pub fn authorization(auth_time: chrono::DateTime<chrono::Utc>) -> Self {
+        Self {
+            auth_time: Some(auth_time),
+        }
+    }
+
+    pub fn access_token() -> Self {
+        Self { auth_time: None }
+    }
+}
+
+impl GrantExtension for Addon {
+    fn identifier(&self) -> &'static str {
+        EXTENSION_ID
+    }
+}
+
+impl AuthorizationAddon for Addon {
+    fn execute(&self, request: &dyn AuthorizationRequest) -> AddonResult {
+        let Some(auth_time) = self.auth_time else {
+            return AddonResult::Err;
+        };
+        let context = AuthContext {
+            nonce: request.extension("nonce").map(|nonce| nonce.into_owned()),
+            auth_time,
+        };
+        match serde_json::to_string(&context) {
+            Ok(encoded) => AddonResult::Data(Value::private(Some(encoded))),
+            Err(_) => AddonResult::Err,
+        }
+    }
+}
+
+impl AccessTokenAddon for Addon {
+    fn execute(&self, _request: &dyn AccessTokenRequest, data: Option<Value>) -> AddonResult {
+        match data {
+            Some(data) => AddonResult::Data(data),
+            None => AddonResult::Ok,
+        }
+    }
+}
+
+/// Left-most half of the SHA-256 hash of the access token, see OpenID Connect
+/// Core 1.0, section 3.1.3.6
+pub fn at_hash(access_token: &str) -> String {
+    use base64::Engine;
+    let digest = openssl::sha::sha256(access_token.as_bytes());
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&digest[..digest.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn at_hash_matches_reference() {
+        // Example from OpenID Connect Core 1.0, Appendix A.3
+        assert_eq!(
+            at_hash("jHkWEdUXMU1BwAsC4vtUsZwnNvTIxEl0z9K3vx5KF0Y"),
+            "77QmUPtjPfzWtF2AnpK9RQ"
+        );
+    }
+
+    #[test]
+    fn openid_scope_is_detected() {
+        assert!(is_openid_request(&"admin openid".parse().unwrap()));
+        assert!(!is_openid_request(&"admin profile".parse().unwrap()));
+        assert!(includes_profile(&"openid profile".parse().unwrap()));
+    }
+}
@@ -1,133 +1,98 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
-use std::str::FromStr;
-use std::sync::LazyLock;
+use std::sync::{Arc, Mutex};
 
 use oxide_auth::endpoint::PreGrant;
 use oxide_auth::primitives::prelude::ClientUrl;
-use oxide_auth::primitives::registrar::{
-    Argon2, BoundClient, Client, EncodedClient, ExactUrl, PasswordPolicy, RegisteredClient,
-    RegisteredUrl, RegistrarError,
-};
+use oxide_auth::primitives::registrar::{BoundClient, ExactUrl, RegisteredUrl, RegistrarError};
 use oxide_auth::primitives::scope::Scope;
 
-fn make_flecs_client() -> Client {
-    Client::public(
-        "flecs",
-        RegisteredUrl::Exact(ExactUrl::new("https://*/".into()).unwrap()),
-        Scope::from_str("admin").unwrap(),
-    )
-}
-
-pub fn build_registrar() -> Registrar {
-    let mut clients = Registrar::new();
-    clients.register_client(make_flecs_client());
-    clients
-}
+use crate::model::oauth_client::ClientType;
+use crate::oauth::scope;
+use crate::persist;
 
-#[derive(Default)]
+/// Registrar backed by the [`OAuthClientDB`](persist::oauth_client_db::OAuthClientDB)
 pub struct Registrar {
-    clients: HashMap<String, EncodedClient>,
-    password_policy: Option<Box<dyn PasswordPolicy>>,
+    db: Arc<Mutex<persist::Db>>,
 }
 
-static DEFAULT_PASSWORD_POLICY: LazyLock<Argon2> = LazyLock::new(Argon2::default);
-
 impl Registrar {
-    pub fn new() -> Self {
-        Registrar::default()
+    pub fn new(db: Arc<Mutex<persist::Db>>) -> Self {
+        Self { db }
     }
 
-    pub fn register_client(&mut self, client: Client) {
-        let password_policy = Self::current_policy(&self.password_policy);
-        let encoded_client = client.encode(password_policy);
-        self.clients
-            .insert(encoded_client.client_id.clone(), encoded_client);
-    }
-
-    pub fn set_password_policy<P: PasswordPolicy + 'static>(&mut self, new_policy: P) {
-        self.password_policy = Some(Box::new(new_policy))
-    }
-
-    fn current_policy(policy: &Option<Box<dyn PasswordPolicy>>) -> &dyn PasswordPolicy {
-        policy
-            .as_ref()
-            .map(|boxed| &**boxed)
-            .unwrap_or(&*DEFAULT_PASSWORD_POLICY)
+    /// Whether authorization requests of the client must carry an S256 PKCE
+    /// challenge. This is the case for all clients that did not opt out,
+    /// including unknown ones.
+    pub fn requires_pkce(&self, client_id: &str) -> bool {
+        let db = self.db.lock().unwrap();
+        db.oauth_clients
+            .query_by_id(client_id)
+            .is_none_or(|client| client.requires_pkce())
     }
 }
 
 impl oxide_auth::primitives::registrar::Registrar for Registrar {
     fn bound_redirect<'a>(&self, bound: ClientUrl<'a>) -> Result<BoundClient<'a>, RegistrarError> {
-        let client = match self.clients.get(bound.client_id.as_ref()) {
-            None => return Err(RegistrarError::Unspecified),
-            Some(stored) => stored,
-        };
-
-        // URI matching is modified so that 'https://*/' means "open redirect" to any
-        // location the user-agent asks for
-        fn is_wildcard_redirect(u: &RegisteredUrl) -> bool {
-            match u {
-                RegisteredUrl::Exact(url) => url.as_str() == "https://*/",
-                _ => false,
-            }
-        }
-
-        let registered_url = if is_wildcard_redirect(&client.redirect_uri) {
-            match bound.redirect_uri {
-                None => return Err(RegistrarError::Unspecified),
-                Some(uri) => RegisteredUrl::Exact((*uri).clone()),
-            }
-        } else {
-            match bound.redirect_uri {
-                None => client.redirect_uri.clone(),
-                Some(url) => {
-                    let original = std::iter::once(&client.redirect_uri);
-                    let alternatives = client.additional_redirect_uris.iter();
-                    if original
-                        .chain(alternatives)
-                        .any(|registered| *registered == *url.as_ref())
-                    {
-                        RegisteredUrl::Exact((*url).clone())
-                    } else {
-                        return Err(RegistrarError::Unspecified);
-                    }
+        let db = self.db.lock().unwrap();
+        let client = db
+            .oauth_clients
+            .query_by_id(bound.client_id.as_ref())
+            .ok_or(RegistrarError::Unspecified)?;
+
+        let redirect_uri = match bound.redirect_uri {
+            Some(requested) => {
+                if !client.allows_redirect(&requested.to_url()) {
+                    return Err(RegistrarError::Unspecified);
                 }
+                requested.into_owned()
             }
+            None => client
+                .default_redirect_uri()
+                .and_then(|uri| ExactUrl::new(uri.to_string()).ok())
+                .ok_or(RegistrarError::Unspecified)?,
         };
 
         Ok(BoundClient {
             client_id: bound.client_id,
-            redirect_uri: Cow::Owned(registered_url),
+            redirect_uri: Cow::Owned(RegisteredUrl::Exact(redirect_uri)),
         })
     }
 
     fn negotiate(
         &self,
         bound: BoundClient,
-        _scope: Option<Scope>,
+        requested: Option<Scope>,
     ) -> Result<PreGrant, RegistrarError> {
-        let client = self
-            .clients
-            .get(bound.client_id.as_ref())
-            .expect("Bound client appears to not have been constructed with this registrar");
+        let db = self.db.lock().unwrap();
+        let client = db
+            .oauth_clients
+            .query_by_id(bound.client_id.as_ref())
+            .ok_or(RegistrarError::Unspecified)?;
+        let scope = scope::negotiate(&client.scope, requested.as_ref())
+            .ok_or(RegistrarError::Unspecified)?;
         Ok(PreGrant {
             client_id: bound.client_id.into_owned(),
             redirect_uri: bound.redirect_uri.into_owned(),
-            scope: client.default_scope.clone(),
+            scope,
         })
     }
 
     fn check(&self, client_id: &str, passphrase: Option<&[u8]>) -> Result<(), RegistrarError> {
-        let password_policy = Self::current_policy(&self.password_policy);
-
-        self.clients
-            .get(client_id)
-            .ok_or(RegistrarError::Unspecified)
-            .and_then(|client| {
-                RegisteredClient::new(client, password_policy).check_authentication(passphrase)
-            })?;
-
-        Ok(())
+        let db = self.db.lock().unwrap();
+        let client = db
+            .oauth_clients
+            .query_by_id(client_id)
+            .ok_or(RegistrarError::Unspecified)?;
+        match (&client.client_type, passphrase) {
+            (ClientType::Public, None) => Ok(()),
+            (ClientType::Confidential { secret, .. }, Some(passphrase)) => {
+                let passphrase =
+                    std::str::from_utf8(passphrase).map_err(|_| RegistrarError::Unspecified)?;
+                secret
+                    .verify(passphrase)
+                    .map_err(|_| RegistrarError::Unspecified)
+            }
+            _ => Err(RegistrarError::Unspecified),
+        }
     }
 }
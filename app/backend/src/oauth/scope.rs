@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use oxide_auth::primitives::scope::Scope;
+
+use crate::model::group::GroupId;
+use crate::oauth::oidc;
+use crate::persist::group_db::GroupDB;
+
+/// Scope that stands for all roles the user holds. This is the default scope
+/// of the built-in `flecs` client, clients allowed this scope may request any
+/// role as scope.
+pub const ALL_ROLES: &str = "admin";
+
+fn is_oidc(scope: &str) -> bool {
+    oidc::SCOPES.contains(&scope)
+}
+
+/// Determine the scope granted to a client that is allowed `allowed` and asks
+/// for `requested`. Without a request the client gets its allowed scope. If
+/// only OpenID Connect scopes are requested, the allowed scope is added. Scopes
+/// the client is not allowed are dropped, `None` is returned if no requested
+/// role is allowed at all.
+pub fn negotiate(allowed: &Scope, requested: Option<&Scope>) -> Option<Scope> {
+    let Some(requested) = requested else {
+        return Some(allowed.clone());
+    };
+    let all_roles = allowed.iter().any(|s| s == ALL_ROLES);
+    let requested_roles: Vec<_> = requested.iter().filter(|s| !is_oidc(s)).collect();
+    let granted_roles: Vec<_> = if requested_roles.is_empty() {
+        allowed.iter().collect()
+    } else {
+        requested_roles
+            .into_iter()
+            .filter(|s| all_roles || allowed.iter().any(|a| a == *s))
+            .collect()
+    };
+    if granted_roles.is_empty() {
+        return None;
+    }
+    requested
+        .iter()
+        .filter(|s| is_oidc(s))
+        .chain(granted_roles)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .parse()
+        .ok()
+}
+
+/// Groups requested by the scope, `None` if the scope does not restrict roles
+fn requested_groups(scope: &Scope) -> Option<Vec<GroupId>> {
+    if scope.iter().any(|s| s == ALL_ROLES) {
+        return None;
+    }
+    let groups: Vec<_> = scope
+        .iter()
+        .filter(|s| !is_oidc(s))
+        .map(|s| GroupId::from(s.to_string()))
+        .collect();
+    (!groups.is_empty()).then_some(groups)
+}
+
+/// Roles a token for `scope` carries: the intersection of the roles held via
+/// `held` and the roles requested by the scope, including sub groups
+pub fn scoped_roles(groups: &GroupDB, held: &[GroupId], scope: &Scope) -> HashSet<GroupId> {
+    let held = groups.query_groups_with_subgroups(held);
+    match requested_groups(scope) {
+        None => held,
+        Some(requested) => groups
+            .query_groups_with_subgroups(&requested)
+            .intersection(&held)
+            .cloned()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(s: &str) -> Scope {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn no_request_grants_allowed_scope() {
+        assert_eq!(negotiate(&scope("admin"), None), Some(scope("admin")));
+    }
+
+    #[test]
+    fn oidc_only_request_adds_allowed_scope() {
+        assert_eq!(
+            negotiate(&scope("admin"), Some(&scope("openid profile"))),
+            Some(scope("admin openid profile"))
+        );
+    }
+
+    #[test]
+    fn all_roles_client_may_request_any_role() {
+        assert_eq!(
+            negotiate(&scope("admin"), Some(&scope("openid tech.flecs.operator"))),
+            Some(scope("openid tech.flecs.operator"))
+        );
+    }
+
+    #[test]
+    fn disallowed_roles_are_dropped() {
+        let allowed = scope("tech.flecs.operator");
+        assert_eq!(
+            negotiate(
+                &allowed,
+                Some(&scope("tech.flecs.operator tech.flecs.admin"))
+            ),
+            Some(scope("tech.flecs.operator"))
+        );
+        assert_eq!(negotiate(&allowed, Some(&scope("tech.flecs.admin"))), None);
+    }
+
+    #[test]
+    fn requested_groups_of_unrestricted_scope() {
+        assert_eq!(requested_groups(&scope("admin openid")), None);
+        assert_eq!(requested_groups(&scope("openid")), None);
+        assert_eq!(
+            requested_groups(&scope("openid tech.flecs.operator")),
+            Some(vec![GroupId::operator()])
+        );
+    }
+}
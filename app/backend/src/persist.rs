@@ -1,36 +1,67 @@
+pub mod authorization_code_db;
 pub mod client_db;
+pub mod consent_db;
+pub mod failed_attempt_db;
 pub mod group_db;
+pub mod initial_access_token_db;
+pub mod key_db;
+pub mod oauth_client_db;
+pub mod policy_db;
+pub mod refresh_token_db;
+pub mod revocation_db;
+pub mod session_db;
 pub mod user_db;
 
 use std::fs::{self, File};
 use std::io::{BufReader, Write};
-use std::path::{Path, PathBuf};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
 
 use anyhow::{Context, Result};
 use serde::{Serialize, de::DeserializeOwned};
 use tracing::warn;
 
+use authorization_code_db::AuthorizationCodeDB;
 use client_db::ClientDB;
+use consent_db::ConsentDB;
 use group_db::GroupDB;
+use initial_access_token_db::InitialAccessTokenDB;
+use oauth_client_db::OAuthClientDB;
+use policy_db::PolicyDB;
+use refresh_token_db::RefreshTokenDB;
+use revocation_db::RevocationDB;
 use user_db::UserDB;
 
+use crate::config;
+
 pub struct Db {
+    pub authorization_codes: AuthorizationCodeDB,
     pub clients: ClientDB,
+    pub consents: ConsentDB,
     pub groups: GroupDB,
+    pub initial_access_tokens: InitialAccessTokenDB,
+    pub oauth_clients: OAuthClientDB,
+    pub policies: PolicyDB,
+    pub refresh_tokens: RefreshTokenDB,
+    pub revoked_tokens: RevocationDB,
     pub users: UserDB,
 }
 
 impl Db {
-    pub fn new(
-        users_path: PathBuf,
-        groups_path: PathBuf,
-        clients_path: PathBuf,
-        ro_clients_path: PathBuf,
-    ) -> anyhow::Result<Self> {
+    pub fn new(config: &config::Database) -> anyhow::Result<Self> {
         Ok(Self {
-            clients: ClientDB::new(clients_path, ro_clients_path)?,
-            groups: GroupDB::new(groups_path)?,
-            users: UserDB::new(users_path)?,
+            authorization_codes: AuthorizationCodeDB::new(config.authorization_codes_path.clone())?,
+            clients: ClientDB::new(config.clients_path.clone(), config.ro_clients_path.clone())?,
+            consents: ConsentDB::new(config.consents_path.clone())?,
+            groups: GroupDB::new(config.groups_path.clone())?,
+            initial_access_tokens: InitialAccessTokenDB::new(
+                config.initial_access_tokens_path.clone(),
+            )?,
+            oauth_clients: OAuthClientDB::new(config.oauth_clients_path.clone())?,
+            policies: PolicyDB::new(config.policy_path.clone())?,
+            refresh_tokens: RefreshTokenDB::new(config.refresh_tokens_path.clone())?,
+            revoked_tokens: RevocationDB::new(config.revoked_tokens_path.clone())?,
+            users: UserDB::new(config.users_path.clone())?,
         })
     }
 }
@@ -71,6 +102,30 @@ where
 }
 
 pub fn save_to_file<T>(path: &Path, data: &T) -> Result<()>
+where
+    T: Serialize,
+{
+    write_to_file(path, data, fs::OpenOptions::new())
+}
+
+/// Like [`save_to_file`], but only the owner may ever read the file, not even
+/// the temporary one it is written to first
+pub fn save_private_to_file<T>(path: &Path, data: &T) -> Result<()>
+where
+    T: Serialize,
+{
+    // Files written by earlier versions may be readable by others, their
+    // backup would keep that mode
+    if path.exists() {
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("restrict permissions of {}", path.display()))?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.mode(0o600);
+    write_to_file(path, data, options)
+}
+
+fn write_to_file<T>(path: &Path, data: &T, mut options: fs::OpenOptions) -> Result<()>
 where
     T: Serialize,
 {
@@ -95,7 +150,16 @@ where
 
     /* Write data to temporary file */
     let tmp = path.with_extension("tmp");
-    let mut tmp_file = fs::File::create(&tmp)
+    // The mode only applies to new files, so a leftover one is replaced
+    if let Err(e) = fs::remove_file(&tmp)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        warn!("remove stale temporary file '{}': {}", tmp.display(), e);
+    }
+    let mut tmp_file = options
+        .write(true)
+        .create_new(true)
+        .open(&tmp)
         .with_context(|| format!("create temporary file '{}'", tmp.display()))?;
     serde_json::to_writer_pretty(&mut tmp_file, data)
         .with_context(|| format!("serialize data to '{}'", tmp.display()))?;
@@ -162,6 +226,24 @@ mod tests {
         (tmp, json_path, backup_path)
     }
 
+    #[test]
+    fn save_private_to_file_restricts_permissions() {
+        use std::fs::{self, Permissions};
+        use std::os::unix::fs::PermissionsExt;
+        let (_tmp, path, backup_path) = build_test_paths();
+        // A leftover temporary file must not pass on its permissions
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, "").unwrap();
+        fs::set_permissions(&tmp_path, Permissions::from_mode(0o644)).unwrap();
+
+        super::save_private_to_file(&path, &*TEST_DATA_1).unwrap();
+        super::save_private_to_file(&path, &*TEST_DATA_2).unwrap();
+        for path in [&path, &backup_path] {
+            let mode = fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
     #[test]
     fn save_and_load_ok() {
         let (_, path, backup_path) = build_test_paths();
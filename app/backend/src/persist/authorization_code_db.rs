@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use oxide_auth::primitives::grant::Grant;
+use tracing::error;
+
+use crate::model::authorization_code::PendingGrant;
+use crate::model::opaque_token::{digest, generate};
+
+mod versioning;
+
+/// Grants of authorization codes by the digest of the code
+pub struct AuthorizationCodeDB {
+    path: PathBuf,
+    grants: HashMap<String, PendingGrant>,
+}
+
+impl AuthorizationCodeDB {
+    pub(super) fn new(path: PathBuf) -> anyhow::Result<Self> {
+        let grants: versioning::AuthorizationCodeStorage = super::load_from_file(path.as_path())?;
+        let mut grants: HashMap<String, PendingGrant> = grants.into();
+        grants.retain(|_, grant| !grant.is_expired());
+        Ok(AuthorizationCodeDB { path, grants })
+    }
+
+    /// Store the grant under a new code, which is returned
+    pub fn insert(&mut self, grant: Grant) -> String {
+        let code = generate();
+        self.grants.insert(digest(&code), grant.into());
+        code
+    }
+
+    /// Remove and return the grant, codes can only be used once
+    pub fn take(&mut self, code: &str) -> Option<Grant> {
+        self.grants.remove(&digest(code)).map(Grant::from)
+    }
+
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        self.grants.retain(|_, grant| !grant.is_expired());
+        super::save_to_file(&self.path, &versioning::StorageRef::new(&self.grants))
+    }
+}
+
+impl Drop for AuthorizationCodeDB {
+    fn drop(&mut self) {
+        self.save()
+            .unwrap_or_else(|e| error!("Could not persist authorization code database: {e}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxide_auth::primitives::grant::Extensions;
+
+    fn make_grant(until: chrono::DateTime<chrono::Utc>) -> Grant {
+        Grant {
+            owner_id: "1".to_string(),
+            client_id: "flecs".to_string(),
+            scope: "admin".parse().unwrap(),
+            redirect_uri: url::Url::parse("https://localhost/").unwrap(),
+            until,
+            extensions: Extensions::new(),
+        }
+    }
+
+    fn make_db() -> AuthorizationCodeDB {
+        AuthorizationCodeDB {
+            path: PathBuf::new(),
+            grants: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn codes_can_be_used_once() {
+        let mut db = make_db();
+        let grant = make_grant(chrono::Utc::now() + chrono::Duration::minutes(10));
+        let code = db.insert(grant.clone());
+        assert!(!db.grants.contains_key(&code));
+        assert_eq!(db.take(&code), Some(grant));
+        assert_eq!(db.take(&code), None);
+    }
+
+    #[test]
+    fn expired_codes_are_dropped_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("authorization_codes.json");
+        let mut db = make_db();
+        let valid = db.insert(make_grant(
+            chrono::Utc::now() + chrono::Duration::minutes(10),
+        ));
+        let expired = db.insert(make_grant(
+            chrono::Utc::now() - chrono::Duration::seconds(1),
+        ));
+        crate::persist::save_to_file(&path, &versioning::StorageRef::new(&db.grants)).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains(&valid));
+
+        let mut db = AuthorizationCodeDB::new(path).unwrap();
+        assert_eq!(db.grants.len(), 1);
+        assert!(db.take(&valid).is_some());
+        assert!(db.take(&expired).is_none());
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::authorization_code::PendingGrant;
+
+#[derive(Deserialize)]
+#[serde(tag = "version")]
+enum StorageEnvelope {
+    #[serde(rename = "1")]
+    V1 {
+        grants: HashMap<String, PendingGrant>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "version")]
+pub(super) enum StorageRef<'a> {
+    #[serde(rename = "1")]
+    V1 {
+        grants: &'a HashMap<String, PendingGrant>,
+    },
+}
+
+impl<'a> StorageRef<'a> {
+    pub(super) fn new(grants: &'a HashMap<String, PendingGrant>) -> Self {
+        Self::V1 { grants }
+    }
+}
+
+/// Pending grants keyed by the digest of their authorization code
+#[derive(Default)]
+pub(super) struct AuthorizationCodeStorage(pub(super) HashMap<String, PendingGrant>);
+
+impl From<AuthorizationCodeStorage> for HashMap<String, PendingGrant> {
+    fn from(value: AuthorizationCodeStorage) -> Self {
+        value.0
+    }
+}
+
+impl<'de> Deserialize<'de> for AuthorizationCodeStorage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        if value.get("version").is_some() {
+            let envelope: StorageEnvelope =
+                serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+            return Ok(match envelope {
+                StorageEnvelope::V1 { grants } => AuthorizationCodeStorage(grants),
+            });
+        }
+
+        Err(serde::de::Error::custom(
+            "unexpected format for authorization code database",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_version_fails() {
+        let json = serde_json::json!({
+            "version": "999",
+            "grants": {}
+        });
+        let result = serde_json::from_value::<AuthorizationCodeStorage>(json);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn unexpected_format_fails() {
+        let json = serde_json::json!("just a string");
+        let result = serde_json::from_value::<AuthorizationCodeStorage>(json);
+        assert!(result.is_err());
+    }
+}
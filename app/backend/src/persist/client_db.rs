@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use tracing::error;
 
 use crate::model::client::{Client, ClientId};
+use crate::model::group::GroupId;
 
 mod versioning;
 
@@ -13,6 +14,16 @@ pub enum InsertClientError {
     DuplicateName(String),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateClientError {
+    #[error("Client with id {0} does not exist")]
+    NotFound(ClientId),
+    #[error("Client with id {0} is read-only")]
+    ReadOnly(ClientId),
+    #[error("Client with name '{0}' already exists")]
+    DuplicateName(String),
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum RemoveClientError {
     #[error("Client with id {0} does not exist")]
@@ -83,6 +94,27 @@ impl ClientDB {
         Ok(id)
     }
 
+    pub fn update(
+        &mut self,
+        id: ClientId,
+        name: String,
+        groups: HashSet<GroupId>,
+    ) -> Result<(), UpdateClientError> {
+        if self.read_only.contains(&id) {
+            return Err(UpdateClientError::ReadOnly(id));
+        }
+        if self.clients.values().any(|c| c.name == name && c.id != id) {
+            return Err(UpdateClientError::DuplicateName(name));
+        }
+        let client = self
+            .clients
+            .get_mut(&id)
+            .ok_or(UpdateClientError::NotFound(id))?;
+        client.name = name;
+        client.groups = groups;
+        Ok(())
+    }
+
     pub fn remove(&mut self, id: ClientId) -> Result<(), RemoveClientError> {
         if self.read_only.contains(&id) {
             return Err(RemoveClientError::ReadOnly(id));
@@ -93,6 +125,17 @@ impl ClientDB {
             .ok_or(RemoveClientError::NotFound(id))
     }
 
+    /// Take the group away from every mutable client that has it, returns how
+    /// many clients had it
+    pub fn remove_group_from_all(&mut self, group: &GroupId) -> usize {
+        self.clients
+            .iter_mut()
+            .filter(|(id, _)| !self.read_only.contains(id))
+            .map(|(_, client)| client.groups.remove(group))
+            .filter(|removed| *removed)
+            .count()
+    }
+
     pub fn save(&self) -> anyhow::Result<()> {
         let mutable_clients: HashMap<ClientId, &Client> = self
             .clients
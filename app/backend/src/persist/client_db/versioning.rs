@@ -83,6 +83,7 @@ mod tests {
             },
             groups: HashSet::new(),
             created_at: chrono::Utc::now(),
+            registration: None,
         }
     }
 
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use oxide_auth::primitives::scope::Scope;
+use tracing::error;
+
+use crate::model::consent::Consent;
+use crate::model::oauth_client::OAuthClientId;
+use crate::model::user::UserId;
+
+mod versioning;
+
+type ConsentKey = (UserId, OAuthClientId);
+
+pub struct ConsentDB {
+    path: PathBuf,
+    consents: HashMap<ConsentKey, Consent>,
+}
+
+impl ConsentDB {
+    pub(super) fn new(path: PathBuf) -> anyhow::Result<Self> {
+        let consents: versioning::ConsentStorage = super::load_from_file(path.as_path())?;
+        Ok(ConsentDB {
+            path,
+            consents: consents.into(),
+        })
+    }
+
+    pub fn query(&self, uid: UserId, client_id: &str) -> Option<&Consent> {
+        self.consents.get(&(uid, client_id.to_string()))
+    }
+
+    pub fn query_by_user(&self, uid: UserId) -> impl Iterator<Item = &Consent> {
+        self.consents
+            .values()
+            .filter(move |consent| consent.uid == uid)
+    }
+
+    /// Remember the consent of a user, extending what they consented to before
+    pub fn grant(&mut self, uid: UserId, client_id: OAuthClientId, scope: &Scope) {
+        let key = (uid, client_id.clone());
+        let scope = match self.consents.get(&key) {
+            Some(previous) => previous
+                .scope
+                .iter()
+                .chain(scope.iter())
+                .collect::<Vec<_>>()
+                .join(" ")
+                .parse()
+                .unwrap_or_else(|_| scope.clone()),
+            None => scope.clone(),
+        };
+        self.consents.insert(
+            key,
+            Consent {
+                uid,
+                client_id,
+                scope,
+                granted_at: chrono::Utc::now(),
+            },
+        );
+    }
+
+    pub fn revoke(&mut self, uid: UserId, client_id: &str) -> bool {
+        self.consents
+            .remove(&(uid, client_id.to_string()))
+            .is_some()
+    }
+
+    /// Returns the number of revoked consents
+    pub fn revoke_user(&mut self, uid: UserId) -> usize {
+        let count = self.consents.len();
+        self.consents
+            .retain(|(consent_uid, _), _| *consent_uid != uid);
+        count - self.consents.len()
+    }
+
+    /// Returns the number of revoked consents
+    pub fn revoke_client(&mut self, client_id: &str) -> usize {
+        let count = self.consents.len();
+        self.consents
+            .retain(|(_, consent_client_id), _| consent_client_id != client_id);
+        count - self.consents.len()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        super::save_to_file(&self.path, &versioning::StorageRef::new(&self.consents))
+    }
+}
+
+impl Drop for ConsentDB {
+    fn drop(&mut self) {
+        self.save()
+            .unwrap_or_else(|e| error!("Could not persist consent database: {e}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_db() -> ConsentDB {
+        ConsentDB {
+            path: PathBuf::new(),
+            consents: HashMap::new(),
+        }
+    }
+
+    fn scope(s: &str) -> Scope {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn grant_extends_previous_consent() {
+        let mut db = make_db();
+        db.grant(1, "app".to_string(), &scope("openid"));
+        db.grant(1, "app".to_string(), &scope("tech.flecs.operator"));
+        let consent = db.query(1, "app").unwrap();
+        assert!(consent.covers(&scope("openid tech.flecs.operator")));
+        assert!(db.query(2, "app").is_none());
+    }
+
+    #[test]
+    fn revoke_user_and_client() {
+        let mut db = make_db();
+        db.grant(1, "app".to_string(), &scope("openid"));
+        db.grant(1, "other".to_string(), &scope("openid"));
+        db.grant(2, "app".to_string(), &scope("openid"));
+
+        assert_eq!(db.revoke_client("app"), 2);
+        assert!(db.query(1, "other").is_some());
+        assert_eq!(db.revoke_user(1), 1);
+        assert_eq!(db.query_by_user(1).count(), 0);
+        assert!(!db.revoke(1, "other"));
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::ConsentKey;
+use crate::model::consent::Consent;
+
+#[derive(Deserialize)]
+#[serde(tag = "version")]
+enum StorageEnvelope {
+    #[serde(rename = "1")]
+    V1 { consents: Vec<Consent> },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "version")]
+pub(super) enum StorageRef<'a> {
+    #[serde(rename = "1")]
+    V1 { consents: Vec<&'a Consent> },
+}
+
+impl<'a> StorageRef<'a> {
+    pub(super) fn new(consents: &'a HashMap<ConsentKey, Consent>) -> Self {
+        Self::V1 {
+            consents: consents.values().collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct ConsentStorage(pub(super) HashMap<ConsentKey, Consent>);
+
+impl From<ConsentStorage> for HashMap<ConsentKey, Consent> {
+    fn from(value: ConsentStorage) -> Self {
+        value.0
+    }
+}
+
+fn vec_to_map(consents: Vec<Consent>) -> HashMap<ConsentKey, Consent> {
+    consents
+        .into_iter()
+        .map(|c| ((c.uid, c.client_id.clone()), c))
+        .collect()
+}
+
+impl<'de> Deserialize<'de> for ConsentStorage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        if value.get("version").is_some() {
+            let envelope: StorageEnvelope =
+                serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+            return Ok(match envelope {
+                StorageEnvelope::V1 { consents } => ConsentStorage(vec_to_map(consents)),
+            });
+        }
+
+        Err(serde::de::Error::custom(
+            "unexpected format for consent database",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_roundtrip_via_storage_ref() {
+        let consent = Consent {
+            uid: 1,
+            client_id: "app".to_string(),
+            scope: "openid tech.flecs.operator".parse().unwrap(),
+            granted_at: chrono::Utc::now(),
+        };
+        let consents = HashMap::from([((1, "app".to_string()), consent)]);
+
+        let storage = StorageRef::new(&consents);
+        let json = serde_json::to_value(&storage).unwrap();
+        assert_eq!(json["version"], "1");
+        assert!(json["consents"][0]["scope"].is_string());
+
+        let wrapper: ConsentStorage = serde_json::from_value(json).unwrap();
+        assert!(wrapper.0.contains_key(&(1, "app".to_string())));
+    }
+
+    #[test]
+    fn unexpected_format_fails() {
+        let json = serde_json::json!("just a string");
+        let result = serde_json::from_value::<ConsentStorage>(json);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tracing::{error, warn};
+
+use crate::model::failed_attempt::{Account, FailedAttempt, Throttle, ThrottlePolicy};
+
+mod versioning;
+
+/// Failed attempts that are kept for admins, older ones are dropped
+const MAX_FAILED_ATTEMPTS: usize = 1000;
+
+/// Consecutive failed attempts to guess the password of users or the secret
+/// of clients, per account and per address they came from
+pub struct FailedAttemptDB {
+    path: PathBuf,
+    policy: ThrottlePolicy,
+    users: HashMap<String, Throttle>,
+    clients: HashMap<String, Throttle>,
+    addresses: HashMap<IpAddr, Throttle>,
+    failed_attempts: VecDeque<FailedAttempt>,
+    /// Attempts whose credentials are being verified, see [`PendingAttempt`]
+    pending_accounts: HashMap<Account, u32>,
+    pending_addresses: HashMap<IpAddr, u32>,
+}
+
+impl FailedAttemptDB {
+    pub fn new(path: PathBuf, policy: ThrottlePolicy) -> anyhow::Result<Self> {
+        let storage: versioning::FailedAttemptStorage = super::load_from_file(path.as_path())?;
+        let mut db = FailedAttemptDB {
+            path,
+            policy,
+            users: storage.users,
+            clients: storage.clients,
+            addresses: storage.addresses,
+            failed_attempts: storage.failed_attempts,
+            pending_accounts: HashMap::new(),
+            pending_addresses: HashMap::new(),
+        };
+        db.remove_expired();
+        Ok(db)
+    }
+
+    pub fn policy(&self) -> &ThrottlePolicy {
+        &self.policy
+    }
+
+    fn throttles(&self, account: &Account) -> &HashMap<String, Throttle> {
+        match account {
+            Account::User { .. } => &self.users,
+            Account::Client { .. } => &self.clients,
+        }
+    }
+
+    fn throttles_mut(&mut self, account: &Account) -> &mut HashMap<String, Throttle> {
+        match account {
+            Account::User { .. } => &mut self.users,
+            Account::Client { .. } => &mut self.clients,
+        }
+    }
+
+    pub fn query_throttle(&self, account: &Account) -> Option<&Throttle> {
+        self.throttles(account)
+            .get(account.id())
+            .filter(|throttle| !throttle.is_expired(&self.policy))
+    }
+
+    /// Time until the account may be tried again from the address, if either
+    /// of them is blocked. Pending attempts are counted as if they failed.
+    pub fn retry_after(
+        &self,
+        account: &Account,
+        ip_address: Option<IpAddr>,
+    ) -> Option<chrono::Duration> {
+        let policy = &self.policy;
+        let account = self
+            .query_throttle(account)
+            .cloned()
+            .unwrap_or_default()
+            .retry_after_pending(
+                policy,
+                policy.account_threshold,
+                self.pending_accounts.get(account).copied().unwrap_or(0),
+            );
+        let address = ip_address.and_then(|ip| {
+            self.addresses
+                .get(&ip)
+                .cloned()
+                .unwrap_or_default()
+                .retry_after_pending(
+                    policy,
+                    policy.address_threshold,
+                    self.pending_addresses.get(&ip).copied().unwrap_or(0),
+                )
+        });
+        account.max(address)
+    }
+
+    /// Reserve an attempt of the account from the address, unless either of
+    /// them is blocked
+    fn begin_attempt(
+        &mut self,
+        account: &Account,
+        ip_address: Option<IpAddr>,
+    ) -> Result<(), chrono::Duration> {
+        if let Some(retry_after) = self.retry_after(account, ip_address) {
+            return Err(retry_after);
+        }
+        *self.pending_accounts.entry(account.clone()).or_default() += 1;
+        if let Some(ip) = ip_address {
+            *self.pending_addresses.entry(ip).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    fn end_attempt(&mut self, account: &Account, ip_address: Option<IpAddr>) {
+        release(&mut self.pending_accounts, account);
+        if let Some(ip) = ip_address {
+            release(&mut self.pending_addresses, &ip);
+        }
+    }
+
+    /// Count the attempt against its account and `address` and keep it for
+    /// admins to review
+    pub fn record_failure(&mut self, attempt: FailedAttempt, address: Option<IpAddr>) {
+        let policy = self.policy;
+        let throttle = self
+            .throttles_mut(&attempt.account)
+            .entry(attempt.account.id().to_string())
+            .or_default();
+        throttle.fail(&policy, policy.account_threshold);
+        if throttle.is_locked(policy.account_threshold) {
+            warn!(
+                "Locked {:?} after {} failed attempts",
+                attempt.account,
+                throttle.failures()
+            );
+        }
+        if let Some(ip) = address {
+            let throttle = self.addresses.entry(ip).or_default();
+            throttle.fail(&policy, policy.address_threshold);
+            if throttle.is_locked(policy.address_threshold) {
+                warn!("Locked {ip} after {} failed attempts", throttle.failures());
+            }
+        }
+        self.failed_attempts.push_back(attempt);
+        while self.failed_attempts.len() > MAX_FAILED_ATTEMPTS {
+            self.failed_attempts.pop_front();
+        }
+    }
+
+    /// Forget the failures of the account, but not of the address, so that
+    /// one known password does not allow guessing others. Returns whether
+    /// the account had any failures.
+    pub fn record_success(&mut self, account: &Account) -> bool {
+        self.unlock(account)
+    }
+
+    /// Returns whether the account had any failures
+    pub fn unlock(&mut self, account: &Account) -> bool {
+        self.throttles_mut(account).remove(account.id()).is_some()
+    }
+
+    /// Most recent failed attempts first
+    pub fn failed_attempts(&self) -> impl Iterator<Item = &FailedAttempt> {
+        self.failed_attempts.iter().rev()
+    }
+
+    pub fn remove_expired(&mut self) {
+        let policy = self.policy;
+        self.users
+            .retain(|_, throttle| !throttle.is_expired(&policy));
+        self.clients
+            .retain(|_, throttle| !throttle.is_expired(&policy));
+        self.addresses
+            .retain(|_, throttle| !throttle.is_expired(&policy));
+    }
+
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        self.remove_expired();
+        super::save_to_file(
+            &self.path,
+            &versioning::StorageRef::new(
+                &self.users,
+                &self.clients,
+                &self.addresses,
+                &self.failed_attempts,
+            ),
+        )
+    }
+}
+
+fn release<K: std::hash::Hash + Eq>(pending: &mut HashMap<K, u32>, key: &K) {
+    if let Some(count) = pending.get_mut(key) {
+        *count -= 1;
+        if *count == 0 {
+            pending.remove(key);
+        }
+    }
+}
+
+impl Drop for FailedAttemptDB {
+    fn drop(&mut self) {
+        self.save()
+            .unwrap_or_else(|e| error!("Could not persist failed attempt database: {e}"));
+    }
+}
+
+/// Attempt to authenticate whose credentials are being verified. It counts
+/// as failed for the throttle until its outcome is recorded, so that
+/// concurrent guesses can not all pass the check before the first one fails.
+/// Dropping it without an outcome releases it.
+pub struct PendingAttempt<'a> {
+    db: &'a Mutex<FailedAttemptDB>,
+    attempt: Option<FailedAttempt>,
+    address: Option<IpAddr>,
+}
+
+impl<'a> PendingAttempt<'a> {
+    /// Returns the time until the account may be tried again from `address`,
+    /// if either of them is blocked. Attempts are only throttled by an
+    /// address that identifies the client, which may be none even if the
+    /// attempt records one.
+    pub fn begin(
+        db: &'a Mutex<FailedAttemptDB>,
+        attempt: FailedAttempt,
+        address: Option<IpAddr>,
+    ) -> Result<Self, chrono::Duration> {
+        db.lock()
+            .unwrap()
+            .begin_attempt(&attempt.account, address)?;
+        Ok(Self {
+            db,
+            attempt: Some(attempt),
+            address,
+        })
+    }
+
+    /// Count the attempt against its account and address
+    pub fn fail(mut self) {
+        let Some(attempt) = self.attempt.take() else {
+            return;
+        };
+        let mut db = self.db.lock().unwrap();
+        db.end_attempt(&attempt.account, self.address);
+        db.record_failure(attempt, self.address);
+        if let Err(e) = db.save() {
+            error!("Could not persist failed attempt database: {e}");
+        }
+    }
+
+    /// Forget the failures of the account
+    pub fn succeed(mut self) {
+        let Some(attempt) = self.attempt.take() else {
+            return;
+        };
+        let mut db = self.db.lock().unwrap();
+        db.end_attempt(&attempt.account, self.address);
+        if db.record_success(&attempt.account)
+            && let Err(e) = db.save()
+        {
+            error!("Could not persist failed attempt database: {e}");
+        }
+    }
+}
+
+impl Drop for PendingAttempt<'_> {
+    fn drop(&mut self) {
+        if let Some(attempt) = self.attempt.take()
+            && let Ok(mut db) = self.db.lock()
+        {
+            db.end_attempt(&attempt.account, self.address);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: ThrottlePolicy = ThrottlePolicy {
+        account_threshold: 3,
+        address_threshold: 5,
+        lockout: chrono::Duration::minutes(15),
+        backoff: chrono::Duration::zero(),
+    };
+
+    fn make_db() -> FailedAttemptDB {
+        FailedAttemptDB {
+            path: PathBuf::new(),
+            policy: POLICY,
+            users: HashMap::new(),
+            clients: HashMap::new(),
+            addresses: HashMap::new(),
+            failed_attempts: VecDeque::new(),
+            pending_accounts: HashMap::new(),
+            pending_addresses: HashMap::new(),
+        }
+    }
+
+    fn user(username: &str) -> Account {
+        Account::User {
+            username: username.to_string(),
+        }
+    }
+
+    fn fail(db: &mut FailedAttemptDB, account: Account, ip: &str) {
+        let ip = Some(ip.parse().unwrap());
+        db.record_failure(FailedAttempt::new(account, ip, None), ip);
+    }
+
+    #[test]
+    fn accounts_are_locked_after_threshold() {
+        let mut db = make_db();
+        for _ in 0..2 {
+            fail(&mut db, user("alice"), "10.0.0.1");
+        }
+        assert!(db.retry_after(&user("alice"), None).is_none());
+        fail(&mut db, user("alice"), "10.0.0.2");
+        assert!(db.retry_after(&user("alice"), None).is_some());
+        assert!(db.retry_after(&user("bob"), None).is_none());
+        assert_eq!(db.failed_attempts().count(), 3);
+
+        assert!(db.unlock(&user("alice")));
+        assert!(db.retry_after(&user("alice"), None).is_none());
+        assert!(!db.unlock(&user("alice")));
+    }
+
+    #[test]
+    fn addresses_are_locked_across_accounts() {
+        let mut db = make_db();
+        let ip = "10.0.0.1".parse().unwrap();
+        for i in 0..5 {
+            fail(&mut db, user(&format!("user{i}")), "10.0.0.1");
+        }
+        assert!(db.retry_after(&user("other"), Some(ip)).is_some());
+        assert!(db.retry_after(&user("other"), None).is_none());
+    }
+
+    #[test]
+    fn success_resets_account_but_not_address() {
+        let mut db = make_db();
+        let ip = "10.0.0.1".parse().unwrap();
+        for _ in 0..2 {
+            fail(&mut db, user("alice"), "10.0.0.1");
+        }
+        db.record_success(&user("alice"));
+        assert!(db.query_throttle(&user("alice")).is_none());
+        assert_eq!(db.addresses[&ip].failures(), 2);
+    }
+
+    #[test]
+    fn users_and_clients_are_separate() {
+        let mut db = make_db();
+        let client = Account::Client {
+            client_id: "alice".to_string(),
+        };
+        for _ in 0..3 {
+            fail(&mut db, client.clone(), "10.0.0.1");
+        }
+        assert!(db.retry_after(&client, None).is_some());
+        assert!(db.retry_after(&user("alice"), None).is_none());
+    }
+
+    fn begin<'a>(
+        db: &'a Mutex<FailedAttemptDB>,
+        account: Account,
+        ip: Option<IpAddr>,
+    ) -> Result<PendingAttempt<'a>, chrono::Duration> {
+        PendingAttempt::begin(db, FailedAttempt::new(account, ip, None), ip)
+    }
+
+    #[test]
+    fn pending_attempts_block_concurrent_guesses() {
+        let db = Mutex::new(make_db());
+        let ip = Some("10.0.0.1".parse().unwrap());
+        let first = begin(&db, user("alice"), ip).unwrap();
+        let second = begin(&db, user("alice"), ip).unwrap();
+        // Attempts dropped without an outcome are released
+        assert!(begin(&db, user("alice"), None).is_ok());
+        let third = begin(&db, user("alice"), ip).unwrap();
+        // Alice is locked if all three fail
+        assert!(begin(&db, user("alice"), None).is_err());
+
+        first.fail();
+        second.fail();
+        assert!(begin(&db, user("alice"), None).is_err());
+        third.succeed();
+        let db = db.into_inner().unwrap();
+        assert!(db.retry_after(&user("alice"), ip).is_none());
+        assert!(db.pending_accounts.is_empty());
+        assert!(db.pending_addresses.is_empty());
+    }
+
+    #[test]
+    fn attempts_without_address_only_count_against_account() {
+        let db = Mutex::new(make_db());
+        let ip = Some("10.0.0.1".parse().unwrap());
+        for i in 0..5 {
+            let attempt = FailedAttempt::new(user(&format!("user{i}")), ip, None);
+            PendingAttempt::begin(&db, attempt, None).unwrap().fail();
+        }
+        let db = db.into_inner().unwrap();
+        assert!(db.retry_after(&user("other"), ip).is_none());
+        assert!(db.addresses.is_empty());
+        assert_eq!(db.failed_attempts().next().unwrap().ip_address, ip);
+    }
+
+    #[test]
+    fn only_recent_failed_attempts_are_kept() {
+        let mut db = make_db();
+        for i in 0..MAX_FAILED_ATTEMPTS + 10 {
+            fail(&mut db, user(&format!("user{i}")), "10.0.0.1");
+        }
+        assert_eq!(db.failed_attempts().count(), MAX_FAILED_ATTEMPTS);
+        assert_eq!(
+            db.failed_attempts().next().unwrap().account,
+            user(&format!("user{}", MAX_FAILED_ATTEMPTS + 9))
+        );
+    }
+
+    #[test]
+    fn lockouts_survive_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("failed_attempts.json");
+        {
+            let mut db = FailedAttemptDB::new(path.clone(), POLICY).unwrap();
+            for _ in 0..3 {
+                fail(&mut db, user("alice"), "10.0.0.1");
+            }
+        }
+        let db = FailedAttemptDB::new(path, POLICY).unwrap();
+        assert!(db.retry_after(&user("alice"), None).is_some());
+        assert_eq!(db.failed_attempts().count(), 3);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::failed_attempt::{FailedAttempt, Throttle};
+
+#[derive(Deserialize)]
+#[serde(tag = "version")]
+enum StorageEnvelope {
+    #[serde(rename = "1")]
+    V1 {
+        users: HashMap<String, Throttle>,
+        clients: HashMap<String, Throttle>,
+        addresses: HashMap<IpAddr, Throttle>,
+        failed_attempts: VecDeque<FailedAttempt>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "version")]
+pub(super) enum StorageRef<'a> {
+    #[serde(rename = "1")]
+    V1 {
+        users: &'a HashMap<String, Throttle>,
+        clients: &'a HashMap<String, Throttle>,
+        addresses: &'a HashMap<IpAddr, Throttle>,
+        failed_attempts: &'a VecDeque<FailedAttempt>,
+    },
+}
+
+impl<'a> StorageRef<'a> {
+    pub(super) fn new(
+        users: &'a HashMap<String, Throttle>,
+        clients: &'a HashMap<String, Throttle>,
+        addresses: &'a HashMap<IpAddr, Throttle>,
+        failed_attempts: &'a VecDeque<FailedAttempt>,
+    ) -> Self {
+        Self::V1 {
+            users,
+            clients,
+            addresses,
+            failed_attempts,
+        }
+    }
+}
+
+/// Throttles by username, client id and address, and the most recent failed
+/// attempts
+#[derive(Default)]
+pub(super) struct FailedAttemptStorage {
+    pub(super) users: HashMap<String, Throttle>,
+    pub(super) clients: HashMap<String, Throttle>,
+    pub(super) addresses: HashMap<IpAddr, Throttle>,
+    pub(super) failed_attempts: VecDeque<FailedAttempt>,
+}
+
+impl<'de> Deserialize<'de> for FailedAttemptStorage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        if value.get("version").is_some() {
+            let envelope: StorageEnvelope =
+                serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+            return Ok(match envelope {
+                StorageEnvelope::V1 {
+                    users,
+                    clients,
+                    addresses,
+                    failed_attempts,
+                } => FailedAttemptStorage {
+                    users,
+                    clients,
+                    addresses,
+                    failed_attempts,
+                },
+            });
+        }
+
+        Err(serde::de::Error::custom(
+            "unexpected format for failed attempt database",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::failed_attempt::Account;
+
+    #[test]
+    fn serialize_roundtrip_via_storage_ref() {
+        let users = HashMap::from([("admin".to_string(), Throttle::new())]);
+        let clients = HashMap::new();
+        let addresses = HashMap::from([("10.0.0.1".parse().unwrap(), Throttle::new())]);
+        let failed_attempts = VecDeque::from([FailedAttempt::new(
+            Account::User {
+                username: "admin".to_string(),
+            },
+            Some("10.0.0.1".parse().unwrap()),
+            None,
+        )]);
+
+        let storage = StorageRef::new(&users, &clients, &addresses, &failed_attempts);
+        let json = serde_json::to_value(&storage).unwrap();
+        assert_eq!(json["version"], "1");
+        assert!(json["addresses"]["10.0.0.1"].is_object());
+
+        let wrapper: FailedAttemptStorage = serde_json::from_value(json).unwrap();
+        assert!(wrapper.users.contains_key("admin"));
+        assert!(wrapper.clients.is_empty());
+        assert_eq!(wrapper.addresses.len(), 1);
+        assert_eq!(
+            wrapper.failed_attempts[0].account,
+            Account::User {
+                username: "admin".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_version_fails() {
+        let json = serde_json::json!({
+            "version": "999",
+            "users": {},
+            "clients": {},
+            "addresses": {},
+            "failed_attempts": []
+        });
+        let result = serde_json::from_value::<FailedAttemptStorage>(json);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn unexpected_format_fails() {
+        let json = serde_json::json!("just a string");
+        let result = serde_json::from_value::<FailedAttemptStorage>(json);
+        assert!(result.is_err());
+    }
+}
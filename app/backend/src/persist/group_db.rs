@@ -1,13 +1,75 @@
-use crate::model::group::{Group, GroupId};
+use crate::model::group::{Group, GroupId, UpdateGroup};
 use std::{
     collections::{HashMap, HashSet},
     path::PathBuf,
 };
-use tracing::error;
+use tracing::{error, warn};
 
 mod default;
 mod versioning;
 
+#[derive(Debug, thiserror::Error)]
+pub enum InsertGroupError {
+    #[error("Group with id {0} already exists")]
+    DuplicateId(GroupId),
+    #[error("Group {0} is built-in")]
+    BuiltIn(GroupId),
+    #[error("Sub group {0} does not exist")]
+    UnknownSubGroup(GroupId),
+    #[error("Sub group {0} would create a cycle")]
+    Cycle(GroupId),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateGroupError {
+    #[error("Group with id {0} does not exist")]
+    NotFound(GroupId),
+    #[error("Group {0} is built-in")]
+    BuiltIn(GroupId),
+    #[error("Sub group {0} does not exist")]
+    UnknownSubGroup(GroupId),
+    #[error("Sub group {0} would create a cycle")]
+    Cycle(GroupId),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RemoveGroupError {
+    #[error("Group with id {0} does not exist")]
+    NotFound(GroupId),
+    #[error("Group {0} is built-in")]
+    BuiltIn(GroupId),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AddSubGroupError {
+    #[error("Group with id {0} does not exist")]
+    NotFound(GroupId),
+    #[error("Group {0} is built-in")]
+    BuiltIn(GroupId),
+    #[error("Sub group {0} does not exist")]
+    UnknownSubGroup(GroupId),
+    #[error("Sub group {0} would create a cycle")]
+    Cycle(GroupId),
+    #[error("Group already has sub group {0}")]
+    AlreadyAssigned(GroupId),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RemoveSubGroupError {
+    #[error("Group with id {0} does not exist")]
+    NotFound(GroupId),
+    #[error("Group {0} is built-in")]
+    BuiltIn(GroupId),
+    #[error("Group does not have sub group {0}")]
+    NotAssigned(GroupId),
+}
+
+/// Reasons why a group may not have some sub group
+enum SubGroupError {
+    Unknown(GroupId),
+    Cycle(GroupId),
+}
+
 pub struct GroupDB {
     path: PathBuf,
     groups: HashMap<GroupId, Group>,
@@ -20,10 +82,159 @@ impl GroupDB {
         Ok(GroupDB { path, groups })
     }
 
+    pub fn query_all(&self) -> impl Iterator<Item = &Group> {
+        self.groups.values()
+    }
+
+    pub fn query_by_id(&self, id: &GroupId) -> Option<&Group> {
+        self.groups.get(id)
+    }
+
+    /// Check that all sub groups exist and that none of them contains the
+    /// group itself, directly or through their own sub groups
+    fn check_sub_groups<'a>(
+        &self,
+        id: &GroupId,
+        sub_groups: impl IntoIterator<Item = &'a GroupId>,
+    ) -> Result<(), SubGroupError> {
+        for sub_group in sub_groups {
+            if !self.groups.contains_key(sub_group) {
+                return Err(SubGroupError::Unknown(sub_group.clone()));
+            }
+            if self
+                .query_groups_with_subgroups(std::slice::from_ref(sub_group))
+                .contains(id)
+            {
+                return Err(SubGroupError::Cycle(sub_group.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn insert(&mut self, group: Group) -> Result<(), InsertGroupError> {
+        if group.id.is_builtin() {
+            return Err(InsertGroupError::BuiltIn(group.id));
+        }
+        if self.groups.contains_key(&group.id) {
+            return Err(InsertGroupError::DuplicateId(group.id));
+        }
+        self.check_sub_groups(&group.id, &group.sub_groups)
+            .map_err(|e| match e {
+                SubGroupError::Unknown(sub_group) => InsertGroupError::UnknownSubGroup(sub_group),
+                SubGroupError::Cycle(sub_group) => InsertGroupError::Cycle(sub_group),
+            })?;
+        self.groups.insert(group.id.clone(), group);
+        Ok(())
+    }
+
+    pub fn update(&mut self, id: &GroupId, update: UpdateGroup) -> Result<(), UpdateGroupError> {
+        if !self.groups.contains_key(id) {
+            return Err(UpdateGroupError::NotFound(id.clone()));
+        }
+        if id.is_builtin() {
+            return Err(UpdateGroupError::BuiltIn(id.clone()));
+        }
+        if let Some(sub_groups) = &update.sub_groups {
+            self.check_sub_groups(id, sub_groups).map_err(|e| match e {
+                SubGroupError::Unknown(sub_group) => UpdateGroupError::UnknownSubGroup(sub_group),
+                SubGroupError::Cycle(sub_group) => UpdateGroupError::Cycle(sub_group),
+            })?;
+        }
+        let group = self
+            .groups
+            .get_mut(id)
+            .ok_or_else(|| UpdateGroupError::NotFound(id.clone()))?;
+        if let Some(name) = update.name {
+            group.name = name;
+        }
+        if let Some(description) = update.description {
+            group.description = Some(description).filter(|d| !d.is_empty());
+        }
+        if let Some(sub_groups) = update.sub_groups {
+            group.sub_groups = sub_groups;
+        }
+        Ok(())
+    }
+
+    /// Remove the group and every reference to it from other groups
+    pub fn remove(&mut self, id: &GroupId) -> Result<Group, RemoveGroupError> {
+        if !self.groups.contains_key(id) {
+            return Err(RemoveGroupError::NotFound(id.clone()));
+        }
+        if id.is_builtin() {
+            return Err(RemoveGroupError::BuiltIn(id.clone()));
+        }
+        let group = self
+            .groups
+            .remove(id)
+            .ok_or_else(|| RemoveGroupError::NotFound(id.clone()))?;
+        for other in self.groups.values_mut() {
+            other.sub_groups.remove(id);
+        }
+        Ok(group)
+    }
+
+    pub fn add_sub_group(
+        &mut self,
+        id: &GroupId,
+        sub_group: GroupId,
+    ) -> Result<(), AddSubGroupError> {
+        if !self.groups.contains_key(id) {
+            return Err(AddSubGroupError::NotFound(id.clone()));
+        }
+        if id.is_builtin() {
+            return Err(AddSubGroupError::BuiltIn(id.clone()));
+        }
+        self.check_sub_groups(id, [&sub_group])
+            .map_err(|e| match e {
+                SubGroupError::Unknown(sub_group) => AddSubGroupError::UnknownSubGroup(sub_group),
+                SubGroupError::Cycle(sub_group) => AddSubGroupError::Cycle(sub_group),
+            })?;
+        let group = self
+            .groups
+            .get_mut(id)
+            .ok_or_else(|| AddSubGroupError::NotFound(id.clone()))?;
+        if !group.sub_groups.insert(sub_group.clone()) {
+            return Err(AddSubGroupError::AlreadyAssigned(sub_group));
+        }
+        Ok(())
+    }
+
+    pub fn remove_sub_group(
+        &mut self,
+        id: &GroupId,
+        sub_group: &GroupId,
+    ) -> Result<(), RemoveSubGroupError> {
+        if !self.groups.contains_key(id) {
+            return Err(RemoveSubGroupError::NotFound(id.clone()));
+        }
+        if id.is_builtin() {
+            return Err(RemoveSubGroupError::BuiltIn(id.clone()));
+        }
+        let group = self
+            .groups
+            .get_mut(id)
+            .ok_or_else(|| RemoveSubGroupError::NotFound(id.clone()))?;
+        if !group.sub_groups.remove(sub_group) {
+            return Err(RemoveSubGroupError::NotAssigned(sub_group.clone()));
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        super::save_to_file(&self.path, &versioning::StorageRef::new(&self.groups))
+    }
+
     pub fn query_groups_with_subgroups(&self, groups: &[GroupId]) -> HashSet<GroupId> {
         let mut stack: Vec<_> = groups
             .iter()
-            .filter_map(|group| self.groups.get(group).map(|group| group.id.clone()))
+            .filter_map(|id| match self.groups.get(id) {
+                Some(group) => Some(group.id.clone()),
+                None => {
+                    warn!("Ignoring unknown group {id}");
+                    None
+                }
+            })
             .collect();
         let mut groups = HashSet::from_iter(stack.iter().cloned());
         while let Some(group_id) = stack.pop() {
@@ -43,7 +254,7 @@ impl GroupDB {
 
 impl Drop for GroupDB {
     fn drop(&mut self) {
-        super::save_to_file(&self.path, &versioning::StorageRef::new(&self.groups))
+        self.save()
             .unwrap_or_else(|e| error!("Could not persist group database: {e}"));
     }
 }
@@ -219,4 +430,170 @@ mod tests {
         let result = db.query_groups_with_subgroups(&[GroupId::admin()]);
         assert_eq!(result, HashSet::from([GroupId::admin()]));
     }
+
+    fn custom(id: &str) -> GroupId {
+        GroupId::from(id.to_string())
+    }
+
+    fn update_sub_groups(sub_groups: Vec<GroupId>) -> UpdateGroup {
+        UpdateGroup {
+            name: None,
+            description: None,
+            sub_groups: Some(HashSet::from_iter(sub_groups)),
+        }
+    }
+
+    #[test]
+    fn insert_custom_group() {
+        let mut db = make_db(vec![make_group(GroupId::operator(), vec![])]);
+        db.insert(make_group(
+            custom("line-supervisor"),
+            vec![GroupId::operator()],
+        ))
+        .unwrap();
+        let result = db.query_groups_with_subgroups(&[custom("line-supervisor")]);
+        assert_eq!(
+            result,
+            HashSet::from([custom("line-supervisor"), GroupId::operator()])
+        );
+    }
+
+    #[test]
+    fn insert_rejects_builtin_duplicate_and_unknown_sub_group() {
+        let mut db = make_db(vec![make_group(custom("a"), vec![])]);
+        assert!(matches!(
+            db.insert(make_group(custom("tech.flecs.new"), vec![])),
+            Err(InsertGroupError::BuiltIn(_))
+        ));
+        assert!(matches!(
+            db.insert(make_group(custom("a"), vec![])),
+            Err(InsertGroupError::DuplicateId(_))
+        ));
+        assert!(matches!(
+            db.insert(make_group(custom("b"), vec![custom("unknown")])),
+            Err(InsertGroupError::UnknownSubGroup(_))
+        ));
+        assert!(matches!(
+            db.insert(make_group(custom("b"), vec![custom("b")])),
+            Err(InsertGroupError::UnknownSubGroup(_))
+        ));
+        assert_eq!(db.query_all().count(), 1);
+    }
+
+    #[test]
+    fn update_rejects_cycles() {
+        let mut db = make_db(vec![
+            make_group(custom("a"), vec![custom("b")]),
+            make_group(custom("b"), vec![custom("c")]),
+            make_group(custom("c"), vec![]),
+        ]);
+        assert!(matches!(
+            db.update(&custom("c"), update_sub_groups(vec![custom("a")])),
+            Err(UpdateGroupError::Cycle(_))
+        ));
+        assert!(matches!(
+            db.update(&custom("c"), update_sub_groups(vec![custom("c")])),
+            Err(UpdateGroupError::Cycle(_))
+        ));
+        assert!(matches!(
+            db.add_sub_group(&custom("b"), custom("a")),
+            Err(AddSubGroupError::Cycle(_))
+        ));
+        assert!(db.query_by_id(&custom("c")).unwrap().sub_groups.is_empty());
+
+        // Replacing the sub groups may drop the edge that made it a cycle
+        db.update(&custom("b"), update_sub_groups(vec![])).unwrap();
+        db.add_sub_group(&custom("c"), custom("a")).unwrap();
+        assert!(matches!(
+            db.add_sub_group(&custom("c"), custom("a")),
+            Err(AddSubGroupError::AlreadyAssigned(_))
+        ));
+    }
+
+    #[test]
+    fn update_sets_fields() {
+        let mut db = make_db(vec![make_group(custom("a"), vec![])]);
+        db.update(
+            &custom("a"),
+            UpdateGroup {
+                name: Some("Line Supervisor".to_string()),
+                description: Some("Supervises a line".to_string()),
+                sub_groups: None,
+            },
+        )
+        .unwrap();
+        let group = db.query_by_id(&custom("a")).unwrap();
+        assert_eq!(group.name, "Line Supervisor");
+        assert_eq!(group.description.as_deref(), Some("Supervises a line"));
+
+        db.update(
+            &custom("a"),
+            UpdateGroup {
+                name: None,
+                description: Some(String::new()),
+                sub_groups: None,
+            },
+        )
+        .unwrap();
+        assert!(db.query_by_id(&custom("a")).unwrap().description.is_none());
+    }
+
+    #[test]
+    fn builtin_groups_are_protected() {
+        let mut db = GroupDB {
+            path: PathBuf::new(),
+            groups: super::default::default_groups(),
+        };
+        db.insert(make_group(custom("a"), vec![])).unwrap();
+        assert!(matches!(
+            db.update(&GroupId::operator(), update_sub_groups(vec![])),
+            Err(UpdateGroupError::BuiltIn(_))
+        ));
+        assert!(matches!(
+            db.remove(&GroupId::operator()),
+            Err(RemoveGroupError::BuiltIn(_))
+        ));
+        assert!(matches!(
+            db.add_sub_group(&GroupId::operator(), custom("a")),
+            Err(AddSubGroupError::BuiltIn(_))
+        ));
+        assert!(matches!(
+            db.remove_sub_group(&GroupId::operator(), &GroupId::core_operator()),
+            Err(RemoveSubGroupError::BuiltIn(_))
+        ));
+        assert!(matches!(
+            db.remove(&custom("unknown")),
+            Err(RemoveGroupError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn remove_drops_references_from_other_groups() {
+        let mut db = make_db(vec![
+            make_group(custom("a"), vec![custom("b")]),
+            make_group(custom("b"), vec![]),
+        ]);
+        db.remove(&custom("b")).unwrap();
+        assert!(db.query_by_id(&custom("b")).is_none());
+        assert!(db.query_by_id(&custom("a")).unwrap().sub_groups.is_empty());
+        assert!(matches!(
+            db.remove_sub_group(&custom("a"), &custom("b")),
+            Err(RemoveSubGroupError::NotAssigned(_))
+        ));
+    }
+
+    #[test]
+    fn groups_survive_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("groups.json");
+        {
+            let mut db = GroupDB::new(path.clone()).unwrap();
+            db.insert(make_group(custom("a"), vec![GroupId::operator()]))
+                .unwrap();
+            db.save().unwrap();
+        }
+        let db = GroupDB::new(path).unwrap();
+        assert!(db.query_by_id(&custom("a")).is_some());
+        assert!(db.query_by_id(&GroupId::admin()).is_some());
+    }
 }
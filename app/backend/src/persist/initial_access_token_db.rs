@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tracing::error;
+
+use crate::model::initial_access_token::InitialAccessToken;
+use crate::model::opaque_token::digest;
+
+mod versioning;
+
+/// Initial access tokens by their digest
+pub struct InitialAccessTokenDB {
+    path: PathBuf,
+    tokens: HashMap<String, InitialAccessToken>,
+}
+
+impl InitialAccessTokenDB {
+    pub(super) fn new(path: PathBuf) -> anyhow::Result<Self> {
+        let tokens: versioning::InitialAccessTokenStorage = super::load_from_file(path.as_path())?;
+        let mut tokens: HashMap<String, InitialAccessToken> = tokens.into();
+        tokens.retain(|_, token| !token.is_expired());
+        Ok(InitialAccessTokenDB { path, tokens })
+    }
+
+    pub fn insert(&mut self, token: InitialAccessToken) {
+        self.tokens.insert(token.digest.clone(), token);
+    }
+
+    pub fn query(&self, token: &str) -> Option<&InitialAccessToken> {
+        self.tokens
+            .get(&digest(token))
+            .filter(|token| !token.is_expired())
+    }
+
+    /// Remove and return the token, tokens can only be redeemed once
+    pub fn redeem(&mut self, token: &str) -> Option<InitialAccessToken> {
+        self.tokens
+            .remove(&digest(token))
+            .filter(|token| !token.is_expired())
+    }
+
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        self.tokens.retain(|_, token| !token.is_expired());
+        super::save_to_file(&self.path, &versioning::StorageRef::new(&self.tokens))
+    }
+}
+
+impl Drop for InitialAccessTokenDB {
+    fn drop(&mut self) {
+        self.save()
+            .unwrap_or_else(|e| error!("Could not persist initial access token database: {e}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn make_db() -> InitialAccessTokenDB {
+        InitialAccessTokenDB {
+            path: PathBuf::new(),
+            tokens: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn token_can_be_redeemed_once() {
+        let mut db = make_db();
+        let (initial_access_token, token) =
+            InitialAccessToken::new(HashSet::new(), chrono::Duration::hours(1));
+        db.insert(initial_access_token);
+        assert!(db.redeem("unknown").is_none());
+        assert!(db.query(&token).is_some());
+        assert!(db.redeem(&token).is_some());
+        assert!(db.query(&token).is_none());
+        assert!(db.redeem(&token).is_none());
+    }
+
+    #[test]
+    fn expired_token_cannot_be_redeemed() {
+        let mut db = make_db();
+        let (mut initial_access_token, token) =
+            InitialAccessToken::new(HashSet::new(), chrono::Duration::hours(1));
+        initial_access_token.expires_at = chrono::Utc::now() - chrono::Duration::seconds(1);
+        db.insert(initial_access_token);
+        assert!(db.redeem(&token).is_none());
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::initial_access_token::InitialAccessToken;
+
+#[derive(Deserialize)]
+#[serde(tag = "version")]
+enum StorageEnvelope {
+    #[serde(rename = "1")]
+    V1 { tokens: Vec<InitialAccessToken> },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "version")]
+pub(super) enum StorageRef<'a> {
+    #[serde(rename = "1")]
+    V1 { tokens: Vec<&'a InitialAccessToken> },
+}
+
+impl<'a> StorageRef<'a> {
+    pub(super) fn new(tokens: &'a HashMap<String, InitialAccessToken>) -> Self {
+        Self::V1 {
+            tokens: tokens.values().collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct InitialAccessTokenStorage(pub(super) HashMap<String, InitialAccessToken>);
+
+impl From<InitialAccessTokenStorage> for HashMap<String, InitialAccessToken> {
+    fn from(value: InitialAccessTokenStorage) -> Self {
+        value.0
+    }
+}
+
+fn vec_to_map(tokens: Vec<InitialAccessToken>) -> HashMap<String, InitialAccessToken> {
+    tokens.into_iter().map(|t| (t.digest.clone(), t)).collect()
+}
+
+impl<'de> Deserialize<'de> for InitialAccessTokenStorage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        if value.get("version").is_some() {
+            let envelope: StorageEnvelope =
+                serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+            return Ok(match envelope {
+                StorageEnvelope::V1 { tokens } => InitialAccessTokenStorage(vec_to_map(tokens)),
+            });
+        }
+
+        Err(serde::de::Error::custom(
+            "unexpected format for initial access token database",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn serialize_roundtrip_via_storage_ref() {
+        let (token, _) = InitialAccessToken::new(HashSet::new(), chrono::Duration::hours(1));
+        let digest = token.digest.clone();
+        let tokens = HashMap::from([(digest.clone(), token)]);
+
+        let storage = StorageRef::new(&tokens);
+        let json = serde_json::to_value(&storage).unwrap();
+        assert_eq!(json["version"], "1");
+        assert!(json["tokens"].is_array());
+
+        let wrapper: InitialAccessTokenStorage = serde_json::from_value(json).unwrap();
+        assert_eq!(wrapper.0.len(), 1);
+        assert!(wrapper.0.contains_key(&digest));
+    }
+
+    #[test]
+    fn unexpected_format_fails() {
+        let json = serde_json::json!("just a string");
+        let result = serde_json::from_value::<InitialAccessTokenStorage>(json);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use jsonwebtoken::jwk::JwkSet;
+use tracing::info;
+
+use crate::model::signing_key::{RetiredKey, SigningKey};
+
+mod versioning;
+
+/// Persistent storage of the keys used to sign issued tokens. A key is only
+/// generated if none has been stored yet, so tokens stay valid across restarts.
+///
+/// Only the active key signs new tokens. After a rotation the previous key is
+/// retired and kept for `retention`, the maximum lifetime of a token it signed.
+pub struct KeyDB {
+    path: PathBuf,
+    active: SigningKey,
+    retired: Vec<RetiredKey>,
+    retention: chrono::Duration,
+}
+
+impl KeyDB {
+    pub fn new(path: PathBuf, retention: chrono::Duration) -> anyhow::Result<Self> {
+        let storage: versioning::KeyStorage = super::load_from_file(path.as_path())
+            .with_context(|| format!("load signing key from '{}'", path.display()))?;
+        if let Some(keys) = storage.0 {
+            let mut db = KeyDB {
+                path,
+                active: keys.active,
+                retired: keys.retired,
+                retention,
+            };
+            if db.purge_expired() {
+                db.save()?;
+            }
+            return Ok(db);
+        }
+        let key = SigningKey::generate().context("generate signing key")?;
+        info!(
+            "Generated new signing key '{}' at {}",
+            key.kid(),
+            path.display()
+        );
+        let db = KeyDB {
+            path,
+            active: key,
+            retired: Vec::new(),
+            retention,
+        };
+        db.save()?;
+        Ok(db)
+    }
+
+    pub fn active(&self) -> &SigningKey {
+        &self.active
+    }
+
+    pub fn retired(&self) -> impl Iterator<Item = &RetiredKey> {
+        self.retired.iter()
+    }
+
+    /// All keys tokens may currently be signed with, active key first.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: std::iter::once(&self.active)
+                .chain(self.retired.iter().map(|retired| &retired.key))
+                .map(|key| key.jwk().clone())
+                .collect(),
+        }
+    }
+
+    /// Replace the active key by a newly generated one and retire the previous
+    /// key. The rotation is only applied if it could be persisted.
+    pub fn rotate(&mut self) -> anyhow::Result<&SigningKey> {
+        let key = SigningKey::generate().context("generate signing key")?;
+        let previous = std::mem::replace(&mut self.active, key);
+        self.retired.push(RetiredKey {
+            key: previous,
+            retired_at: chrono::Utc::now(),
+        });
+        if let Err(e) = self.save() {
+            let previous = self.retired.pop().expect("Retired key was just pushed");
+            self.active = previous.key;
+            return Err(e);
+        }
+        info!("Rotated signing key, '{}' is now active", self.active.kid());
+        if self.purge_expired() {
+            self.save()?;
+        }
+        Ok(&self.active)
+    }
+
+    /// Returns `true` if any retired key was removed.
+    fn purge_expired(&mut self) -> bool {
+        let now = chrono::Utc::now();
+        let retention = self.retention;
+        let count = self.retired.len();
+        self.retired.retain(|retired| {
+            let keep = retired.retired_at + retention > now;
+            if !keep {
+                info!("Dropping expired signing key '{}'", retired.key.kid());
+            }
+            keep
+        });
+        count != self.retired.len()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        super::save_private_to_file(
+            &self.path,
+            &versioning::StorageRef::new(&self.active, &self.retired),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::tempdir;
+
+    const RETENTION: chrono::Duration = chrono::Duration::days(1);
+
+    #[test]
+    fn key_is_generated_on_first_run() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("signing_key.json");
+
+        KeyDB::new(path.clone(), RETENTION)
+            .expect("Key should be generated if file does not exist yet");
+        assert!(path.exists() && path.is_file());
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn key_is_reloaded_on_restart() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("signing_key.json");
+
+        let first = KeyDB::new(path.clone(), RETENTION).unwrap();
+        let second = KeyDB::new(path, RETENTION).unwrap();
+        assert_eq!(first.active().kid(), second.active().kid());
+        assert_eq!(first.active().jwk(), second.active().jwk());
+    }
+
+    #[test]
+    fn corrupt_key_file_fails() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("signing_key.json");
+        fs::write(&path, "{\"version\": \"1\", \"key\": \"garbage\"}").unwrap();
+
+        KeyDB::new(path.clone(), RETENTION)
+            .err()
+            .expect("Corrupt key file should not be replaced");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"version\": \"1\", \"key\": \"garbage\"}"
+        );
+    }
+
+    #[test]
+    fn rotate_retires_previous_key() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("signing_key.json");
+
+        let mut db = KeyDB::new(path.clone(), RETENTION).unwrap();
+        let previous = db.active().kid().to_string();
+        let active = db.rotate().unwrap().kid().to_string();
+        assert_ne!(previous, active);
+
+        let jwks = db.jwks();
+        assert_eq!(jwks.keys.len(), 2);
+        assert!(jwks.find(&previous).is_some());
+        assert!(jwks.find(&active).is_some());
+
+        let reloaded = KeyDB::new(path, RETENTION).unwrap();
+        assert_eq!(reloaded.active().kid(), active);
+        assert_eq!(reloaded.retired().count(), 1);
+    }
+
+    #[test]
+    fn expired_retired_keys_are_dropped() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("signing_key.json");
+
+        let mut db = KeyDB::new(path.clone(), chrono::Duration::zero()).unwrap();
+        let active = db.rotate().unwrap().kid().to_string();
+        assert_eq!(db.retired().count(), 0);
+        assert_eq!(db.jwks().keys.len(), 1);
+        assert!(db.jwks().find(&active).is_some());
+    }
+}
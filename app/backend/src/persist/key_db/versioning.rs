@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::signing_key::{RetiredKey, SigningKey};
+
+#[derive(Deserialize)]
+#[serde(tag = "version")]
+enum StorageEnvelope {
+    #[serde(rename = "1")]
+    V1 { key: SigningKey },
+    #[serde(rename = "2")]
+    V2 {
+        active: SigningKey,
+        retired: Vec<RetiredKey>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "version")]
+pub(super) enum StorageRef<'a> {
+    #[serde(rename = "2")]
+    V2 {
+        active: &'a SigningKey,
+        retired: &'a [RetiredKey],
+    },
+}
+
+impl<'a> StorageRef<'a> {
+    pub(super) fn new(active: &'a SigningKey, retired: &'a [RetiredKey]) -> Self {
+        Self::V2 { active, retired }
+    }
+}
+
+pub(super) struct Keys {
+    pub(super) active: SigningKey,
+    pub(super) retired: Vec<RetiredKey>,
+}
+
+/// Empty if no key has been stored yet, i.e. on first run.
+#[derive(Default)]
+pub(super) struct KeyStorage(pub(super) Option<Keys>);
+
+impl<'de> Deserialize<'de> for KeyStorage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        if value.get("version").is_some() {
+            let envelope: StorageEnvelope =
+                serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+            return Ok(KeyStorage(Some(match envelope {
+                StorageEnvelope::V1 { key } => Keys {
+                    active: key,
+                    retired: Vec::new(),
+                },
+                StorageEnvelope::V2 { active, retired } => Keys { active, retired },
+            })));
+        }
+
+        Err(serde::de::Error::custom(
+            "unexpected format for signing key database",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_versioned_v1() {
+        let key = SigningKey::generate().unwrap();
+        let json = serde_json::json!({
+            "version": "1",
+            "key": serde_json::to_value(&key).unwrap()
+        });
+        let storage: KeyStorage = serde_json::from_value(json).unwrap();
+        let keys = storage.0.unwrap();
+        assert_eq!(keys.active.kid(), key.kid());
+        assert!(keys.retired.is_empty());
+    }
+
+    #[test]
+    fn serialize_roundtrip_via_storage_ref() {
+        let active = SigningKey::generate().unwrap();
+        let retired = vec![RetiredKey {
+            key: SigningKey::generate().unwrap(),
+            retired_at: chrono::Utc::now(),
+        }];
+
+        let storage = StorageRef::new(&active, &retired);
+        let json = serde_json::to_value(&storage).unwrap();
+        assert_eq!(json["version"], "2");
+        assert_eq!(json["active"]["kid"], active.kid());
+        assert!(json["retired"].is_array());
+
+        let wrapper: KeyStorage = serde_json::from_value(json).unwrap();
+        let keys = wrapper.0.unwrap();
+        assert_eq!(keys.active.kid(), active.kid());
+        assert_eq!(keys.retired.len(), 1);
+        assert_eq!(keys.retired[0].key.kid(), retired[0].key.kid());
+    }
+
+    #[test]
+    fn unknown_version_fails() {
+        let json = serde_json::json!({
+            "version": "999",
+            "key": {}
+        });
+        let result = serde_json::from_value::<KeyStorage>(json);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn unexpected_format_fails() {
+        let json = serde_json::json!("just a string");
+        let result = serde_json::from_value::<KeyStorage>(json);
+        assert!(result.is_err());
+    }
+}
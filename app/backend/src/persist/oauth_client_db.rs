@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tracing::error;
+
+use crate::model::oauth_client::{
+    InvalidOAuthClient, OAuthClient, OAuthClientId, UpdateOAuthClient,
+};
+
+mod default;
+mod versioning;
+
+#[derive(Debug, thiserror::Error)]
+pub enum InsertOAuthClientError {
+    #[error("OAuth client with id '{0}' already exists")]
+    DuplicateId(OAuthClientId),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateOAuthClientError {
+    #[error("OAuth client with id '{0}' does not exist")]
+    NotFound(OAuthClientId),
+    #[error(transparent)]
+    Invalid(#[from] InvalidOAuthClient),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RemoveOAuthClientError {
+    #[error("OAuth client with id '{0}' does not exist")]
+    NotFound(OAuthClientId),
+}
+
+/// Clients of the authorization code flow. Without a database file the
+/// built-in `flecs` client is registered.
+pub struct OAuthClientDB {
+    path: PathBuf,
+    clients: HashMap<OAuthClientId, OAuthClient>,
+}
+
+impl OAuthClientDB {
+    pub(super) fn new(path: PathBuf) -> anyhow::Result<Self> {
+        let clients: versioning::OAuthClientStorage = super::load_from_file(path.as_path())?;
+        Ok(OAuthClientDB {
+            path,
+            clients: clients.into(),
+        })
+    }
+
+    pub fn query_all(&self) -> impl Iterator<Item = &OAuthClient> {
+        self.clients.values()
+    }
+
+    pub fn query_by_id(&self, id: &str) -> Option<&OAuthClient> {
+        self.clients.get(id)
+    }
+
+    pub fn update(
+        &mut self,
+        id: &str,
+        update: UpdateOAuthClient,
+    ) -> Result<(), UpdateOAuthClientError> {
+        let client = self
+            .clients
+            .get_mut(id)
+            .ok_or_else(|| UpdateOAuthClientError::NotFound(id.to_string()))?;
+        Ok(client.update(update)?)
+    }
+
+    pub fn insert(&mut self, client: OAuthClient) -> Result<(), InsertOAuthClientError> {
+        if self.clients.contains_key(&client.id) {
+            return Err(InsertOAuthClientError::DuplicateId(client.id));
+        }
+        self.clients.insert(client.id.clone(), client);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<OAuthClient, RemoveOAuthClientError> {
+        self.clients
+            .remove(id)
+            .ok_or_else(|| RemoveOAuthClientError::NotFound(id.to_string()))
+    }
+
+    /// Let the built-in `flecs` client only redirect to the FLECS web UI under
+    /// the given hosts, unless it was removed
+    pub fn restrict_flecs_hosts(&mut self, hosts: &[String]) -> anyhow::Result<()> {
+        if let Some(flecs) = self.clients.get_mut(default::FLECS_CLIENT_ID) {
+            flecs.redirect_uris = default::flecs_redirect_uris(hosts)?;
+            flecs.post_logout_redirect_uris = default::flecs_post_logout_redirect_uris(hosts)?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        super::save_to_file(&self.path, &versioning::StorageRef::new(&self.clients))
+    }
+}
+
+impl Drop for OAuthClientDB {
+    fn drop(&mut self) {
+        self.save()
+            .unwrap_or_else(|e| error!("Could not persist OAuth client database: {e}"));
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use crate::model::oauth_client::{ClientType, OAuthClient, OAuthClientId};
+use crate::model::redirect_uri::RedirectUri;
+use crate::oauth::scope;
+
+pub(super) const FLECS_CLIENT_ID: &str = "flecs";
+
+/// The FLECS web UI. Its redirect uris are set from the configured hosts on
+/// startup, see [`flecs_redirect_uris`] and [`flecs_post_logout_redirect_uris`].
+pub(super) fn default_clients() -> HashMap<OAuthClientId, OAuthClient> {
+    let flecs = OAuthClient {
+        id: FLECS_CLIENT_ID.to_string(),
+        name: "FLECS".to_string(),
+        client_type: ClientType::Public,
+        redirect_uris: Vec::new(),
+        scope: scope::ALL_ROLES.parse().unwrap(),
+        first_party: true,
+        post_logout_redirect_uris: Vec::new(),
+        backchannel_logout_uri: None,
+        created_at: chrono::Utc::now(),
+    };
+    HashMap::from([(flecs.id.clone(), flecs)])
+}
+
+/// The callback of the FLECS web UI under each host, which have to be exact
+/// as the client is public and may request all roles
+pub(super) fn flecs_redirect_uris(hosts: &[String]) -> anyhow::Result<Vec<RedirectUri>> {
+    flecs_uris(hosts, "/oauth/callback")
+}
+
+/// The start page of the FLECS web UI under each host
+pub(super) fn flecs_post_logout_redirect_uris(
+    hosts: &[String],
+) -> anyhow::Result<Vec<RedirectUri>> {
+    flecs_uris(hosts, "/")
+}
+
+fn flecs_uris(hosts: &[String], path: &str) -> anyhow::Result<Vec<RedirectUri>> {
+    hosts
+        .iter()
+        .map(|host| {
+            let uri: RedirectUri = format!("https://{host}{path}").parse()?;
+            anyhow::ensure!(
+                !uri.is_pattern(),
+                "FLECS UI host '{host}' must not be a pattern"
+            );
+            Ok(uri)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flecs_redirect_uris_are_exact() {
+        let uris = flecs_redirect_uris(&["flecs.local".to_string()]).unwrap();
+        assert_eq!(uris[0].to_string(), "https://flecs.local/oauth/callback");
+        assert!(flecs_redirect_uris(&["*".to_string()]).is_err());
+        assert!(flecs_redirect_uris(&["flecs.local:*".to_string()]).is_err());
+        let uris = flecs_post_logout_redirect_uris(&["flecs.local".to_string()]).unwrap();
+        assert_eq!(uris[0].to_string(), "https://flecs.local/");
+    }
+}
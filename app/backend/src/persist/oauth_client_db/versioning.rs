@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::oauth_client::{OAuthClient, OAuthClientId};
+
+#[derive(Deserialize)]
+#[serde(tag = "version")]
+enum StorageEnvelope {
+    #[serde(rename = "1")]
+    V1 { clients: Vec<OAuthClient> },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "version")]
+pub(super) enum StorageRef<'a> {
+    #[serde(rename = "1")]
+    V1 { clients: Vec<&'a OAuthClient> },
+}
+
+impl<'a> StorageRef<'a> {
+    pub(super) fn new(clients: &'a HashMap<OAuthClientId, OAuthClient>) -> Self {
+        Self::V1 {
+            clients: clients.values().collect(),
+        }
+    }
+}
+
+pub(super) struct OAuthClientStorage(pub(super) HashMap<OAuthClientId, OAuthClient>);
+
+impl Default for OAuthClientStorage {
+    fn default() -> Self {
+        Self(super::default::default_clients())
+    }
+}
+
+impl From<OAuthClientStorage> for HashMap<OAuthClientId, OAuthClient> {
+    fn from(value: OAuthClientStorage) -> Self {
+        value.0
+    }
+}
+
+impl<'de> Deserialize<'de> for OAuthClientStorage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        if value.get("version").is_some() {
+            let envelope: StorageEnvelope =
+                serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+            return Ok(match envelope {
+                StorageEnvelope::V1 { clients } => OAuthClientStorage(
+                    clients
+                        .into_iter()
+                        .map(|client| (client.id.clone(), client))
+                        .collect(),
+                ),
+            });
+        }
+
+        Err(serde::de::Error::custom(
+            "unexpected format for OAuth client database",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_contains_flecs_client() {
+        let storage = OAuthClientStorage::default();
+        assert!(storage.0.contains_key("flecs"));
+    }
+
+    #[test]
+    fn serialize_roundtrip_via_storage_ref() {
+        let clients = super::super::default::default_clients();
+        let json = serde_json::to_value(StorageRef::new(&clients)).unwrap();
+        assert_eq!(json["version"], "1");
+        assert_eq!(json["clients"][0]["id"], "flecs");
+
+        let storage: OAuthClientStorage = serde_json::from_value(json).unwrap();
+        let flecs = &storage.0["flecs"];
+        assert!(flecs.requires_pkce());
+        assert_eq!(flecs.scope.to_string(), "admin");
+    }
+
+    #[test]
+    fn unknown_version_fails() {
+        let json = serde_json::json!({
+            "version": "999",
+            "clients": []
+        });
+        assert!(serde_json::from_value::<OAuthClientStorage>(json).is_err());
+    }
+
+    #[test]
+    fn unexpected_format_fails() {
+        let json = serde_json::json!("just a string");
+        assert!(serde_json::from_value::<OAuthClientStorage>(json).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use crate::model::policy::{PolicyRule, RoleInheritance};
+
+mod versioning;
+
+/// Changes to the casbin policy made at runtime, layered over the read-only
+/// policy shipped with fence. Rules of the shipped policy are never copied, so
+/// that updates of it still take effect.
+///
+/// Unlike the other databases this one is not saved when dropped, the file
+/// may be edited by hand and reloaded with SIGHUP.
+#[derive(Clone)]
+pub struct PolicyDB {
+    path: PathBuf,
+    added_rules: BTreeSet<PolicyRule>,
+    removed_rules: BTreeSet<PolicyRule>,
+    added_inheritances: BTreeSet<RoleInheritance>,
+    removed_inheritances: BTreeSet<RoleInheritance>,
+}
+
+impl PolicyDB {
+    pub(super) fn new(path: PathBuf) -> anyhow::Result<Self> {
+        let mut db = PolicyDB {
+            path,
+            added_rules: BTreeSet::new(),
+            removed_rules: BTreeSet::new(),
+            added_inheritances: BTreeSet::new(),
+            removed_inheritances: BTreeSet::new(),
+        };
+        db.reload()?;
+        Ok(db)
+    }
+
+    /// Replace all changes by the ones on disk
+    pub fn reload(&mut self) -> anyhow::Result<()> {
+        let storage: versioning::PolicyStorage = super::load_from_file(self.path.as_path())?;
+        self.added_rules = storage.added_rules;
+        self.removed_rules = storage.removed_rules;
+        self.added_inheritances = storage.added_inheritances;
+        self.removed_inheritances = storage.removed_inheritances;
+        Ok(())
+    }
+
+    pub fn added_rules(&self) -> impl Iterator<Item = &PolicyRule> {
+        self.added_rules.iter()
+    }
+
+    pub fn removed_rules(&self) -> impl Iterator<Item = &PolicyRule> {
+        self.removed_rules.iter()
+    }
+
+    pub fn added_inheritances(&self) -> impl Iterator<Item = &RoleInheritance> {
+        self.added_inheritances.iter()
+    }
+
+    pub fn removed_inheritances(&self) -> impl Iterator<Item = &RoleInheritance> {
+        self.removed_inheritances.iter()
+    }
+
+    /// Record a rule that is not part of the policy yet
+    pub fn add_rule(&mut self, rule: PolicyRule) {
+        if !self.removed_rules.remove(&rule) {
+            self.added_rules.insert(rule);
+        }
+    }
+
+    /// Record a rule that is part of the policy, shipped or added
+    pub fn remove_rule(&mut self, rule: PolicyRule) {
+        if !self.added_rules.remove(&rule) {
+            self.removed_rules.insert(rule);
+        }
+    }
+
+    /// Record an inheritance that is not part of the policy yet
+    pub fn add_inheritance(&mut self, inheritance: RoleInheritance) {
+        if !self.removed_inheritances.remove(&inheritance) {
+            self.added_inheritances.insert(inheritance);
+        }
+    }
+
+    /// Record an inheritance that is part of the policy, shipped or added
+    pub fn remove_inheritance(&mut self, inheritance: RoleInheritance) {
+        if !self.added_inheritances.remove(&inheritance) {
+            self.removed_inheritances.insert(inheritance);
+        }
+    }
+
+    /// Record a change and persist it. If it cannot be saved, the change is
+    /// dropped again, so that no later save or reload applies it.
+    pub fn update(&mut self, change: impl FnOnce(&mut Self)) -> anyhow::Result<()> {
+        let mut updated = self.clone();
+        change(&mut updated);
+        updated.save()?;
+        *self = updated;
+        Ok(())
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        super::save_to_file(
+            &self.path,
+            &versioning::StorageRef::new(
+                &self.added_rules,
+                &self.removed_rules,
+                &self.added_inheritances,
+                &self.removed_inheritances,
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(role: &str) -> PolicyRule {
+        PolicyRule {
+            role: role.to_string(),
+            path: "/users".to_string(),
+            method: "GET".to_string(),
+        }
+    }
+
+    #[test]
+    fn removing_added_rule_forgets_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = PolicyDB::new(dir.path().join("policy.json")).unwrap();
+        db.add_rule(rule("a"));
+        assert_eq!(db.added_rules().collect::<Vec<_>>(), [&rule("a")]);
+        db.remove_rule(rule("a"));
+        assert_eq!(db.added_rules().count(), 0);
+        assert_eq!(db.removed_rules().count(), 0);
+    }
+
+    #[test]
+    fn adding_removed_shipped_rule_restores_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = PolicyDB::new(dir.path().join("policy.json")).unwrap();
+        db.remove_rule(rule("shipped"));
+        assert_eq!(db.removed_rules().collect::<Vec<_>>(), [&rule("shipped")]);
+        db.add_rule(rule("shipped"));
+        assert_eq!(db.added_rules().count(), 0);
+        assert_eq!(db.removed_rules().count(), 0);
+    }
+
+    #[test]
+    fn changes_survive_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.json");
+        let inheritance = RoleInheritance {
+            role: "a".to_string(),
+            inherited_role: "b".to_string(),
+        };
+        let mut db = PolicyDB::new(path.clone()).unwrap();
+        db.add_rule(rule("a"));
+        db.remove_inheritance(inheritance.clone());
+        db.save().unwrap();
+
+        let mut other = PolicyDB::new(path).unwrap();
+        assert_eq!(other.added_rules().collect::<Vec<_>>(), [&rule("a")]);
+        assert_eq!(
+            other.removed_inheritances().collect::<Vec<_>>(),
+            [&inheritance]
+        );
+
+        // Unsaved changes are dropped by a reload
+        other.add_rule(rule("b"));
+        other.reload().unwrap();
+        assert_eq!(other.added_rules().count(), 1);
+    }
+
+    #[test]
+    fn failed_update_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.json");
+        let mut db = PolicyDB::new(path.clone()).unwrap();
+        // A directory in place of the temporary file makes saving fail
+        std::fs::create_dir_all(path.with_extension("tmp").join("blocker")).unwrap();
+        db.update(|db| db.add_rule(rule("a"))).unwrap_err();
+        assert_eq!(db.added_rules().count(), 0);
+
+        std::fs::remove_dir_all(path.with_extension("tmp")).unwrap();
+        db.update(|db| db.add_rule(rule("b"))).unwrap();
+        db.reload().unwrap();
+        assert_eq!(db.added_rules().collect::<Vec<_>>(), [&rule("b")]);
+    }
+}
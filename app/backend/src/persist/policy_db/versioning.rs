@@ -0,0 +1,122 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::policy::{PolicyRule, RoleInheritance};
+
+#[derive(Deserialize)]
+#[serde(tag = "version")]
+enum StorageEnvelope {
+    #[serde(rename = "1")]
+    V1 {
+        added_rules: BTreeSet<PolicyRule>,
+        removed_rules: BTreeSet<PolicyRule>,
+        added_inheritances: BTreeSet<RoleInheritance>,
+        removed_inheritances: BTreeSet<RoleInheritance>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "version")]
+pub(super) enum StorageRef<'a> {
+    #[serde(rename = "1")]
+    V1 {
+        added_rules: &'a BTreeSet<PolicyRule>,
+        removed_rules: &'a BTreeSet<PolicyRule>,
+        added_inheritances: &'a BTreeSet<RoleInheritance>,
+        removed_inheritances: &'a BTreeSet<RoleInheritance>,
+    },
+}
+
+impl<'a> StorageRef<'a> {
+    pub(super) fn new(
+        added_rules: &'a BTreeSet<PolicyRule>,
+        removed_rules: &'a BTreeSet<PolicyRule>,
+        added_inheritances: &'a BTreeSet<RoleInheritance>,
+        removed_inheritances: &'a BTreeSet<RoleInheritance>,
+    ) -> Self {
+        Self::V1 {
+            added_rules,
+            removed_rules,
+            added_inheritances,
+            removed_inheritances,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct PolicyStorage {
+    pub(super) added_rules: BTreeSet<PolicyRule>,
+    pub(super) removed_rules: BTreeSet<PolicyRule>,
+    pub(super) added_inheritances: BTreeSet<RoleInheritance>,
+    pub(super) removed_inheritances: BTreeSet<RoleInheritance>,
+}
+
+impl<'de> Deserialize<'de> for PolicyStorage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        if value.get("version").is_some() {
+            let envelope: StorageEnvelope =
+                serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+            return Ok(match envelope {
+                StorageEnvelope::V1 {
+                    added_rules,
+                    removed_rules,
+                    added_inheritances,
+                    removed_inheritances,
+                } => PolicyStorage {
+                    added_rules,
+                    removed_rules,
+                    added_inheritances,
+                    removed_inheritances,
+                },
+            });
+        }
+
+        Err(serde::de::Error::custom(
+            "unexpected format for policy database",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_roundtrip_via_storage_ref() {
+        let rules = BTreeSet::from([PolicyRule {
+            role: "line-supervisor".to_string(),
+            path: "/users".to_string(),
+            method: "GET".to_string(),
+        }]);
+        let inheritances = BTreeSet::from([RoleInheritance {
+            role: "line-supervisor".to_string(),
+            inherited_role: "tech.flecs.fence.list_users".to_string(),
+        }]);
+        let json = serde_json::to_value(StorageRef::new(
+            &rules,
+            &BTreeSet::new(),
+            &BTreeSet::new(),
+            &inheritances,
+        ))
+        .unwrap();
+        assert_eq!(json["version"], "1");
+
+        let storage: PolicyStorage = serde_json::from_value(json).unwrap();
+        assert_eq!(storage.added_rules, rules);
+        assert!(storage.removed_rules.is_empty());
+        assert!(storage.added_inheritances.is_empty());
+        assert_eq!(storage.removed_inheritances, inheritances);
+    }
+
+    #[test]
+    fn unexpected_format_fails() {
+        let json = serde_json::json!({"added_rules": []});
+        assert!(serde_json::from_value::<PolicyStorage>(json).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tracing::error;
+
+use crate::model::refresh_token::{FamilyId, TokenFamily};
+use crate::model::user::UserId;
+
+mod versioning;
+
+pub enum Lookup<'a> {
+    /// The token is the current token of its family
+    Valid(&'a TokenFamily),
+    /// The token was already exchanged for a new one
+    Reused(FamilyId),
+    Unknown,
+}
+
+pub struct RefreshTokenDB {
+    path: PathBuf,
+    families: HashMap<FamilyId, TokenFamily>,
+}
+
+impl RefreshTokenDB {
+    pub(super) fn new(path: PathBuf) -> anyhow::Result<Self> {
+        let families: versioning::RefreshTokenStorage = super::load_from_file(path.as_path())?;
+        let mut families: HashMap<FamilyId, TokenFamily> = families.into();
+        families.retain(|_, family| !family.is_expired());
+        Ok(RefreshTokenDB { path, families })
+    }
+
+    pub fn insert(&mut self, family: TokenFamily) -> FamilyId {
+        let id = family.id;
+        self.families.insert(id, family);
+        id
+    }
+
+    pub fn find(&self, token: &str) -> Lookup<'_> {
+        for family in self.families.values() {
+            if family.is_current(token) {
+                if family.is_expired() {
+                    return Lookup::Unknown;
+                }
+                return Lookup::Valid(family);
+            }
+            if family.was_used(token) {
+                return Lookup::Reused(family.id);
+            }
+        }
+        Lookup::Unknown
+    }
+
+    /// Exchange the current token of a family for a new one, returns `None`
+    /// if the token is not the current token of any valid family
+    pub fn rotate(&mut self, token: &str, lifetime: chrono::Duration) -> Option<String> {
+        self.families
+            .values_mut()
+            .find(|family| family.is_current(token) && !family.is_expired())
+            .map(|family| family.rotate(lifetime))
+    }
+
+    pub fn query_by_id(&self, id: FamilyId) -> Option<&TokenFamily> {
+        self.families.get(&id)
+    }
+
+    pub fn revoke(&mut self, id: FamilyId) -> bool {
+        self.families.remove(&id).is_some()
+    }
+
+    /// Returns the number of revoked families
+    pub fn revoke_user(&mut self, uid: UserId) -> usize {
+        let count = self.families.len();
+        self.families.retain(|_, family| family.uid != uid);
+        count - self.families.len()
+    }
+
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        self.families.retain(|_, family| !family.is_expired());
+        super::save_to_file(&self.path, &versioning::StorageRef::new(&self.families))
+    }
+}
+
+impl Drop for RefreshTokenDB {
+    fn drop(&mut self) {
+        self.save()
+            .unwrap_or_else(|e| error!("Could not persist refresh token database: {e}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIFETIME: chrono::Duration = chrono::Duration::days(1);
+
+    fn make_db() -> RefreshTokenDB {
+        RefreshTokenDB {
+            path: PathBuf::new(),
+            families: HashMap::new(),
+        }
+    }
+
+    fn make_family(uid: UserId) -> (TokenFamily, String) {
+        TokenFamily::new(
+            uid,
+            "flecs".to_string(),
+            "admin".to_string(),
+            url::Url::parse("https://localhost/").unwrap(),
+            "credential".to_string(),
+            LIFETIME,
+        )
+    }
+
+    #[test]
+    fn find_current_token() {
+        let mut db = make_db();
+        let (family, token) = make_family(1);
+        let id = db.insert(family);
+        assert!(matches!(db.find(&token), Lookup::Valid(f) if f.id == id));
+        assert!(matches!(db.find("unknown"), Lookup::Unknown));
+    }
+
+    #[test]
+    fn rotated_token_is_reported_as_reused() {
+        let mut db = make_db();
+        let (family, first) = make_family(1);
+        let id = db.insert(family);
+
+        let second = db.rotate(&first, LIFETIME).unwrap();
+        assert!(matches!(db.find(&first), Lookup::Reused(f) if f == id));
+        assert!(matches!(db.find(&second), Lookup::Valid(f) if f.id == id));
+        assert!(db.rotate(&first, LIFETIME).is_none());
+    }
+
+    #[test]
+    fn revoke_user_removes_only_their_families() {
+        let mut db = make_db();
+        let (family, token_1) = make_family(1);
+        db.insert(family);
+        let (family, token_2) = make_family(2);
+        db.insert(family);
+
+        assert_eq!(db.revoke_user(1), 1);
+        assert!(matches!(db.find(&token_1), Lookup::Unknown));
+        assert!(matches!(db.find(&token_2), Lookup::Valid(_)));
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::refresh_token::{FamilyId, TokenFamily};
+
+#[derive(Deserialize)]
+#[serde(tag = "version")]
+enum StorageEnvelope {
+    #[serde(rename = "1")]
+    V1 { families: Vec<TokenFamily> },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "version")]
+pub(super) enum StorageRef<'a> {
+    #[serde(rename = "1")]
+    V1 { families: Vec<&'a TokenFamily> },
+}
+
+impl<'a> StorageRef<'a> {
+    pub(super) fn new(families: &'a HashMap<FamilyId, TokenFamily>) -> Self {
+        Self::V1 {
+            families: families.values().collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct RefreshTokenStorage(pub(super) HashMap<FamilyId, TokenFamily>);
+
+impl From<RefreshTokenStorage> for HashMap<FamilyId, TokenFamily> {
+    fn from(value: RefreshTokenStorage) -> Self {
+        value.0
+    }
+}
+
+fn vec_to_map(families: Vec<TokenFamily>) -> HashMap<FamilyId, TokenFamily> {
+    families.into_iter().map(|f| (f.id, f)).collect()
+}
+
+impl<'de> Deserialize<'de> for RefreshTokenStorage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        if value.get("version").is_some() {
+            let envelope: StorageEnvelope =
+                serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+            return Ok(match envelope {
+                StorageEnvelope::V1 { families } => RefreshTokenStorage(vec_to_map(families)),
+            });
+        }
+
+        Err(serde::de::Error::custom(
+            "unexpected format for refresh token database",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_roundtrip_via_storage_ref() {
+        let (family, _) = TokenFamily::new(
+            1,
+            "flecs".to_string(),
+            "admin".to_string(),
+            url::Url::parse("https://localhost/").unwrap(),
+            "credential".to_string(),
+            chrono::Duration::days(1),
+        );
+        let id = family.id;
+        let families = HashMap::from([(id, family)]);
+
+        let storage = StorageRef::new(&families);
+        let json = serde_json::to_value(&storage).unwrap();
+        assert_eq!(json["version"], "1");
+        assert!(json["families"].is_array());
+
+        let wrapper: RefreshTokenStorage = serde_json::from_value(json).unwrap();
+        assert_eq!(wrapper.0.len(), 1);
+        assert!(wrapper.0.contains_key(&id));
+    }
+
+    #[test]
+    fn unknown_version_fails() {
+        let json = serde_json::json!({
+            "version": "999",
+            "families": []
+        });
+        let result = serde_json::from_value::<RefreshTokenStorage>(json);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn unexpected_format_fails() {
+        let json = serde_json::json!("just a string");
+        let result = serde_json::from_value::<RefreshTokenStorage>(json);
+        assert!(result.is_err());
+    }
+}
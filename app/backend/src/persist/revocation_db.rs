@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tracing::error;
+
+mod versioning;
+
+/// Identifiers (`jti`) of access tokens that were revoked before they expired.
+/// An entry is only kept until the original expiry of its token, after that the
+/// token is rejected anyway.
+pub struct RevocationDB {
+    path: PathBuf,
+    revoked: HashMap<String, chrono::DateTime<chrono::Utc>>,
+}
+
+impl RevocationDB {
+    pub(super) fn new(path: PathBuf) -> anyhow::Result<Self> {
+        let revoked: versioning::RevocationStorage = super::load_from_file(path.as_path())?;
+        let mut db = RevocationDB {
+            path,
+            revoked: revoked.into(),
+        };
+        db.prune();
+        Ok(db)
+    }
+
+    pub fn revoke(&mut self, jti: String, expires_at: chrono::DateTime<chrono::Utc>) {
+        self.revoked.insert(jti, expires_at);
+    }
+
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        self.revoked.contains_key(jti)
+    }
+
+    /// Drop all entries whose token expired, returns the number of dropped entries
+    pub fn prune(&mut self) -> usize {
+        let now = chrono::Utc::now();
+        let count = self.revoked.len();
+        self.revoked.retain(|_, expires_at| *expires_at > now);
+        count - self.revoked.len()
+    }
+
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        self.prune();
+        super::save_to_file(&self.path, &versioning::StorageRef::new(&self.revoked))
+    }
+}
+
+impl Drop for RevocationDB {
+    fn drop(&mut self) {
+        self.save()
+            .unwrap_or_else(|e| error!("Could not persist token revocation database: {e}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_db() -> RevocationDB {
+        RevocationDB {
+            path: PathBuf::new(),
+            revoked: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn revoked_token_is_revoked() {
+        let mut db = make_db();
+        db.revoke(
+            "jti".to_string(),
+            chrono::Utc::now() + chrono::Duration::hours(1),
+        );
+        assert!(db.is_revoked("jti"));
+        assert!(!db.is_revoked("other"));
+    }
+
+    #[test]
+    fn prune_drops_expired_entries() {
+        let mut db = make_db();
+        db.revoke(
+            "expired".to_string(),
+            chrono::Utc::now() - chrono::Duration::seconds(1),
+        );
+        db.revoke(
+            "valid".to_string(),
+            chrono::Utc::now() + chrono::Duration::hours(1),
+        );
+        assert_eq!(db.prune(), 1);
+        assert!(!db.is_revoked("expired"));
+        assert!(db.is_revoked("valid"));
+    }
+
+    #[test]
+    fn revocations_are_reloaded() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("revoked_tokens.json");
+        {
+            let mut db = RevocationDB::new(path.clone()).unwrap();
+            db.revoke(
+                "jti".to_string(),
+                chrono::Utc::now() + chrono::Duration::hours(1),
+            );
+            db.save().unwrap();
+        }
+        let db = RevocationDB::new(path).unwrap();
+        assert!(db.is_revoked("jti"));
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+#[serde(tag = "version")]
+enum StorageEnvelope {
+    #[serde(rename = "1")]
+    V1 {
+        revoked: HashMap<String, chrono::DateTime<chrono::Utc>>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "version")]
+pub(super) enum StorageRef<'a> {
+    #[serde(rename = "1")]
+    V1 {
+        revoked: &'a HashMap<String, chrono::DateTime<chrono::Utc>>,
+    },
+}
+
+impl<'a> StorageRef<'a> {
+    pub(super) fn new(revoked: &'a HashMap<String, chrono::DateTime<chrono::Utc>>) -> Self {
+        Self::V1 { revoked }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct RevocationStorage(pub(super) HashMap<String, chrono::DateTime<chrono::Utc>>);
+
+impl From<RevocationStorage> for HashMap<String, chrono::DateTime<chrono::Utc>> {
+    fn from(value: RevocationStorage) -> Self {
+        value.0
+    }
+}
+
+impl<'de> Deserialize<'de> for RevocationStorage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        if value.get("version").is_some() {
+            let envelope: StorageEnvelope =
+                serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+            return Ok(match envelope {
+                StorageEnvelope::V1 { revoked } => RevocationStorage(revoked),
+            });
+        }
+
+        Err(serde::de::Error::custom(
+            "unexpected format for token revocation database",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_roundtrip_via_storage_ref() {
+        let revoked = HashMap::from([("jti".to_string(), chrono::Utc::now())]);
+        let json = serde_json::to_value(StorageRef::new(&revoked)).unwrap();
+        assert_eq!(json["version"], "1");
+
+        let storage: RevocationStorage = serde_json::from_value(json).unwrap();
+        assert_eq!(storage.0, revoked);
+    }
+
+    #[test]
+    fn unexpected_format_fails() {
+        let json = serde_json::json!({"revoked": {}});
+        assert!(serde_json::from_value::<RevocationStorage>(json).is_err());
+    }
+}
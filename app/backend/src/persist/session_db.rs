@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tracing::error;
+
+use crate::model::opaque_token::{digest, generate};
+use crate::model::session::{
+    LoginSession, SessionId, SessionTimeouts, TwoFactorSession, UserSession,
+};
+use crate::model::user::UserId;
+
+mod versioning;
+
+/// Login and user sessions by the digest of their sid, the sids themselves
+/// only ever live in the cookies of the users
+pub struct SessionDB {
+    path: PathBuf,
+    timeouts: SessionTimeouts,
+    login_sessions: HashMap<String, LoginSession>,
+    two_factor_sessions: HashMap<String, TwoFactorSession>,
+    user_sessions: HashMap<String, UserSession>,
+}
+
+impl SessionDB {
+    pub fn new(path: PathBuf, timeouts: SessionTimeouts) -> anyhow::Result<Self> {
+        let sessions: versioning::SessionStorage = super::load_from_file(path.as_path())?;
+        let mut db = SessionDB {
+            path,
+            timeouts,
+            login_sessions: sessions.login_sessions,
+            two_factor_sessions: sessions.two_factor_sessions,
+            user_sessions: sessions.user_sessions,
+        };
+        db.remove_expired();
+        Ok(db)
+    }
+
+    pub fn timeouts(&self) -> &SessionTimeouts {
+        &self.timeouts
+    }
+
+    /// Store the session under a new sid, which is returned
+    pub fn insert_login(&mut self, session: LoginSession) -> String {
+        let sid = generate();
+        self.login_sessions.insert(digest(&sid), session);
+        sid
+    }
+
+    /// Remove and return the login session, expired sessions are returned as
+    /// well so that the caller can tell the user about it
+    pub fn take_login(&mut self, sid: &str) -> Option<LoginSession> {
+        self.login_sessions.remove(&digest(sid))
+    }
+
+    /// Store the session under a new sid, which is returned
+    pub fn insert_two_factor(&mut self, session: TwoFactorSession) -> String {
+        let sid = generate();
+        self.two_factor_sessions.insert(digest(&sid), session);
+        sid
+    }
+
+    /// Look up an unexpired two-factor session, which stays valid until the
+    /// second factor was verified
+    pub fn two_factor_session(&self, sid: &str) -> Option<&TwoFactorSession> {
+        self.two_factor_sessions
+            .get(&digest(sid))
+            .filter(|session| !session.is_expired())
+    }
+
+    pub fn remove_two_factor(&mut self, sid: &str) -> Option<TwoFactorSession> {
+        self.two_factor_sessions.remove(&digest(sid))
+    }
+
+    /// Store the session under a new sid, which is returned
+    pub fn insert_user(&mut self, session: UserSession) -> String {
+        let sid = generate();
+        self.user_sessions.insert(digest(&sid), session);
+        sid
+    }
+
+    /// Look up an unexpired user session, expired sessions are removed
+    pub fn user_session_mut(&mut self, sid: &str) -> Option<&mut UserSession> {
+        let key = digest(sid);
+        if self
+            .user_sessions
+            .get(&key)
+            .is_some_and(|session| session.is_expired(&self.timeouts))
+        {
+            self.user_sessions.remove(&key);
+        }
+        self.user_sessions.get_mut(&key)
+    }
+
+    pub fn remove_user(&mut self, sid: &str) -> Option<UserSession> {
+        self.user_sessions.remove(&digest(sid))
+    }
+
+    /// Unexpired sessions of the user
+    pub fn query_by_user(&self, uid: UserId) -> impl Iterator<Item = &UserSession> {
+        self.user_sessions
+            .values()
+            .filter(move |session| session.get_uid() == uid && !session.is_expired(&self.timeouts))
+    }
+
+    /// Remove the session with the public id, if it belongs to the user
+    pub fn remove_by_id(&mut self, uid: UserId, id: SessionId) -> Option<UserSession> {
+        let key = self
+            .user_sessions
+            .iter()
+            .find(|(_, session)| session.get_uid() == uid && session.get_id() == id)
+            .map(|(key, _)| key.clone())?;
+        self.user_sessions.remove(&key)
+    }
+
+    /// Remove all sessions of the user except the one with the sid, returns
+    /// the number of removed sessions
+    pub fn remove_other_user_sessions(&mut self, uid: UserId, sid: &str) -> usize {
+        let keep = digest(sid);
+        let count = self.user_sessions.len();
+        self.user_sessions
+            .retain(|key, session| session.get_uid() != uid || *key == keep);
+        count - self.user_sessions.len()
+    }
+
+    /// Returns the removed sessions
+    pub fn remove_user_sessions(&mut self, uid: UserId) -> Vec<UserSession> {
+        let keys: Vec<_> = self
+            .user_sessions
+            .iter()
+            .filter(|(_, session)| session.get_uid() == uid)
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.iter()
+            .filter_map(|key| self.user_sessions.remove(key))
+            .collect()
+    }
+
+    pub fn remove_expired(&mut self) {
+        self.login_sessions
+            .retain(|_, session| !session.is_expired());
+        self.two_factor_sessions
+            .retain(|_, session| !session.is_expired());
+        self.user_sessions
+            .retain(|_, session| !session.is_expired(&self.timeouts));
+    }
+
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        self.remove_expired();
+        super::save_to_file(
+            &self.path,
+            &versioning::StorageRef::new(
+                &self.login_sessions,
+                &self.two_factor_sessions,
+                &self.user_sessions,
+            ),
+        )
+    }
+}
+
+impl Drop for SessionDB {
+    fn drop(&mut self) {
+        self.save()
+            .unwrap_or_else(|e| error!("Could not persist session database: {e}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUTS: SessionTimeouts = SessionTimeouts {
+        idle: chrono::Duration::minutes(30),
+        absolute: chrono::Duration::hours(8),
+    };
+
+    fn make_db() -> SessionDB {
+        SessionDB {
+            path: PathBuf::new(),
+            timeouts: TIMEOUTS,
+            login_sessions: HashMap::new(),
+            two_factor_sessions: HashMap::new(),
+            user_sessions: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn login_sessions_can_be_taken_once() {
+        let mut db = make_db();
+        let sid = db.insert_login(LoginSession::new("q=test".to_string()));
+        assert!(!db.login_sessions.contains_key(&sid));
+        assert_eq!(db.take_login(&sid).unwrap().get_q(), "q=test");
+        assert!(db.take_login(&sid).is_none());
+    }
+
+    #[test]
+    fn two_factor_sessions_are_found_by_sid_until_removed() {
+        let mut db = make_db();
+        let sid = db.insert_two_factor(TwoFactorSession::new(7, Some("q=test".into()), None));
+        assert!(!db.two_factor_sessions.contains_key(&sid));
+        assert_eq!(db.two_factor_session(&sid).unwrap().get_q(), Some("q=test"));
+        assert!(db.two_factor_session(&sid).is_some());
+        assert_eq!(db.remove_two_factor(&sid).unwrap().get_uid(), 7);
+        assert!(db.two_factor_session(&sid).is_none());
+    }
+
+    #[test]
+    fn user_sessions_are_found_by_sid() {
+        let mut db = make_db();
+        let sid = db.insert_user(UserSession::new(7));
+        assert!(!db.user_sessions.contains_key(&sid));
+        assert_eq!(db.user_session_mut(&sid).unwrap().get_uid(), 7);
+        assert!(db.user_session_mut("unknown").is_none());
+
+        assert_eq!(db.remove_user(&sid).unwrap().get_uid(), 7);
+        assert!(db.user_session_mut(&sid).is_none());
+    }
+
+    #[test]
+    fn sessions_are_removed_by_public_id_of_their_user_only() {
+        let mut db = make_db();
+        let sid = db.insert_user(UserSession::new(7));
+        db.insert_user(UserSession::new(7));
+        db.insert_user(UserSession::new(8));
+        let id = db.user_session_mut(&sid).unwrap().get_id();
+        assert_eq!(db.query_by_user(7).count(), 2);
+
+        assert!(db.remove_by_id(8, id).is_none());
+        assert!(db.remove_by_id(7, id).is_some());
+        assert!(db.user_session_mut(&sid).is_none());
+        assert_eq!(db.query_by_user(7).count(), 1);
+
+        let other = db.insert_user(UserSession::new(7));
+        assert_eq!(db.remove_other_user_sessions(7, &other), 1);
+        assert!(db.user_session_mut(&other).is_some());
+
+        assert_eq!(db.remove_user_sessions(7).len(), 1);
+        assert_eq!(db.query_by_user(7).count(), 0);
+        assert_eq!(db.query_by_user(8).count(), 1);
+    }
+
+    #[test]
+    fn sessions_survive_reload_without_plain_sids() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sessions.json");
+        let (login_sid, user_sid) = {
+            let mut db = SessionDB::new(path.clone(), TIMEOUTS).unwrap();
+            let login_sid = db.insert_login(LoginSession::new("q=test".to_string()));
+            let user_sid = db.insert_user(UserSession::new(7));
+            (login_sid, user_sid)
+        };
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains(&login_sid));
+        assert!(!content.contains(&user_sid));
+
+        let mut db = SessionDB::new(path, TIMEOUTS).unwrap();
+        assert!(db.take_login(&login_sid).is_some());
+        assert_eq!(db.user_session_mut(&user_sid).unwrap().get_uid(), 7);
+    }
+
+    #[test]
+    fn expired_sessions_are_dropped_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sessions.json");
+        let user_sid = {
+            let mut db = SessionDB::new(path.clone(), TIMEOUTS).unwrap();
+            db.insert_user(UserSession::new(7))
+        };
+
+        let short = SessionTimeouts {
+            idle: chrono::Duration::zero(),
+            absolute: chrono::Duration::zero(),
+        };
+        let mut db = SessionDB::new(path, short).unwrap();
+        assert!(db.user_sessions.is_empty());
+        assert!(db.user_session_mut(&user_sid).is_none());
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::session::{LoginSession, TwoFactorSession, UserSession};
+
+#[derive(Deserialize)]
+#[serde(tag = "version")]
+enum StorageEnvelope {
+    #[serde(rename = "1")]
+    V1 {
+        login_sessions: HashMap<String, LoginSession>,
+        user_sessions: HashMap<String, UserSession>,
+    },
+    #[serde(rename = "2")]
+    V2 {
+        login_sessions: HashMap<String, LoginSession>,
+        two_factor_sessions: HashMap<String, TwoFactorSession>,
+        user_sessions: HashMap<String, UserSession>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "version")]
+pub(super) enum StorageRef<'a> {
+    #[serde(rename = "2")]
+    V2 {
+        login_sessions: &'a HashMap<String, LoginSession>,
+        two_factor_sessions: &'a HashMap<String, TwoFactorSession>,
+        user_sessions: &'a HashMap<String, UserSession>,
+    },
+}
+
+impl<'a> StorageRef<'a> {
+    pub(super) fn new(
+        login_sessions: &'a HashMap<String, LoginSession>,
+        two_factor_sessions: &'a HashMap<String, TwoFactorSession>,
+        user_sessions: &'a HashMap<String, UserSession>,
+    ) -> Self {
+        Self::V2 {
+            login_sessions,
+            two_factor_sessions,
+            user_sessions,
+        }
+    }
+}
+
+/// Sessions keyed by the digest of their sid
+#[derive(Default)]
+pub(super) struct SessionStorage {
+    pub(super) login_sessions: HashMap<String, LoginSession>,
+    pub(super) two_factor_sessions: HashMap<String, TwoFactorSession>,
+    pub(super) user_sessions: HashMap<String, UserSession>,
+}
+
+impl<'de> Deserialize<'de> for SessionStorage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        if value.get("version").is_some() {
+            let envelope: StorageEnvelope =
+                serde_json::from_value(value).map_err(serde::de::Error::custom)?;
+            return Ok(match envelope {
+                StorageEnvelope::V1 {
+                    login_sessions,
+                    user_sessions,
+                } => SessionStorage {
+                    login_sessions,
+                    two_factor_sessions: HashMap::new(),
+                    user_sessions,
+                },
+                StorageEnvelope::V2 {
+                    login_sessions,
+                    two_factor_sessions,
+                    user_sessions,
+                } => SessionStorage {
+                    login_sessions,
+                    two_factor_sessions,
+                    user_sessions,
+                },
+            });
+        }
+
+        Err(serde::de::Error::custom(
+            "unexpected format for session database",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_roundtrip_via_storage_ref() {
+        let login_sessions =
+            HashMap::from([("login".to_string(), LoginSession::new("q=test".to_string()))]);
+        let two_factor_sessions = HashMap::from([(
+            "two_factor".to_string(),
+            TwoFactorSession::new(2, None, None),
+        )]);
+        let user_sessions = HashMap::from([("user".to_string(), UserSession::new(1))]);
+
+        let storage = StorageRef::new(&login_sessions, &two_factor_sessions, &user_sessions);
+        let json = serde_json::to_value(&storage).unwrap();
+        assert_eq!(json["version"], "2");
+        assert!(json["login_sessions"].is_object());
+        assert!(json["two_factor_sessions"].is_object());
+        assert!(json["user_sessions"].is_object());
+
+        let wrapper: SessionStorage = serde_json::from_value(json).unwrap();
+        assert_eq!(wrapper.login_sessions["login"].get_q(), "q=test");
+        assert_eq!(wrapper.two_factor_sessions["two_factor"].get_uid(), 2);
+        assert_eq!(wrapper.user_sessions["user"].get_uid(), 1);
+    }
+
+    #[test]
+    fn deserialize_versioned_v1() {
+        let json = serde_json::json!({
+            "version": "1",
+            "login_sessions": {},
+            "user_sessions": {
+                "user": serde_json::to_value(UserSession::new(1)).unwrap()
+            }
+        });
+        let storage: SessionStorage = serde_json::from_value(json).unwrap();
+        assert!(storage.two_factor_sessions.is_empty());
+        assert_eq!(storage.user_sessions["user"].get_uid(), 1);
+    }
+
+    #[test]
+    fn unknown_version_fails() {
+        let json = serde_json::json!({
+            "version": "999",
+            "login_sessions": {},
+            "user_sessions": {}
+        });
+        let result = serde_json::from_value::<SessionStorage>(json);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn unexpected_format_fails() {
+        let json = serde_json::json!("just a string");
+        let result = serde_json::from_value::<SessionStorage>(json);
+        assert!(result.is_err());
+    }
+}
@@ -4,6 +4,7 @@ use tracing::error;
 
 use crate::model::group::GroupId;
 use crate::model::password::{self, HashError};
+use crate::model::totp::Totp;
 use crate::model::user::{CreateUser, SUPER_ADMIN_ID, SuperAdmin, UpdateUser, User, UserId};
 
 mod versioning;
@@ -50,6 +51,12 @@ pub enum UpdateUserError {
     Password(#[from] HashError),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum SetTotpError {
+    #[error("User with id {0} does not exist")]
+    NotFound(UserId),
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum RemoveGroupError {
     #[error("User with id {0} does not exist")]
@@ -78,8 +85,10 @@ impl UserDB {
         self.users.values().find(|u| u.name == name)
     }
 
+    /// Users carry the secrets of their authenticators, so only the owner
+    /// may read the file
     pub fn save(&self) -> anyhow::Result<()> {
-        super::save_to_file(&self.path, &versioning::StorageRef::new(&self.users))
+        super::save_private_to_file(&self.path, &versioning::StorageRef::new(&self.users))
     }
 
     pub fn query_by_uid(&self, uid: UserId) -> Option<&User> {
@@ -103,18 +112,19 @@ impl UserDB {
             full_name: String::new(),
             password: password::Password::new(&create.password)?,
             groups: create.groups,
+            totp: None,
         };
         self.users.insert(id, user);
         Ok(id)
     }
 
-    pub fn remove(&mut self, uid: UserId) -> Result<(), RemoveUserError> {
+    /// Returns the removed user
+    pub fn remove(&mut self, uid: UserId) -> Result<User, RemoveUserError> {
         if uid == SUPER_ADMIN_ID {
             return Err(RemoveUserError::SuperAdmin);
         }
         self.users
             .remove(&uid)
-            .map(|_| ())
             .ok_or(RemoveUserError::NotFound(uid))
     }
 
@@ -153,6 +163,33 @@ impl UserDB {
         Ok(())
     }
 
+    /// Take the group away from every user that has it, returns how many
+    /// users had it
+    pub fn remove_group_from_all(&mut self, group: &GroupId) -> usize {
+        self.users
+            .values_mut()
+            .map(|user| user.groups.remove(group))
+            .filter(|removed| *removed)
+            .count()
+    }
+
+    /// Replace the authenticator of the user, returns the previous one
+    pub fn set_totp(
+        &mut self,
+        uid: UserId,
+        totp: Option<Totp>,
+    ) -> Result<Option<Totp>, SetTotpError> {
+        let user = self
+            .users
+            .get_mut(&uid)
+            .ok_or(SetTotpError::NotFound(uid))?;
+        Ok(std::mem::replace(&mut user.totp, totp))
+    }
+
+    pub fn totp_mut(&mut self, uid: UserId) -> Option<&mut Totp> {
+        self.users.get_mut(&uid)?.totp.as_mut()
+    }
+
     pub fn update(&mut self, uid: UserId, update: UpdateUser) -> Result<(), UpdateUserError> {
         if let Some(ref name) = update.name
             && self.users.values().any(|u| u.id != uid && u.name == *name)
@@ -187,3 +224,26 @@ impl Drop for UserDB {
             .unwrap_or_else(|e| error!("Could not persist user database: {e}"));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn users_file_is_private() {
+        use std::fs::{self, Permissions};
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.json");
+        let backup_path = path.with_extension("bak");
+        let db = UserDB::new(path.clone()).unwrap();
+        db.save().unwrap();
+        // Files written by earlier versions may be readable by others
+        fs::set_permissions(&path, Permissions::from_mode(0o644)).unwrap();
+        db.save().unwrap();
+        for path in [&path, &backup_path] {
+            let mode = fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use serde::{Deserialize, Serialize};
 use tracing::warn;
@@ -13,24 +13,49 @@ use crate::model::{
 #[serde(tag = "version")]
 enum StorageEnvelope {
     #[serde(rename = "1")]
-    V1 { users: Vec<User> },
+    V1 { users: Vec<UserV1> },
+    #[serde(rename = "2")]
+    V2 { users: Vec<User> },
 }
 
 #[derive(Serialize)]
 #[serde(tag = "version")]
 pub(super) enum StorageRef<'a> {
-    #[serde(rename = "1")]
-    V1 { users: Vec<&'a User> },
+    #[serde(rename = "2")]
+    V2 { users: Vec<&'a User> },
 }
 
 impl<'a> StorageRef<'a> {
     pub(super) fn new(users: &'a HashMap<UserId, User>) -> Self {
-        Self::V1 {
+        Self::V2 {
             users: users.values().collect(),
         }
     }
 }
 
+/// User format of version 1, without second factor
+#[derive(Deserialize)]
+struct UserV1 {
+    id: UserId,
+    name: String,
+    full_name: String,
+    password: Password,
+    groups: HashSet<GroupId>,
+}
+
+impl From<UserV1> for User {
+    fn from(user: UserV1) -> Self {
+        Self {
+            id: user.id,
+            name: user.name,
+            full_name: user.full_name,
+            password: user.password,
+            groups: user.groups,
+            totp: None,
+        }
+    }
+}
+
 /// Legacy user format: `uid` instead of `id`, no `groups` field.
 #[derive(Deserialize)]
 struct LegacyUser {
@@ -57,6 +82,7 @@ impl LegacyUser {
             full_name: self.full_name,
             password: self.password,
             groups,
+            totp: None,
         }
     }
 }
@@ -85,7 +111,10 @@ impl<'de> Deserialize<'de> for UserStorage {
             let envelope: StorageEnvelope =
                 serde_json::from_value(value).map_err(serde::de::Error::custom)?;
             return Ok(match envelope {
-                StorageEnvelope::V1 { users } => UserStorage(vec_to_map(users)),
+                StorageEnvelope::V1 { users } => {
+                    UserStorage(vec_to_map(users.into_iter().map(User::from).collect()))
+                }
+                StorageEnvelope::V2 { users } => UserStorage(vec_to_map(users)),
             });
         }
 
@@ -115,7 +144,7 @@ impl<'de> Deserialize<'de> for UserStorage {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashSet;
+    use crate::model::totp::{Totp, TotpSecret};
 
     fn test_password() -> Password {
         Password::new("TestPassword123!").unwrap()
@@ -138,7 +167,7 @@ mod tests {
         });
         let storage: UserStorage = serde_json::from_value(json).unwrap();
         assert_eq!(storage.0.len(), 1);
-        assert!(storage.0.contains_key(&0));
+        assert!(storage.0[&0].totp.is_none());
     }
 
     #[test]
@@ -185,17 +214,37 @@ mod tests {
                 full_name: "Admin".to_string(),
                 password: test_password(),
                 groups: HashSet::from([GroupId::admin()]),
+                totp: Some(Totp::new(TotpSecret::generate())),
             },
         );
 
         let storage = StorageRef::new(&users);
         let json = serde_json::to_value(&storage).unwrap();
-        assert_eq!(json["version"], "1");
+        assert_eq!(json["version"], "2");
         assert!(json["users"].is_array());
 
         let wrapper: UserStorage = serde_json::from_value(json).unwrap();
         assert_eq!(wrapper.0.len(), 1);
-        assert!(wrapper.0.contains_key(&0));
+        assert!(wrapper.0[&0].totp.is_some());
+    }
+
+    #[test]
+    fn deserialize_versioned_v2_without_totp() {
+        let password = test_password();
+        let json = serde_json::json!({
+            "version": "2",
+            "users": [
+                {
+                    "id": 1,
+                    "name": "user",
+                    "full_name": "User",
+                    "password": serde_json::to_value(&password).unwrap(),
+                    "groups": []
+                }
+            ]
+        });
+        let storage: UserStorage = serde_json::from_value(json).unwrap();
+        assert!(storage.0[&1].totp.is_none());
     }
 
     #[test]
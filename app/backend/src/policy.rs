@@ -0,0 +1,323 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use casbin::{CoreApi, MgmtApi, RbacApi};
+
+use crate::model::group::GroupId;
+use crate::model::permission::{EffectiveRoles, Permission};
+use crate::model::policy::{PolicyDecision, PolicyRule, RoleInheritance};
+use crate::persist::policy_db::PolicyDB;
+
+/// The casbin policy treats '*' as the anonymous/public role everybody has
+pub const PUBLIC_ROLE: &str = "*";
+
+/// Roles together with all roles they inherit through the casbin policy
+pub fn implicit_roles<'a>(
+    enforcer: &casbin::Enforcer,
+    roles: impl IntoIterator<Item = &'a str>,
+) -> HashSet<String> {
+    let mut implicit_roles = HashSet::new();
+    for role in roles {
+        implicit_roles.extend(enforcer.get_implicit_roles_for_user(role, None));
+        implicit_roles.insert(role.to_string());
+    }
+    implicit_roles
+}
+
+/// Resolve what the groups grant, the groups have to include their sub groups
+/// already like the roles of a token do
+pub fn effective_roles(enforcer: &casbin::Enforcer, groups: HashSet<GroupId>) -> EffectiveRoles {
+    let roles = implicit_roles(enforcer, groups.iter().map(GroupId::as_ref));
+    let permissions: BTreeSet<_> = roles
+        .iter()
+        .flat_map(|role| enforcer.get_permissions_for_user(role, None))
+        .filter_map(|rule| match rule.as_slice() {
+            [_, path, method, ..] => Some(Permission {
+                path: path.clone(),
+                method: method.clone(),
+            }),
+            _ => None,
+        })
+        .collect();
+    let roles: BTreeSet<_> = roles
+        .into_iter()
+        .filter(|role| !groups.contains(&GroupId::from(role.clone())))
+        .collect();
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+    EffectiveRoles {
+        groups,
+        roles: roles.into_iter().collect(),
+        permissions: permissions.into_iter().collect(),
+    }
+}
+
+/// Check a request like the role middleware does and explain the decision.
+/// `held` are the roles the subject holds directly, `roles` the ones its token
+/// carries and `sub_groups` the sub groups of every group to trace the chain.
+pub fn explain(
+    enforcer: &casbin::Enforcer,
+    sub_groups: &HashMap<GroupId, HashSet<GroupId>>,
+    held: &HashSet<String>,
+    roles: &HashSet<String>,
+    path: &str,
+    method: &str,
+) -> casbin::Result<PolicyDecision> {
+    let mut rule = None;
+    for role in std::iter::once(PUBLIC_ROLE).chain(roles.iter().map(String::as_str)) {
+        let (allowed, rules) = enforcer.enforce_ex((role, path, method))?;
+        if allowed {
+            rule = rules.first().and_then(|rule| PolicyRule::from_casbin(rule));
+            break;
+        }
+    }
+    let role_chain = match &rule {
+        Some(rule) if rule.role == PUBLIC_ROLE => vec![PUBLIC_ROLE.to_string()],
+        Some(rule) => role_chain(enforcer, sub_groups, held, &rule.role),
+        None => Vec::new(),
+    };
+    let mut roles: Vec<_> = roles.iter().cloned().collect();
+    roles.sort();
+    Ok(PolicyDecision {
+        allowed: rule.is_some(),
+        roles,
+        rule,
+        role_chain,
+    })
+}
+
+/// Shortest path from one of the held roles to `target`, following sub groups
+/// and the `g` lines of the casbin policy
+fn role_chain(
+    enforcer: &casbin::Enforcer,
+    sub_groups: &HashMap<GroupId, HashSet<GroupId>>,
+    held: &HashSet<String>,
+    target: &str,
+) -> Vec<String> {
+    let mut parents: HashMap<String, Option<String>> =
+        held.iter().map(|role| (role.clone(), None)).collect();
+    let mut queue: VecDeque<_> = held.iter().cloned().collect();
+    while let Some(role) = queue.pop_front() {
+        if role == target {
+            let mut chain = vec![role];
+            while let Some(Some(parent)) = parents.get(chain.last().unwrap()) {
+                chain.push(parent.clone());
+            }
+            chain.reverse();
+            return chain;
+        }
+        let groups = sub_groups
+            .get(&GroupId::from(role.clone()))
+            .into_iter()
+            .flatten()
+            .map(|group| group.as_ref().to_string());
+        for next in groups.chain(enforcer.get_roles_for_user(&role, None)) {
+            if !parents.contains_key(&next) {
+                parents.insert(next.clone(), Some(role.clone()));
+                queue.push_back(next);
+            }
+        }
+    }
+    Vec::new()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyChangeError {
+    #[error("{0}")]
+    Invalid(&'static str),
+    #[error("Policy already contains this line")]
+    AlreadyPresent,
+    #[error("Policy does not contain this line")]
+    NotPresent,
+    #[error(transparent)]
+    Casbin(#[from] casbin::Error),
+    #[error(transparent)]
+    Persist(#[from] anyhow::Error),
+}
+
+/// All `p` lines of the policy, shipped and added at runtime
+pub fn rules(enforcer: &casbin::Enforcer) -> Vec<PolicyRule> {
+    let rules: BTreeSet<_> = enforcer
+        .get_policy()
+        .iter()
+        .filter_map(|rule| PolicyRule::from_casbin(rule))
+        .collect();
+    rules.into_iter().collect()
+}
+
+/// All `g` lines of the policy, shipped and added at runtime
+pub fn inheritances(enforcer: &casbin::Enforcer) -> Vec<RoleInheritance> {
+    let inheritances: BTreeSet<_> = enforcer
+        .get_grouping_policy()
+        .iter()
+        .filter_map(|inheritance| RoleInheritance::from_casbin(inheritance))
+        .collect();
+    inheritances.into_iter().collect()
+}
+
+/// Layer the changes made at runtime over an enforcer that loaded the shipped
+/// policy
+pub fn apply_changes(enforcer: &mut casbin::Enforcer, changes: &PolicyDB) -> casbin::Result<()> {
+    let model = enforcer.get_mut_model();
+    for rule in changes.removed_rules() {
+        model.remove_policy("p", "p", rule.to_casbin());
+    }
+    for rule in changes.added_rules() {
+        model.add_policy("p", "p", rule.to_casbin());
+    }
+    for inheritance in changes.removed_inheritances() {
+        model.remove_policy("g", "g", inheritance.to_casbin());
+    }
+    for inheritance in changes.added_inheritances() {
+        model.add_policy("g", "g", inheritance.to_casbin());
+    }
+    enforcer.build_role_links()
+}
+
+/// Endpoints the admin role needs to repair the policy, so that no runtime
+/// change can lock everybody out of it
+const POLICY_ENDPOINTS: [(&str, &str); 8] = [
+    ("/policy/check", "POST"),
+    ("/policy/rules", "GET"),
+    ("/policy/rules", "POST"),
+    ("/policy/rules", "DELETE"),
+    ("/policy/inheritances", "GET"),
+    ("/policy/inheritances", "POST"),
+    ("/policy/inheritances", "DELETE"),
+    ("/policy/reload", "POST"),
+];
+
+fn admin_manages_policy(enforcer: &casbin::Enforcer) -> casbin::Result<bool> {
+    let admin = GroupId::admin();
+    for (path, method) in POLICY_ENDPOINTS {
+        if !enforcer.enforce((PUBLIC_ROLE, path, method))?
+            && !enforcer.enforce((admin.as_ref(), path, method))?
+        {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Try a `p` or `g` line removal on the running policy and undo it again,
+/// it must leave the admin role in charge of the policy
+fn ensure_admin_keeps_policy(
+    enforcer: &mut casbin::Enforcer,
+    sec: &str,
+    line: Vec<String>,
+) -> Result<(), PolicyChangeError> {
+    enforcer
+        .get_mut_model()
+        .remove_policy(sec, sec, line.clone());
+    enforcer.build_role_links()?;
+    let allowed = admin_manages_policy(enforcer);
+    enforcer.get_mut_model().add_policy(sec, sec, line);
+    enforcer.build_role_links()?;
+    if !allowed? {
+        return Err(PolicyChangeError::Invalid(
+            "tech.flecs.admin must keep access to the policy",
+        ));
+    }
+    Ok(())
+}
+
+fn validate_rule(rule: &PolicyRule) -> Result<(), PolicyChangeError> {
+    if rule.role.is_empty() {
+        return Err(PolicyChangeError::Invalid("Role must not be empty"));
+    }
+    if !rule.path.starts_with('/') {
+        return Err(PolicyChangeError::Invalid("Path must start with '/'"));
+    }
+    let valid_method = rule.method == "*"
+        || (!rule.method.is_empty() && rule.method.chars().all(|c| c.is_ascii_uppercase()));
+    if !valid_method {
+        return Err(PolicyChangeError::Invalid(
+            "Method must be '*' or an uppercase HTTP method",
+        ));
+    }
+    Ok(())
+}
+
+fn validate_inheritance(inheritance: &RoleInheritance) -> Result<(), PolicyChangeError> {
+    if inheritance.role.is_empty() || inheritance.inherited_role.is_empty() {
+        return Err(PolicyChangeError::Invalid("Roles must not be empty"));
+    }
+    if inheritance.role == PUBLIC_ROLE || inheritance.inherited_role == PUBLIC_ROLE {
+        return Err(PolicyChangeError::Invalid(
+            "The public role cannot inherit or be inherited",
+        ));
+    }
+    if inheritance.role == inheritance.inherited_role {
+        return Err(PolicyChangeError::Invalid("Role cannot inherit itself"));
+    }
+    Ok(())
+}
+
+/// Add a `p` line to the running policy and persist it
+pub fn add_rule(
+    enforcer: &mut casbin::Enforcer,
+    changes: &mut PolicyDB,
+    rule: PolicyRule,
+) -> Result<(), PolicyChangeError> {
+    validate_rule(&rule)?;
+    if enforcer.has_policy(rule.to_casbin()) {
+        return Err(PolicyChangeError::AlreadyPresent);
+    }
+    changes.update(|changes| changes.add_rule(rule.clone()))?;
+    enforcer
+        .get_mut_model()
+        .add_policy("p", "p", rule.to_casbin());
+    Ok(())
+}
+
+/// Remove a `p` line from the running policy and persist its removal, unless
+/// the admin role would lose access to the policy
+pub fn remove_rule(
+    enforcer: &mut casbin::Enforcer,
+    changes: &mut PolicyDB,
+    rule: PolicyRule,
+) -> Result<(), PolicyChangeError> {
+    if !enforcer.has_policy(rule.to_casbin()) {
+        return Err(PolicyChangeError::NotPresent);
+    }
+    ensure_admin_keeps_policy(enforcer, "p", rule.to_casbin())?;
+    changes.update(|changes| changes.remove_rule(rule.clone()))?;
+    enforcer
+        .get_mut_model()
+        .remove_policy("p", "p", rule.to_casbin());
+    Ok(())
+}
+
+/// Add a `g` line to the running policy and persist it
+pub fn add_inheritance(
+    enforcer: &mut casbin::Enforcer,
+    changes: &mut PolicyDB,
+    inheritance: RoleInheritance,
+) -> Result<(), PolicyChangeError> {
+    validate_inheritance(&inheritance)?;
+    if enforcer.has_grouping_policy(inheritance.to_casbin()) {
+        return Err(PolicyChangeError::AlreadyPresent);
+    }
+    changes.update(|changes| changes.add_inheritance(inheritance.clone()))?;
+    enforcer
+        .get_mut_model()
+        .add_policy("g", "g", inheritance.to_casbin());
+    Ok(enforcer.build_role_links()?)
+}
+
+/// Remove a `g` line from the running policy and persist its removal, unless
+/// the admin role would lose access to the policy
+pub fn remove_inheritance(
+    enforcer: &mut casbin::Enforcer,
+    changes: &mut PolicyDB,
+    inheritance: RoleInheritance,
+) -> Result<(), PolicyChangeError> {
+    if !enforcer.has_grouping_policy(inheritance.to_casbin()) {
+        return Err(PolicyChangeError::NotPresent);
+    }
+    ensure_admin_keeps_policy(enforcer, "g", inheritance.to_casbin())?;
+    changes.update(|changes| changes.remove_inheritance(inheritance.clone()))?;
+    enforcer
+        .get_mut_model()
+        .remove_policy("g", "g", inheritance.to_casbin());
+    Ok(enforcer.build_role_links()?)
+}
@@ -1,5 +1,13 @@
 pub mod clients;
+pub mod failed_attempts;
+pub mod groups;
+pub mod keys;
 pub mod login;
+pub mod logout;
 pub mod meta;
 pub mod oauth;
+pub mod oauth_clients;
+pub mod policy;
+pub mod userinfo;
 pub mod users;
+pub mod well_known;
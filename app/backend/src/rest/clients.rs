@@ -2,13 +2,20 @@ use base64::Engine;
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use rand_core::TryRngCore;
 
-use casbin::RbacApi;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use thiserror::Error;
 
 use crate::model::client::{
     AuthMethod, Client, ClientSummary, CreateAuthMethod, CreateClient, CreateClientResponse,
+    Registration,
 };
+use crate::model::group::GroupId;
 use crate::model::password::Password;
 use crate::persist::client_db::InsertClientError;
+use crate::persist::group_db::GroupDB;
+use crate::policy;
 use crate::state;
 use crate::token::Roles;
 use axum::extract::{Json, State};
@@ -16,6 +23,7 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 
 pub mod cid;
+pub mod initial_access_tokens;
 
 #[utoipa::path(
     get,
@@ -47,49 +55,122 @@ pub async fn post(
     axum::Extension(Roles(caller_roles)): axum::Extension<Roles>,
     Json(create): Json<CreateClient>,
 ) -> Response {
-    let mut expanded_roles = caller_roles.clone();
-    {
-        let enforcer = state.enforcer.lock().unwrap();
-        for role in &caller_roles {
-            for implicit in enforcer.get_implicit_roles_for_user(role, None) {
-                expanded_roles.insert(implicit);
-            }
-        }
+    if let Err(e) = ensure_groups_held(&state, &caller_roles, &create.groups) {
+        return e.into_response();
+    }
+    match create_client(&state, create, None) {
+        Ok(response) => (StatusCode::CREATED, Json(response)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Cannot assign groups not held by caller: {}", .0.join(", "))]
+pub(crate) struct GroupsNotHeld(Vec<String>);
+
+impl IntoResponse for GroupsNotHeld {
+    fn into_response(self) -> Response {
+        (StatusCode::FORBIDDEN, self.to_string()).into_response()
     }
+}
+
+/// Custom groups are not known to the casbin policy, so they are held if the
+/// caller holds every built-in role they grant through their sub groups
+fn custom_group_held(
+    groups: &GroupDB,
+    enforcer: &casbin::Enforcer,
+    group: &GroupId,
+    expanded_roles: &HashSet<String>,
+) -> bool {
+    if group.is_builtin() || groups.query_by_id(group).is_none() {
+        return false;
+    }
+    let granted_groups = groups.query_groups_with_subgroups(std::slice::from_ref(group));
+    policy::implicit_roles(enforcer, granted_groups.iter().map(GroupId::as_ref))
+        .into_iter()
+        .filter(|role| GroupId::from(role.clone()).is_builtin())
+        .all(|role| expanded_roles.contains(&role))
+}
+
+/// Callers may only hand out groups they hold themselves, directly or through
+/// the roles they inherit
+pub(crate) fn ensure_groups_held(
+    state: &state::AppState,
+    caller_roles: &HashSet<String>,
+    groups: &HashSet<GroupId>,
+) -> Result<(), GroupsNotHeld> {
+    let db = state.db.lock().unwrap();
+    ensure_groups_held_in(&db.groups, &state.enforcer, caller_roles, groups)
+}
 
-    let unauthorized_groups: Vec<_> = create
-        .groups
+/// Like [`ensure_groups_held`], for callers that hold the db lock already so
+/// that the groups can not change between the check and the write
+pub(crate) fn ensure_groups_held_in(
+    group_db: &GroupDB,
+    enforcer: &Mutex<casbin::Enforcer>,
+    caller_roles: &HashSet<String>,
+    groups: &HashSet<GroupId>,
+) -> Result<(), GroupsNotHeld> {
+    let enforcer = enforcer.lock().unwrap();
+    let expanded_roles = policy::implicit_roles(&enforcer, caller_roles.iter().map(String::as_str));
+
+    let mut unauthorized_groups: Vec<_> = groups
         .iter()
         .filter(|g| !expanded_roles.contains(g.as_ref()))
+        .filter(|g| !custom_group_held(group_db, &enforcer, g, &expanded_roles))
+        .map(|g| g.to_string())
         .collect();
-    if !unauthorized_groups.is_empty() {
-        return (
-            StatusCode::FORBIDDEN,
-            format!(
-                "Cannot assign groups not held by caller: {}",
-                unauthorized_groups
-                    .iter()
-                    .map(|g| g.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            ),
-        )
-            .into_response();
+    if unauthorized_groups.is_empty() {
+        return Ok(());
     }
+    unauthorized_groups.sort();
+    Err(GroupsNotHeld(unauthorized_groups))
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum CreateClientError {
+    #[error("Invalid PEM certificate: {0}")]
+    InvalidCertificate(openssl::error::ErrorStack),
+    #[error(transparent)]
+    Insert(#[from] InsertClientError),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl IntoResponse for CreateClientError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::InvalidCertificate(_) => StatusCode::BAD_REQUEST,
+            Self::Insert(InsertClientError::DuplicateName(_)) => StatusCode::CONFLICT,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
 
+/// Create and persist a client, returning its credentials
+fn create_client(
+    state: &state::AppState,
+    create: CreateClient,
+    registration: Option<Registration>,
+) -> Result<CreateClientResponse, CreateClientError> {
+    let (client, response) = new_client(create, registration)?;
+    let mut db = state.db.lock().unwrap();
+    db.clients.insert(client)?;
+    db.clients.save()?;
+    Ok(response)
+}
+
+/// Generate the credentials of a new client without storing it. Shared by the
+/// admin API and the dynamic client registration.
+pub(crate) fn new_client(
+    create: CreateClient,
+    registration: Option<Registration>,
+) -> Result<(Client, CreateClientResponse), CreateClientError> {
     let (auth_method, secret, certificate, private_key) = match create.auth_method {
         CreateAuthMethod::Secret => {
-            let mut secret_bytes = [0u8; 32];
-            rand_core::OsRng
-                .try_fill_bytes(&mut secret_bytes)
-                .expect("OS RNG should work");
-            let plaintext_secret = URL_SAFE_NO_PAD.encode(secret_bytes);
-            let hashed = match Password::new(&plaintext_secret) {
-                Ok(h) => h,
-                Err(e) => {
-                    return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
-                }
-            };
+            let plaintext_secret = generate_secret();
+            let hashed = Password::new(&plaintext_secret).map_err(anyhow::Error::from)?;
             (
                 AuthMethod::Secret { secret: hashed },
                 Some(plaintext_secret),
@@ -98,22 +179,12 @@ pub async fn post(
             )
         }
         CreateAuthMethod::Certificate { pem: Some(pem_str) } => {
-            if let Err(e) = openssl::x509::X509::from_pem(pem_str.as_bytes()) {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    format!("Invalid PEM certificate: {e}"),
-                )
-                    .into_response();
-            }
+            openssl::x509::X509::from_pem(pem_str.as_bytes())
+                .map_err(CreateClientError::InvalidCertificate)?;
             (AuthMethod::Certificate { pem: pem_str }, None, None, None)
         }
         CreateAuthMethod::Certificate { pem: None } => {
-            let (cert_pem, key_pem) = match generate_self_signed_cert(&create.name) {
-                Ok(pair) => pair,
-                Err(e) => {
-                    return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
-                }
-            };
+            let (cert_pem, key_pem) = generate_self_signed_cert(&create.name)?;
             (
                 AuthMethod::Certificate {
                     pem: cert_pem.clone(),
@@ -136,6 +207,7 @@ pub async fn post(
         auth_method,
         groups: create.groups,
         created_at: chrono::Utc::now(),
+        registration,
     };
 
     let response = CreateClientResponse {
@@ -148,19 +220,16 @@ pub async fn post(
         certificate,
         private_key,
     };
+    Ok((client, response))
+}
 
-    let mut db = state.db.lock().unwrap();
-    if let Err(InsertClientError::DuplicateName(name)) = db.clients.insert(client) {
-        return (
-            StatusCode::CONFLICT,
-            format!("Client with name '{name}' already exists"),
-        )
-            .into_response();
-    }
-    if let Err(e) = db.clients.save() {
-        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
-    }
-    (StatusCode::CREATED, Json(response)).into_response()
+/// Random client secret, only its hash is stored
+pub(crate) fn generate_secret() -> String {
+    let mut secret_bytes = [0u8; 32];
+    rand_core::OsRng
+        .try_fill_bytes(&mut secret_bytes)
+        .expect("OS RNG should work");
+    URL_SAFE_NO_PAD.encode(secret_bytes)
 }
 
 fn generate_self_signed_cert(cn: &str) -> Result<(String, String), anyhow::Error> {
@@ -5,6 +5,8 @@ use axum::extract::{Json, Path, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 
+pub mod effective_roles;
+
 #[utoipa::path(
     get,
     path="/clients/{cid}",
@@ -0,0 +1,32 @@
+use crate::model::client::ClientId;
+use crate::model::permission::EffectiveRoles;
+use crate::policy;
+use crate::state;
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    get,
+    path="/clients/{cid}/effective-roles",
+    responses(
+        (status = OK, description = "Groups, roles and permissions the client holds", body = EffectiveRoles),
+        (status = NOT_FOUND, description = "Client does not exist"),
+        (status = BAD_REQUEST, description = "Invalid client ID"),
+    ),
+    params(
+        ("cid" = String, description = "Client UUID")
+    )
+)]
+pub async fn get(State(state): State<state::AppState>, Path(cid): Path<ClientId>) -> Response {
+    let groups = {
+        let db = state.db.lock().unwrap();
+        let Some(client) = db.clients.query_by_id(cid) else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        let groups: Vec<_> = client.groups.iter().cloned().collect();
+        db.groups.query_groups_with_subgroups(&groups)
+    };
+    let enforcer = state.enforcer.lock().unwrap();
+    Json(policy::effective_roles(&enforcer, groups)).into_response()
+}
@@ -0,0 +1,49 @@
+use crate::model::initial_access_token::{
+    CreateInitialAccessToken, CreateInitialAccessTokenResponse, DEFAULT_LIFETIME_SECONDS,
+    InitialAccessToken,
+};
+use crate::rest::clients::ensure_groups_held;
+use crate::state;
+use crate::token::Roles;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    post,
+    path="/clients/initial-access-tokens",
+    responses(
+        (status = CREATED, description = "Initial access token for a single dynamic client registration", body = CreateInitialAccessTokenResponse),
+        (status = FORBIDDEN, description = "Caller does not have all requested groups", body = String),
+        (status = BAD_REQUEST, description = "Invalid request body", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = CreateInitialAccessToken)
+)]
+pub async fn post(
+    State(state): State<state::AppState>,
+    axum::Extension(Roles(caller_roles)): axum::Extension<Roles>,
+    Json(create): Json<CreateInitialAccessToken>,
+) -> Response {
+    if let Err(e) = ensure_groups_held(&state, &caller_roles, &create.groups) {
+        return e.into_response();
+    }
+    let expires_in = create.expires_in.unwrap_or(DEFAULT_LIFETIME_SECONDS);
+    if expires_in == 0 {
+        return (StatusCode::BAD_REQUEST, "expires_in must be positive").into_response();
+    }
+    let (initial_access_token, token) =
+        InitialAccessToken::new(create.groups, chrono::Duration::seconds(expires_in.into()));
+    let response = CreateInitialAccessTokenResponse {
+        token,
+        groups: initial_access_token.groups.clone(),
+        expires_at: initial_access_token.expires_at,
+    };
+
+    let mut db = state.db.lock().unwrap();
+    db.initial_access_tokens.insert(initial_access_token);
+    if let Err(e) = db.initial_access_tokens.save() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+    (StatusCode::CREATED, Json(response)).into_response()
+}
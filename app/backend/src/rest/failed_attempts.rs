@@ -0,0 +1,15 @@
+use crate::model::failed_attempt::FailedAttempt;
+use crate::state;
+use axum::extract::{Json, State};
+
+#[utoipa::path(
+    get,
+    path="/failed-attempts",
+    responses(
+        (status = OK, description = "Most recent failed attempts to log in or to authenticate a client, newest first", body = Vec<FailedAttempt>),
+    )
+)]
+pub async fn get(State(state): State<state::AppState>) -> Json<Vec<FailedAttempt>> {
+    let failed_attempts = state.failed_attempts.lock().unwrap();
+    Json(failed_attempts.failed_attempts().cloned().collect())
+}
@@ -0,0 +1,72 @@
+use crate::model::group::{CreateGroup, Group};
+use crate::persist::group_db::InsertGroupError;
+use crate::rest::clients::ensure_groups_held_in;
+use crate::state;
+use crate::token::Roles;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+pub mod gid;
+
+#[utoipa::path(
+    get,
+    path="/groups",
+    responses(
+        (status = OK, description = "List of all groups", body = Vec<Group>),
+    )
+)]
+pub async fn get(State(state): State<state::AppState>) -> Json<Vec<Group>> {
+    let db = state.db.lock().unwrap();
+    let mut groups: Vec<_> = db.groups.query_all().cloned().collect();
+    groups.sort_by(|a, b| a.id.as_ref().cmp(b.id.as_ref()));
+    Json(groups)
+}
+
+#[utoipa::path(
+    post,
+    path="/groups",
+    responses(
+        (status = CREATED, description = "Group was created", body = Group),
+        (status = CONFLICT, description = "Group with that id already exists or sub groups would form a cycle", body = String),
+        (status = FORBIDDEN, description = "Group id is in the built-in namespace or caller does not hold a sub group", body = String),
+        (status = BAD_REQUEST, description = "Invalid request body or unknown sub group", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = CreateGroup)
+)]
+pub async fn post(
+    State(state): State<state::AppState>,
+    axum::Extension(Roles(caller_roles)): axum::Extension<Roles>,
+    Json(create): Json<CreateGroup>,
+) -> Response {
+    let group = Group::from(create);
+    let mut db = state.db.lock().unwrap();
+    // Unknown sub groups are rejected by the insert
+    let sub_groups = group
+        .sub_groups
+        .iter()
+        .filter(|sub_group| db.groups.query_by_id(sub_group).is_some())
+        .cloned()
+        .collect();
+    if let Err(e) = ensure_groups_held_in(&db.groups, &state.enforcer, &caller_roles, &sub_groups) {
+        return e.into_response();
+    }
+    match db.groups.insert(group.clone()) {
+        Ok(()) => {
+            if let Err(e) = db.groups.save() {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            (StatusCode::CREATED, Json(group)).into_response()
+        }
+        Err(e @ (InsertGroupError::DuplicateId(_) | InsertGroupError::Cycle(_))) => {
+            (StatusCode::CONFLICT, e.to_string()).into_response()
+        }
+        Err(e @ InsertGroupError::BuiltIn(_)) => {
+            (StatusCode::FORBIDDEN, e.to_string()).into_response()
+        }
+        Err(e @ InsertGroupError::UnknownSubGroup(_)) => {
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::model::group::{Group, GroupId, UpdateGroup};
+use crate::persist::group_db::{RemoveGroupError, UpdateGroupError};
+use crate::rest::clients::ensure_groups_held_in;
+use crate::state;
+use crate::token::Roles;
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+pub mod sub_groups;
+
+#[utoipa::path(
+    get,
+    path="/groups/{gid}",
+    responses(
+        (status = OK, description = "Return a single group", body = Group),
+        (status = NOT_FOUND, description = "Group does not exist"),
+    ),
+    params(
+        ("gid" = GroupId, description = "Group ID")
+    )
+)]
+pub async fn get(State(state): State<state::AppState>, Path(gid): Path<GroupId>) -> Response {
+    let db = state.db.lock().unwrap();
+    match db.groups.query_by_id(&gid) {
+        Some(group) => Json(group).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path="/groups/{gid}",
+    responses(
+        (status = NO_CONTENT, description = "Group was updated"),
+        (status = NOT_FOUND, description = "Group does not exist"),
+        (status = FORBIDDEN, description = "Group is built-in or caller does not hold an added sub group", body = String),
+        (status = CONFLICT, description = "Sub groups would form a cycle", body = String),
+        (status = BAD_REQUEST, description = "Invalid request body or unknown sub group", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    params(
+        ("gid" = GroupId, description = "Group ID")
+    ),
+    request_body(content = UpdateGroup)
+)]
+pub async fn patch(
+    State(state): State<state::AppState>,
+    axum::Extension(Roles(caller_roles)): axum::Extension<Roles>,
+    Path(gid): Path<GroupId>,
+    Json(update): Json<UpdateGroup>,
+) -> Response {
+    let mut db = state.db.lock().unwrap();
+    // Only newly added sub groups are checked, unknown ones are rejected by
+    // the update
+    if let (Some(sub_groups), Some(group)) = (&update.sub_groups, db.groups.query_by_id(&gid)) {
+        let added_groups = sub_groups
+            .iter()
+            .filter(|sub_group| !group.sub_groups.contains(sub_group))
+            .filter(|sub_group| db.groups.query_by_id(sub_group).is_some())
+            .cloned()
+            .collect();
+        if let Err(e) =
+            ensure_groups_held_in(&db.groups, &state.enforcer, &caller_roles, &added_groups)
+        {
+            return e.into_response();
+        }
+    }
+    match db.groups.update(&gid, update) {
+        Ok(()) => {
+            if let Err(e) = db.groups.save() {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(UpdateGroupError::NotFound(_)) => StatusCode::NOT_FOUND.into_response(),
+        Err(e @ UpdateGroupError::BuiltIn(_)) => {
+            (StatusCode::FORBIDDEN, e.to_string()).into_response()
+        }
+        Err(e @ UpdateGroupError::Cycle(_)) => {
+            (StatusCode::CONFLICT, e.to_string()).into_response()
+        }
+        Err(e @ UpdateGroupError::UnknownSubGroup(_)) => {
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path="/groups/{gid}",
+    responses(
+        (status = NO_CONTENT, description = "Group was deleted and taken away from all users, clients and groups"),
+        (status = NOT_FOUND, description = "Group does not exist"),
+        (status = FORBIDDEN, description = "Group is built-in", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    params(
+        ("gid" = GroupId, description = "Group ID")
+    )
+)]
+pub async fn delete(State(state): State<state::AppState>, Path(gid): Path<GroupId>) -> Response {
+    let mut db = state.db.lock().unwrap();
+    match db.groups.remove(&gid) {
+        Ok(_) => {
+            if let Err(e) = db.groups.save() {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            /* a group created later with the same id must not inherit members */
+            if db.users.remove_group_from_all(&gid) > 0
+                && let Err(e) = db.users.save()
+            {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            if db.clients.remove_group_from_all(&gid) > 0
+                && let Err(e) = db.clients.save()
+            {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(RemoveGroupError::NotFound(_)) => StatusCode::NOT_FOUND.into_response(),
+        Err(e @ RemoveGroupError::BuiltIn(_)) => {
+            (StatusCode::FORBIDDEN, e.to_string()).into_response()
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use crate::model::group::GroupId;
+use crate::state;
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+pub mod sub_gid;
+
+#[utoipa::path(
+    get,
+    path="/groups/{gid}/sub-groups",
+    responses(
+        (status = OK, description = "Direct sub groups of the group", body = HashSet<GroupId>),
+        (status = NOT_FOUND, description = "Group does not exist"),
+    ),
+    params(
+        ("gid" = GroupId, description = "Group ID")
+    )
+)]
+pub async fn get(State(state): State<state::AppState>, Path(gid): Path<GroupId>) -> Response {
+    let db = state.db.lock().unwrap();
+    match db.groups.query_by_id(&gid) {
+        Some(group) => Json(&group.sub_groups).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+
+use crate::model::group::GroupId;
+use crate::persist::group_db::{AddSubGroupError, RemoveSubGroupError};
+use crate::rest::clients::ensure_groups_held_in;
+use crate::state;
+use crate::token::Roles;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    put,
+    path="/groups/{gid}/sub-groups/{sub_gid}",
+    responses(
+        (status = NO_CONTENT, description = "Sub group was added to the group"),
+        (status = NOT_FOUND, description = "Group does not exist"),
+        (status = FORBIDDEN, description = "Group is built-in or caller does not hold the sub group", body = String),
+        (status = CONFLICT, description = "Group already has this sub group or it would form a cycle", body = String),
+        (status = BAD_REQUEST, description = "Sub group does not exist", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    params(
+        ("gid" = GroupId, description = "Group ID to add the sub group to"),
+        ("sub_gid" = GroupId, description = "Sub group to add"),
+    ),
+)]
+pub async fn put(
+    State(state): State<state::AppState>,
+    axum::Extension(Roles(caller_roles)): axum::Extension<Roles>,
+    Path((gid, sub_gid)): Path<(GroupId, GroupId)>,
+) -> Response {
+    let mut db = state.db.lock().unwrap();
+    if db.groups.query_by_id(&sub_gid).is_some()
+        && let Err(e) = ensure_groups_held_in(
+            &db.groups,
+            &state.enforcer,
+            &caller_roles,
+            &HashSet::from([sub_gid.clone()]),
+        )
+    {
+        return e.into_response();
+    }
+    match db.groups.add_sub_group(&gid, sub_gid) {
+        Ok(()) => {
+            if let Err(e) = db.groups.save() {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(AddSubGroupError::NotFound(_)) => StatusCode::NOT_FOUND.into_response(),
+        Err(e @ AddSubGroupError::BuiltIn(_)) => {
+            (StatusCode::FORBIDDEN, e.to_string()).into_response()
+        }
+        Err(e @ (AddSubGroupError::AlreadyAssigned(_) | AddSubGroupError::Cycle(_))) => {
+            (StatusCode::CONFLICT, e.to_string()).into_response()
+        }
+        Err(e @ AddSubGroupError::UnknownSubGroup(_)) => {
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path="/groups/{gid}/sub-groups/{sub_gid}",
+    responses(
+        (status = NO_CONTENT, description = "Sub group was removed from the group"),
+        (status = NOT_FOUND, description = "Group does not exist or does not have this sub group"),
+        (status = FORBIDDEN, description = "Group is built-in", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    params(
+        ("gid" = GroupId, description = "Group ID to remove the sub group from"),
+        ("sub_gid" = GroupId, description = "Sub group to remove"),
+    ),
+)]
+pub async fn delete(
+    State(state): State<state::AppState>,
+    Path((gid, sub_gid)): Path<(GroupId, GroupId)>,
+) -> Response {
+    let mut db = state.db.lock().unwrap();
+    match db.groups.remove_sub_group(&gid, &sub_gid) {
+        Ok(()) => {
+            if let Err(e) = db.groups.save() {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(RemoveSubGroupError::NotFound(_)) => StatusCode::NOT_FOUND.into_response(),
+        Err(RemoveSubGroupError::NotAssigned(_)) => StatusCode::NOT_FOUND.into_response(),
+        Err(e @ RemoveSubGroupError::BuiltIn(_)) => {
+            (StatusCode::FORBIDDEN, e.to_string()).into_response()
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use crate::model::signing_key::KeySummary;
+use crate::state;
+use axum::extract::{Json, State};
+
+pub mod rotate;
+
+#[utoipa::path(
+    get,
+    path="/keys",
+    responses(
+        (status = OK, description = "List of the active and all retired signing keys", body = Vec<KeySummary>),
+    )
+)]
+pub async fn get(State(state): State<state::AppState>) -> Json<Vec<KeySummary>> {
+    Json(state.issuer.lock().unwrap().key_summaries())
+}
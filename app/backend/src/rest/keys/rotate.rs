@@ -0,0 +1,21 @@
+use crate::model::signing_key::KeySummary;
+use crate::state;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    post,
+    path="/keys/rotate",
+    responses(
+        (status = OK, description = "Signing key was rotated, returns the new active key", body = KeySummary),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    )
+)]
+pub async fn post(State(state): State<state::AppState>) -> Response {
+    let mut issuer = state.issuer.lock().unwrap();
+    match issuer.rotate() {
+        Ok(key) => Json(KeySummary::from(key)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
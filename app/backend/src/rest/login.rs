@@ -1,17 +1,32 @@
 use askama::Template;
-use axum::http::{HeaderMap, StatusCode};
+use axum::http::{HeaderMap, HeaderName, StatusCode, header};
 use axum::response::Html;
 use axum::{
     extract::{Form, State},
-    response::{IntoResponse, Redirect},
+    response::{IntoResponse, Redirect, Response},
 };
 use cookie::Cookie;
 use serde::{Deserialize, Serialize};
+use tracing::error;
 use utoipa::ToSchema;
 
-use crate::model::session::UserSession;
+use crate::middleware::client_info::ClientInfo;
+use crate::model::failed_attempt::{Account, retry_after_seconds};
+use crate::model::session::{TWO_FACTOR_SESSION_EXPIRY, TwoFactorSession, UserSession};
+use crate::model::totp::TotpSecret;
+use crate::model::user::UserId;
+use crate::persist::session_db::SessionDB;
 use crate::state;
 
+pub mod totp;
+
+/// Headers of the pages users enter credentials or decisions on, which
+/// must not be framed by other sites to trick users into using them
+pub(crate) const DENY_FRAMING: [(HeaderName, &str); 2] = [
+    (header::X_FRAME_OPTIONS, "DENY"),
+    (header::CONTENT_SECURITY_POLICY, "frame-ancestors 'none'"),
+];
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct LoginRequest {
     username: String,
@@ -34,73 +49,139 @@ struct LoginTemplate<'a> {
     path="/login",
     responses(
         (status = FOUND, description = "Login successful"),
-        (status = NOT_FOUND, description = "No users")
+        (status = NOT_FOUND, description = "No users"),
+        (status = TOO_MANY_REQUESTS, description = "Too many failed attempts, retry after the time in the Retry-After header")
     )
 )]
 
 pub async fn get() -> impl IntoResponse {
-    Html(LoginTemplate { error: None }.render().unwrap())
+    (
+        DENY_FRAMING,
+        Html(LoginTemplate { error: None }.render().unwrap()),
+    )
+}
+
+/// What the user has to do after entering their password
+enum SecondFactor {
+    None,
+    /// Enter a code of their authenticator
+    Totp,
+    /// Set up an authenticator, because their groups require one
+    Enroll,
 }
 
 pub async fn post(
     State(state): State<state::AppState>,
+    client_info: ClientInfo,
     headers: HeaderMap,
     Form(payload): Form<LoginRequest>,
-) -> impl IntoResponse {
+) -> Response {
+    /* refuse guesses while the account or address is throttled */
+    let account = Account::User {
+        username: payload.username.clone(),
+    };
+    let attempt = match client_info.begin_attempt(&state.failed_attempts, account) {
+        Ok(attempt) => attempt,
+        Err(retry_after) => {
+            let mut response = render_error(
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many failed attempts, please try again later",
+            );
+            response.headers_mut().insert(
+                axum::http::header::RETRY_AFTER,
+                retry_after_seconds(retry_after).into(),
+            );
+            return response;
+        }
+    };
+
     /* verify username/password */
-    let db = state.db.lock().unwrap();
-    let user = db.users.query_by_name(&payload.username);
-    if user.is_none() {
-        let html = LoginTemplate {
-            error: Some("Invalid username and/or password"),
-        };
-        return Err((StatusCode::FORBIDDEN, Html(html.render().unwrap())));
+    let verified = state
+        .db
+        .lock()
+        .unwrap()
+        .users
+        .query_by_name(&payload.username)
+        .filter(|user| user.password.verify(&payload.password).is_ok())
+        .map(|user| {
+            let second_factor = if user.has_totp() {
+                SecondFactor::Totp
+            } else if user.requires_totp(&state.totp_required_groups) {
+                SecondFactor::Enroll
+            } else {
+                SecondFactor::None
+            };
+            (user.id, second_factor)
+        });
+    let Some((uid, second_factor)) = verified else {
+        attempt.fail();
+        return render_error(StatusCode::FORBIDDEN, "Invalid username and/or password");
+    };
+    /* failures are only forgotten once the second factor was verified as
+     * well, otherwise every correct password would allow more guesses of it */
+    if matches!(second_factor, SecondFactor::None) {
+        attempt.succeed();
+    } else {
+        drop(attempt);
     }
 
-    let user = user.unwrap();
-    user.password.verify(&payload.password).map_err(|_| {
-        (
-            StatusCode::FORBIDDEN,
-            Html(
-                LoginTemplate {
-                    error: Some("Invalid username and/or password"),
-                }
-                .render()
-                .unwrap(),
-            ),
-        )
-    })?;
-
     /* login successful, remove login session, if any */
-    let mut login_sessions = state.login_sessions.lock().unwrap();
+    let mut sessions = state.sessions.lock().unwrap();
     let sid = extract_sid_from_request_headers(&headers);
     let login_session = match sid {
-        Some(s) => login_sessions.take(s.as_str()),
+        Some(s) => sessions.take_login(s.as_str()),
         None => None,
     };
 
     if let Some(login_session) = &login_session
         && login_session.is_expired()
     {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Html(
-                LoginTemplate {
-                    error: Some("Session expired"),
-                }
-                .render()
-                .unwrap(),
-            ),
-        ));
+        return render_error(StatusCode::FORBIDDEN, "Session expired");
     }
+    let q = login_session.map(|s| s.get_q().to_string());
+
+    /* ask for the second factor before the user is logged in */
+    let enrollment = match second_factor {
+        SecondFactor::None => {
+            let set_cookie = start_user_session(&mut sessions, uid, client_info);
+            return (set_cookie, continue_login(q.as_deref())).into_response();
+        }
+        SecondFactor::Totp => None,
+        SecondFactor::Enroll => Some(TotpSecret::generate()),
+    };
+    let two_factor_sid = sessions.insert_two_factor(TwoFactorSession::new(uid, q, enrollment));
+    if let Err(e) = sessions.save() {
+        error!("Could not persist session database: {e}");
+    }
+    (
+        sid_cookie(two_factor_sid, TWO_FACTOR_SESSION_EXPIRY),
+        Redirect::to("/login/totp"),
+    )
+        .into_response()
+}
 
-    /* create new user-session and tie it to the user's uid */
+/// Create a new user session tied to the uid, returns the header that sets
+/// its cookie
+pub(crate) fn start_user_session(
+    sessions: &mut SessionDB,
+    uid: UserId,
+    client_info: ClientInfo,
+) -> HeaderMap {
     /* @todo add granted scope to user session */
-    let user_session = UserSession::new(user.id);
+    let user_session =
+        UserSession::new(uid).with_client_info(client_info.user_agent, client_info.ip_address);
+    let user_sid = sessions.insert_user(user_session);
+    if let Err(e) = sessions.save() {
+        error!("Could not persist session database: {e}");
+    }
+    sid_cookie(user_sid, sessions.timeouts().absolute)
+}
 
-    let cookie = Cookie::build(("sid", user_session.get_sid()))
+fn sid_cookie(sid: String, max_age: chrono::Duration) -> HeaderMap {
+    let cookie = Cookie::build(("sid", sid))
         .path("/")
         .http_only(true)
+        .max_age(cookie::time::Duration::seconds(max_age.num_seconds()))
         .build();
 
     let mut set_cookie = HeaderMap::new();
@@ -108,20 +189,23 @@ pub async fn post(
         axum::http::header::SET_COOKIE,
         cookie.to_string().parse().unwrap(),
     );
+    set_cookie
+}
 
-    let mut user_sessions = state.user_sessions.lock().unwrap();
-    user_sessions.insert(user_session);
-
-    match login_session {
-        Some(s) => Ok((
-            set_cookie,
-            Redirect::to(format!("/oauth/authorize?{}", s.get_q()).as_str()).into_response(),
-        )),
-        None => Ok((set_cookie, Html("Login successful").into_response())),
+/// Continue with the authorization request the user logged in for, if any
+pub(crate) fn continue_login(q: Option<&str>) -> Response {
+    match q {
+        Some(q) => Redirect::to(format!("/oauth/authorize?{q}").as_str()).into_response(),
+        None => Html("Login successful").into_response(),
     }
 }
 
-fn extract_sid_from_request_headers(headers: &HeaderMap) -> Option<String> {
+fn render_error(status: StatusCode, error: &str) -> Response {
+    let html = LoginTemplate { error: Some(error) };
+    (status, DENY_FRAMING, Html(html.render().unwrap())).into_response()
+}
+
+pub(crate) fn extract_sid_from_request_headers(headers: &HeaderMap) -> Option<String> {
     headers
         .get_all(axum::http::header::COOKIE)
         .iter()
@@ -0,0 +1,182 @@
+use askama::Template;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Html;
+use axum::{
+    extract::{Form, State},
+    response::{IntoResponse, Response},
+};
+use tracing::error;
+
+use crate::middleware::client_info::ClientInfo;
+use crate::model::failed_attempt::{Account, retry_after_seconds};
+use crate::model::session::TwoFactorSession;
+use crate::model::totp::{Totp, TotpCode, TotpEnrollment};
+use crate::rest::login::{
+    DENY_FRAMING, continue_login, extract_sid_from_request_headers, start_user_session,
+};
+use crate::state;
+
+#[derive(Template)]
+#[template(path = "login_totp.html")]
+struct TotpTemplate<'a> {
+    enrollment: Option<TotpEnrollment>,
+    error: Option<&'a str>,
+}
+
+#[derive(Template)]
+#[template(path = "recovery_codes.html")]
+struct RecoveryCodesTemplate {
+    recovery_codes: Vec<String>,
+    next: Option<String>,
+}
+
+/// Pending second step of the login of the user with the cookie, along with
+/// their name
+fn two_factor_session(
+    state: &state::AppState,
+    headers: &HeaderMap,
+) -> Option<(String, TwoFactorSession, String)> {
+    let sid = extract_sid_from_request_headers(headers)?;
+    let session = state
+        .sessions
+        .lock()
+        .unwrap()
+        .two_factor_session(&sid)
+        .cloned()?;
+    let username = state
+        .db
+        .lock()
+        .unwrap()
+        .users
+        .query_by_uid(session.get_uid())?
+        .name
+        .clone();
+    Some((sid, session, username))
+}
+
+fn render(
+    status: StatusCode,
+    session: &TwoFactorSession,
+    username: &str,
+    error: Option<&str>,
+) -> Response {
+    let html = TotpTemplate {
+        enrollment: session
+            .enrollment()
+            .map(|secret| TotpEnrollment::new(secret, username)),
+        error,
+    };
+    (status, DENY_FRAMING, Html(html.render().unwrap())).into_response()
+}
+
+fn render_expired() -> Response {
+    let html = TotpTemplate {
+        enrollment: None,
+        error: Some("Session expired, please log in again"),
+    };
+    (
+        StatusCode::FORBIDDEN,
+        DENY_FRAMING,
+        Html(html.render().unwrap()),
+    )
+        .into_response()
+}
+
+pub async fn get(State(state): State<state::AppState>, headers: HeaderMap) -> Response {
+    match two_factor_session(&state, &headers) {
+        Some((_, session, username)) => render(StatusCode::OK, &session, &username, None),
+        None => render_expired(),
+    }
+}
+
+pub async fn post(
+    State(state): State<state::AppState>,
+    client_info: ClientInfo,
+    headers: HeaderMap,
+    Form(payload): Form<TotpCode>,
+) -> Response {
+    let Some((sid, session, username)) = two_factor_session(&state, &headers) else {
+        return render_expired();
+    };
+    let uid = session.get_uid();
+
+    /* codes are guessed like passwords, so they are throttled the same way */
+    let attempt = match client_info.begin_attempt(
+        &state.failed_attempts,
+        Account::User {
+            username: username.clone(),
+        },
+    ) {
+        Ok(attempt) => attempt,
+        Err(retry_after) => {
+            let mut response = render(
+                StatusCode::TOO_MANY_REQUESTS,
+                &session,
+                &username,
+                Some("Too many failed attempts, please try again later"),
+            );
+            response.headers_mut().insert(
+                axum::http::header::RETRY_AFTER,
+                retry_after_seconds(retry_after).into(),
+            );
+            return response;
+        }
+    };
+
+    /* verify the code, a new authenticator is stored once it works */
+    let verified = {
+        let mut db = state.db.lock().unwrap();
+        let verified = match session.enrollment() {
+            Some(secret) => {
+                let mut totp = Totp::new(secret.clone());
+                if totp.verify_code(&payload.code) {
+                    totp.confirm();
+                    let recovery_codes = totp.generate_recovery_codes();
+                    db.users
+                        .set_totp(uid, Some(totp))
+                        .ok()
+                        .map(|_| Some(recovery_codes))
+                } else {
+                    None
+                }
+            }
+            None => db
+                .users
+                .totp_mut(uid)
+                .filter(|totp| totp.is_confirmed())
+                .is_some_and(|totp| totp.verify(&payload.code))
+                .then_some(None),
+        };
+        if verified.is_some()
+            && let Err(e) = db.users.save()
+        {
+            error!("Could not persist user database: {e}");
+        }
+        verified
+    };
+    let Some(recovery_codes) = verified else {
+        attempt.fail();
+        return render(
+            StatusCode::FORBIDDEN,
+            &session,
+            &username,
+            Some("Invalid code"),
+        );
+    };
+    attempt.succeed();
+
+    /* login successful, replace the two-factor session by a user session */
+    let mut sessions = state.sessions.lock().unwrap();
+    sessions.remove_two_factor(&sid);
+    let set_cookie = start_user_session(&mut sessions, uid, client_info);
+    match recovery_codes {
+        Some(recovery_codes) => {
+            let html = RecoveryCodesTemplate {
+                recovery_codes,
+                next: session.get_q().map(|q| format!("/oauth/authorize?{q}")),
+            };
+            (set_cookie, DENY_FRAMING, Html(html.render().unwrap())).into_response()
+        }
+        None => (set_cookie, continue_login(session.get_q())).into_response(),
+    }
+}
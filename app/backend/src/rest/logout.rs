@@ -0,0 +1,171 @@
+use axum::extract::{Form, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use cookie::Cookie;
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::error;
+
+use crate::model::session::UserSession;
+use crate::oauth::logout::{self, Notification};
+use crate::rest::login::extract_sid_from_request_headers;
+use crate::state;
+use crate::token::{self, VerifyTokenError};
+
+/// Parameters of OpenID Connect RP-Initiated Logout 1.0, section 2
+#[derive(Debug, Default, Deserialize)]
+pub struct LogoutRequest {
+    id_token_hint: Option<String>,
+    client_id: Option<String>,
+    post_logout_redirect_uri: Option<String>,
+    state: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum LogoutError {
+    #[error("Invalid id_token_hint: {0}")]
+    InvalidIdTokenHint(#[from] VerifyTokenError),
+    #[error("client_id does not match the audience of id_token_hint")]
+    ClientMismatch,
+    #[error("post_logout_redirect_uri requires id_token_hint")]
+    MissingIdTokenHint,
+    #[error("post_logout_redirect_uri '{0}' is not registered for the client")]
+    UnregisteredRedirectUri(String),
+}
+
+impl IntoResponse for LogoutError {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+    }
+}
+
+pub async fn get(
+    State(state): State<state::AppState>,
+    headers: HeaderMap,
+    Query(request): Query<LogoutRequest>,
+) -> Result<Response, LogoutError> {
+    logout(state, headers, request).await
+}
+
+pub async fn post(
+    State(state): State<state::AppState>,
+    headers: HeaderMap,
+    Form(request): Form<LogoutRequest>,
+) -> Result<Response, LogoutError> {
+    logout(state, headers, request).await
+}
+
+/// End the single sign-on session of the user and notify the clients they
+/// signed in to. A session of another user than the one of the id token hint
+/// is left alone.
+async fn logout(
+    state: state::AppState,
+    headers: HeaderMap,
+    request: LogoutRequest,
+) -> Result<Response, LogoutError> {
+    let hint = match &request.id_token_hint {
+        Some(id_token) => {
+            let (jwks, issuer_url) = {
+                let issuer = state.issuer.lock().unwrap();
+                (issuer.jwks(), issuer.url.clone())
+            };
+            Some(token::verify_id_token_hint(id_token, &jwks, &issuer_url)?)
+        }
+        None => None,
+    };
+    if let (Some(client_id), Some(hint)) = (&request.client_id, &hint)
+        && *client_id != hint.client_id
+    {
+        return Err(LogoutError::ClientMismatch);
+    }
+    let redirect_uri = match &request.post_logout_redirect_uri {
+        Some(uri) => {
+            // Without a token the client issued to the user, anyone could
+            // send users to the uri through the logout endpoint
+            let hint = hint.as_ref().ok_or(LogoutError::MissingIdTokenHint)?;
+            let unregistered = || LogoutError::UnregisteredRedirectUri(uri.clone());
+            let url = url::Url::parse(uri).map_err(|_| unregistered())?;
+            let db = state.db.lock().unwrap();
+            db.oauth_clients
+                .query_by_id(&hint.client_id)
+                .filter(|client| client.allows_post_logout_redirect(&url))
+                .ok_or_else(unregistered)?;
+            Some(url)
+        }
+        None => None,
+    };
+
+    let session = extract_sid_from_request_headers(&headers).and_then(|sid| {
+        let mut sessions = state.sessions.lock().unwrap();
+        let uid = sessions.user_session_mut(&sid)?.get_uid();
+        if hint.as_ref().is_some_and(|hint| hint.uid != uid) {
+            return None;
+        }
+        let session = sessions.remove_user(&sid);
+        if let Err(e) = sessions.save() {
+            error!("Could not persist session database: {e}");
+        }
+        session
+    });
+
+    if let Some(session) = session {
+        notify_clients(&state, &session).await;
+    }
+
+    let cookie = Cookie::build(("sid", ""))
+        .path("/")
+        .http_only(true)
+        .max_age(cookie::time::Duration::ZERO)
+        .build();
+    let mut set_cookie = HeaderMap::new();
+    set_cookie.insert(header::SET_COOKIE, cookie.to_string().parse().unwrap());
+
+    Ok(match redirect_uri {
+        Some(mut url) => {
+            if let Some(state) = &request.state {
+                url.query_pairs_mut().append_pair("state", state);
+            }
+            (set_cookie, Redirect::to(url.as_str())).into_response()
+        }
+        None => (set_cookie, Html("Logout successful")).into_response(),
+    })
+}
+
+/// Send back-channel logout tokens to the clients the user signed in to
+/// during the session that ended
+pub(crate) async fn notify_clients(state: &state::AppState, session: &UserSession) {
+    let uid = session.get_uid();
+    let uris: Vec<_> = {
+        let db = state.db.lock().unwrap();
+        session
+            .clients()
+            .filter_map(|client_id| {
+                let client = db.oauth_clients.query_by_id(client_id)?;
+                Some((client_id.clone(), client.backchannel_logout_uri.clone()?))
+            })
+            .collect()
+    };
+    let notifications = {
+        let issuer = state.issuer.lock().unwrap();
+        let key = issuer.signing_key();
+        uris.into_iter()
+            .filter_map(|(client_id, uri)| {
+                let logout_token = token::issue_logout_token(
+                    uid,
+                    &client_id,
+                    issuer.url.clone(),
+                    Some(key.kid().to_string()),
+                    key.encoding_key(),
+                )
+                .inspect_err(|e| error!("Could not issue logout token: {e}"))
+                .ok()?;
+                Some(Notification {
+                    client_id,
+                    uri,
+                    logout_token,
+                })
+            })
+            .collect()
+    };
+    logout::notify(notifications).await;
+}
@@ -1,2 +1,4 @@
 pub mod issuer;
 pub mod jwk;
+pub mod jwks;
+pub mod password_policy;
@@ -9,10 +9,10 @@ use axum::response::{IntoResponse, Response};
     path="/meta/jwk",
     tag = "Experimental",
     responses(
-        (status = OK, description = "Jwk that has to be used to verify issued tokens", body = serde_json::Value)
+        (status = OK, description = "Jwk of the active signing key, see /meta/jwks for all keys that have to be used to verify issued tokens", body = serde_json::Value)
     )
 )]
 pub async fn get(State(state): State<state::AppState>) -> Response {
-    let jwk = state.issuer.lock().unwrap().jwk.clone();
+    let jwk = state.issuer.lock().unwrap().signing_key().jwk().clone();
     (StatusCode::OK, Json(jwk)).into_response()
 }
@@ -0,0 +1,15 @@
+use crate::model::password::PasswordPolicy;
+use crate::state;
+use axum::Json;
+use axum::extract::State;
+
+#[utoipa::path(
+    get,
+    path="/meta/password-policy",
+    responses(
+        (status = OK, description = "Rules that passwords of users have to follow", body = PasswordPolicy)
+    )
+)]
+pub async fn get(State(state): State<state::AppState>) -> Json<PasswordPolicy> {
+    Json(state.password_policy.clone())
+}
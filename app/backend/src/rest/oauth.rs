@@ -1,2 +1,5 @@
 pub mod authorize;
+pub mod introspect;
+pub mod register;
+pub mod revoke;
 pub mod token;
@@ -1,20 +1,189 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+
+use crate::model::group::GroupId;
+use crate::model::opaque_token::digest;
 use crate::model::session::LoginSession;
+use crate::model::user::UserId;
+use crate::oauth::registrar::Registrar;
+use crate::oauth::{oidc, scope};
+use crate::persist::Db;
+use crate::rest::login::DENY_FRAMING;
 use crate::state::AppState;
+use askama::Template;
 use axum::extract::{RawQuery, State};
 use axum::http::{HeaderMap, StatusCode, header};
-use axum::response::{IntoResponse, Redirect};
+use axum::response::{IntoResponse, Redirect, Response};
 use cookie::{Cookie, time};
-use oxide_auth::endpoint::{OwnerConsent, Solicitation};
+use oxide_auth::endpoint::{
+    AuthorizationFlow, OwnerConsent, PreGrant, QueryParameter, Solicitation,
+};
 use oxide_auth::frontends::simple::endpoint::{FnSolicitor, Vacant};
-use oxide_auth_axum::OAuthRequest;
+use oxide_auth::frontends::simple::extensions::{AddonList, Extended, Pkce};
+use oxide_auth::primitives::registrar::{ClientUrl, ExactUrl, Registrar as _};
+use oxide_auth_axum::{OAuthRequest, OAuthResponse};
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use tracing::{debug, error};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RedirectQuery {
     redirect_uri: Option<String>,
 }
 
+/// Parameters of the authorization request that fence evaluates itself,
+/// before oxide-auth handles the request
+#[derive(Debug, Default)]
+struct AuthorizeParams {
+    client_id: Option<String>,
+    redirect_uri: Option<String>,
+    state: Option<String>,
+    /// `prompt=none`: fail instead of showing the login or consent page
+    prompt_none: bool,
+    /// `prompt=login`: the user has to log in again even with a session
+    prompt_login: bool,
+    max_age: Option<chrono::Duration>,
+}
+
+impl AuthorizeParams {
+    /// `None` if a parameter is invalid
+    fn parse(query: &str) -> Option<Self> {
+        let mut params = Self::default();
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "client_id" => params.client_id = Some(value.into_owned()),
+                "redirect_uri" => params.redirect_uri = Some(value.into_owned()),
+                "state" => params.state = Some(value.into_owned()),
+                "prompt" => {
+                    let prompts: Vec<_> = value.split_whitespace().collect();
+                    params.prompt_none = prompts.contains(&"none");
+                    params.prompt_login = prompts.contains(&"login");
+                    if params.prompt_none && prompts.len() > 1 {
+                        return None;
+                    }
+                }
+                "max_age" => {
+                    let seconds = value.parse::<u32>().ok()?;
+                    params.max_age = Some(chrono::Duration::seconds(seconds.into()));
+                }
+                _ => {}
+            }
+        }
+        Some(params)
+    }
+}
+
+/// The query to continue with once the user logged in. The login satisfies
+/// `prompt=login` and `max_age`, so they must not send the user back to the
+/// login page.
+fn query_after_login(query: &str) -> String {
+    form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(
+            form_urlencoded::parse(query.as_bytes())
+                .filter(|(key, value)| key != "max_age" && !(key == "prompt" && value == "login")),
+        )
+        .finish()
+}
+
+fn redirect_with_error(mut redirect_uri: url::Url, error: &str, state: Option<&str>) -> Response {
+    redirect_uri.query_pairs_mut().append_pair("error", error);
+    if let Some(state) = state {
+        redirect_uri.query_pairs_mut().append_pair("state", state);
+    }
+    (
+        StatusCode::FOUND,
+        [(header::LOCATION, redirect_uri.to_string())],
+    )
+        .into_response()
+}
+
+/// Report an error to the client, provided the redirect uri is registered
+fn error_redirect(registrar: &Registrar, params: &AuthorizeParams, error: &str) -> Response {
+    let invalid = || (StatusCode::BAD_REQUEST, "Invalid OAuth request").into_response();
+    let Some(client_id) = params.client_id.as_deref() else {
+        return invalid();
+    };
+    let Ok(redirect_uri) = params.redirect_uri.clone().map(ExactUrl::new).transpose() else {
+        return invalid();
+    };
+    let bound = registrar.bound_redirect(ClientUrl {
+        client_id: Cow::Borrowed(client_id),
+        redirect_uri: redirect_uri.map(Cow::Owned),
+    });
+    match bound {
+        Ok(bound) => {
+            redirect_with_error(bound.redirect_uri.to_url(), error, params.state.as_deref())
+        }
+        Err(_) => invalid(),
+    }
+}
+
+#[derive(Template)]
+#[template(path = "consent.html")]
+struct ConsentTemplate<'a> {
+    client_name: &'a str,
+    scopes: Vec<String>,
+    query: &'a str,
+    csrf_token: &'a str,
+}
+
+/// Ties the consent form to the session of the user, so that other sites
+/// cannot submit it on their behalf
+fn csrf_token(sid: &str) -> String {
+    digest(&format!("consent:{sid}"))
+}
+
+fn describe_scope(db: &Db, scope: &str) -> String {
+    match scope {
+        oidc::OPENID_SCOPE => "Confirm your identity".to_string(),
+        oidc::PROFILE_SCOPE => "Read your name and username".to_string(),
+        scope::ALL_ROLES => "Act with all of your roles".to_string(),
+        group => match db.groups.query_by_id(&GroupId::from(group.to_string())) {
+            Some(group) => format!("Act with your role {}", group.name),
+            None => format!("Use the scope {group}"),
+        },
+    }
+}
+
+/// Whether the user has to be asked before the client gets the pre grant
+fn needs_consent(db: &Db, uid: UserId, pre_grant: &PreGrant) -> bool {
+    let first_party = db
+        .oauth_clients
+        .query_by_id(&pre_grant.client_id)
+        .is_some_and(|client| client.first_party);
+    let consented = db
+        .consents
+        .query(uid, &pre_grant.client_id)
+        .is_some_and(|consent| consent.covers(&pre_grant.scope));
+    !first_party && !consented
+}
+
+fn consent_page(db: &Db, pre_grant: &PreGrant, query: &str, csrf_token: &str) -> OAuthResponse {
+    let client_name = db
+        .oauth_clients
+        .query_by_id(&pre_grant.client_id)
+        .map_or(pre_grant.client_id.as_str(), |client| client.name.as_str());
+    let html = ConsentTemplate {
+        client_name,
+        scopes: pre_grant
+            .scope
+            .iter()
+            .map(|scope| describe_scope(db, scope))
+            .collect(),
+        query,
+        csrf_token,
+    }
+    .render()
+    .unwrap();
+    OAuthResponse::default()
+        .content_type("text/html; charset=utf-8")
+        .unwrap()
+        .body(&html)
+}
+
+/// Authorization endpoint. Logged in users are asked for consent unless the
+/// client is a first party client or they consented before, the consent page
+/// posts the decision back to this endpoint. The single sign-on session of
+/// the user is shared by all clients until it expires.
 pub async fn get(
     State(state): State<AppState>,
     RawQuery(raw_query): RawQuery,
@@ -30,18 +199,40 @@ pub async fn get(
         .find(|cookie| cookie.name() == "sid")
         .map(|cookie| cookie.value().to_string());
 
-    /* Try to find matching user_session (i.e. user is logged in) */
-    let mut user_sessions = state.user_sessions.lock().unwrap();
-    let user_session = match sid {
-        Some(ref sid) => user_sessions.take(sid.as_str()),
-        None => None,
+    let query = raw_query.unwrap_or_default();
+    let params = match AuthorizeParams::parse(&query) {
+        Some(params) => params,
+        None => return (StatusCode::BAD_REQUEST, "Invalid OAuth request").into_response(),
     };
 
-    /* Either user has no sid or is not logged in -> redirect to login page */
-    if sid.is_none() || user_session.is_none() {
-        let session = LoginSession::new(raw_query.unwrap());
+    /* Try to find matching user_session (i.e. user is logged in) */
+    let mut sessions = state.sessions.lock().unwrap();
+    let user_session = sid
+        .as_deref()
+        .and_then(|sid| Some((sid, sessions.user_session_mut(sid)?)))
+        .filter(|(_, session)| {
+            !params.prompt_login
+                && !params
+                    .max_age
+                    .is_some_and(|max_age| session.is_older_than(max_age))
+        })
+        .map(|(sid, session)| {
+            session.touch();
+            (sid, session.get_uid(), session.get_auth_time())
+        });
+
+    /* User is not logged in or has to authenticate again -> redirect to login page */
+    let Some((sid, uid, auth_time)) = user_session else {
+        if params.prompt_none {
+            drop(sessions);
+            return error_redirect(&state.registrar.lock().unwrap(), &params, "login_required");
+        }
+        let sid = sessions.insert_login(LoginSession::new(query_after_login(&query)));
+        if let Err(e) = sessions.save() {
+            error!("Could not persist session database: {e}");
+        }
 
-        let cookie = Cookie::build(("sid", session.get_sid()))
+        let cookie = Cookie::build(("sid", sid))
             .path("/")
             .http_only(true)
             .max_age(time::Duration::minutes(5))
@@ -50,21 +241,59 @@ pub async fn get(
         let mut set_cookie = HeaderMap::new();
         set_cookie.insert(header::SET_COOKIE, cookie.to_string().parse().unwrap());
 
-        let mut login_sessions = state.login_sessions.lock().unwrap();
-        login_sessions.insert(session);
-
         return (set_cookie, Redirect::to("/login")).into_response();
-    }
-
-    let user_session = user_session.unwrap();
-    let uid = user_session.get_uid();
-    let solicitor = FnSolicitor(
-        move |_req: &mut oxide_auth_axum::OAuthRequest, _pre_grant: Solicitation| {
-            OwnerConsent::<oxide_auth_axum::OAuthResponse>::Authorized(uid.to_string())
-        },
-    );
+    };
+    drop(sessions);
 
     let mut registrar = state.registrar.lock().unwrap();
+    let csrf_token = csrf_token(sid);
+    let pkce = match &params.client_id {
+        Some(client_id) if !registrar.requires_pkce(client_id) => Pkce::optional(),
+        _ => Pkce::required(),
+    };
+    let mut addons = AddonList::new();
+    addons.push_authorization(oidc::Addon::authorization(auth_time));
+    addons.push_authorization(pkce);
+    let consent_required = Cell::new(None);
+    let authorized_client = Cell::new(None);
+    let solicitor = FnSolicitor(|req: &mut OAuthRequest, solicitation: Solicitation| {
+        let pre_grant = solicitation.pre_grant();
+        let decision = req
+            .body()
+            .filter(|form| form.unique_value("csrf_token").as_deref() == Some(csrf_token.as_str()))
+            .and_then(|form| {
+                let remember = form.unique_value("remember").is_some();
+                form.unique_value("consent")
+                    .map(|consent| (consent == "allow", remember))
+            });
+        let mut db = state.db.lock().unwrap();
+        let consent = match decision {
+            Some((true, remember)) => {
+                if remember {
+                    db.consents
+                        .grant(uid, pre_grant.client_id.clone(), &pre_grant.scope);
+                    if let Err(e) = db.consents.save() {
+                        error!("Could not persist consent database: {e}");
+                    }
+                }
+                OwnerConsent::Authorized(uid.to_string())
+            }
+            Some((false, _)) => OwnerConsent::Denied,
+            None if needs_consent(&db, uid, pre_grant) => {
+                if params.prompt_none {
+                    consent_required.set(Some(pre_grant.redirect_uri.to_url()));
+                    return OwnerConsent::Denied;
+                }
+                OwnerConsent::InProgress(consent_page(&db, pre_grant, &query, &csrf_token))
+            }
+            None => OwnerConsent::Authorized(uid.to_string()),
+        };
+        if let OwnerConsent::Authorized(_) = consent {
+            authorized_client.set(Some(pre_grant.client_id.clone()));
+        }
+        consent
+    });
+
     let mut authorizer = state.authorizer.lock().unwrap();
     let mut issuer = state.issuer.lock().unwrap();
 
@@ -78,10 +307,31 @@ pub async fn get(
     };
     debug!("Triggering authorization_flow()");
 
-    let resp = ep.authorization_flow().execute(req);
+    let resp = AuthorizationFlow::prepare(Extended::extend_with(ep, addons))
+        .and_then(|mut flow| flow.execute(req));
+
+    if let Some(redirect_uri) = consent_required.take() {
+        return redirect_with_error(redirect_uri, "consent_required", params.state.as_deref());
+    }
+
+    /* Remember the client for back-channel logout */
+    if let Some(client_id) = authorized_client.take()
+        && resp.is_ok()
+    {
+        let mut sessions = state.sessions.lock().unwrap();
+        if sessions
+            .user_session_mut(sid)
+            .is_some_and(|session| session.add_client(client_id))
+            && let Err(e) = sessions.save()
+        {
+            error!("Could not persist session database: {e}");
+        }
+    }
 
     match resp {
-        Ok(r) => r.into_response(),
+        // OAuthResponse takes no other headers, so the consent page gets
+        // them here
+        Ok(r) => (DENY_FRAMING, r).into_response(),
         Err(e) => {
             debug!("{:#?}", e);
             (StatusCode::BAD_REQUEST, "Invalid OAuth request").into_response()
@@ -0,0 +1,111 @@
+use crate::middleware::client_info::ClientInfo;
+use crate::oauth::client_auth::{self, form_value};
+use crate::state::AppState;
+use crate::token::{self, Subject, VerifiedToken};
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use tracing::debug;
+
+/// Response of the introspection endpoint (RFC 7662). Everything except
+/// `active` is omitted for inactive tokens.
+#[derive(Debug, Default, Serialize)]
+struct Introspection {
+    active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<String>,
+    /// Whether the token was issued to a `user` or a `client`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subject_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iss: Option<url::Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jti: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    roles: Option<Vec<String>>,
+}
+
+impl Introspection {
+    fn active(verified: VerifiedToken, issuer: url::Url) -> Self {
+        let (sub, subject_type) = match verified.subject {
+            Subject::User(uid) => (uid.to_string(), "user"),
+            Subject::Client(cid) => (cid.to_string(), "client"),
+        };
+        let mut roles: Vec<_> = verified.roles.0.into_iter().collect();
+        roles.sort();
+        Self {
+            active: true,
+            sub: Some(sub),
+            subject_type: Some(subject_type),
+            client_id: verified.client_id,
+            scope: verified.scope,
+            token_type: Some("Bearer"),
+            exp: Some(verified.expires_at.timestamp()),
+            iss: Some(issuer),
+            jti: verified.jti,
+            roles: Some(roles),
+        }
+    }
+}
+
+/// Token introspection (RFC 7662) for resource servers that can not verify
+/// tokens themselves. Callers authenticate as a client of the `ClientDB`.
+pub async fn post(
+    State(state): State<AppState>,
+    client_info: ClientInfo,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Response {
+    let form: Vec<(String, String)> = form_urlencoded::parse(&body).into_owned().collect();
+    {
+        let db = state.db.lock().unwrap();
+        if let Err(e) = client_auth::authenticate_throttled(
+            &db.clients,
+            &state.failed_attempts,
+            &client_info,
+            &headers,
+            &form,
+        ) {
+            return e.into_response();
+        }
+    }
+    let Some(token) = form_value(&form, "token") else {
+        return (StatusCode::BAD_REQUEST, "Missing token").into_response();
+    };
+
+    let (jwks, issuer) = {
+        let issuer = state.issuer.lock().unwrap();
+        (issuer.jwks(), issuer.url.clone())
+    };
+    let db = state.db.lock().unwrap();
+    let verified = match token::verify(token, &jwks, &issuer, &db.revoked_tokens) {
+        Ok(verified) => verified,
+        Err(e) => {
+            debug!("Introspected token is not active: {e}");
+            return Json(Introspection::default()).into_response();
+        }
+    };
+    // Tokens of deleted users or clients are no longer active
+    let subject_exists = match verified.subject {
+        Subject::User(uid) => db.users.query_by_uid(uid).is_some(),
+        Subject::Client(cid) => db.clients.query_by_id(cid).is_some(),
+    };
+    if !subject_exists {
+        debug!(
+            "Subject {} of introspected token does not exist",
+            verified.subject
+        );
+        return Json(Introspection::default()).into_response();
+    }
+    Json(Introspection::active(verified, issuer)).into_response()
+}
@@ -0,0 +1,142 @@
+use crate::middleware::token::AuthToken;
+use crate::model::client::ClientId;
+use crate::model::client::Registration;
+use crate::model::client_registration::{ClientInformation, ClientMetadata, InvalidClientMetadata};
+use crate::model::opaque_token::{self, digest};
+use crate::oauth::discovery;
+use crate::persist::client_db::{InsertClientError, UpdateClientError};
+use crate::rest::clients::{CreateClientError, new_client};
+use crate::state::AppState;
+use axum::extract::{Json, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use thiserror::Error;
+
+pub mod cid;
+
+#[derive(Debug, Error)]
+pub(crate) enum RegistrationError {
+    #[error("Missing or invalid access token")]
+    InvalidToken,
+    #[error(transparent)]
+    InvalidClientMetadata(#[from] InvalidClientMetadata),
+    #[error(transparent)]
+    Create(#[from] CreateClientError),
+    #[error(transparent)]
+    Update(#[from] UpdateClientError),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+/// Error response of RFC 7591, section 3.2.2
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: &'static str,
+    error_description: String,
+}
+
+impl IntoResponse for RegistrationError {
+    fn into_response(self) -> Response {
+        let (status, error) = match &self {
+            Self::InvalidToken => (StatusCode::UNAUTHORIZED, "invalid_token"),
+            Self::InvalidClientMetadata(_)
+            | Self::Create(CreateClientError::InvalidCertificate(_))
+            | Self::Create(CreateClientError::Insert(InsertClientError::DuplicateName(_)))
+            | Self::Update(UpdateClientError::DuplicateName(_)) => {
+                (StatusCode::BAD_REQUEST, "invalid_client_metadata")
+            }
+            Self::Create(CreateClientError::Internal(_))
+            | Self::Update(UpdateClientError::NotFound(_) | UpdateClientError::ReadOnly(_))
+            | Self::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "server_error"),
+        };
+        let body = Json(ErrorResponse {
+            error,
+            error_description: self.to_string(),
+        });
+        if status == StatusCode::UNAUTHORIZED {
+            let challenge = [(header::WWW_AUTHENTICATE, "Bearer error=\"invalid_token\"")];
+            (status, challenge, body).into_response()
+        } else {
+            (status, body).into_response()
+        }
+    }
+}
+
+pub(crate) fn registration_client_uri(state: &AppState, cid: ClientId) -> url::Url {
+    let issuer = state.issuer.lock().unwrap().url.clone();
+    discovery::endpoint(&issuer, &format!("/oauth/register/{cid}"))
+}
+
+/// Dynamic client registration (RFC 7591) authorized by an initial access
+/// token, which is only spent once the client is registered
+#[utoipa::path(
+    post,
+    path="/oauth/register",
+    responses(
+        (status = CREATED, description = "Client was registered", body = ClientInformation),
+        (status = BAD_REQUEST, description = "Invalid client metadata"),
+        (status = UNAUTHORIZED, description = "Missing or invalid initial access token"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error"),
+    ),
+    request_body(content = ClientMetadata)
+)]
+pub async fn post(
+    State(state): State<AppState>,
+    AuthToken(token): AuthToken,
+    Json(metadata): Json<ClientMetadata>,
+) -> Response {
+    match register(&state, token.as_deref(), metadata) {
+        Ok(information) => (StatusCode::CREATED, Json(information)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+fn register(
+    state: &AppState,
+    token: Option<&str>,
+    metadata: ClientMetadata,
+) -> Result<ClientInformation, RegistrationError> {
+    let token = token.ok_or(RegistrationError::InvalidToken)?;
+    let allowed_groups = {
+        let db = state.db.lock().unwrap();
+        db.initial_access_tokens
+            .query(token)
+            .ok_or(RegistrationError::InvalidToken)?
+            .groups
+            .clone()
+    };
+
+    metadata.check_grant_types()?;
+    let create = crate::model::client::CreateClient {
+        name: metadata.client_name()?,
+        auth_method: metadata.auth_method()?,
+        groups: metadata.groups(&allowed_groups)?,
+    };
+    let registration_access_token = opaque_token::generate();
+    let registration = Registration {
+        access_token: digest(&registration_access_token),
+        allowed_groups,
+    };
+    let (client, created) = new_client(create, Some(registration))?;
+    let mut information =
+        ClientInformation::new(&client, registration_client_uri(state, client.id));
+
+    {
+        // Redeemed together with the insert, so the token is neither spent on
+        // a failed registration nor used for two registrations at once
+        let mut db = state.db.lock().unwrap();
+        if db.initial_access_tokens.query(token).is_none() {
+            return Err(RegistrationError::InvalidToken);
+        }
+        db.clients.insert(client).map_err(CreateClientError::from)?;
+        db.initial_access_tokens.redeem(token);
+        db.initial_access_tokens.save()?;
+        db.clients.save()?;
+    }
+    information.client_secret = created.secret;
+    information.certificate = created.certificate;
+    information.private_key = created.private_key;
+    information.registration_access_token = Some(registration_access_token);
+    Ok(information)
+}
@@ -0,0 +1,137 @@
+use crate::middleware::token::AuthToken;
+use crate::model::client::{Client, ClientId};
+use crate::model::client_registration::{ClientInformation, ClientMetadata};
+use crate::model::opaque_token::digest;
+use crate::persist::client_db::ClientDB;
+use crate::rest::oauth::register::{RegistrationError, registration_client_uri};
+use crate::state::AppState;
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+/// Look up a dynamically registered client by its registration access token.
+/// Unknown clients are reported like invalid tokens, so the endpoint does not
+/// reveal which clients exist.
+fn authorize<'a>(
+    clients: &'a ClientDB,
+    cid: ClientId,
+    token: Option<&str>,
+) -> Result<&'a Client, RegistrationError> {
+    let token = token.ok_or(RegistrationError::InvalidToken)?;
+    clients
+        .query_by_id(cid)
+        .filter(|client| {
+            client
+                .registration
+                .as_ref()
+                .is_some_and(|registration| registration.access_token == digest(token))
+        })
+        .ok_or(RegistrationError::InvalidToken)
+}
+
+/// Read the current registration of the client (RFC 7592). Secrets are only
+/// returned when the client is registered.
+#[utoipa::path(
+    get,
+    path="/oauth/register/{cid}",
+    responses(
+        (status = OK, description = "Registration of the client", body = ClientInformation),
+        (status = UNAUTHORIZED, description = "Missing or invalid registration access token"),
+    ),
+    params(
+        ("cid" = String, description = "Client UUID")
+    )
+)]
+pub async fn get(
+    State(state): State<AppState>,
+    AuthToken(token): AuthToken,
+    Path(cid): Path<ClientId>,
+) -> Response {
+    let uri = registration_client_uri(&state, cid);
+    let db = state.db.lock().unwrap();
+    match authorize(&db.clients, cid, token.as_deref()) {
+        Ok(client) => Json(ClientInformation::new(client, uri)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Replace the metadata of the client (RFC 7592), omitted values are reset to
+/// their defaults. Name and scope can be changed, the authentication method
+/// cannot.
+#[utoipa::path(
+    put,
+    path="/oauth/register/{cid}",
+    responses(
+        (status = OK, description = "Registration was updated", body = ClientInformation),
+        (status = BAD_REQUEST, description = "Invalid client metadata"),
+        (status = UNAUTHORIZED, description = "Missing or invalid registration access token"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error"),
+    ),
+    params(
+        ("cid" = String, description = "Client UUID")
+    ),
+    request_body(content = ClientMetadata)
+)]
+pub async fn put(
+    State(state): State<AppState>,
+    AuthToken(token): AuthToken,
+    Path(cid): Path<ClientId>,
+    Json(metadata): Json<ClientMetadata>,
+) -> Response {
+    match update(&state, cid, token.as_deref(), metadata) {
+        Ok(information) => Json(information).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+fn update(
+    state: &AppState,
+    cid: ClientId,
+    token: Option<&str>,
+    metadata: ClientMetadata,
+) -> Result<ClientInformation, RegistrationError> {
+    let uri = registration_client_uri(state, cid);
+    let mut db = state.db.lock().unwrap();
+    let client = authorize(&db.clients, cid, token)?;
+    metadata.check_update_of(client)?;
+    let allowed_groups = client
+        .registration
+        .as_ref()
+        .map(|registration| &registration.allowed_groups)
+        .ok_or(RegistrationError::InvalidToken)?;
+    let groups = metadata.groups(allowed_groups)?;
+    db.clients.update(cid, metadata.client_name()?, groups)?;
+    db.clients.save()?;
+    let client = authorize(&db.clients, cid, token)?;
+    Ok(ClientInformation::new(client, uri))
+}
+
+#[utoipa::path(
+    delete,
+    path="/oauth/register/{cid}",
+    responses(
+        (status = NO_CONTENT, description = "Client was deleted"),
+        (status = UNAUTHORIZED, description = "Missing or invalid registration access token"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error"),
+    ),
+    params(
+        ("cid" = String, description = "Client UUID")
+    )
+)]
+pub async fn delete(
+    State(state): State<AppState>,
+    AuthToken(token): AuthToken,
+    Path(cid): Path<ClientId>,
+) -> Response {
+    let mut db = state.db.lock().unwrap();
+    if let Err(e) = authorize(&db.clients, cid, token.as_deref()) {
+        return e.into_response();
+    }
+    if let Err(e) = db.clients.remove(cid) {
+        return RegistrationError::Internal(e.into()).into_response();
+    }
+    if let Err(e) = db.clients.save() {
+        return RegistrationError::Internal(e).into_response();
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
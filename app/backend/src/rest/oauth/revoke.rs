@@ -0,0 +1,137 @@
+use crate::middleware::client_info::ClientInfo;
+use crate::oauth::client_auth::{self, ClientAuthError, form_value};
+use crate::persist::refresh_token_db::Lookup;
+use crate::state::AppState;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use oxide_auth::primitives::registrar::Registrar;
+use tracing::{debug, info};
+
+/// Token revocation (RFC 7009) for access and refresh tokens. Clients have to
+/// authenticate and may only revoke tokens issued to them, tokens of other
+/// clients are ignored. As required by the RFC, unknown or invalid tokens are
+/// answered with success as well.
+pub async fn post(
+    State(state): State<AppState>,
+    client_info: ClientInfo,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Response {
+    let form: Vec<(String, String)> = form_urlencoded::parse(&body).into_owned().collect();
+    let client_id = match authenticate(&state, &client_info, &headers, &form) {
+        Ok(client_id) => client_id,
+        Err(e) => return e.into_response(),
+    };
+    let Some(token) = form_value(&form, "token") else {
+        return (StatusCode::BAD_REQUEST, "Missing token").into_response();
+    };
+    let result = match form_value(&form, "token_type_hint") {
+        Some("access_token") => match revoke_access_token(&state, &client_id, token) {
+            Ok(false) => revoke_refresh_token(&state, &client_id, token),
+            result => result,
+        },
+        _ => match revoke_refresh_token(&state, &client_id, token) {
+            Ok(false) => revoke_access_token(&state, &client_id, token),
+            result => result,
+        },
+    };
+    match result {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Returns the id of the client asking for the revocation. Clients of the
+/// authorization code flow authenticate like at the token endpoint, public
+/// ones by their id alone, machine clients like at the introspection endpoint.
+fn authenticate(
+    state: &AppState,
+    client_info: &ClientInfo,
+    headers: &HeaderMap,
+    form: &[(String, String)],
+) -> Result<String, ClientAuthError> {
+    let client_id =
+        client_auth::client_id(headers, form).ok_or(ClientAuthError::MissingClientId)?;
+    {
+        let db = state.db.lock().unwrap();
+        if db.oauth_clients.query_by_id(&client_id).is_none() {
+            return client_auth::authenticate_throttled(
+                &db.clients,
+                &state.failed_attempts,
+                client_info,
+                headers,
+                form,
+            )
+            .map(|client| client.id.to_string());
+        }
+    }
+    let secret = client_auth::extract_basic_auth(headers)
+        .map(|(_, secret)| secret)
+        .or_else(|| form_value(form, "client_secret").map(str::to_string));
+    let attempt =
+        client_auth::check_throttle(&state.failed_attempts, client_id.clone(), client_info)?;
+    let success = state
+        .registrar
+        .lock()
+        .unwrap()
+        .check(&client_id, secret.as_deref().map(str::as_bytes))
+        .is_ok();
+    client_auth::record_attempt(attempt, success);
+    match success {
+        true => Ok(client_id),
+        false => Err(ClientAuthError::InvalidClientSecret),
+    }
+}
+
+/// Returns `true` if the token was a refresh token, revoking its whole family
+/// if it was issued to `client_id`
+fn revoke_refresh_token(state: &AppState, client_id: &str, token: &str) -> anyhow::Result<bool> {
+    let mut db = state.db.lock().unwrap();
+    let family = match db.refresh_tokens.find(token) {
+        Lookup::Valid(family) => family,
+        Lookup::Reused(id) => match db.refresh_tokens.query_by_id(id) {
+            Some(family) => family,
+            None => return Ok(false),
+        },
+        Lookup::Unknown => return Ok(false),
+    };
+    let id = family.id;
+    if family.client_id != client_id {
+        debug!("Not revoking refresh token family {id} of another client");
+        return Ok(true);
+    }
+    info!("Revoking refresh token family {id}");
+    db.refresh_tokens.revoke(id);
+    db.refresh_tokens.save()?;
+    Ok(true)
+}
+
+/// Returns `true` if the token was a valid access token, revoking it if it
+/// was issued to `client_id`
+fn revoke_access_token(state: &AppState, client_id: &str, token: &str) -> anyhow::Result<bool> {
+    let (jwks, issuer) = {
+        let issuer = state.issuer.lock().unwrap();
+        (issuer.jwks(), issuer.url.clone())
+    };
+    let id = match crate::token::identify(token, &jwks, &issuer) {
+        Ok(Some(id)) => id,
+        Ok(None) => {
+            debug!("Access token without jti can not be revoked");
+            return Ok(true);
+        }
+        Err(e) => {
+            debug!("Not revoking invalid access token: {e}");
+            return Ok(false);
+        }
+    };
+    if id.client_id.as_deref() != Some(client_id) {
+        debug!("Not revoking access token {} of another client", id.jti);
+        return Ok(true);
+    }
+    info!("Revoking access token {}", id.jti);
+    let mut db = state.db.lock().unwrap();
+    db.revoked_tokens.revoke(id.jti, id.expires_at);
+    db.revoked_tokens.save()?;
+    Ok(true)
+}
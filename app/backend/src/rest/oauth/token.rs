@@ -1,21 +1,27 @@
-use base64::Engine;
-use base64::engine::general_purpose::STANDARD;
-
-use crate::model::client::AuthMethod;
+use crate::middleware::client_info::ClientInfo;
+use crate::oauth::client_auth::{self, form_value};
+use crate::oauth::{oidc, scope};
 use crate::state::AppState;
 use crate::token;
 use axum::extract::{FromRequest, State};
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use oxide_auth::endpoint::AccessTokenFlow;
 use oxide_auth::frontends::simple::endpoint::Vacant;
+use oxide_auth::frontends::simple::extensions::{AddonList, Extended, Pkce};
+use oxide_auth::primitives::registrar::Registrar;
+use oxide_auth::primitives::scope::Scope;
 use oxide_auth_axum::OAuthRequest;
 use tracing::debug;
 
 pub async fn post(
     State(state): State<AppState>,
+    client_info: ClientInfo,
     request: axum::extract::Request,
 ) -> impl IntoResponse {
-    let (parts, body) = request.into_parts();
+    let (mut parts, body) = request.into_parts();
 
     let body_bytes = match axum::body::to_bytes(body, 1024 * 16).await {
         Ok(b) => b,
@@ -23,10 +29,19 @@ pub async fn post(
     };
 
     let form: Vec<(String, String)> = form_urlencoded::parse(&body_bytes).into_owned().collect();
-    let grant_type = get_form_value(&form, "grant_type");
+    let grant_type = form_value(&form, "grant_type");
 
     if grant_type == Some("client_credentials") {
-        return handle_client_credentials(&state, &parts.headers, &form).into_response();
+        return handle_client_credentials(&state, &client_info, &parts.headers, &form)
+            .into_response();
+    }
+
+    let body_bytes = match secret_post_to_basic(&mut parts.headers, &form) {
+        Some(body) => axum::body::Bytes::from(body),
+        None => body_bytes,
+    };
+    if let Err(e) = check_registrar_secret(&state, &client_info, &parts.headers) {
+        return e.into_response();
     }
 
     // Rebuild request for oxide-auth flow
@@ -36,22 +51,35 @@ pub async fn post(
         Err(_) => return (StatusCode::BAD_REQUEST, "Invalid OAuth request").into_response(),
     };
 
-    let mut registrar = state.registrar.lock().unwrap();
-    let mut authorizer = state.authorizer.lock().unwrap();
-    let mut issuer = state.issuer.lock().unwrap();
-
-    let ep = oxide_auth::frontends::simple::endpoint::Generic {
-        registrar: &mut *registrar,
-        authorizer: &mut *authorizer,
-        issuer: &mut *issuer,
-        solicitor: Vacant,
-        scopes: Vacant,
-        response: Vacant,
+    let resp = {
+        let mut registrar = state.registrar.lock().unwrap();
+        let mut authorizer = state.authorizer.lock().unwrap();
+        let mut issuer = state.issuer.lock().unwrap();
+
+        let ep = oxide_auth::frontends::simple::endpoint::Generic {
+            registrar: &mut *registrar,
+            authorizer: &mut *authorizer,
+            issuer: &mut *issuer,
+            solicitor: Vacant,
+            scopes: Vacant,
+            response: Vacant,
+        };
+        if grant_type == Some("refresh_token") {
+            debug!("Triggering refresh_flow()");
+            ep.refresh_flow().execute(oauth_req)
+        } else {
+            debug!("Triggering access_token_flow()");
+            let mut addons = AddonList::new();
+            addons.push_access_token(oidc::Addon::access_token());
+            // Whether a client must use PKCE is enforced when the code is
+            // issued, a code bound to a challenge always requires its verifier
+            addons.push_access_token(Pkce::optional());
+            AccessTokenFlow::prepare(Extended::extend_with(ep, addons))
+                .and_then(|mut flow| flow.execute(oauth_req))
+        }
     };
-    debug!("Triggering access_token_flow()");
-    let resp = ep.access_token_flow().execute(oauth_req);
     match resp {
-        Ok(r) => r.into_response(),
+        Ok(r) => with_id_token(&state, r.into_response()).await,
         Err(e) => {
             debug!("{:#?}", e);
             (StatusCode::BAD_REQUEST, "Invalid OAuth request").into_response()
@@ -59,181 +87,142 @@ pub async fn post(
     }
 }
 
-fn get_form_value<'a>(form: &'a [(String, String)], key: &str) -> Option<&'a str> {
-    form.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+/// Add the id token that the issuer created alongside the access token of a
+/// successful token response, if any
+async fn with_id_token(state: &AppState, response: Response) -> Response {
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, 1024 * 16).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Ok(mut json) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return Response::from_parts(parts, axum::body::Body::from(bytes));
+    };
+    let id_token = json
+        .get("access_token")
+        .and_then(|token| token.as_str())
+        .and_then(|token| state.issuer.lock().unwrap().take_id_token(token));
+    let Some(id_token) = id_token else {
+        return Response::from_parts(parts, axum::body::Body::from(bytes));
+    };
+    json["id_token"] = serde_json::Value::String(id_token);
+    Response::from_parts(parts, axum::body::Body::from(json.to_string()))
 }
 
-const JWT_BEARER_ASSERTION_TYPE: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
-
-fn handle_client_credentials(
-    state: &AppState,
-    headers: &axum::http::HeaderMap,
+/// The flows of oxide-auth only accept client secrets in an HTTP Basic
+/// authorization header, move the credentials of a client using
+/// `client_secret_post` there. Returns the form without them, if moved.
+fn secret_post_to_basic(
+    headers: &mut axum::http::HeaderMap,
     form: &[(String, String)],
-) -> impl IntoResponse {
-    // Check if this is a JWT bearer assertion (certificate auth)
-    if get_form_value(form, "client_assertion_type") == Some(JWT_BEARER_ASSERTION_TYPE) {
-        return handle_certificate_credentials(state, form).into_response();
+) -> Option<String> {
+    if headers.contains_key(header::AUTHORIZATION) {
+        return None;
     }
-
-    handle_secret_credentials(state, headers, form).into_response()
+    let client_id = form_value(form, "client_id")?;
+    let secret = form_value(form, "client_secret")?;
+    let credentials = STANDARD.encode(format!("{client_id}:{secret}"));
+    let authorization = HeaderValue::from_str(&format!("Basic {credentials}")).ok()?;
+    headers.insert(header::AUTHORIZATION, authorization);
+    headers.remove(header::CONTENT_LENGTH);
+    let form = form
+        .iter()
+        .filter(|(key, _)| key != "client_id" && key != "client_secret");
+    Some(
+        form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(form)
+            .finish(),
+    )
 }
 
-fn handle_secret_credentials(
+/// Clients of the registrar authenticate with HTTP Basic auth in the flows of
+/// oxide-auth, which can not tell failed authentications from other errors.
+/// Check their secret beforehand to throttle guessing it.
+fn check_registrar_secret(
     state: &AppState,
+    client_info: &ClientInfo,
     headers: &axum::http::HeaderMap,
-    form: &[(String, String)],
-) -> impl IntoResponse {
-    // Try HTTP Basic Auth first, then fall back to body params
-    let (client_id_str, client_secret) = match extract_basic_auth(headers) {
-        Some((id, secret)) => (id, secret),
-        None => {
-            let Some(id) = get_form_value(form, "client_id") else {
-                return (StatusCode::BAD_REQUEST, "Missing client_id").into_response();
-            };
-            let Some(secret) = get_form_value(form, "client_secret") else {
-                return (StatusCode::BAD_REQUEST, "Missing client_secret").into_response();
-            };
-            (id.to_string(), secret.to_string())
-        }
-    };
-
-    let Ok(client_id) = client_id_str.parse() else {
-        return (StatusCode::UNAUTHORIZED, "Unknown client").into_response();
-    };
-
-    let db = state.db.lock().unwrap();
-    let Some(client) = db.clients.query_by_id(client_id) else {
-        return (StatusCode::UNAUTHORIZED, "Unknown client").into_response();
+) -> Result<(), client_auth::ClientAuthError> {
+    let Some((client_id, secret)) = client_auth::extract_basic_auth(headers) else {
+        return Ok(());
     };
-
-    match &client.auth_method {
-        AuthMethod::Secret { secret } => {
-            if secret.verify(&client_secret).is_err() {
-                return (StatusCode::UNAUTHORIZED, "Invalid client secret").into_response();
-            }
-        }
-        AuthMethod::Certificate { .. } => {
-            return (
-                StatusCode::BAD_REQUEST,
-                "Client uses certificate authentication, not secret",
-            )
-                .into_response();
-        }
-    }
-
-    let groups = client.groups.clone();
-    issue_client_token(state, client_id, &groups, db)
+    let attempt =
+        client_auth::check_throttle(&state.failed_attempts, client_id.clone(), client_info)?;
+    let success = state
+        .registrar
+        .lock()
+        .unwrap()
+        .check(&client_id, Some(secret.as_bytes()))
+        .is_ok();
+    // The flow rejects the client itself, so that errors look as before
+    client_auth::record_attempt(attempt, success);
+    Ok(())
 }
 
-fn handle_certificate_credentials(
+fn handle_client_credentials(
     state: &AppState,
+    client_info: &ClientInfo,
+    headers: &axum::http::HeaderMap,
     form: &[(String, String)],
-) -> impl IntoResponse {
-    let Some(client_id_str) = get_form_value(form, "client_id") else {
-        return (StatusCode::BAD_REQUEST, "Missing client_id").into_response();
-    };
-    let Some(assertion) = get_form_value(form, "client_assertion") else {
-        return (StatusCode::BAD_REQUEST, "Missing client_assertion").into_response();
-    };
-
-    let Ok(client_id) = client_id_str.parse::<uuid::Uuid>() else {
-        return (StatusCode::UNAUTHORIZED, "Unknown client").into_response();
-    };
-
+) -> Response {
     let db = state.db.lock().unwrap();
-    let Some(client) = db.clients.query_by_id(client_id) else {
-        return (StatusCode::UNAUTHORIZED, "Unknown client").into_response();
-    };
-
-    let AuthMethod::Certificate { pem: cert_pem } = &client.auth_method else {
-        return (
-            StatusCode::BAD_REQUEST,
-            "Client uses secret authentication, not certificate",
-        )
-            .into_response();
-    };
-
-    // Extract public key from stored certificate
-    let x509 = match openssl::x509::X509::from_pem(cert_pem.as_bytes()) {
-        Ok(c) => c,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to parse stored certificate: {e}"),
-            )
-                .into_response();
-        }
-    };
-    let pub_key_pem = match x509.public_key().and_then(|k| k.public_key_to_pem()) {
-        Ok(pem) => pem,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to extract public key: {e}"),
-            )
-                .into_response();
-        }
+    let client = match client_auth::authenticate_throttled(
+        &db.clients,
+        &state.failed_attempts,
+        client_info,
+        headers,
+        form,
+    ) {
+        Ok(client) => client,
+        Err(e) => return e.into_response(),
     };
-
-    // Verify the JWT assertion
-    let decoding_key = match jsonwebtoken::DecodingKey::from_rsa_pem(&pub_key_pem) {
-        Ok(k) => k,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to create decoding key: {e}"),
-            )
-                .into_response();
-        }
+    let scope = match form_value(form, "scope")
+        .map(str::parse::<Scope>)
+        .transpose()
+    {
+        Ok(scope) => scope,
+        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid scope").into_response(),
     };
-    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::RS256);
-    validation.set_audience(&["flecs-core-api", "fence-api"]);
-    validation.set_issuer(&[client_id_str]);
-    validation.set_required_spec_claims(&["exp", "aud", "iss", "sub"]);
-
-    let assertion_claims =
-        match jsonwebtoken::decode::<serde_json::Value>(assertion, &decoding_key, &validation) {
-            Ok(data) => data.claims,
-            Err(e) => {
-                return (
-                    StatusCode::UNAUTHORIZED,
-                    format!("Invalid client assertion: {e}"),
-                )
-                    .into_response();
-            }
-        };
-
-    // Verify sub == client_id
-    if assertion_claims.get("sub").and_then(|v| v.as_str()) != Some(client_id_str) {
-        return (
-            StatusCode::UNAUTHORIZED,
-            "Assertion sub must match client_id",
-        )
-            .into_response();
-    }
-
+    let client_id = client.id;
     let groups = client.groups.clone();
-    issue_client_token(state, client_id, &groups, db)
+    issue_client_token(state, client_id, &groups, scope, db)
 }
 
 fn issue_client_token(
     state: &AppState,
     client_id: uuid::Uuid,
     client_groups: &std::collections::HashSet<crate::model::group::GroupId>,
+    scope: Option<Scope>,
     db: std::sync::MutexGuard<'_, crate::persist::Db>,
 ) -> axum::response::Response {
     let groups_vec: Vec<_> = client_groups.iter().cloned().collect();
-    let groups = db.groups.query_groups_with_subgroups(&groups_vec);
-    let roles: Vec<String> = groups.iter().map(|g| g.as_ref().to_string()).collect();
+    let groups = match &scope {
+        None => db.groups.query_groups_with_subgroups(&groups_vec),
+        Some(scope) => scope::scoped_roles(&db.groups, &groups_vec, scope),
+    };
     drop(db);
+    if scope.is_some() && groups.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Requested scope exceeds the groups of the client",
+        )
+            .into_response();
+    }
+    let roles: Vec<String> = groups.iter().map(|g| g.as_ref().to_string()).collect();
+    let scope = scope.map(|scope| scope.to_string());
 
-    let issuer = state.issuer.lock().unwrap();
+    let mut issuer = state.issuer.lock().unwrap();
+    issuer.rotate_if_due();
+    let key = issuer.signing_key();
     let issued = match token::issue_client_token(
         client_id,
         roles,
+        scope.clone(),
         issuer.url.clone(),
-        issuer.jwk.common.key_id.clone(),
-        &issuer.encoding_key,
+        Some(key.kid().to_string()),
+        key.encoding_key(),
     ) {
         Ok(t) => t,
         Err(e) => {
@@ -241,19 +230,13 @@ fn issue_client_token(
         }
     };
 
-    axum::Json(serde_json::json!({
+    let mut body = serde_json::json!({
         "access_token": issued.token,
         "token_type": "Bearer",
         "expires_in": 600
-    }))
-    .into_response()
-}
-
-fn extract_basic_auth(headers: &axum::http::HeaderMap) -> Option<(String, String)> {
-    let auth = headers.get("authorization")?.to_str().ok()?;
-    let encoded = auth.strip_prefix("Basic ")?;
-    let decoded = STANDARD.decode(encoded).ok()?;
-    let decoded_str = String::from_utf8(decoded).ok()?;
-    let (id, secret) = decoded_str.split_once(':')?;
-    Some((id.to_string(), secret.to_string()))
+    });
+    if let Some(scope) = scope {
+        body["scope"] = scope.into();
+    }
+    axum::Json(body).into_response()
 }
@@ -0,0 +1,111 @@
+use crate::model::oauth_client::{
+    self, ClientType, CreateClientType, CreateOAuthClient, CreateOAuthClientResponse, OAuthClient,
+    OAuthClientSummary,
+};
+use crate::model::password::Password;
+use crate::persist::oauth_client_db::InsertOAuthClientError;
+use crate::rest::clients::generate_secret;
+use crate::state;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+pub mod client_id;
+
+#[utoipa::path(
+    get,
+    path="/oauth-clients",
+    responses(
+        (status = OK, description = "List of all OAuth clients", body = Vec<OAuthClientSummary>),
+    )
+)]
+pub async fn get(State(state): State<state::AppState>) -> Json<Vec<OAuthClientSummary>> {
+    let db = state.db.lock().unwrap();
+    let clients = db
+        .oauth_clients
+        .query_all()
+        .map(OAuthClientSummary::from)
+        .collect();
+    Json(clients)
+}
+
+#[utoipa::path(
+    post,
+    path="/oauth-clients",
+    responses(
+        (status = CREATED, description = "OAuth client was registered", body = CreateOAuthClientResponse),
+        (status = CONFLICT, description = "OAuth client with that id already exists", body = String),
+        (status = BAD_REQUEST, description = "Invalid request body", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = CreateOAuthClient)
+)]
+pub async fn post(
+    State(state): State<state::AppState>,
+    Json(create): Json<CreateOAuthClient>,
+) -> Response {
+    let redirect_uris = match oauth_client::parse_redirect_uris(&create.redirect_uris) {
+        Ok(uris) => uris,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let scope = match oauth_client::parse_scope(&create.scope) {
+        Ok(scope) => scope,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let post_logout_redirect_uris =
+        match oauth_client::parse_post_logout_redirect_uris(&create.post_logout_redirect_uris) {
+            Ok(uris) => uris,
+            Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        };
+    let backchannel_logout_uri = match create
+        .backchannel_logout_uri
+        .as_deref()
+        .map(oauth_client::parse_backchannel_logout_uri)
+        .transpose()
+    {
+        Ok(uri) => uri,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let (client_type, secret) = match create.client_type {
+        CreateClientType::Public => (ClientType::Public, None),
+        CreateClientType::Confidential { require_pkce } => {
+            let secret = generate_secret();
+            let hashed = match Password::new(&secret) {
+                Ok(hashed) => hashed,
+                Err(e) => {
+                    return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+                }
+            };
+            let client_type = ClientType::Confidential {
+                secret: hashed,
+                require_pkce,
+            };
+            (client_type, Some(secret))
+        }
+    };
+
+    let client = OAuthClient {
+        id: create.id,
+        name: create.name,
+        client_type,
+        redirect_uris,
+        scope,
+        first_party: create.first_party,
+        post_logout_redirect_uris,
+        backchannel_logout_uri,
+        created_at: chrono::Utc::now(),
+    };
+    let response = CreateOAuthClientResponse {
+        client: OAuthClientSummary::from(&client),
+        secret,
+    };
+
+    let mut db = state.db.lock().unwrap();
+    if let Err(e @ InsertOAuthClientError::DuplicateId(_)) = db.oauth_clients.insert(client) {
+        return (StatusCode::CONFLICT, e.to_string()).into_response();
+    }
+    if let Err(e) = db.oauth_clients.save() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+    (StatusCode::CREATED, Json(response)).into_response()
+}
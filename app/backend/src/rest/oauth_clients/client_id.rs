@@ -0,0 +1,100 @@
+use crate::model::oauth_client::{OAuthClientId, OAuthClientSummary, UpdateOAuthClient};
+use crate::persist::oauth_client_db::{RemoveOAuthClientError, UpdateOAuthClientError};
+use crate::state;
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    get,
+    path="/oauth-clients/{client_id}",
+    responses(
+        (status = OK, description = "Return a single OAuth client", body = OAuthClientSummary),
+        (status = NOT_FOUND, description = "OAuth client does not exist"),
+    ),
+    params(
+        ("client_id" = String, description = "OAuth client ID")
+    )
+)]
+pub async fn get(
+    State(state): State<state::AppState>,
+    Path(client_id): Path<OAuthClientId>,
+) -> Response {
+    let db = state.db.lock().unwrap();
+    match db.oauth_clients.query_by_id(&client_id) {
+        Some(client) => Json(OAuthClientSummary::from(client)).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path="/oauth-clients/{client_id}",
+    responses(
+        (status = NO_CONTENT, description = "OAuth client was updated"),
+        (status = NOT_FOUND, description = "OAuth client does not exist"),
+        (status = BAD_REQUEST, description = "Invalid request body", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    params(
+        ("client_id" = String, description = "OAuth client ID")
+    ),
+    request_body(content = UpdateOAuthClient)
+)]
+pub async fn patch(
+    State(state): State<state::AppState>,
+    Path(client_id): Path<OAuthClientId>,
+    Json(update): Json<UpdateOAuthClient>,
+) -> Response {
+    let mut db = state.db.lock().unwrap();
+    match db.oauth_clients.update(&client_id, update) {
+        Ok(()) => {
+            if let Err(e) = db.oauth_clients.save() {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            if db.consents.revoke_client(&client_id) > 0
+                && let Err(e) = db.consents.save()
+            {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(UpdateOAuthClientError::NotFound(_)) => StatusCode::NOT_FOUND.into_response(),
+        Err(UpdateOAuthClientError::Invalid(e)) => {
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path="/oauth-clients/{client_id}",
+    responses(
+        (status = NO_CONTENT, description = "OAuth client was deleted"),
+        (status = NOT_FOUND, description = "OAuth client does not exist"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    params(
+        ("client_id" = String, description = "OAuth client ID")
+    )
+)]
+pub async fn delete(
+    State(state): State<state::AppState>,
+    Path(client_id): Path<OAuthClientId>,
+) -> Response {
+    let mut db = state.db.lock().unwrap();
+    match db.oauth_clients.remove(&client_id) {
+        Ok(_) => {
+            if let Err(e) = db.oauth_clients.save() {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            if db.consents.revoke_client(&client_id) > 0
+                && let Err(e) = db.consents.save()
+            {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(RemoveOAuthClientError::NotFound(_)) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
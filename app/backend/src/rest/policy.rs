@@ -0,0 +1,4 @@
+pub mod check;
+pub mod inheritances;
+pub mod reload;
+pub mod rules;
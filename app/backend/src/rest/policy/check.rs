@@ -0,0 +1,76 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::model::group::GroupId;
+use crate::model::policy::{PolicyCheck, PolicyDecision, PolicySubject};
+use crate::persist::group_db::GroupDB;
+use crate::policy;
+use crate::state;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+/// Groups held directly and the roles a token issued for them would carry
+fn held_and_token_roles(
+    groups: &GroupDB,
+    held: &HashSet<GroupId>,
+) -> (HashSet<String>, HashSet<String>) {
+    let held_groups: Vec<_> = held.iter().cloned().collect();
+    let roles = groups
+        .query_groups_with_subgroups(&held_groups)
+        .into_iter()
+        .map(|group| group.to_string())
+        .collect();
+    let held = held.iter().map(|group| group.to_string()).collect();
+    (held, roles)
+}
+
+#[utoipa::path(
+    post,
+    path="/policy/check",
+    responses(
+        (status = OK, description = "Whether the subject may send the request and why", body = PolicyDecision),
+        (status = NOT_FOUND, description = "User or client does not exist"),
+        (status = BAD_REQUEST, description = "Invalid request body", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = PolicyCheck)
+)]
+pub async fn post(
+    State(state): State<state::AppState>,
+    Json(check): Json<PolicyCheck>,
+) -> Response {
+    let (held, roles, sub_groups) = {
+        let db = state.db.lock().unwrap();
+        let (held, roles) = match check.subject {
+            PolicySubject::User { uid } => match db.users.query_by_uid(uid) {
+                Some(user) => held_and_token_roles(&db.groups, &user.groups),
+                None => return StatusCode::NOT_FOUND.into_response(),
+            },
+            PolicySubject::Client { cid } => match db.clients.query_by_id(cid) {
+                Some(client) => held_and_token_roles(&db.groups, &client.groups),
+                None => return StatusCode::NOT_FOUND.into_response(),
+            },
+            // Roles of a token are checked as they are, their sub groups were
+            // added when it was issued
+            PolicySubject::Roles { roles } => (roles.clone(), roles),
+        };
+        let sub_groups: HashMap<_, _> = db
+            .groups
+            .query_all()
+            .map(|group| (group.id.clone(), group.sub_groups.clone()))
+            .collect();
+        (held, roles, sub_groups)
+    };
+    let enforcer = state.enforcer.lock().unwrap();
+    match policy::explain(
+        &enforcer,
+        &sub_groups,
+        &held,
+        &roles,
+        &check.path,
+        &check.method.to_uppercase(),
+    ) {
+        Ok(decision) => Json(decision).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
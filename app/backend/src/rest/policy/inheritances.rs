@@ -0,0 +1,66 @@
+use crate::model::policy::RoleInheritance;
+use crate::policy;
+use crate::state;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+use super::rules::error_response;
+
+#[utoipa::path(
+    get,
+    path="/policy/inheritances",
+    responses(
+        (status = OK, description = "All `g` lines of the running policy", body = Vec<RoleInheritance>),
+    )
+)]
+pub async fn get(State(state): State<state::AppState>) -> Json<Vec<RoleInheritance>> {
+    let enforcer = state.enforcer.lock().unwrap();
+    Json(policy::inheritances(&enforcer))
+}
+
+#[utoipa::path(
+    post,
+    path="/policy/inheritances",
+    responses(
+        (status = CREATED, description = "Inheritance was added and is in effect"),
+        (status = CONFLICT, description = "Policy already contains the inheritance", body = String),
+        (status = BAD_REQUEST, description = "Invalid request body", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = RoleInheritance)
+)]
+pub async fn post(
+    State(state): State<state::AppState>,
+    Json(inheritance): Json<RoleInheritance>,
+) -> Response {
+    let mut db = state.db.lock().unwrap();
+    let mut enforcer = state.enforcer.lock().unwrap();
+    match policy::add_inheritance(&mut enforcer, &mut db.policies, inheritance) {
+        Ok(()) => StatusCode::CREATED.into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path="/policy/inheritances",
+    responses(
+        (status = NO_CONTENT, description = "Inheritance was removed, shipped inheritances stay removed across updates"),
+        (status = NOT_FOUND, description = "Policy does not contain the inheritance"),
+        (status = BAD_REQUEST, description = "Invalid request body or tech.flecs.admin would lose access to the policy", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = RoleInheritance)
+)]
+pub async fn delete(
+    State(state): State<state::AppState>,
+    Json(inheritance): Json<RoleInheritance>,
+) -> Response {
+    let mut db = state.db.lock().unwrap();
+    let mut enforcer = state.enforcer.lock().unwrap();
+    match policy::remove_inheritance(&mut enforcer, &mut db.policies, inheritance) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(e),
+    }
+}
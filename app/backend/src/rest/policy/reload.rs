@@ -0,0 +1,19 @@
+use crate::state;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    post,
+    path="/policy/reload",
+    responses(
+        (status = NO_CONTENT, description = "Shipped policy and changes were reloaded from disk"),
+        (status = INTERNAL_SERVER_ERROR, description = "Policy could not be loaded, the running one is kept", body = String),
+    )
+)]
+pub async fn post(State(state): State<state::AppState>) -> Response {
+    match state.reload_policy().await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")).into_response(),
+    }
+}
@@ -0,0 +1,77 @@
+use crate::model::policy::PolicyRule;
+use crate::policy::{self, PolicyChangeError};
+use crate::state;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    get,
+    path="/policy/rules",
+    responses(
+        (status = OK, description = "All `p` lines of the running policy", body = Vec<PolicyRule>),
+    )
+)]
+pub async fn get(State(state): State<state::AppState>) -> Json<Vec<PolicyRule>> {
+    let enforcer = state.enforcer.lock().unwrap();
+    Json(policy::rules(&enforcer))
+}
+
+#[utoipa::path(
+    post,
+    path="/policy/rules",
+    responses(
+        (status = CREATED, description = "Rule was added and is in effect"),
+        (status = CONFLICT, description = "Policy already contains the rule", body = String),
+        (status = BAD_REQUEST, description = "Invalid request body", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = PolicyRule)
+)]
+pub async fn post(
+    State(state): State<state::AppState>,
+    Json(mut rule): Json<PolicyRule>,
+) -> Response {
+    rule.method = rule.method.to_uppercase();
+    let mut db = state.db.lock().unwrap();
+    let mut enforcer = state.enforcer.lock().unwrap();
+    match policy::add_rule(&mut enforcer, &mut db.policies, rule) {
+        Ok(()) => StatusCode::CREATED.into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path="/policy/rules",
+    responses(
+        (status = NO_CONTENT, description = "Rule was removed, shipped rules stay removed across updates"),
+        (status = NOT_FOUND, description = "Policy does not contain the rule"),
+        (status = BAD_REQUEST, description = "Invalid request body or tech.flecs.admin would lose access to the policy", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = PolicyRule)
+)]
+pub async fn delete(
+    State(state): State<state::AppState>,
+    Json(mut rule): Json<PolicyRule>,
+) -> Response {
+    rule.method = rule.method.to_uppercase();
+    let mut db = state.db.lock().unwrap();
+    let mut enforcer = state.enforcer.lock().unwrap();
+    match policy::remove_rule(&mut enforcer, &mut db.policies, rule) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+pub(super) fn error_response(e: PolicyChangeError) -> Response {
+    match e {
+        PolicyChangeError::Invalid(_) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        PolicyChangeError::AlreadyPresent => (StatusCode::CONFLICT, e.to_string()).into_response(),
+        PolicyChangeError::NotPresent => StatusCode::NOT_FOUND.into_response(),
+        PolicyChangeError::Casbin(_) | PolicyChangeError::Persist(_) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::model::user::UserInfo;
+use crate::oauth::oidc;
+use crate::state;
+use crate::token::{GrantedScope, Subject};
+use axum::Extension;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    get,
+    path="/userinfo",
+    responses(
+        (status = OK, description = "Claims about the authenticated user", body = UserInfo),
+        (status = UNAUTHORIZED, description = "Not authenticated as a user"),
+        (status = FORBIDDEN, description = "Token was not granted the openid scope"),
+    )
+)]
+pub async fn get(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+    scope: Option<Extension<GrantedScope>>,
+) -> Response {
+    userinfo(&state, subject, scope)
+}
+
+#[utoipa::path(
+    post,
+    path="/userinfo",
+    responses(
+        (status = OK, description = "Claims about the authenticated user", body = UserInfo),
+        (status = UNAUTHORIZED, description = "Not authenticated as a user"),
+        (status = FORBIDDEN, description = "Token was not granted the openid scope"),
+    )
+)]
+pub async fn post(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+    scope: Option<Extension<GrantedScope>>,
+) -> Response {
+    userinfo(&state, subject, scope)
+}
+
+fn userinfo(
+    state: &state::AppState,
+    subject: Option<Extension<Subject>>,
+    scope: Option<Extension<GrantedScope>>,
+) -> Response {
+    let Some(Extension(Subject::User(uid))) = subject else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    // Claims are only released to tokens of an OpenID Connect authentication
+    let scope = scope
+        .and_then(|Extension(GrantedScope(scope))| scope)
+        .and_then(|scope| scope.parse().ok())
+        .filter(oidc::is_openid_request);
+    let Some(scope) = scope else {
+        return (
+            StatusCode::FORBIDDEN,
+            [(
+                http::header::WWW_AUTHENTICATE,
+                "Bearer error=\"insufficient_scope\"",
+            )],
+        )
+            .into_response();
+    };
+    let db = state.db.lock().unwrap();
+    match db.users.query_by_uid(uid) {
+        Some(user) => Json(UserInfo::new(user, &scope)).into_response(),
+        None => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
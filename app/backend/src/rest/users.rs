@@ -1,7 +1,19 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use thiserror::Error;
+
+use crate::model::failed_attempt::Account;
+use crate::model::group::GroupId;
+use crate::model::password::PolicyError;
 use crate::model::user;
-use crate::model::user::{CreateUser, UserSummary};
+use crate::model::user::{CreateUser, User, UserSummary};
+use crate::persist::group_db::GroupDB;
 use crate::persist::user_db::InsertUserError;
+use crate::rest::clients::{GroupsNotHeld, ensure_groups_held_in};
+use crate::rest::logout::notify_clients;
 use crate::state;
+use crate::token::Roles;
 use axum::extract::{Json, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
@@ -10,6 +22,85 @@ pub mod self_;
 pub mod super_admin;
 pub mod uid;
 
+#[derive(Debug, Error)]
+pub(crate) enum AssignGroupsError {
+    #[error("Unknown groups: {}", .0.join(", "))]
+    Unknown(Vec<String>),
+    #[error(transparent)]
+    NotHeld(#[from] GroupsNotHeld),
+}
+
+impl IntoResponse for AssignGroupsError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Unknown(_) => (StatusCode::BAD_REQUEST, self.to_string()).into_response(),
+            Self::NotHeld(e) => e.into_response(),
+        }
+    }
+}
+
+/// Users may only be given groups that exist, so that typos do not go
+/// unnoticed, and that the caller holds, so that nobody can escalate
+/// privileges through another account. Called with the db locked, so the
+/// groups cannot change before they are assigned.
+pub(crate) fn ensure_groups_assignable(
+    group_db: &GroupDB,
+    enforcer: &Mutex<casbin::Enforcer>,
+    caller_roles: &HashSet<String>,
+    groups: &HashSet<GroupId>,
+) -> Result<(), AssignGroupsError> {
+    let mut unknown_groups: Vec<_> = groups
+        .iter()
+        .filter(|group| group_db.query_by_id(group).is_none())
+        .map(|group| group.to_string())
+        .collect();
+    if !unknown_groups.is_empty() {
+        unknown_groups.sort();
+        return Err(AssignGroupsError::Unknown(unknown_groups));
+    }
+    ensure_groups_held_in(group_db, enforcer, caller_roles, groups)?;
+    Ok(())
+}
+
+/// Remove what a deleted user leaves behind: refresh token families,
+/// consents, the throttle on the username and sessions, whose clients get a
+/// back-channel logout. Called once the user is removed from the user database.
+pub(crate) async fn remove_user_artifacts(
+    state: &state::AppState,
+    user: &User,
+) -> anyhow::Result<()> {
+    {
+        let mut db = state.db.lock().unwrap();
+        if db.refresh_tokens.revoke_user(user.id) > 0 {
+            db.refresh_tokens.save()?;
+        }
+        if db.consents.revoke_user(user.id) > 0 {
+            db.consents.save()?;
+        }
+    }
+    {
+        let account = Account::User {
+            username: user.name.clone(),
+        };
+        let mut failed_attempts = state.failed_attempts.lock().unwrap();
+        if failed_attempts.unlock(&account) {
+            failed_attempts.save()?;
+        }
+    }
+    let sessions = {
+        let mut sessions = state.sessions.lock().unwrap();
+        let removed = sessions.remove_user_sessions(user.id);
+        if !removed.is_empty() {
+            sessions.save()?;
+        }
+        removed
+    };
+    for session in &sessions {
+        notify_clients(state, session).await;
+    }
+    Ok(())
+}
+
 #[utoipa::path(
     get,
     path="/users",
@@ -29,13 +120,26 @@ pub async fn get(State(state): State<state::AppState>) -> Json<Vec<UserSummary>>
     responses(
         (status = CREATED, description = "User was created", body = user::UserId),
         (status = CONFLICT, description = "User with that name already exists"),
-        (status = BAD_REQUEST, description = "Invalid request body", body = String),
+        (status = BAD_REQUEST, description = "Invalid request body, unknown groups or password that does not meet the password policy", body = PolicyError),
+        (status = FORBIDDEN, description = "Caller does not have all requested groups", body = String),
         (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
     ),
     request_body(content = CreateUser)
 )]
-pub async fn post(State(state): State<state::AppState>, Json(user): Json<CreateUser>) -> Response {
+pub async fn post(
+    State(state): State<state::AppState>,
+    axum::Extension(Roles(caller_roles)): axum::Extension<Roles>,
+    Json(user): Json<CreateUser>,
+) -> Response {
+    if let Err(e) = state.password_policy.validate(&user.password) {
+        return (StatusCode::BAD_REQUEST, Json(e)).into_response();
+    }
     let mut db = state.db.lock().unwrap();
+    if let Err(e) =
+        ensure_groups_assignable(&db.groups, &state.enforcer, &caller_roles, &user.groups)
+    {
+        return e.into_response();
+    }
     let id = match db.users.insert(user) {
         Ok(id) => id,
         Err(InsertUserError::DuplicateName(name)) => {
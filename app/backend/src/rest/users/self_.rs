@@ -1,20 +1,31 @@
-use crate::model::user::UpdateUser;
+use crate::middleware::client_info::ClientInfo;
+use crate::model::failed_attempt::{Account, retry_after_seconds};
+use crate::model::password::PolicyError;
+use crate::model::user::{UpdateUser, UserId};
 use crate::persist::user_db::{RemoveUserError, UpdateUserError};
+use crate::rest::login::extract_sid_from_request_headers;
 use crate::state;
 use crate::token::Subject;
 use axum::Extension;
 use axum::extract::{Json, State};
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 
+pub mod consents;
+pub mod effective_roles;
+pub mod sessions;
+pub mod totp;
+
 #[utoipa::path(
     patch,
     path="/users/self",
     responses(
         (status = NO_CONTENT, description = "User was updated"),
         (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Current password is wrong"),
         (status = CONFLICT, description = "User with that name already exists"),
-        (status = BAD_REQUEST, description = "Invalid request body", body = String),
+        (status = BAD_REQUEST, description = "Invalid request body, missing current password or password that does not meet the password policy", body = PolicyError),
+        (status = TOO_MANY_REQUESTS, description = "Too many failed attempts, retry after the time in the Retry-After header"),
         (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
     ),
     request_body(content = UpdateUser)
@@ -22,17 +33,54 @@ use axum::response::{IntoResponse, Response};
 pub async fn patch(
     State(state): State<state::AppState>,
     subject: Option<Extension<Subject>>,
+    client_info: ClientInfo,
+    headers: HeaderMap,
     Json(update): Json<UpdateUser>,
 ) -> Response {
     let Some(Extension(Subject::User(uid))) = subject else {
         return StatusCode::UNAUTHORIZED.into_response();
     };
+    if let Some(password) = &update.password
+        && let Err(e) = state.password_policy.validate(password)
+    {
+        return (StatusCode::BAD_REQUEST, Json(e)).into_response();
+    }
+    let password_changed = update.password.is_some();
+    if (password_changed || update.name.is_some())
+        && let Err(e) = verify_current_password(
+            &state,
+            uid,
+            update.current_password.as_deref(),
+            &client_info,
+        )
+    {
+        return e.into_response();
+    }
     let mut db = state.db.lock().unwrap();
     match db.users.update(uid, update) {
         Ok(()) => {
             if let Err(e) = db.users.save() {
                 return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
             }
+            if !password_changed {
+                return StatusCode::NO_CONTENT.into_response();
+            }
+            /* sign out everywhere else, whoever knew the old password */
+            if db.refresh_tokens.revoke_user(uid) > 0
+                && let Err(e) = db.refresh_tokens.save()
+            {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            let mut sessions = state.sessions.lock().unwrap();
+            let removed = match extract_sid_from_request_headers(&headers) {
+                Some(sid) => sessions.remove_other_user_sessions(uid, &sid),
+                None => sessions.remove_user_sessions(uid).len(),
+            };
+            if removed > 0
+                && let Err(e) = sessions.save()
+            {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
             StatusCode::NO_CONTENT.into_response()
         }
         Err(UpdateUserError::NotFound(_)) => StatusCode::UNAUTHORIZED.into_response(),
@@ -47,6 +95,70 @@ pub async fn patch(
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum CurrentPasswordError {
+    #[error("current_password is required for this change")]
+    Missing,
+    #[error("Invalid current password")]
+    Invalid,
+    #[error("Too many failed attempts, please try again later")]
+    Throttled(chrono::Duration),
+    #[error("User does not exist")]
+    UnknownUser,
+}
+
+impl IntoResponse for CurrentPasswordError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::Missing => StatusCode::BAD_REQUEST,
+            Self::Invalid => StatusCode::FORBIDDEN,
+            Self::UnknownUser => StatusCode::UNAUTHORIZED,
+            Self::Throttled(retry_after) => {
+                let retry_after = [(header::RETRY_AFTER, retry_after_seconds(retry_after))];
+                return (StatusCode::TOO_MANY_REQUESTS, retry_after, self.to_string())
+                    .into_response();
+            }
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// Changing the credentials requires the current password, so that a stolen
+/// token is not enough to take over the account. Wrong passwords count as
+/// failed attempts of the account.
+pub(crate) fn verify_current_password(
+    state: &state::AppState,
+    uid: UserId,
+    current_password: Option<&str>,
+    client_info: &ClientInfo,
+) -> Result<(), CurrentPasswordError> {
+    let current_password = current_password.ok_or(CurrentPasswordError::Missing)?;
+    let username = state
+        .db
+        .lock()
+        .unwrap()
+        .users
+        .query_by_uid(uid)
+        .map(|user| user.name.clone())
+        .ok_or(CurrentPasswordError::UnknownUser)?;
+    let attempt = client_info
+        .begin_attempt(&state.failed_attempts, Account::User { username })
+        .map_err(CurrentPasswordError::Throttled)?;
+    let valid = state
+        .db
+        .lock()
+        .unwrap()
+        .users
+        .query_by_uid(uid)
+        .is_some_and(|user| user.password.verify(current_password).is_ok());
+    if !valid {
+        attempt.fail();
+        return Err(CurrentPasswordError::Invalid);
+    }
+    attempt.succeed();
+    Ok(())
+}
+
 #[utoipa::path(
     delete,
     path="/users/self",
@@ -64,15 +176,20 @@ pub async fn delete(
     let Some(Extension(Subject::User(uid))) = subject else {
         return StatusCode::UNAUTHORIZED.into_response();
     };
-    let mut db = state.db.lock().unwrap();
-    match db.users.remove(uid) {
-        Ok(()) => {
-            if let Err(e) = db.users.save() {
-                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
-            }
-            StatusCode::NO_CONTENT.into_response()
+    let user = {
+        let mut db = state.db.lock().unwrap();
+        let user = match db.users.remove(uid) {
+            Ok(user) => user,
+            Err(RemoveUserError::NotFound(_)) => return StatusCode::UNAUTHORIZED.into_response(),
+            Err(RemoveUserError::SuperAdmin) => return StatusCode::FORBIDDEN.into_response(),
+        };
+        if let Err(e) = db.users.save() {
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
         }
-        Err(RemoveUserError::NotFound(_)) => StatusCode::UNAUTHORIZED.into_response(),
-        Err(RemoveUserError::SuperAdmin) => StatusCode::FORBIDDEN.into_response(),
+        user
+    };
+    match super::remove_user_artifacts(&state, &user).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
 }
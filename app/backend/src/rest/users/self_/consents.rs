@@ -0,0 +1,41 @@
+use crate::model::consent::ConsentSummary;
+use crate::state;
+use crate::token::Subject;
+use axum::Extension;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+pub mod client_id;
+
+#[utoipa::path(
+    get,
+    path="/users/self/consents",
+    responses(
+        (status = OK, description = "Clients the user consented to", body = Vec<ConsentSummary>),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+    ),
+)]
+pub async fn get(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+) -> Response {
+    let Some(Extension(Subject::User(uid))) = subject else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let db = state.db.lock().unwrap();
+    let consents: Vec<_> = db
+        .consents
+        .query_by_user(uid)
+        .map(|consent| ConsentSummary {
+            client_id: consent.client_id.clone(),
+            client_name: db
+                .oauth_clients
+                .query_by_id(&consent.client_id)
+                .map(|client| client.name.clone()),
+            scope: consent.scope.to_string(),
+            granted_at: consent.granted_at,
+        })
+        .collect();
+    Json(consents).into_response()
+}
@@ -0,0 +1,38 @@
+use crate::model::oauth_client::OAuthClientId;
+use crate::state;
+use crate::token::Subject;
+use axum::Extension;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    delete,
+    path="/users/self/consents/{client_id}",
+    responses(
+        (status = NO_CONTENT, description = "Consent was revoked, the client has to ask again"),
+        (status = NOT_FOUND, description = "User did not consent to the client"),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    params(
+        ("client_id" = String, description = "OAuth client id")
+    )
+)]
+pub async fn delete(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+    Path(client_id): Path<OAuthClientId>,
+) -> Response {
+    let Some(Extension(Subject::User(uid))) = subject else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let mut db = state.db.lock().unwrap();
+    if !db.consents.revoke(uid, &client_id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if let Err(e) = db.consents.save() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
@@ -0,0 +1,32 @@
+use crate::model::group::GroupId;
+use crate::model::permission::EffectiveRoles;
+use crate::policy;
+use crate::state;
+use crate::token::{Roles, Subject};
+use axum::Extension;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    get,
+    path="/users/self/effective-roles",
+    responses(
+        (status = OK, description = "Groups, roles and permissions granted by the access token of the user", body = EffectiveRoles),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+    ),
+)]
+pub async fn get(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+    Extension(Roles(roles)): Extension<Roles>,
+) -> Response {
+    let Some(Extension(Subject::User(_))) = subject else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    // The token carries the groups limited to its scope, which is what the
+    // user can actually do with it
+    let groups = roles.into_iter().map(GroupId::from).collect();
+    let enforcer = state.enforcer.lock().unwrap();
+    Json(policy::effective_roles(&enforcer, groups)).into_response()
+}
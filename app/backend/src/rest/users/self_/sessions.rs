@@ -0,0 +1,32 @@
+use crate::model::session::SessionSummary;
+use crate::state;
+use crate::token::Subject;
+use axum::Extension;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+pub mod sid;
+
+#[utoipa::path(
+    get,
+    path="/users/self/sessions",
+    responses(
+        (status = OK, description = "Active single sign-on sessions of the user", body = Vec<SessionSummary>),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+    ),
+)]
+pub async fn get(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+) -> Response {
+    let Some(Extension(Subject::User(uid))) = subject else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let sessions = state.sessions.lock().unwrap();
+    let sessions: Vec<_> = sessions
+        .query_by_user(uid)
+        .map(SessionSummary::from)
+        .collect();
+    Json(sessions).into_response()
+}
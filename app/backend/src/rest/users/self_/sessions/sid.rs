@@ -0,0 +1,31 @@
+use crate::model::session::SessionId;
+use crate::rest::users::uid::sessions::sid::revoke_session;
+use crate::state;
+use crate::token::Subject;
+use axum::Extension;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    delete,
+    path="/users/self/sessions/{sid}",
+    responses(
+        (status = NO_CONTENT, description = "Session was revoked, it has to log in again"),
+        (status = NOT_FOUND, description = "User has no such session"),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+    ),
+    params(
+        ("sid" = String, description = "Public id of the session")
+    )
+)]
+pub async fn delete(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+    Path(sid): Path<SessionId>,
+) -> Response {
+    let Some(Extension(Subject::User(uid))) = subject else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    revoke_session(&state, uid, sid).await
+}
@@ -0,0 +1,132 @@
+use crate::middleware::client_info::ClientInfo;
+use crate::model::totp::{Totp, TotpEnrollment, TotpSecret, TotpStatus};
+use crate::model::user::CurrentPassword;
+use crate::rest::users::self_::verify_current_password;
+use crate::state;
+use crate::token::Subject;
+use axum::Extension;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+pub mod confirm;
+pub mod recovery_codes;
+
+#[utoipa::path(
+    get,
+    path="/users/self/totp",
+    responses(
+        (status = OK, description = "Two-factor authentication of the user", body = TotpStatus),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+    ),
+)]
+pub async fn get(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+) -> Response {
+    let Some(Extension(Subject::User(uid))) = subject else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let db = state.db.lock().unwrap();
+    let Some(user) = db.users.query_by_uid(uid) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    Json(TotpStatus {
+        enabled: user.has_totp(),
+        required: user.requires_totp(&state.totp_required_groups),
+        recovery_codes_left: user
+            .totp
+            .as_ref()
+            .filter(|totp| totp.is_confirmed())
+            .map_or(0, Totp::recovery_codes_left),
+    })
+    .into_response()
+}
+
+#[utoipa::path(
+    post,
+    path="/users/self/totp",
+    responses(
+        (status = OK, description = "New authenticator, which has to be confirmed with one of its codes", body = TotpEnrollment),
+        (status = BAD_REQUEST, description = "Invalid request body"),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Current password is wrong"),
+        (status = CONFLICT, description = "Two-factor authentication is already enabled"),
+        (status = TOO_MANY_REQUESTS, description = "Too many failed attempts, retry after the time in the Retry-After header"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = CurrentPassword)
+)]
+pub async fn post(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+    client_info: ClientInfo,
+    Json(request): Json<CurrentPassword>,
+) -> Response {
+    let Some(Extension(Subject::User(uid))) = subject else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    if let Err(e) =
+        verify_current_password(&state, uid, Some(&request.current_password), &client_info)
+    {
+        return e.into_response();
+    }
+    let mut db = state.db.lock().unwrap();
+    let Some(user) = db.users.query_by_uid(uid) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    if user.has_totp() {
+        return (
+            StatusCode::CONFLICT,
+            "Two-factor authentication is already enabled",
+        )
+            .into_response();
+    }
+    let secret = TotpSecret::generate();
+    let enrollment = TotpEnrollment::new(&secret, &user.name);
+    if let Err(e) = db.users.set_totp(uid, Some(Totp::new(secret))) {
+        return (StatusCode::UNAUTHORIZED, e.to_string()).into_response();
+    }
+    if let Err(e) = db.users.save() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+    Json(enrollment).into_response()
+}
+
+#[utoipa::path(
+    delete,
+    path="/users/self/totp",
+    responses(
+        (status = NO_CONTENT, description = "Two-factor authentication was disabled"),
+        (status = BAD_REQUEST, description = "Invalid request body"),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Current password is wrong"),
+        (status = TOO_MANY_REQUESTS, description = "Too many failed attempts, retry after the time in the Retry-After header"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = CurrentPassword)
+)]
+pub async fn delete(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+    client_info: ClientInfo,
+    Json(request): Json<CurrentPassword>,
+) -> Response {
+    let Some(Extension(Subject::User(uid))) = subject else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    if let Err(e) =
+        verify_current_password(&state, uid, Some(&request.current_password), &client_info)
+    {
+        return e.into_response();
+    }
+    let mut db = state.db.lock().unwrap();
+    match db.users.set_totp(uid, None) {
+        Ok(None) => StatusCode::NO_CONTENT.into_response(),
+        Ok(Some(_)) => match db.users.save() {
+            Ok(()) => StatusCode::NO_CONTENT.into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        },
+        Err(e) => (StatusCode::UNAUTHORIZED, e.to_string()).into_response(),
+    }
+}
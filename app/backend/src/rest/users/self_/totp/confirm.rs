@@ -0,0 +1,43 @@
+use crate::model::totp::{RecoveryCodes, TotpCode};
+use crate::state;
+use crate::token::Subject;
+use axum::Extension;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    post,
+    path="/users/self/totp/confirm",
+    responses(
+        (status = OK, description = "Two-factor authentication was enabled, the recovery codes are only shown once", body = RecoveryCodes),
+        (status = BAD_REQUEST, description = "Invalid request body"),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Invalid code"),
+        (status = CONFLICT, description = "No authenticator to confirm"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = TotpCode)
+)]
+pub async fn post(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+    Json(request): Json<TotpCode>,
+) -> Response {
+    let Some(Extension(Subject::User(uid))) = subject else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let mut db = state.db.lock().unwrap();
+    let Some(totp) = db.users.totp_mut(uid).filter(|totp| !totp.is_confirmed()) else {
+        return (StatusCode::CONFLICT, "No authenticator to confirm").into_response();
+    };
+    if !totp.verify_code(&request.code) {
+        return (StatusCode::FORBIDDEN, "Invalid code").into_response();
+    }
+    totp.confirm();
+    let recovery_codes = totp.generate_recovery_codes();
+    if let Err(e) = db.users.save() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+    Json(RecoveryCodes { recovery_codes }).into_response()
+}
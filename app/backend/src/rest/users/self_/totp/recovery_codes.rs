@@ -0,0 +1,53 @@
+use crate::middleware::client_info::ClientInfo;
+use crate::model::totp::RecoveryCodes;
+use crate::model::user::CurrentPassword;
+use crate::rest::users::self_::verify_current_password;
+use crate::state;
+use crate::token::Subject;
+use axum::Extension;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    post,
+    path="/users/self/totp/recovery-codes",
+    responses(
+        (status = OK, description = "New recovery codes, which replace all previous ones and are only shown once", body = RecoveryCodes),
+        (status = BAD_REQUEST, description = "Invalid request body"),
+        (status = UNAUTHORIZED, description = "Not authenticated"),
+        (status = FORBIDDEN, description = "Current password is wrong"),
+        (status = CONFLICT, description = "Two-factor authentication is not enabled"),
+        (status = TOO_MANY_REQUESTS, description = "Too many failed attempts, retry after the time in the Retry-After header"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    request_body(content = CurrentPassword)
+)]
+pub async fn post(
+    State(state): State<state::AppState>,
+    subject: Option<Extension<Subject>>,
+    client_info: ClientInfo,
+    Json(request): Json<CurrentPassword>,
+) -> Response {
+    let Some(Extension(Subject::User(uid))) = subject else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    if let Err(e) =
+        verify_current_password(&state, uid, Some(&request.current_password), &client_info)
+    {
+        return e.into_response();
+    }
+    let mut db = state.db.lock().unwrap();
+    let Some(totp) = db.users.totp_mut(uid).filter(|totp| totp.is_confirmed()) else {
+        return (
+            StatusCode::CONFLICT,
+            "Two-factor authentication is not enabled",
+        )
+            .into_response();
+    };
+    let recovery_codes = totp.generate_recovery_codes();
+    if let Err(e) = db.users.save() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+    Json(RecoveryCodes { recovery_codes }).into_response()
+}
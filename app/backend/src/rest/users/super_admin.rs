@@ -1,3 +1,4 @@
+use crate::model::password::PolicyError;
 use crate::model::user::SuperAdmin;
 use crate::state;
 use axum::extract::{Json, State};
@@ -32,7 +33,7 @@ pub async fn get(State(state): State<state::AppState>) -> StatusCode {
     responses(
         (status = OK, description = "Super admin was created"),
         (status = CONFLICT, description = "Super admin already exists"),
-        (status = BAD_REQUEST, description = "Invalid password", body = String),
+        (status = BAD_REQUEST, description = "Password does not meet the password policy", body = PolicyError),
         (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
     ),
 )]
@@ -40,6 +41,9 @@ pub async fn post(
     State(state): State<state::AppState>,
     Json(super_admin): Json<SuperAdmin>,
 ) -> Response {
+    if let Err(e) = state.password_policy.validate(&super_admin.password) {
+        return (StatusCode::BAD_REQUEST, Json(e)).into_response();
+    }
     let mut db = state.db.lock().unwrap();
     if db.users.contains_super_admin() {
         return StatusCode::CONFLICT.into_response();
@@ -1,3 +1,4 @@
+use crate::model::password::PolicyError;
 use crate::model::user;
 use crate::model::user::{UpdateUser, UserSummary};
 use crate::persist::user_db::{RemoveUserError, UpdateUserError};
@@ -6,7 +7,11 @@ use axum::extract::{Json, Path, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 
+pub mod effective_roles;
+pub mod lockout;
 pub mod roles;
+pub mod sessions;
+pub mod totp;
 
 #[utoipa::path(
     get,
@@ -35,7 +40,7 @@ pub async fn get(State(state): State<state::AppState>, Path(uid): Path<user::Use
         (status = NO_CONTENT, description = "User was updated"),
         (status = NOT_FOUND, description = "User does not exist"),
         (status = CONFLICT, description = "User with that name already exists"),
-        (status = BAD_REQUEST, description = "Invalid request body or user ID", body = String),
+        (status = BAD_REQUEST, description = "Invalid request body, user ID or password that does not meet the password policy", body = PolicyError),
         (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
     ),
     params(
@@ -48,6 +53,11 @@ pub async fn patch(
     Path(uid): Path<user::UserId>,
     Json(update): Json<UpdateUser>,
 ) -> Response {
+    if let Some(password) = &update.password
+        && let Err(e) = state.password_policy.validate(password)
+    {
+        return (StatusCode::BAD_REQUEST, Json(e)).into_response();
+    }
     let mut db = state.db.lock().unwrap();
     match db.users.update(uid, update) {
         Ok(()) => {
@@ -86,15 +96,20 @@ pub async fn delete(
     State(state): State<state::AppState>,
     Path(uid): Path<user::UserId>,
 ) -> Response {
-    let mut db = state.db.lock().unwrap();
-    match db.users.remove(uid) {
-        Ok(()) => {
-            if let Err(e) = db.users.save() {
-                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
-            }
-            StatusCode::NO_CONTENT.into_response()
+    let user = {
+        let mut db = state.db.lock().unwrap();
+        let user = match db.users.remove(uid) {
+            Ok(user) => user,
+            Err(RemoveUserError::NotFound(_)) => return StatusCode::NOT_FOUND.into_response(),
+            Err(RemoveUserError::SuperAdmin) => return StatusCode::FORBIDDEN.into_response(),
+        };
+        if let Err(e) = db.users.save() {
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
         }
-        Err(RemoveUserError::NotFound(_)) => StatusCode::NOT_FOUND.into_response(),
-        Err(RemoveUserError::SuperAdmin) => StatusCode::FORBIDDEN.into_response(),
+        user
+    };
+    match super::remove_user_artifacts(&state, &user).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
 }
@@ -0,0 +1,32 @@
+use crate::model::permission::EffectiveRoles;
+use crate::model::user;
+use crate::policy;
+use crate::state;
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    get,
+    path="/users/{uid}/effective-roles",
+    responses(
+        (status = OK, description = "Groups, roles and permissions the user holds", body = EffectiveRoles),
+        (status = NOT_FOUND, description = "User does not exist"),
+        (status = BAD_REQUEST, description = "Invalid user ID"),
+    ),
+    params(
+        ("uid" = user::UserId, description = "User ID to resolve the permissions of")
+    ),
+)]
+pub async fn get(State(state): State<state::AppState>, Path(uid): Path<user::UserId>) -> Response {
+    let groups = {
+        let db = state.db.lock().unwrap();
+        let Some(user) = db.users.query_by_uid(uid) else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        let groups: Vec<_> = user.groups.iter().cloned().collect();
+        db.groups.query_groups_with_subgroups(&groups)
+    };
+    let enforcer = state.enforcer.lock().unwrap();
+    Json(policy::effective_roles(&enforcer, groups)).into_response()
+}
@@ -0,0 +1,67 @@
+use crate::model::failed_attempt::{Account, Lockout};
+use crate::model::user;
+use crate::state;
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use tracing::error;
+
+fn account(state: &state::AppState, uid: user::UserId) -> Option<Account> {
+    let db = state.db.lock().unwrap();
+    db.users.query_by_uid(uid).map(|user| Account::User {
+        username: user.name.clone(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path="/users/{uid}/lockout",
+    responses(
+        (status = OK, description = "Failed login attempts and lockout of the user", body = Lockout),
+        (status = NOT_FOUND, description = "User does not exist"),
+        (status = BAD_REQUEST, description = "Invalid user ID"),
+    ),
+    params(
+        ("uid" = user::UserId, description = "User ID to query the lockout of")
+    ),
+)]
+pub async fn get(State(state): State<state::AppState>, Path(uid): Path<user::UserId>) -> Response {
+    let Some(account) = account(&state, uid) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let failed_attempts = state.failed_attempts.lock().unwrap();
+    let threshold = failed_attempts.policy().account_threshold;
+    let lockout = failed_attempts
+        .query_throttle(&account)
+        .map(|throttle| Lockout::new(throttle, threshold))
+        .unwrap_or_default();
+    Json(lockout).into_response()
+}
+
+#[utoipa::path(
+    delete,
+    path="/users/{uid}/lockout",
+    responses(
+        (status = NO_CONTENT, description = "User can log in again"),
+        (status = NOT_FOUND, description = "User does not exist"),
+        (status = BAD_REQUEST, description = "Invalid user ID"),
+    ),
+    params(
+        ("uid" = user::UserId, description = "User ID to unlock")
+    ),
+)]
+pub async fn delete(
+    State(state): State<state::AppState>,
+    Path(uid): Path<user::UserId>,
+) -> Response {
+    let Some(account) = account(&state, uid) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let mut failed_attempts = state.failed_attempts.lock().unwrap();
+    if failed_attempts.unlock(&account)
+        && let Err(e) = failed_attempts.save()
+    {
+        error!("Could not persist failed attempt database: {e}");
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
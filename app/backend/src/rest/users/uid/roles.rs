@@ -3,7 +3,9 @@ use std::collections::HashSet;
 use crate::model::group::GroupId;
 use crate::model::user;
 use crate::persist::user_db::SetGroupsError;
+use crate::rest::users::ensure_groups_assignable;
 use crate::state;
+use crate::token::Roles;
 use axum::extract::{Json, Path, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
@@ -36,7 +38,8 @@ pub async fn get(State(state): State<state::AppState>, Path(uid): Path<user::Use
     responses(
         (status = NO_CONTENT, description = "Roles were assigned to the user"),
         (status = NOT_FOUND, description = "User does not exist"),
-        (status = BAD_REQUEST, description = "Invalid request body or user ID"),
+        (status = BAD_REQUEST, description = "Invalid request body, user ID or unknown roles", body = String),
+        (status = FORBIDDEN, description = "Caller does not have all added roles", body = String),
         (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
     ),
     params(
@@ -46,10 +49,21 @@ pub async fn get(State(state): State<state::AppState>, Path(uid): Path<user::Use
 )]
 pub async fn put(
     State(state): State<state::AppState>,
+    axum::Extension(Roles(caller_roles)): axum::Extension<Roles>,
     Path(uid): Path<user::UserId>,
     Json(groups): Json<HashSet<GroupId>>,
 ) -> Response {
     let mut db = state.db.lock().unwrap();
+    /* roles the user already has may be kept, even if the caller lacks them */
+    let added_groups = match db.users.query_by_uid(uid) {
+        Some(user) => groups.difference(&user.groups).cloned().collect(),
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    if let Err(e) =
+        ensure_groups_assignable(&db.groups, &state.enforcer, &caller_roles, &added_groups)
+    {
+        return e.into_response();
+    }
     match db.users.set_groups(uid, groups) {
         Ok(()) => {
             if let Err(e) = db.users.save() {
@@ -1,7 +1,9 @@
 use crate::model::group::GroupId;
 use crate::model::user;
 use crate::persist::user_db::{AddGroupError, RemoveGroupError};
+use crate::rest::users::ensure_groups_assignable;
 use crate::state;
+use crate::token::Roles;
 use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
@@ -13,7 +15,8 @@ use axum::response::{IntoResponse, Response};
         (status = NO_CONTENT, description = "Role was assigned to the user"),
         (status = NOT_FOUND, description = "User does not exist"),
         (status = CONFLICT, description = "User already has this role"),
-        (status = BAD_REQUEST, description = "Invalid user ID or role"),
+        (status = BAD_REQUEST, description = "Invalid user ID or unknown role", body = String),
+        (status = FORBIDDEN, description = "Caller does not have this role", body = String),
         (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
     ),
     params(
@@ -23,9 +26,18 @@ use axum::response::{IntoResponse, Response};
 )]
 pub async fn put(
     State(state): State<state::AppState>,
+    axum::Extension(Roles(caller_roles)): axum::Extension<Roles>,
     Path((uid, role)): Path<(user::UserId, GroupId)>,
 ) -> Response {
     let mut db = state.db.lock().unwrap();
+    if let Err(e) = ensure_groups_assignable(
+        &db.groups,
+        &state.enforcer,
+        &caller_roles,
+        &[role.clone()].into(),
+    ) {
+        return e.into_response();
+    }
     match db.users.add_group(uid, role) {
         Ok(()) => {
             if let Err(e) = db.users.save() {
@@ -0,0 +1,32 @@
+use crate::model::session::SessionSummary;
+use crate::model::user;
+use crate::state;
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+pub mod sid;
+
+#[utoipa::path(
+    get,
+    path="/users/{uid}/sessions",
+    responses(
+        (status = OK, description = "Active single sign-on sessions of the user", body = Vec<SessionSummary>),
+        (status = NOT_FOUND, description = "User does not exist"),
+        (status = BAD_REQUEST, description = "Invalid user ID"),
+    ),
+    params(
+        ("uid" = user::UserId, description = "User ID to list sessions for")
+    ),
+)]
+pub async fn get(State(state): State<state::AppState>, Path(uid): Path<user::UserId>) -> Response {
+    if state.db.lock().unwrap().users.query_by_uid(uid).is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let sessions = state.sessions.lock().unwrap();
+    let sessions: Vec<_> = sessions
+        .query_by_user(uid)
+        .map(SessionSummary::from)
+        .collect();
+    Json(sessions).into_response()
+}
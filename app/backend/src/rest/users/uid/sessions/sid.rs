@@ -0,0 +1,49 @@
+use crate::model::session::SessionId;
+use crate::model::user;
+use crate::rest::logout::notify_clients;
+use crate::state;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use tracing::error;
+
+#[utoipa::path(
+    delete,
+    path="/users/{uid}/sessions/{sid}",
+    responses(
+        (status = NO_CONTENT, description = "Session was revoked, the user has to log in again"),
+        (status = NOT_FOUND, description = "User has no such session"),
+        (status = BAD_REQUEST, description = "Invalid user or session ID"),
+    ),
+    params(
+        ("uid" = user::UserId, description = "User ID the session belongs to"),
+        ("sid" = String, description = "Public id of the session")
+    )
+)]
+pub async fn delete(
+    State(state): State<state::AppState>,
+    Path((uid, sid)): Path<(user::UserId, SessionId)>,
+) -> Response {
+    revoke_session(&state, uid, sid).await
+}
+
+/// End the session like a logout would, including the back-channel logout
+/// of the clients of the session
+pub(crate) async fn revoke_session(
+    state: &state::AppState,
+    uid: user::UserId,
+    sid: SessionId,
+) -> Response {
+    let session = {
+        let mut sessions = state.sessions.lock().unwrap();
+        let Some(session) = sessions.remove_by_id(uid, sid) else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        if let Err(e) = sessions.save() {
+            error!("Could not persist session database: {e}");
+        }
+        session
+    };
+    notify_clients(state, &session).await;
+    StatusCode::NO_CONTENT.into_response()
+}
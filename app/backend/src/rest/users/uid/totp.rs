@@ -0,0 +1,34 @@
+use crate::model::user;
+use crate::persist::user_db::SetTotpError;
+use crate::state;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    delete,
+    path="/users/{uid}/totp",
+    responses(
+        (status = NO_CONTENT, description = "Two-factor authentication of the user was reset"),
+        (status = NOT_FOUND, description = "User does not exist"),
+        (status = BAD_REQUEST, description = "Invalid user ID"),
+        (status = INTERNAL_SERVER_ERROR, description = "Internal Server Error", body = String),
+    ),
+    params(
+        ("uid" = user::UserId, description = "User ID to reset two-factor authentication of")
+    ),
+)]
+pub async fn delete(
+    State(state): State<state::AppState>,
+    Path(uid): Path<user::UserId>,
+) -> Response {
+    let mut db = state.db.lock().unwrap();
+    match db.users.set_totp(uid, None) {
+        Ok(None) => StatusCode::NO_CONTENT.into_response(),
+        Ok(Some(_)) => match db.users.save() {
+            Ok(()) => StatusCode::NO_CONTENT.into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        },
+        Err(SetTotpError::NotFound(_)) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
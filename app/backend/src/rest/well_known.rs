@@ -0,0 +1,3 @@
+pub mod jwks;
+pub mod oauth_authorization_server;
+pub mod openid_configuration;
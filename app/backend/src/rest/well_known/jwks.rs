@@ -0,0 +1,17 @@
+use crate::state;
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[utoipa::path(
+    get,
+    path="/.well-known/jwks.json",
+    responses(
+        (status = OK, description = "Jwk set of all keys that have to be used to verify issued tokens, same as /meta/jwks", body = serde_json::Value)
+    )
+)]
+pub async fn get(State(state): State<state::AppState>) -> Response {
+    let jwks = state.issuer.lock().unwrap().jwks();
+    (StatusCode::OK, Json(jwks)).into_response()
+}
@@ -0,0 +1,16 @@
+use crate::oauth::discovery::ProviderMetadata;
+use crate::state;
+use axum::Json;
+use axum::extract::State;
+
+#[utoipa::path(
+    get,
+    path="/.well-known/oauth-authorization-server",
+    responses(
+        (status = OK, description = "OAuth 2.0 authorization server metadata (RFC 8414)", body = ProviderMetadata)
+    )
+)]
+pub async fn get(State(state): State<state::AppState>) -> Json<ProviderMetadata> {
+    let issuer = state.issuer.lock().unwrap().url.clone();
+    Json(ProviderMetadata::new(&issuer))
+}
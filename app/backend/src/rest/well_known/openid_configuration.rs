@@ -0,0 +1,16 @@
+use crate::oauth::discovery::ProviderMetadata;
+use crate::state;
+use axum::Json;
+use axum::extract::State;
+
+#[utoipa::path(
+    get,
+    path="/.well-known/openid-configuration",
+    responses(
+        (status = OK, description = "OpenID Connect provider metadata", body = ProviderMetadata)
+    )
+)]
+pub async fn get(State(state): State<state::AppState>) -> Json<ProviderMetadata> {
+    let issuer = state.issuer.lock().unwrap().url.clone();
+    Json(ProviderMetadata::new(&issuer))
+}
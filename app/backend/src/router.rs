@@ -1,13 +1,23 @@
 use crate::{rest, state::AppState};
 use axum::{
     Router,
-    routing::{get, patch, post, put},
+    routing::{delete, get, patch, post, put},
 };
 use tower_http::cors::{Any, CorsLayer};
 
 mod layer;
 
 pub fn build_router(state: AppState) -> Router {
+    // Authorized by opaque initial and registration access tokens instead of
+    // JWTs, so the token middleware must not see these requests
+    let registration_router = Router::new()
+        .route("/oauth/register", post(rest::oauth::register::post))
+        .route(
+            "/oauth/register/{cid}",
+            get(rest::oauth::register::cid::get)
+                .put(rest::oauth::register::cid::put)
+                .delete(rest::oauth::register::cid::delete),
+        );
     let verify_token_middleware =
         axum::middleware::from_fn_with_state(state.clone(), crate::middleware::token::middleware);
     let verify_roles_middleware =
@@ -15,13 +25,68 @@ pub fn build_router(state: AppState) -> Router {
     Router::new()
         .route("/login", get(rest::login::get))
         .route("/login", post(rest::login::post))
+        .route(
+            "/login/totp",
+            get(rest::login::totp::get).post(rest::login::totp::post),
+        )
+        .route("/logout", get(rest::logout::get).post(rest::logout::post))
         .route("/meta/issuer", get(rest::meta::issuer::get))
         .route("/meta/jwk", get(rest::meta::jwk::get))
+        .route("/meta/jwks", get(rest::meta::jwks::get))
+        .route(
+            "/meta/password-policy",
+            get(rest::meta::password_policy::get),
+        )
+        .route(
+            "/.well-known/openid-configuration",
+            get(rest::well_known::openid_configuration::get),
+        )
+        .route(
+            "/.well-known/oauth-authorization-server",
+            get(rest::well_known::oauth_authorization_server::get),
+        )
+        .route("/.well-known/jwks.json", get(rest::well_known::jwks::get))
+        .route("/keys", get(rest::keys::get))
+        .route("/keys/rotate", post(rest::keys::rotate::post))
         .route("/users", get(rest::users::get).post(rest::users::post))
         .route(
             "/users/self",
             patch(rest::users::self_::patch).delete(rest::users::self_::delete),
         )
+        .route(
+            "/users/self/consents",
+            get(rest::users::self_::consents::get),
+        )
+        .route(
+            "/users/self/consents/{client_id}",
+            delete(rest::users::self_::consents::client_id::delete),
+        )
+        .route(
+            "/users/self/effective-roles",
+            get(rest::users::self_::effective_roles::get),
+        )
+        .route(
+            "/users/self/sessions",
+            get(rest::users::self_::sessions::get),
+        )
+        .route(
+            "/users/self/sessions/{sid}",
+            delete(rest::users::self_::sessions::sid::delete),
+        )
+        .route(
+            "/users/self/totp",
+            get(rest::users::self_::totp::get)
+                .post(rest::users::self_::totp::post)
+                .delete(rest::users::self_::totp::delete),
+        )
+        .route(
+            "/users/self/totp/confirm",
+            post(rest::users::self_::totp::confirm::post),
+        )
+        .route(
+            "/users/self/totp/recovery-codes",
+            post(rest::users::self_::totp::recovery_codes::post),
+        )
         .route(
             "/users/super-admin",
             get(rest::users::super_admin::get).post(rest::users::super_admin::post),
@@ -32,10 +97,27 @@ pub fn build_router(state: AppState) -> Router {
                 .patch(rest::users::uid::patch)
                 .delete(rest::users::uid::delete),
         )
+        .route(
+            "/users/{uid}/effective-roles",
+            get(rest::users::uid::effective_roles::get),
+        )
+        .route(
+            "/users/{uid}/lockout",
+            get(rest::users::uid::lockout::get).delete(rest::users::uid::lockout::delete),
+        )
         .route(
             "/users/{uid}/roles",
             get(rest::users::uid::roles::get).put(rest::users::uid::roles::put),
         )
+        .route(
+            "/users/{uid}/sessions",
+            get(rest::users::uid::sessions::get),
+        )
+        .route(
+            "/users/{uid}/sessions/{sid}",
+            delete(rest::users::uid::sessions::sid::delete),
+        )
+        .route("/users/{uid}/totp", delete(rest::users::uid::totp::delete))
         .route(
             "/users/{uid}/roles/{role}",
             put(rest::users::uid::roles::role::put).delete(rest::users::uid::roles::role::delete),
@@ -44,12 +126,70 @@ pub fn build_router(state: AppState) -> Router {
             "/clients",
             get(rest::clients::get).post(rest::clients::post),
         )
+        .route(
+            "/clients/initial-access-tokens",
+            post(rest::clients::initial_access_tokens::post),
+        )
         .route(
             "/clients/{cid}",
             get(rest::clients::cid::get).delete(rest::clients::cid::delete),
         )
-        .route("/oauth/authorize", get(rest::oauth::authorize::get))
+        .route(
+            "/clients/{cid}/effective-roles",
+            get(rest::clients::cid::effective_roles::get),
+        )
+        .route("/failed-attempts", get(rest::failed_attempts::get))
+        .route("/groups", get(rest::groups::get).post(rest::groups::post))
+        .route(
+            "/groups/{gid}",
+            get(rest::groups::gid::get)
+                .patch(rest::groups::gid::patch)
+                .delete(rest::groups::gid::delete),
+        )
+        .route(
+            "/groups/{gid}/sub-groups",
+            get(rest::groups::gid::sub_groups::get),
+        )
+        .route(
+            "/groups/{gid}/sub-groups/{sub_gid}",
+            put(rest::groups::gid::sub_groups::sub_gid::put)
+                .delete(rest::groups::gid::sub_groups::sub_gid::delete),
+        )
+        .route(
+            "/oauth-clients",
+            get(rest::oauth_clients::get).post(rest::oauth_clients::post),
+        )
+        .route(
+            "/oauth-clients/{client_id}",
+            get(rest::oauth_clients::client_id::get)
+                .patch(rest::oauth_clients::client_id::patch)
+                .delete(rest::oauth_clients::client_id::delete),
+        )
+        .route("/policy/check", post(rest::policy::check::post))
+        .route(
+            "/policy/rules",
+            get(rest::policy::rules::get)
+                .post(rest::policy::rules::post)
+                .delete(rest::policy::rules::delete),
+        )
+        .route(
+            "/policy/inheritances",
+            get(rest::policy::inheritances::get)
+                .post(rest::policy::inheritances::post)
+                .delete(rest::policy::inheritances::delete),
+        )
+        .route("/policy/reload", post(rest::policy::reload::post))
+        .route(
+            "/oauth/authorize",
+            get(rest::oauth::authorize::get).post(rest::oauth::authorize::get),
+        )
         .route("/oauth/token", post(rest::oauth::token::post))
+        .route("/oauth/revoke", post(rest::oauth::revoke::post))
+        .route("/oauth/introspect", post(rest::oauth::introspect::post))
+        .route(
+            "/userinfo",
+            get(rest::userinfo::get).post(rest::userinfo::post),
+        )
         .layer(
             CorsLayer::new()
                 .allow_methods(Any)
@@ -58,6 +198,7 @@ pub fn build_router(state: AppState) -> Router {
         )
         .layer(verify_roles_middleware)
         .layer(verify_token_middleware)
+        .merge(registration_router)
         .layer(layer::logging())
         .with_state(state)
 }
@@ -1,14 +1,20 @@
+use anyhow::Context;
 use casbin::CoreApi;
-use oxide_auth::primitives::prelude::RandomGenerator;
-use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tracing::{error, info};
 
 use crate::config::Config;
-use crate::model::session;
+use crate::model::group::GroupId;
+use crate::model::password::PasswordPolicy;
 use crate::oauth::endpoint::{Authorizer, Issuer};
-use crate::oauth::registrar::{Registrar, build_registrar};
+use crate::oauth::registrar::Registrar;
 use crate::persist;
+use crate::persist::failed_attempt_db::FailedAttemptDB;
+use crate::persist::session_db::SessionDB;
+use crate::policy;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -16,35 +22,106 @@ pub struct AppState {
     pub authorizer: Arc<Mutex<Authorizer>>,
     pub issuer: Arc<Mutex<Issuer>>,
     pub enforcer: Arc<Mutex<casbin::Enforcer>>,
-    pub login_sessions: Arc<Mutex<HashSet<session::LoginSession>>>,
-    pub user_sessions: Arc<Mutex<HashSet<session::UserSession>>>,
+    pub sessions: Arc<Mutex<SessionDB>>,
+    pub failed_attempts: Arc<Mutex<FailedAttemptDB>>,
+    pub trust_forwarded_for: bool,
+    pub password_policy: PasswordPolicy,
+    /// Members of these groups have to log in with two-factor authentication
+    pub totp_required_groups: Vec<GroupId>,
+    /// The casbin model and shipped policy the enforcer is rebuilt from when
+    /// the policy is reloaded
+    pub casbin_model_path: PathBuf,
+    pub casbin_policy_path: PathBuf,
     pub db: Arc<Mutex<persist::Db>>,
 }
 
 impl AppState {
-    pub fn new(enforcer: casbin::Enforcer, config: &Config) -> Self {
-        let db = Arc::new(Mutex::new(
-            persist::Db::new(
-                config.database.users_path.clone(),
-                config.database.groups_path.clone(),
-                config.database.clients_path.clone(),
-                config.database.ro_clients_path.clone(),
+    pub fn new(mut enforcer: casbin::Enforcer, config: &Config) -> anyhow::Result<Self> {
+        let mut db = persist::Db::new(&config.database).context("load databases")?;
+        db.oauth_clients
+            .restrict_flecs_hosts(&config.auth.flecs_ui_hosts)
+            .context("configure the FLECS client")?;
+        policy::apply_changes(&mut enforcer, &db.policies).context("apply policy changes")?;
+        let db = Arc::new(Mutex::new(db));
+        let issuer = Issuer::new(db.clone(), &config.auth).context("create token issuer")?;
+        let sessions = SessionDB::new(config.database.sessions_path.clone(), (&config.auth).into())
+            .with_context(|| {
+                format!(
+                    "load sessions from {}",
+                    config.database.sessions_path.display()
+                )
+            })?;
+        let failed_attempts = FailedAttemptDB::new(
+            config.database.failed_attempts_path.clone(),
+            (&config.auth).into(),
+        )
+        .with_context(|| {
+            format!(
+                "load failed attempts from {}",
+                config.database.failed_attempts_path.display()
             )
-            .unwrap(),
-        ));
-        Self {
-            registrar: Arc::new(Mutex::new(build_registrar())),
-            authorizer: Arc::new(Mutex::new(Authorizer::new(RandomGenerator::new(16)))),
-            issuer: Arc::new(Mutex::new(Issuer::new(
-                db.clone(),
-                config.auth.issuer_url.clone(),
-            ))),
+        })?;
+        Ok(Self {
+            registrar: Arc::new(Mutex::new(Registrar::new(db.clone()))),
+            authorizer: Arc::new(Mutex::new(Authorizer::new(db.clone()))),
+            issuer: Arc::new(Mutex::new(issuer)),
             enforcer: Arc::new(Mutex::new(enforcer)),
-            login_sessions: Arc::new(Mutex::new(HashSet::new())),
-            user_sessions: Arc::new(Mutex::new(HashSet::new())),
+            sessions: Arc::new(Mutex::new(sessions)),
+            failed_attempts: Arc::new(Mutex::new(failed_attempts)),
+            trust_forwarded_for: config.auth.trust_forwarded_for,
+            password_policy: (&config.auth).into(),
+            totp_required_groups: config.auth.totp_required_groups.clone(),
+            casbin_model_path: config.auth.casbin_model_path.clone(),
+            casbin_policy_path: config.auth.casbin_policy_path.clone(),
             db,
+        })
+    }
+
+    /// Forget login and user sessions that expired and persist the activity
+    /// of the remaining ones
+    pub fn remove_expired_sessions(&self) {
+        if let Err(e) = self.sessions.lock().unwrap().save() {
+            error!("Could not persist session database: {e}");
+        }
+    }
+
+    /// Forget failed attempts that are too old to count anymore
+    pub fn remove_expired_failed_attempts(&self) {
+        if let Err(e) = self.failed_attempts.lock().unwrap().save() {
+            error!("Could not persist failed attempt database: {e}");
         }
     }
+
+    /// Rebuild the enforcer from the shipped policy and the changes on disk,
+    /// replacing the running one only if both could be loaded
+    pub async fn reload_policy(&self) -> anyhow::Result<()> {
+        let mut enforcer = construct_enforcer(
+            self.casbin_model_path.clone(),
+            self.casbin_policy_path.clone(),
+        )
+        .await?;
+        // Changes made while the shipped policy was loading are on disk by
+        // now, the database lock keeps further ones from being lost
+        let mut db = self.db.lock().unwrap();
+        db.policies.reload()?;
+        policy::apply_changes(&mut enforcer, &db.policies)?;
+        *self.enforcer.lock().unwrap() = enforcer;
+        info!("Reloaded casbin policy");
+        Ok(())
+    }
+}
+
+const SESSION_GC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Periodically remove expired sessions and failed attempts, which are
+/// otherwise only dropped when they are used again
+pub async fn collect_expired_sessions(state: AppState) {
+    let mut interval = tokio::time::interval(SESSION_GC_INTERVAL);
+    loop {
+        interval.tick().await;
+        state.remove_expired_sessions();
+        state.remove_expired_failed_attempts();
+    }
 }
 
 pub async fn construct_enforcer(
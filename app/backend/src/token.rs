@@ -9,15 +9,21 @@ use std::ops::Add;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
-use crate::model::user::UserId;
+use crate::model::user::{User, UserId};
+use crate::oauth::{oidc, scope};
 use crate::persist;
+use crate::persist::revocation_db::RevocationDB;
 
-const TOKEN_DURATION: chrono::Duration = chrono::Duration::days(1);
+pub const TOKEN_DURATION: chrono::Duration = chrono::Duration::days(1);
 const CLIENT_TOKEN_DURATION: chrono::Duration = chrono::Duration::minutes(10);
 
 #[derive(Debug, Clone, Default)]
 pub struct Roles(pub HashSet<String>);
 
+/// Scope granted to the token a request was authenticated with
+#[derive(Debug, Clone)]
+pub struct GrantedScope(pub Option<String>);
+
 #[derive(Debug, Clone)]
 pub enum Subject {
     User(UserId),
@@ -47,9 +53,15 @@ struct Claims {
     sub: String,
     exp: u64,
     iss: url::Url,
+    /// Unique id of the token, used to revoke it. Tokens issued by earlier
+    /// versions have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    jti: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
     token_type: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    email: Option<String>,
     aud: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     preferred_username: Option<String>,
@@ -76,14 +88,16 @@ pub fn issue(
         .query_by_uid(uid)
         .ok_or_else(|| anyhow::anyhow!("Unknown user id {uid}"))?;
     let user_groups: Vec<_> = user.groups.iter().cloned().collect();
-    let groups = db.groups.query_groups_with_subgroups(&user_groups);
+    let groups = scope::scoped_roles(&db.groups, &user_groups, &grant.scope);
     let roles: Vec<String> = groups.iter().map(|g| g.as_ref().to_string()).collect();
     let claims = Claims {
         sub: grant.owner_id,
         exp: until.timestamp() as u64,
         iss: issuer,
+        jti: Some(uuid::Uuid::new_v4().to_string()),
+        client_id: Some(grant.client_id),
+        scope: Some(grant.scope.to_string()),
         token_type: "user".to_string(),
-        email: Some("test@flecs.local".to_string()),
         aud: vec!["flecs-core-api".to_string(), "fence-api".to_string()],
         preferred_username: Some(user.name.clone()),
         realm_access: RealmAccess {
@@ -111,9 +125,152 @@ pub fn issue(
     })
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct IdTokenClaims {
+    iss: url::Url,
+    sub: String,
+    aud: String,
+    exp: i64,
+    iat: i64,
+    auth_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+    azp: String,
+    at_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preferred_username: Option<String>,
+}
+
+/// Issue an OpenID Connect id token accompanying `access_token`, if the grant
+/// includes the `openid` scope
+pub fn issue_id_token(
+    grant: &Grant,
+    user: &User,
+    access_token: &str,
+    issuer: url::Url,
+    kid: Option<String>,
+    encoding_key: &EncodingKey,
+) -> Result<Option<String>, anyhow::Error> {
+    if !oidc::is_openid_request(&grant.scope) {
+        return Ok(None);
+    }
+    let context = oidc::AuthContext::from_grant(grant)
+        .ok_or_else(|| anyhow::anyhow!("Grant carries no authentication context"))?;
+    let now = chrono::Utc::now();
+    let profile = oidc::includes_profile(&grant.scope);
+    let claims = IdTokenClaims {
+        iss: issuer,
+        sub: user.id.to_string(),
+        aud: grant.client_id.clone(),
+        exp: now.add(TOKEN_DURATION).timestamp(),
+        iat: now.timestamp(),
+        auth_time: context.auth_time.timestamp(),
+        nonce: context.nonce,
+        azp: grant.client_id.clone(),
+        at_hash: oidc::at_hash(access_token),
+        name: profile.then(|| user.full_name.clone()),
+        preferred_username: profile.then(|| user.name.clone()),
+    };
+    let token = jsonwebtoken::encode(
+        &jsonwebtoken::Header {
+            kid,
+            alg: Algorithm::RS256,
+            ..jsonwebtoken::Header::default()
+        },
+        &claims,
+        encoding_key,
+    )?;
+    Ok(Some(token))
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenHintClaims {
+    sub: String,
+    aud: String,
+}
+
+/// User and client of an id token that a client passed as `id_token_hint`
+#[derive(Debug)]
+pub struct IdTokenHint {
+    pub uid: UserId,
+    pub client_id: String,
+}
+
+/// Verify an id token issued by fence, which may already have expired as
+/// clients typically hint at the session with the last id token they received
+pub fn verify_id_token_hint(
+    token: &str,
+    jwks: &jsonwebtoken::jwk::JwkSet,
+    issuer_url: &url::Url,
+) -> Result<IdTokenHint, VerifyTokenError> {
+    let (decoding_key, mut validation) = decoding_key(token, jwks, issuer_url)?;
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+    validation.set_required_spec_claims(&["aud", "iss", "sub"]);
+    let claims =
+        jsonwebtoken::decode::<IdTokenHintClaims>(token, &decoding_key, &validation)?.claims;
+    let uid = claims
+        .sub
+        .parse::<UserId>()
+        .map_err(|e| VerifyTokenError::InvalidSubject(format!("invalid user id: {e}")))?;
+    Ok(IdTokenHint {
+        uid,
+        client_id: claims.aud,
+    })
+}
+
+const LOGOUT_TOKEN_DURATION: chrono::Duration = chrono::Duration::minutes(2);
+const BACKCHANNEL_LOGOUT_EVENT: &str = "http://schemas.openid.net/event/backchannel-logout";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LogoutTokenClaims {
+    iss: url::Url,
+    sub: String,
+    aud: String,
+    iat: i64,
+    exp: i64,
+    jti: String,
+    events: serde_json::Value,
+}
+
+/// Issue a logout token telling the client that the user logged out, see
+/// OpenID Connect Back-Channel Logout 1.0, section 2.4
+pub fn issue_logout_token(
+    uid: UserId,
+    client_id: &str,
+    issuer: url::Url,
+    kid: Option<String>,
+    encoding_key: &EncodingKey,
+) -> Result<String, anyhow::Error> {
+    let now = chrono::Utc::now();
+    let claims = LogoutTokenClaims {
+        iss: issuer,
+        sub: uid.to_string(),
+        aud: client_id.to_string(),
+        iat: now.timestamp(),
+        exp: now.add(LOGOUT_TOKEN_DURATION).timestamp(),
+        jti: uuid::Uuid::new_v4().to_string(),
+        events: serde_json::json!({ BACKCHANNEL_LOGOUT_EVENT: {} }),
+    };
+    let token = jsonwebtoken::encode(
+        &jsonwebtoken::Header {
+            typ: Some("logout+jwt".to_string()),
+            kid,
+            alg: Algorithm::RS256,
+            ..jsonwebtoken::Header::default()
+        },
+        &claims,
+        encoding_key,
+    )?;
+    Ok(token)
+}
+
 pub fn issue_client_token(
     client_id: uuid::Uuid,
     roles: Vec<String>,
+    scope: Option<String>,
     issuer: url::Url,
     kid: Option<String>,
     encoding_key: &EncodingKey,
@@ -123,8 +280,10 @@ pub fn issue_client_token(
         sub: client_id.to_string(),
         exp: until.timestamp() as u64,
         iss: issuer,
+        jti: Some(uuid::Uuid::new_v4().to_string()),
+        client_id: Some(client_id.to_string()),
+        scope,
         token_type: "client".to_string(),
-        email: None,
         aud: vec!["flecs-core-api".to_string(), "fence-api".to_string()],
         preferred_username: None,
         realm_access: RealmAccess {
@@ -164,6 +323,8 @@ pub enum VerifyTokenError {
     UnknownKid(String),
     #[error("Invalid subject: {0}")]
     InvalidSubject(String),
+    #[error("Token was revoked")]
+    Revoked,
     #[error(transparent)]
     JsonWebToken(#[from] jsonwebtoken::errors::Error),
 }
@@ -192,11 +353,12 @@ fn algorithm_from_jwk(jwk: &jsonwebtoken::jwk::Jwk) -> Result<Algorithm, VerifyT
     )
 }
 
-pub fn verify(
+/// Key that signed the token and a validation of tokens of the issuer
+fn decoding_key(
     token: &str,
     jwks: &jsonwebtoken::jwk::JwkSet,
     issuer_url: &url::Url,
-) -> Result<(Roles, Subject), VerifyTokenError> {
+) -> Result<(jsonwebtoken::DecodingKey, jsonwebtoken::Validation), VerifyTokenError> {
     let token_header = jsonwebtoken::decode_header(token)?;
     let kid = token_header.kid.as_deref().ok_or(VerifyTokenError::NoKid)?;
     let jwk = jwks
@@ -204,11 +366,74 @@ pub fn verify(
         .ok_or_else(|| VerifyTokenError::UnknownKid(kid.to_string()))?;
     let algorithm = algorithm_from_jwk(jwk)?;
     let mut validation = jsonwebtoken::Validation::new(algorithm);
-    let decoding_key = jsonwebtoken::DecodingKey::from_jwk(jwk)?;
-    validation.set_audience(&["flecs-core-api"]);
     validation.set_issuer(&[issuer_url.as_str()]);
+    Ok((jsonwebtoken::DecodingKey::from_jwk(jwk)?, validation))
+}
+
+fn decode(
+    token: &str,
+    jwks: &jsonwebtoken::jwk::JwkSet,
+    issuer_url: &url::Url,
+) -> Result<Claims, VerifyTokenError> {
+    let (decoding_key, mut validation) = decoding_key(token, jwks, issuer_url)?;
+    validation.set_audience(&["flecs-core-api"]);
     validation.set_required_spec_claims(&["exp", "aud", "iss", "sub"]);
-    let claims = jsonwebtoken::decode::<Claims>(token, &decoding_key, &validation)?.claims;
+    Ok(jsonwebtoken::decode::<Claims>(token, &decoding_key, &validation)?.claims)
+}
+
+/// Identifies an issued token for revocation
+pub struct TokenId {
+    pub jti: String,
+    /// Client the token was issued to, unknown for user tokens issued by earlier versions
+    pub client_id: Option<String>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Returns the id of a valid token, or `None` if it was issued without one
+pub fn identify(
+    token: &str,
+    jwks: &jsonwebtoken::jwk::JwkSet,
+    issuer_url: &url::Url,
+) -> Result<Option<TokenId>, VerifyTokenError> {
+    let claims = decode(token, jwks, issuer_url)?;
+    let client_id = match claims.token_type.as_str() {
+        "client" => Some(claims.sub),
+        _ => claims.client_id,
+    };
+    Ok(claims.jti.map(|jti| TokenId {
+        jti,
+        client_id,
+        expires_at: chrono::DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_default(),
+    }))
+}
+
+/// Details of a token that passed [`verify`]
+#[derive(Debug)]
+pub struct VerifiedToken {
+    pub subject: Subject,
+    pub roles: Roles,
+    /// Client the token was issued to, unknown for user tokens issued by earlier versions
+    pub client_id: Option<String>,
+    /// Scope granted to the token, unknown for tokens issued by earlier versions
+    pub scope: Option<String>,
+    pub jti: Option<String>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+pub fn verify(
+    token: &str,
+    jwks: &jsonwebtoken::jwk::JwkSet,
+    issuer_url: &url::Url,
+    revocations: &RevocationDB,
+) -> Result<VerifiedToken, VerifyTokenError> {
+    let claims = decode(token, jwks, issuer_url)?;
+    if claims
+        .jti
+        .as_deref()
+        .is_some_and(|jti| revocations.is_revoked(jti))
+    {
+        return Err(VerifyTokenError::Revoked);
+    }
     let subject =
         match claims.token_type.as_str() {
             "user" => {
@@ -237,5 +462,16 @@ pub fn verify(
             .chain(claims.resource_access.account.roles)
             .collect(),
     );
-    Ok((roles, subject))
+    let client_id = match &subject {
+        Subject::Client(cid) => Some(cid.to_string()),
+        Subject::User(_) => claims.client_id,
+    };
+    Ok(VerifiedToken {
+        subject,
+        roles,
+        client_id,
+        scope: claims.scope,
+        jti: claims.jti,
+        expires_at: chrono::DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_default(),
+    })
 }
@@ -152,3 +152,81 @@ async fn test_get_meta_jwk() {
     let jwk: serde_json::Value = serde_json::from_str(&body).unwrap();
     assert!(jwk.get("kty").is_some(), "JWK should contain key type");
 }
+
+#[tokio::test]
+async fn test_token_valid_after_restart() {
+    let app = common::TestApp::new().await;
+
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    let token = app.mint_token(0);
+
+    let (users_path, _tempdir) = app.shutdown();
+    let data_dir = users_path.parent().unwrap().to_path_buf();
+
+    // Restart with the data volume of the previous instance
+    let app = common::TestApp::new_with_setup(|path| {
+        for file in ["users.json", "signing_key.json"] {
+            std::fs::copy(data_dir.join(file), path.join(file)).unwrap();
+        }
+    })
+    .await;
+
+    let req = Request::get("/users")
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_corrupt_signing_key_is_not_replaced() {
+    let tempdir = tempfile::TempDir::new().unwrap();
+    let key_path = tempdir.path().join("signing_key.json");
+    std::fs::write(&key_path, "not a key").unwrap();
+
+    let casbin_source = format!(
+        "{}/../../docker/fs/usr/local/share/fence",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let config = user_manager::config::Config {
+        database: user_manager::config::Database {
+            users_path: tempdir.path().join("users.json"),
+            groups_path: tempdir.path().join("groups.json"),
+            authorization_codes_path: tempdir.path().join("authorization_codes.json"),
+            clients_path: tempdir.path().join("clients.json"),
+            ro_clients_path: tempdir.path().join("ro_clients.json"),
+            consents_path: tempdir.path().join("consents.json"),
+            oauth_clients_path: tempdir.path().join("oauth_clients.json"),
+            initial_access_tokens_path: tempdir.path().join("initial_access_tokens.json"),
+            refresh_tokens_path: tempdir.path().join("refresh_tokens.json"),
+            revoked_tokens_path: tempdir.path().join("revoked_tokens.json"),
+            sessions_path: tempdir.path().join("sessions.json"),
+            failed_attempts_path: tempdir.path().join("failed_attempts.json"),
+            policy_path: tempdir.path().join("policy.json"),
+        },
+        auth: user_manager::config::Auth {
+            signing_key_path: key_path.clone(),
+            casbin_model_path: format!("{casbin_source}/casbin_model.conf").into(),
+            casbin_policy_path: format!("{casbin_source}/casbin_policy.csv").into(),
+            ..Default::default()
+        },
+    };
+    let enforcer = user_manager::state::construct_enforcer(
+        config.auth.casbin_model_path.clone(),
+        config.auth.casbin_policy_path.clone(),
+    )
+    .await
+    .unwrap();
+
+    // Startup fails with an error naming the key file instead of panicking
+    let Err(e) = user_manager::state::AppState::new(enforcer, &config) else {
+        panic!("corrupt signing key was accepted");
+    };
+    assert!(format!("{e:#}").contains(&key_path.display().to_string()));
+    assert_eq!(std::fs::read_to_string(&key_path).unwrap(), "not a key");
+}
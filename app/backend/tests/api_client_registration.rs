@@ -0,0 +1,319 @@
+mod common;
+
+use http::Request;
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+async fn issue_initial_access_token(app: &common::TestApp, token: &str, groups: &str) -> String {
+    let req = Request::post("/clients/initial-access-tokens")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(r#"{{"groups": {groups}}}"#)))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {body}");
+    let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+    response["token"].as_str().unwrap().to_string()
+}
+
+async fn register(
+    app: &common::TestApp,
+    token: Option<&str>,
+    metadata: &str,
+) -> (http::StatusCode, serde_json::Value) {
+    let mut req = Request::post("/oauth/register").header("content-type", "application/json");
+    if let Some(token) = token {
+        req = req.header("authorization", format!("Bearer {token}"));
+    }
+    let (status, body) = app
+        .request_body(req.body(json_body(metadata)).unwrap())
+        .await;
+    (status, serde_json::from_str(&body).unwrap_or_default())
+}
+
+/// Register a secret client with the groups of a fresh initial access token
+async fn register_app(app: &common::TestApp, name: &str) -> serde_json::Value {
+    let admin_token = setup_admin(app).await;
+    let initial_access_token = issue_initial_access_token(
+        app,
+        &admin_token,
+        r#"["tech.flecs.operator", "tech.flecs.technician"]"#,
+    )
+    .await;
+    let (status, response) = register(
+        app,
+        Some(&initial_access_token),
+        &format!(r#"{{"client_name": "{name}", "scope": "tech.flecs.operator"}}"#),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {response}");
+    response
+}
+
+fn registration_request(
+    method: http::Method,
+    client: &serde_json::Value,
+    token: &str,
+    body: Option<&str>,
+) -> Request<axum::body::Body> {
+    let uri = url::Url::parse(client["registration_client_uri"].as_str().unwrap()).unwrap();
+    Request::builder()
+        .method(method)
+        .uri(uri.path())
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(body.map(json_body).unwrap_or_default())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_issue_initial_access_token_requires_auth() {
+    let app = common::TestApp::new().await;
+    let req = Request::post("/clients/initial-access-tokens")
+        .header("content-type", "application/json")
+        .body(json_body(r#"{"groups": []}"#))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_register_requires_initial_access_token() {
+    let app = common::TestApp::new().await;
+    let (status, response) = register(&app, None, r#"{"client_name": "app"}"#).await;
+    assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+    assert_eq!(response["error"], "invalid_token");
+
+    let (status, response) = register(&app, Some("unknown"), r#"{"client_name": "app"}"#).await;
+    assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+    assert_eq!(response["error"], "invalid_token");
+    assert_eq!(app.state.db.lock().unwrap().clients.query_all().count(), 0);
+}
+
+#[tokio::test]
+async fn test_register_client_and_request_token() {
+    let app = common::TestApp::new().await;
+    let client = register_app(&app, "my-app").await;
+
+    assert_eq!(client["client_name"], "my-app");
+    assert_eq!(client["token_endpoint_auth_method"], "client_secret_basic");
+    assert_eq!(
+        client["grant_types"],
+        serde_json::json!(["client_credentials"])
+    );
+    assert_eq!(client["scope"], "tech.flecs.operator");
+    assert_eq!(client["client_secret_expires_at"], 0);
+    assert!(client["client_id_issued_at"].is_i64());
+    assert!(client["registration_access_token"].is_string());
+    let client_id = client["client_id"].as_str().unwrap();
+    assert!(
+        client["registration_client_uri"]
+            .as_str()
+            .unwrap()
+            .ends_with(&format!("/oauth/register/{client_id}"))
+    );
+
+    let form = format!(
+        "grant_type=client_credentials&client_id={client_id}&client_secret={}",
+        client["client_secret"].as_str().unwrap()
+    );
+    let req = Request::post("/oauth/token")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(form))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+}
+
+#[tokio::test]
+async fn test_initial_access_token_is_single_use() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let initial_access_token =
+        issue_initial_access_token(&app, &admin_token, r#"["tech.flecs.operator"]"#).await;
+
+    let (status, _) = register(
+        &app,
+        Some(&initial_access_token),
+        r#"{"client_name": "first"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let (status, _) = register(
+        &app,
+        Some(&initial_access_token),
+        r#"{"client_name": "second"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_register_rejects_invalid_metadata() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+
+    for metadata in [
+        r#"{"client_name": "app", "scope": "tech.flecs.admin"}"#,
+        r#"{"client_name": "app", "grant_types": ["authorization_code"]}"#,
+        r#"{"client_name": "app", "token_endpoint_auth_method": "none"}"#,
+        r#"{"scope": "tech.flecs.operator"}"#,
+    ] {
+        let initial_access_token =
+            issue_initial_access_token(&app, &admin_token, r#"["tech.flecs.operator"]"#).await;
+        let (status, response) = register(&app, Some(&initial_access_token), metadata).await;
+        assert_eq!(
+            status,
+            http::StatusCode::BAD_REQUEST,
+            "metadata: {metadata}"
+        );
+        assert_eq!(response["error"], "invalid_client_metadata");
+    }
+    assert_eq!(app.state.db.lock().unwrap().clients.query_all().count(), 0);
+}
+
+#[tokio::test]
+async fn test_initial_access_token_survives_invalid_metadata() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let initial_access_token =
+        issue_initial_access_token(&app, &admin_token, r#"["tech.flecs.operator"]"#).await;
+
+    let (status, _) = register(
+        &app,
+        Some(&initial_access_token),
+        r#"{"client_name": "app", "scope": "tech.flecs.admin"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    let (status, _) = register(
+        &app,
+        Some(&initial_access_token),
+        r#"{"client_name": "app"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_register_private_key_jwt_client() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let initial_access_token =
+        issue_initial_access_token(&app, &admin_token, r#"["tech.flecs.operator"]"#).await;
+
+    let (status, client) = register(
+        &app,
+        Some(&initial_access_token),
+        r#"{"client_name": "jwt-app", "token_endpoint_auth_method": "private_key_jwt"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {client}");
+    assert_eq!(client["token_endpoint_auth_method"], "private_key_jwt");
+    assert!(client["certificate"].is_string());
+    assert!(client["private_key"].is_string());
+    assert!(client.get("client_secret").is_none());
+}
+
+#[tokio::test]
+async fn test_read_update_and_delete_registration() {
+    let app = common::TestApp::new().await;
+    let client = register_app(&app, "my-app").await;
+    let token = client["registration_access_token"].as_str().unwrap();
+
+    let req = registration_request(http::Method::GET, &client, token, None);
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    let read: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(read["client_name"], "my-app");
+    assert!(read.get("client_secret").is_none());
+    assert!(read.get("registration_access_token").is_none());
+
+    // The scope may be changed within the groups of the initial access token
+    let req = registration_request(
+        http::Method::PUT,
+        &client,
+        token,
+        Some(r#"{"client_name": "renamed", "scope": "tech.flecs.technician"}"#),
+    );
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    let updated: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(updated["client_name"], "renamed");
+    assert_eq!(updated["scope"], "tech.flecs.technician");
+
+    let req = registration_request(
+        http::Method::PUT,
+        &client,
+        token,
+        Some(r#"{"client_name": "renamed", "scope": "tech.flecs.admin"}"#),
+    );
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST, "body: {body}");
+
+    let req = registration_request(http::Method::DELETE, &client, token, None);
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    assert_eq!(app.state.db.lock().unwrap().clients.query_all().count(), 0);
+
+    let req = registration_request(http::Method::GET, &client, token, None);
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_registration_requires_registration_access_token() {
+    let app = common::TestApp::new().await;
+    let client = register_app(&app, "my-app").await;
+
+    for token in ["unknown", client["client_secret"].as_str().unwrap()] {
+        let req = registration_request(http::Method::DELETE, &client, token, None);
+        let (status, _) = app.request_body(req).await;
+        assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+    }
+
+    // Clients created by an admin cannot be managed via registration
+    let admin_token = app.mint_token(0);
+    let req = Request::post("/clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {admin_token}"))
+        .body(json_body(
+            r#"{"name": "svc", "auth_method": {"type": "Secret"}, "groups": []}"#,
+        ))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let created: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let req = Request::get(format!(
+        "/oauth/register/{}",
+        created["id"].as_str().unwrap()
+    ))
+    .header(
+        "authorization",
+        format!(
+            "Bearer {}",
+            client["registration_access_token"].as_str().unwrap()
+        ),
+    )
+    .body(axum::body::Body::empty())
+    .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,256 @@
+mod common;
+
+use common::{CODE_CHALLENGE, REDIRECT_URI};
+use http::Request;
+use http_body_util::BodyExt;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+/// Set up the super admin and a third party client `app`, returning an admin token
+async fn setup(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    let token = app.mint_token(0);
+
+    let req = Request::post("/oauth-clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"id": "app", "name": "Third Party App", "client_type": {{"type": "Public"}}, "redirect_uris": ["{REDIRECT_URI}"], "scope": "admin"}}"#
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {body}");
+    token
+}
+
+fn app_query() -> String {
+    format!(
+        "response_type=code&client_id=app&redirect_uri={REDIRECT_URI}&state=teststate&scope=openid%20tech.flecs.operator&code_challenge={CODE_CHALLENGE}&code_challenge_method=S256"
+    )
+}
+
+async fn authorize(app: &common::TestApp, sid: &str) -> http::Response<axum::body::Body> {
+    let req = Request::get(format!("/oauth/authorize?{}", app_query()))
+        .header("cookie", format!("sid={sid}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    app.request(req).await
+}
+
+async fn submit(app: &common::TestApp, sid: &str, form: &str) -> http::Response<axum::body::Body> {
+    let req = Request::post(format!("/oauth/authorize?{}", app_query()))
+        .header("cookie", format!("sid={sid}"))
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(form.to_string()))
+        .unwrap();
+    app.request(req).await
+}
+
+/// Expect the consent page and return its CSRF token
+async fn consent_page(response: http::Response<axum::body::Body>) -> String {
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let html = String::from_utf8_lossy(&body).to_string();
+    assert!(html.contains("Third Party App"), "html: {html}");
+    assert!(html.contains("Act with your role Operator"), "html: {html}");
+    let (_, rest) = html
+        .split_once(r#"name="csrf_token" value=""#)
+        .expect("consent page should carry a csrf token");
+    rest.split('"').next().unwrap().to_string()
+}
+
+async fn list_consents(app: &common::TestApp, token: &str) -> Vec<serde_json::Value> {
+    let req = Request::get("/users/self/consents")
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    serde_json::from_str(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_third_party_client_requires_consent() {
+    let app = common::TestApp::new().await;
+    let token = setup(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    let csrf_token = consent_page(authorize(&app, &sid).await).await;
+    let response = submit(
+        &app,
+        &sid,
+        &format!("consent=allow&remember=on&csrf_token={csrf_token}"),
+    )
+    .await;
+    assert!(response.status().is_redirection());
+    assert!(common::redirect_param(&response, "code").is_some());
+
+    let consents = list_consents(&app, &token).await;
+    assert_eq!(consents.len(), 1);
+    assert_eq!(consents[0]["client_id"], "app");
+    assert_eq!(consents[0]["client_name"], "Third Party App");
+    let mut scope: Vec<_> = consents[0]["scope"].as_str().unwrap().split(' ').collect();
+    scope.sort();
+    assert_eq!(scope, ["openid", "tech.flecs.operator"]);
+
+    // Remembered consent is not asked again
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+    let response = authorize(&app, &sid).await;
+    assert!(common::redirect_param(&response, "code").is_some());
+}
+
+#[tokio::test]
+async fn test_consent_is_only_remembered_on_request() {
+    let app = common::TestApp::new().await;
+    let token = setup(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    let csrf_token = consent_page(authorize(&app, &sid).await).await;
+    let response = submit(
+        &app,
+        &sid,
+        &format!("consent=allow&csrf_token={csrf_token}"),
+    )
+    .await;
+    assert!(common::redirect_param(&response, "code").is_some());
+    assert!(list_consents(&app, &token).await.is_empty());
+
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+    consent_page(authorize(&app, &sid).await).await;
+}
+
+#[tokio::test]
+async fn test_denied_consent_redirects_with_error() {
+    let app = common::TestApp::new().await;
+    let token = setup(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    let csrf_token = consent_page(authorize(&app, &sid).await).await;
+    let response = submit(
+        &app,
+        &sid,
+        &format!("consent=deny&remember=on&csrf_token={csrf_token}"),
+    )
+    .await;
+    assert!(response.status().is_redirection());
+    assert!(common::redirect_param(&response, "code").is_none());
+    assert_eq!(
+        common::redirect_param(&response, "error").as_deref(),
+        Some("access_denied")
+    );
+    assert!(list_consents(&app, &token).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_consent_requires_csrf_token() {
+    let app = common::TestApp::new().await;
+    setup(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    consent_page(authorize(&app, &sid).await).await;
+    let response = submit(&app, &sid, "consent=allow&csrf_token=forged").await;
+    assert!(common::redirect_param(&response, "code").is_none());
+    consent_page(response).await;
+}
+
+#[tokio::test]
+async fn test_revoked_consent_is_asked_again() {
+    let app = common::TestApp::new().await;
+    let token = setup(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+    let csrf_token = consent_page(authorize(&app, &sid).await).await;
+    submit(
+        &app,
+        &sid,
+        &format!("consent=allow&remember=on&csrf_token={csrf_token}"),
+    )
+    .await;
+
+    let revoke = || {
+        Request::delete("/users/self/consents/app")
+            .header("authorization", format!("Bearer {token}"))
+            .body(axum::body::Body::empty())
+            .unwrap()
+    };
+    let (status, _) = app.request_body(revoke()).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let (status, _) = app.request_body(revoke()).await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+    consent_page(authorize(&app, &sid).await).await;
+}
+
+#[tokio::test]
+async fn test_deleting_client_removes_consents() {
+    let app = common::TestApp::new().await;
+    let token = setup(&app).await;
+    app.state
+        .db
+        .lock()
+        .unwrap()
+        .consents
+        .grant(0, "app".to_string(), &"openid".parse().unwrap());
+
+    let req = Request::delete("/oauth-clients/app")
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    assert!(list_consents(&app, &token).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_list_consents_requires_user() {
+    let app = common::TestApp::new().await;
+    let req = Request::get("/users/self/consents")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+}
+
+fn assert_denies_framing(response: &http::Response<axum::body::Body>) {
+    assert_eq!(response.headers()["x-frame-options"], "DENY");
+    assert_eq!(
+        response.headers()["content-security-policy"],
+        "frame-ancestors 'none'"
+    );
+}
+
+#[tokio::test]
+async fn test_pages_cannot_be_framed() {
+    let app = common::TestApp::new().await;
+    setup(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    let response = authorize(&app, &sid).await;
+    assert_denies_framing(&response);
+    consent_page(response).await;
+
+    for page in ["/login", "/login/totp"] {
+        let req = Request::get(page).body(axum::body::Body::empty()).unwrap();
+        let response = app.request(req).await;
+        assert!(
+            response.headers()["content-type"]
+                .to_str()
+                .unwrap()
+                .starts_with("text/html"),
+            "{page}"
+        );
+        assert_denies_framing(&response);
+    }
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+async fn get_json(app: &common::TestApp, uri: &str) -> serde_json::Value {
+    let req = Request::get(uri).body(axum::body::Body::empty()).unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK);
+    serde_json::from_str(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_openid_configuration() {
+    let app = common::TestApp::new().await;
+    let metadata = get_json(&app, "/.well-known/openid-configuration").await;
+
+    let issuer = get_json(&app, "/meta/issuer").await;
+    assert_eq!(metadata["issuer"], issuer);
+    let issuer = issuer.as_str().unwrap().trim_end_matches('/');
+    assert_eq!(
+        metadata["authorization_endpoint"],
+        format!("{issuer}/oauth/authorize")
+    );
+    assert_eq!(metadata["token_endpoint"], format!("{issuer}/oauth/token"));
+    assert_eq!(
+        metadata["registration_endpoint"],
+        format!("{issuer}/oauth/register")
+    );
+    assert_eq!(metadata["end_session_endpoint"], format!("{issuer}/logout"));
+    assert_eq!(metadata["backchannel_logout_supported"], true);
+    assert_eq!(
+        metadata["jwks_uri"],
+        format!("{issuer}/.well-known/jwks.json")
+    );
+    assert_eq!(
+        metadata["token_endpoint_auth_methods_supported"],
+        serde_json::json!(["none", "client_secret_basic", "client_secret_post"])
+    );
+    let grant_types = metadata["grant_types_supported"].as_array().unwrap();
+    assert!(grant_types.contains(&"authorization_code".into()));
+    assert!(grant_types.contains(&"client_credentials".into()));
+    assert_eq!(
+        metadata["id_token_signing_alg_values_supported"],
+        serde_json::json!(["RS256"])
+    );
+    assert_eq!(
+        metadata["code_challenge_methods_supported"],
+        serde_json::json!(["S256"])
+    );
+    assert_eq!(
+        metadata["scopes_supported"],
+        serde_json::json!(["openid", "profile"])
+    );
+    assert!(
+        !metadata["claims_supported"]
+            .as_array()
+            .unwrap()
+            .contains(&"email".into())
+    );
+}
+
+#[tokio::test]
+async fn test_oauth_authorization_server_matches_openid_configuration() {
+    let app = common::TestApp::new().await;
+    assert_eq!(
+        get_json(&app, "/.well-known/oauth-authorization-server").await,
+        get_json(&app, "/.well-known/openid-configuration").await
+    );
+}
+
+#[tokio::test]
+async fn test_well_known_jwks_matches_meta_jwks() {
+    let app = common::TestApp::new().await;
+    assert_eq!(
+        get_json(&app, "/.well-known/jwks.json").await,
+        get_json(&app, "/meta/jwks").await
+    );
+}
+
+/// Run the authorization code flow for `client_id` and exchange the code,
+/// authenticating the client with the given token endpoint auth method
+async fn exchange_code_using(
+    app: &common::TestApp,
+    client_id: &str,
+    secret: &str,
+    method: &str,
+) -> http::StatusCode {
+    use base64::Engine;
+    use common::{CODE_CHALLENGE, CODE_VERIFIER, REDIRECT_URI};
+
+    let query = format!(
+        "response_type=code&client_id={client_id}&redirect_uri={REDIRECT_URI}&state=teststate&code_challenge={CODE_CHALLENGE}&code_challenge_method=S256"
+    );
+    let response = app
+        .authorize_response("admin", VALID_PASSWORD, &query)
+        .await;
+    let code = common::redirect_param(&response, "code").expect("authorization code");
+    let mut form = url::form_urlencoded::Serializer::new(String::new());
+    form.append_pair("grant_type", "authorization_code")
+        .append_pair("code", &code)
+        .append_pair("redirect_uri", REDIRECT_URI)
+        .append_pair("code_verifier", CODE_VERIFIER);
+    let mut req =
+        Request::post("/oauth/token").header("content-type", "application/x-www-form-urlencoded");
+    match method {
+        "none" => {
+            form.append_pair("client_id", client_id);
+        }
+        "client_secret_basic" => {
+            let credentials =
+                base64::engine::general_purpose::STANDARD.encode(format!("{client_id}:{secret}"));
+            req = req.header("authorization", format!("Basic {credentials}"));
+        }
+        "client_secret_post" => {
+            form.append_pair("client_id", client_id)
+                .append_pair("client_secret", secret);
+        }
+        method => panic!("no test for token endpoint auth method {method}"),
+    }
+    let req = req.body(axum::body::Body::from(form.finish())).unwrap();
+    let (status, body) = app.request_body(req).await;
+    if status == http::StatusCode::OK {
+        let tokens: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert!(tokens["access_token"].is_string(), "body: {body}");
+    }
+    status
+}
+
+#[tokio::test]
+async fn test_advertised_token_endpoint_auth_methods_exchange_codes() {
+    let app = common::TestApp::new().await;
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(format!(
+            r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#
+        )))
+        .unwrap();
+    app.request(req).await;
+    let req = Request::post("/oauth-clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {}", app.mint_token(0)))
+        .body(axum::body::Body::from(format!(
+            r#"{{"id": "backend", "name": "Backend", "client_type": {{"type": "Confidential"}}, "redirect_uris": ["{}"], "scope": "admin", "first_party": true}}"#,
+            common::REDIRECT_URI
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {body}");
+    let client: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let secret = client["secret"].as_str().unwrap();
+
+    let metadata = get_json(&app, "/.well-known/openid-configuration").await;
+    for method in metadata["token_endpoint_auth_methods_supported"]
+        .as_array()
+        .unwrap()
+    {
+        let method = method.as_str().unwrap();
+        let (client_id, secret) = match method {
+            "none" => ("flecs", ""),
+            _ => ("backend", secret),
+        };
+        assert_eq!(
+            exchange_code_using(&app, client_id, secret, method).await,
+            http::StatusCode::OK,
+            "method: {method}"
+        );
+        if method != "none" {
+            assert_ne!(
+                exchange_code_using(&app, client_id, "wrong", method).await,
+                http::StatusCode::OK,
+                "method: {method}"
+            );
+        }
+    }
+}
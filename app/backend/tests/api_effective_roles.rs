@@ -0,0 +1,200 @@
+mod common;
+
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+/// Send a JSON request and return the status and the parsed body
+async fn send(
+    app: &common::TestApp,
+    req: http::request::Builder,
+    token: &str,
+    json: &str,
+) -> (http::StatusCode, serde_json::Value) {
+    let req = req
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(json))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    (status, serde_json::from_str(&body).unwrap_or_default())
+}
+
+async fn create_operator(app: &common::TestApp, token: &str) -> u16 {
+    let (status, body) = send(
+        app,
+        Request::post("/users"),
+        token,
+        &format!(
+            r#"{{"name": "operator", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.operator"]}}"#
+        ),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    body.as_u64().unwrap() as u16
+}
+
+fn strings<'a>(body: &'a serde_json::Value, field: &str) -> Vec<&'a str> {
+    body[field]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|value| value.as_str().unwrap())
+        .collect()
+}
+
+fn has_permission(body: &serde_json::Value, path: &str, method: &str) -> bool {
+    body["permissions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|permission| permission["path"] == path && permission["method"] == method)
+}
+
+#[tokio::test]
+async fn test_effective_roles_of_admin() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let (status, body) = send(&app, Request::get("/users/0/effective-roles"), &token, "").await;
+    assert_eq!(status, http::StatusCode::OK);
+    let groups = strings(&body, "groups");
+    assert!(groups.contains(&"tech.flecs.admin"));
+    assert!(groups.contains(&"tech.flecs.operator"));
+    assert!(groups.contains(&"tech.flecs.core.admin"));
+    let roles = strings(&body, "roles");
+    assert!(roles.contains(&"tech.flecs.fence.admin"));
+    assert!(roles.contains(&"tech.flecs.fence.list_users"));
+    assert!(!roles.contains(&"tech.flecs.admin"));
+    assert!(has_permission(&body, "/users", "GET"));
+    assert!(has_permission(&body, "/users/:uid/roles/:role", "PUT"));
+    // Public endpoints are available to everybody and not listed
+    assert!(!has_permission(&body, "/login", "GET"));
+}
+
+#[tokio::test]
+async fn test_effective_roles_of_operator() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let uid = create_operator(&app, &token).await;
+
+    let (status, body) = send(
+        &app,
+        Request::get(format!("/users/{uid}/effective-roles")),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(
+        strings(&body, "groups"),
+        ["tech.flecs.core.operator", "tech.flecs.operator"]
+    );
+    assert!(strings(&body, "roles").contains(&"tech.flecs.fence.operator"));
+    assert!(!has_permission(&body, "/users", "GET"));
+
+    // Operators may not look at the permissions of others
+    let operator_token = app.mint_token(uid);
+    let (status, _) = send(
+        &app,
+        Request::get("/users/0/effective-roles"),
+        &operator_token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+
+    let (status, _) = send(&app, Request::get("/users/999/effective-roles"), &token, "").await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_effective_roles_of_self() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let uid = create_operator(&app, &token).await;
+
+    let (status, body) = send(
+        &app,
+        Request::get("/users/self/effective-roles"),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert!(has_permission(&body, "/users", "GET"));
+
+    let operator_token = app.mint_token(uid);
+    let (status, body) = send(
+        &app,
+        Request::get("/users/self/effective-roles"),
+        &operator_token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert!(strings(&body, "groups").contains(&"tech.flecs.operator"));
+    assert!(!has_permission(&body, "/users", "GET"));
+
+    let req = Request::get("/users/self/effective-roles")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_effective_roles_of_client() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let (status, client) = send(
+        &app,
+        Request::post("/clients"),
+        &token,
+        r#"{"name": "line-monitor", "auth_method": {"type": "Secret"}, "groups": ["tech.flecs.technician"]}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let cid = client["id"].as_str().unwrap();
+
+    let (status, body) = send(
+        &app,
+        Request::get(format!("/clients/{cid}/effective-roles")),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    let groups = strings(&body, "groups");
+    assert!(groups.contains(&"tech.flecs.technician"));
+    assert!(groups.contains(&"tech.flecs.operator"));
+    assert!(!groups.contains(&"tech.flecs.admin"));
+    assert!(strings(&body, "roles").contains(&"tech.flecs.fence.technician"));
+
+    let (status, _) = send(
+        &app,
+        Request::get(format!("/clients/{}/effective-roles", uuid::Uuid::new_v4())),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+}
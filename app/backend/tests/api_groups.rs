@@ -0,0 +1,406 @@
+mod common;
+
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+/// Send a JSON request and return the status and the parsed body
+async fn send(
+    app: &common::TestApp,
+    req: http::request::Builder,
+    token: &str,
+    json: &str,
+) -> (http::StatusCode, serde_json::Value) {
+    let req = req
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(json))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    (status, serde_json::from_str(&body).unwrap_or_default())
+}
+
+async fn create_group(app: &common::TestApp, token: &str, json: &str) -> http::StatusCode {
+    send(app, Request::post("/groups"), token, json).await.0
+}
+
+#[tokio::test]
+async fn test_list_groups_includes_builtin_groups() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let (status, body) = send(&app, Request::get("/groups"), &token, "").await;
+    assert_eq!(status, http::StatusCode::OK);
+    let ids: Vec<_> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|group| group["id"].as_str().unwrap())
+        .collect();
+    assert!(ids.contains(&"tech.flecs.admin"));
+    assert!(ids.contains(&"tech.flecs.operator"));
+
+    let (status, body) = send(
+        &app,
+        Request::get("/groups/tech.flecs.operator"),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["name"], "Operator");
+    assert_eq!(
+        body["sub_groups"],
+        serde_json::json!(["tech.flecs.core.operator"])
+    );
+
+    let (status, _) = send(&app, Request::get("/groups/unknown"), &token, "").await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_create_update_and_delete_group() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let status = create_group(
+        &app,
+        &token,
+        r#"{"id": "line-supervisor", "name": "Line Supervisor", "sub_groups": ["tech.flecs.operator"]}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let status = create_group(
+        &app,
+        &token,
+        r#"{"id": "line-supervisor", "name": "Again"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CONFLICT);
+
+    let (status, _) = send(
+        &app,
+        Request::patch("/groups/line-supervisor"),
+        &token,
+        r#"{"description": "Supervises a production line"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let (_, body) = send(&app, Request::get("/groups/line-supervisor"), &token, "").await;
+    assert_eq!(body["name"], "Line Supervisor");
+    assert_eq!(body["description"], "Supervises a production line");
+
+    let (status, _) = send(&app, Request::delete("/groups/line-supervisor"), &token, "").await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Request::get("/groups/line-supervisor"), &token, "").await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+    let (status, _) = send(&app, Request::delete("/groups/line-supervisor"), &token, "").await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_manage_sub_groups() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    create_group(
+        &app,
+        &token,
+        r#"{"id": "line-supervisor", "name": "Line Supervisor"}"#,
+    )
+    .await;
+    create_group(
+        &app,
+        &token,
+        r#"{"id": "line-worker", "name": "Line Worker"}"#,
+    )
+    .await;
+
+    let path = "/groups/line-supervisor/sub-groups/line-worker";
+    let (status, _) = send(&app, Request::put(path), &token, "").await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Request::put(path), &token, "").await;
+    assert_eq!(status, http::StatusCode::CONFLICT);
+    let (status, body) = send(
+        &app,
+        Request::get("/groups/line-supervisor/sub-groups"),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body, serde_json::json!(["line-worker"]));
+
+    // The worker group may not contain its supervisors
+    let (status, _) = send(
+        &app,
+        Request::put("/groups/line-worker/sub-groups/line-supervisor"),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CONFLICT);
+    let (status, _) = send(
+        &app,
+        Request::patch("/groups/line-worker"),
+        &token,
+        r#"{"sub_groups": ["line-supervisor"]}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CONFLICT);
+    let (status, _) = send(
+        &app,
+        Request::put("/groups/line-worker/sub-groups/unknown"),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(&app, Request::delete(path), &token, "").await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Request::delete(path), &token, "").await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_builtin_groups_are_protected() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    create_group(
+        &app,
+        &token,
+        r#"{"id": "line-worker", "name": "Line Worker"}"#,
+    )
+    .await;
+
+    let status = create_group(
+        &app,
+        &token,
+        r#"{"id": "tech.flecs.custom", "name": "Custom"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &app,
+        Request::patch("/groups/tech.flecs.operator"),
+        &token,
+        r#"{"name": "Renamed"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &app,
+        Request::delete("/groups/tech.flecs.operator"),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &app,
+        Request::put("/groups/tech.flecs.operator/sub-groups/line-worker"),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+
+    let (_, body) = send(
+        &app,
+        Request::get("/groups/tech.flecs.operator"),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(body["name"], "Operator");
+}
+
+#[tokio::test]
+async fn test_custom_group_grants_sub_group_permissions() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    create_group(
+        &app,
+        &token,
+        r#"{"id": "site-admin", "name": "Site Admin", "sub_groups": ["tech.flecs.admin"]}"#,
+    )
+    .await;
+    let (status, body) = send(
+        &app,
+        Request::post("/users"),
+        &token,
+        &format!(
+            r#"{{"name": "alice", "password": "{VALID_PASSWORD}", "groups": ["site-admin"]}}"#
+        ),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let uid = body.as_u64().unwrap() as u16;
+    let user_token = app.mint_token(uid);
+
+    let (status, _) = send(&app, Request::get("/groups"), &user_token, "").await;
+    assert_eq!(status, http::StatusCode::OK);
+
+    // Deleting the group takes it away from its members
+    let (status, _) = send(&app, Request::delete("/groups/site-admin"), &token, "").await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let (_, body) = send(
+        &app,
+        Request::get(format!("/users/{uid}/roles")),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(body, serde_json::json!([]));
+    let user_token = app.mint_token(uid);
+    let (status, _) = send(&app, Request::get("/groups"), &user_token, "").await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_manage_groups_requires_permission() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let (_, body) = send(
+        &app,
+        Request::post("/users"),
+        &token,
+        &format!(
+            r#"{{"name": "operator", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.operator"]}}"#
+        ),
+    )
+    .await;
+    let uid = body.as_u64().unwrap() as u16;
+    let operator_token = app.mint_token(uid);
+
+    let (status, _) = send(&app, Request::get("/groups"), &operator_token, "").await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+    let status = create_group(
+        &app,
+        &operator_token,
+        r#"{"id": "line-worker", "name": "Line Worker"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_groups_persist_across_restart() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    create_group(
+        &app,
+        &token,
+        r#"{"id": "line-worker", "name": "Line Worker"}"#,
+    )
+    .await;
+    let (_, tempdir) = app.shutdown();
+
+    let app = common::TestApp::new_with_setup(|dir| {
+        for file in ["users.json", "groups.json"] {
+            std::fs::copy(tempdir.path().join(file), dir.join(file)).unwrap();
+        }
+    })
+    .await;
+    let token = app.mint_token(0);
+    let (status, body) = send(&app, Request::get("/groups/line-worker"), &token, "").await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["name"], "Line Worker");
+}
+
+#[tokio::test]
+async fn test_sub_groups_must_be_held_by_caller() {
+    let app = common::TestApp::new_with_setup(|dir| {
+        let path = dir.join("casbin_policy.csv");
+        let mut policy = std::fs::read_to_string(&path).unwrap();
+        policy.push_str("g,group-managers,tech.flecs.fence.list_groups\n");
+        policy.push_str("g,group-managers,tech.flecs.fence.create_group\n");
+        policy.push_str("g,group-managers,tech.flecs.fence.update_group\n");
+        std::fs::write(path, policy).unwrap();
+    })
+    .await;
+    let token = setup_admin(&app).await;
+    for json in [
+        r#"{"id": "group-managers", "name": "Group Managers"}"#,
+        r#"{"id": "line", "name": "Line"}"#,
+        r#"{"id": "site-admin", "name": "Site Admin", "sub_groups": ["tech.flecs.admin"]}"#,
+    ] {
+        assert_eq!(
+            create_group(&app, &token, json).await,
+            http::StatusCode::CREATED
+        );
+    }
+    let (status, body) = send(
+        &app,
+        Request::post("/users"),
+        &token,
+        &format!(
+            r#"{{"name": "manager", "password": "{VALID_PASSWORD}", "groups": ["group-managers", "line"]}}"#
+        ),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let manager_token = app.mint_token(body.as_u64().unwrap() as u16);
+
+    for sub_gid in ["tech.flecs.admin", "site-admin"] {
+        let (status, _) = send(
+            &app,
+            Request::put(format!("/groups/line/sub-groups/{sub_gid}")),
+            &manager_token,
+            "",
+        )
+        .await;
+        assert_eq!(status, http::StatusCode::FORBIDDEN);
+    }
+    let (status, _) = send(
+        &app,
+        Request::patch("/groups/line"),
+        &manager_token,
+        r#"{"sub_groups": ["tech.flecs.admin"]}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+    let status = create_group(
+        &app,
+        &manager_token,
+        r#"{"id": "escalated", "name": "Escalated", "sub_groups": ["site-admin"]}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+    let (_, body) = send(&app, Request::get("/groups/line"), &token, "").await;
+    assert_eq!(body["sub_groups"], serde_json::json!([]));
+
+    // Groups the caller holds can still be nested
+    let status = create_group(
+        &app,
+        &manager_token,
+        r#"{"id": "shift", "name": "Shift", "sub_groups": ["line"]}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let (status, _) = send(
+        &app,
+        Request::put("/groups/shift/sub-groups/group-managers"),
+        &manager_token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+/// Create a client with secret auth and return (client_id, client_secret).
+async fn create_secret_client(app: &common::TestApp, token: &str, name: &str) -> (String, String) {
+    let req = Request::post("/clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "{name}", "auth_method": {{"type": "Secret"}}, "groups": ["tech.flecs.operator"]}}"#
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {body}");
+    let resp: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let id = resp["id"].as_str().unwrap().to_string();
+    let secret = resp["secret"].as_str().unwrap().to_string();
+    (id, secret)
+}
+
+async fn introspect(
+    app: &common::TestApp,
+    client: &(String, String),
+    token: &str,
+) -> (http::StatusCode, serde_json::Value) {
+    let (client_id, client_secret) = client;
+    let req = Request::post("/oauth/introspect")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header(
+            "authorization",
+            format!(
+                "Basic {}",
+                STANDARD.encode(format!("{client_id}:{client_secret}"))
+            ),
+        )
+        .body(axum::body::Body::from(format!("token={token}")))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    (status, serde_json::from_str(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_introspect_user_token() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let client = create_secret_client(&app, &token, "legacy").await;
+
+    let (status, resp) = introspect(&app, &client, &token).await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(resp["active"], true);
+    assert_eq!(resp["sub"], "0");
+    assert_eq!(resp["subject_type"], "user");
+    assert_eq!(resp["scope"], "admin");
+    assert_eq!(resp["token_type"], "Bearer");
+    assert!(resp["exp"].as_i64().unwrap() > chrono::Utc::now().timestamp());
+    let roles = resp["roles"].as_array().unwrap();
+    assert!(roles.contains(&"tech.flecs.admin".into()));
+}
+
+#[tokio::test]
+async fn test_introspect_client_token() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let client = create_secret_client(&app, &token, "legacy").await;
+
+    let req = Request::post("/oauth/token")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(format!(
+            "grant_type=client_credentials&client_id={}&client_secret={}",
+            client.0, client.1
+        )))
+        .unwrap();
+    let (_, body) = app.request_body(req).await;
+    let issued: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    let (status, resp) = introspect(&app, &client, issued["access_token"].as_str().unwrap()).await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(resp["active"], true);
+    assert_eq!(resp["sub"], client.0);
+    assert_eq!(resp["client_id"], client.0);
+    assert_eq!(resp["subject_type"], "client");
+}
+
+#[tokio::test]
+async fn test_introspect_invalid_token_is_inactive() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let client = create_secret_client(&app, &token, "legacy").await;
+
+    let (status, resp) = introspect(&app, &client, "garbage").await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(resp, serde_json::json!({"active": false}));
+}
+
+#[tokio::test]
+async fn test_introspect_revoked_token_is_inactive() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let client = create_secret_client(&app, &token, "legacy").await;
+    let revoked = app.mint_token(0);
+
+    let req = Request::post("/oauth/revoke")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(format!(
+            "token={revoked}&client_id=flecs"
+        )))
+        .unwrap();
+    app.request(req).await;
+
+    let (_, resp) = introspect(&app, &client, &revoked).await;
+    assert_eq!(resp, serde_json::json!({"active": false}));
+}
+
+#[tokio::test]
+async fn test_introspect_token_of_deleted_user_is_inactive() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let client = create_secret_client(&app, &token, "legacy").await;
+
+    let req = Request::post("/users")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "operator", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.operator"]}}"#
+        )))
+        .unwrap();
+    let (_, body) = app.request_body(req).await;
+    let uid: u16 = serde_json::from_str(&body).unwrap();
+    let user_token = app.mint_token(uid);
+    let (_, resp) = introspect(&app, &client, &user_token).await;
+    assert_eq!(resp["active"], true);
+
+    let req = Request::delete(format!("/users/{uid}"))
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    app.request(req).await;
+
+    let (_, resp) = introspect(&app, &client, &user_token).await;
+    assert_eq!(resp, serde_json::json!({"active": false}));
+}
+
+#[tokio::test]
+async fn test_introspect_requires_client_authentication() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let client = create_secret_client(&app, &token, "legacy").await;
+
+    let req = Request::post("/oauth/introspect")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(format!("token={token}")))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+
+    let wrong_secret = (client.0.clone(), "wrong".to_string());
+    let (status, _) = introspect(&app, &wrong_secret, &token).await;
+    assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+}
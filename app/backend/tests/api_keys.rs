@@ -0,0 +1,128 @@
+mod common;
+
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+async fn rotate(app: &common::TestApp, token: &str) -> (http::StatusCode, String) {
+    let req = Request::post("/keys/rotate")
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    app.request_body(req).await
+}
+
+async fn get_jwks(app: &common::TestApp) -> serde_json::Value {
+    let req = Request::get("/meta/jwks")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK);
+    serde_json::from_str(&body).unwrap()
+}
+
+fn kids(jwks: &serde_json::Value) -> Vec<String> {
+    jwks["keys"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|k| k["kid"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_get_meta_jwks_contains_active_key() {
+    let app = common::TestApp::new().await;
+    let jwks = get_jwks(&app).await;
+
+    let req = Request::get("/meta/jwk")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (_, body) = app.request_body(req).await;
+    let jwk: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(kids(&jwks), vec![jwk["kid"].as_str().unwrap().to_string()]);
+}
+
+#[tokio::test]
+async fn test_rotate_requires_auth() {
+    let app = common::TestApp::new().await;
+    let req = Request::post("/keys/rotate")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_rotate_forbidden_for_operator() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+
+    let req = Request::post("/users")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {admin_token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "operator", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.operator"]}}"#
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let uid: u16 = serde_json::from_str(&body).unwrap();
+
+    let (status, _) = rotate(&app, &app.mint_token(uid)).await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_rotate_keeps_old_tokens_valid() {
+    let app = common::TestApp::new().await;
+    let old_token = setup_admin(&app).await;
+    let old_kids = kids(&get_jwks(&app).await);
+
+    let (status, body) = rotate(&app, &old_token).await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    let new_key: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let new_kid = new_key["kid"].as_str().unwrap().to_string();
+    assert_eq!(new_key["active"], true);
+    assert!(!old_kids.contains(&new_kid));
+
+    // Both keys are published, the active one first
+    let jwks = kids(&get_jwks(&app).await);
+    assert_eq!(jwks, vec![new_kid.clone(), old_kids[0].clone()]);
+
+    // Token signed by the retired key is still accepted
+    let req = Request::get("/keys")
+        .header("authorization", format!("Bearer {old_token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK);
+    let keys: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys[0]["kid"], new_kid);
+    assert_eq!(keys[1]["active"], false);
+    assert!(keys[1]["retired_at"].is_string());
+
+    // New tokens are signed by the new key
+    let new_token = app.mint_token(0);
+    let header = jsonwebtoken::decode_header(&new_token).unwrap();
+    assert_eq!(header.kid.as_deref(), Some(new_kid.as_str()));
+}
@@ -0,0 +1,320 @@
+mod common;
+
+use http::Request;
+use tower::ServiceExt;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+async fn login_from(
+    app: &common::TestApp,
+    username: &str,
+    password: &str,
+    forwarded_for: &str,
+) -> http::Response<axum::body::Body> {
+    let req = Request::post("/login")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("x-forwarded-for", forwarded_for)
+        .body(axum::body::Body::from(format!(
+            "username={username}&password={password}"
+        )))
+        .unwrap();
+    app.request(req).await
+}
+
+async fn get_json(app: &common::TestApp, token: &str, path: &str) -> serde_json::Value {
+    let req = Request::get(path)
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    serde_json::from_str(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_account_is_locked_until_admin_unlocks_it() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    for i in 0..5 {
+        let response = login_from(&app, "admin", "wrong", &format!("10.0.0.{i}")).await;
+        assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+    }
+
+    // The correct password does not help while the account is locked
+    let response = login_from(&app, "admin", VALID_PASSWORD, "10.0.1.1").await;
+    assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 14 * 60 && retry_after <= 15 * 60);
+
+    let lockout = get_json(&app, &token, "/users/0/lockout").await;
+    assert_eq!(lockout["failures"], 5);
+    assert!(lockout["locked_until"].is_string());
+
+    let req = Request::delete("/users/0/lockout")
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+
+    let lockout = get_json(&app, &token, "/users/0/lockout").await;
+    assert_eq!(lockout["failures"], 0);
+    assert!(lockout["locked_until"].is_null());
+
+    let response = login_from(&app, "admin", VALID_PASSWORD, "10.0.1.1").await;
+    assert!(common::extract_sid(&response).is_some());
+}
+
+#[tokio::test]
+async fn test_unknown_users_are_locked_like_known_ones() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    for _ in 0..5 {
+        let response = login_from(&app, "nobody", "wrong", "10.0.0.1").await;
+        assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+    }
+    let response = login_from(&app, "nobody", "wrong", "10.0.0.2").await;
+    assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_successful_login_resets_account_failures() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    for _ in 0..2 {
+        for _ in 0..4 {
+            login_from(&app, "admin", "wrong", "10.0.0.1").await;
+        }
+        let response = login_from(&app, "admin", VALID_PASSWORD, "10.0.0.1").await;
+        assert!(common::extract_sid(&response).is_some());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_guesses_do_not_exceed_threshold() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let guesses = (0..12).map(|i| {
+        let router = app.router.clone();
+        let req = Request::post("/login")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .header("x-forwarded-for", format!("10.0.0.{i}"))
+            .body(axum::body::Body::from("username=admin&password=wrong"))
+            .unwrap();
+        tokio::spawn(async move { router.oneshot(req).await.unwrap().status() })
+    });
+    let statuses = futures_util::future::join_all(guesses).await;
+    let guessed = statuses
+        .iter()
+        .filter(|status| *status.as_ref().unwrap() == http::StatusCode::FORBIDDEN)
+        .count();
+    assert!(guessed <= 5, "{guessed} guesses were checked");
+}
+
+#[tokio::test]
+async fn test_address_is_locked_across_accounts() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    for i in 0..20 {
+        login_from(&app, &format!("user{i}"), "wrong", "10.0.0.1").await;
+    }
+
+    let response = login_from(&app, "admin", VALID_PASSWORD, "10.0.0.1").await;
+    assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+    let response = login_from(&app, "admin", VALID_PASSWORD, "10.0.0.2").await;
+    assert!(common::extract_sid(&response).is_some());
+}
+
+/// Log in through a reverse proxy at 172.21.0.1 that does not forward the
+/// address of the client
+async fn login_via_proxy(
+    app: &common::TestApp,
+    username: &str,
+    password: &str,
+) -> http::Response<axum::body::Body> {
+    let mut req = Request::post("/login")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(format!(
+            "username={username}&password={password}"
+        )))
+        .unwrap();
+    let proxy: std::net::SocketAddr = "172.21.0.1:40000".parse().unwrap();
+    req.extensions_mut()
+        .insert(axum::extract::ConnectInfo(proxy));
+    app.request(req).await
+}
+
+#[tokio::test]
+async fn test_proxy_address_is_not_locked() {
+    for trust_forwarded_for in [false, true] {
+        let app = common::TestApp::new_with_config(|config| {
+            config.auth.trust_forwarded_for = trust_forwarded_for;
+        })
+        .await;
+        let token = setup_admin(&app).await;
+
+        for i in 0..25 {
+            let response = login_via_proxy(&app, &format!("user{i}"), "wrong").await;
+            assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+        }
+        let response = login_via_proxy(&app, "admin", VALID_PASSWORD).await;
+        assert!(common::extract_sid(&response).is_some());
+
+        // The address is still recorded for admins to review
+        let attempts = get_json(&app, &token, "/failed-attempts").await;
+        assert_eq!(attempts[0]["ip_address"], "172.21.0.1");
+    }
+}
+
+#[tokio::test]
+async fn test_client_secret_guesses_are_throttled() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let req = Request::post("/clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(
+            r#"{"name": "svc", "auth_method": {"type": "Secret"}, "groups": ["tech.flecs.admin"]}"#,
+        ))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {body}");
+    let client: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let client_id = client["id"].as_str().unwrap();
+    let secret = client["secret"].as_str().unwrap();
+
+    let token_request = |secret: &str| {
+        Request::post("/oauth/token")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(axum::body::Body::from(format!(
+                "grant_type=client_credentials&client_id={client_id}&client_secret={secret}"
+            )))
+            .unwrap()
+    };
+    for _ in 0..5 {
+        let (status, _) = app.request_body(token_request("wrong")).await;
+        assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+    }
+    let response = app.request(token_request(secret)).await;
+    assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key("retry-after"));
+}
+
+#[tokio::test]
+async fn test_failed_attempts_are_listed_for_admins_only() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let req = Request::post("/login")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("x-forwarded-for", "10.0.0.7")
+        .header("user-agent", "curl/8.0")
+        .body(axum::body::Body::from("username=admin&password=wrong"))
+        .unwrap();
+    app.request(req).await;
+
+    let attempts = get_json(&app, &token, "/failed-attempts").await;
+    let attempts = attempts.as_array().unwrap();
+    assert_eq!(attempts.len(), 1);
+    assert_eq!(attempts[0]["account"]["type"], "user");
+    assert_eq!(attempts[0]["account"]["username"], "admin");
+    assert_eq!(attempts[0]["ip_address"], "10.0.0.7");
+    assert_eq!(attempts[0]["user_agent"], "curl/8.0");
+
+    let req = Request::post("/users")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "operator", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.operator"]}}"#
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let operator: u16 = serde_json::from_str(&body).unwrap();
+    let operator_token = app.mint_token(operator);
+
+    for path in ["/failed-attempts", "/users/0/lockout"] {
+        let req = Request::get(path)
+            .header("authorization", format!("Bearer {operator_token}"))
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let (status, _) = app.request_body(req).await;
+        assert_eq!(status, http::StatusCode::FORBIDDEN, "{path}");
+    }
+}
+
+#[tokio::test]
+async fn test_lockout_of_unknown_user_is_not_found() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let req = Request::delete("/users/42/lockout")
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_recreated_user_does_not_inherit_lockout() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let create_operator = || {
+        Request::post("/users")
+            .header("content-type", "application/json")
+            .header("authorization", format!("Bearer {token}"))
+            .body(json_body(&format!(
+                r#"{{"name": "operator", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.operator"]}}"#
+            )))
+            .unwrap()
+    };
+    let (status, body) = app.request_body(create_operator()).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let uid: u16 = serde_json::from_str(&body).unwrap();
+    for i in 0..5 {
+        login_from(&app, "operator", "wrong", &format!("10.0.0.{i}")).await;
+    }
+    let response = login_from(&app, "operator", VALID_PASSWORD, "10.0.1.1").await;
+    assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+
+    let req = Request::delete(format!("/users/{uid}"))
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+
+    let (status, _) = app.request_body(create_operator()).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let response = login_from(&app, "operator", VALID_PASSWORD, "10.0.1.1").await;
+    assert!(common::extract_sid(&response).is_some());
+}
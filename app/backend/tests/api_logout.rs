@@ -0,0 +1,362 @@
+mod common;
+
+use base64::Engine;
+use common::{CODE_CHALLENGE, CODE_VERIFIER, REDIRECT_URI};
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+async fn create_client(app: &common::TestApp, token: &str, extra_json: &str) {
+    let req = Request::post("/oauth-clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"id": "app", "name": "App", "client_type": {{"type": "Public"}}, "redirect_uris": ["{REDIRECT_URI}"], "scope": "admin", "first_party": true{extra_json}}}"#
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {body}");
+}
+
+/// Authorize the client with the session of `sid`, returning the code
+async fn authorize(app: &common::TestApp, sid: &str, client_id: &str) -> Option<String> {
+    let req = Request::get(format!(
+        "/oauth/authorize?response_type=code&client_id={client_id}&redirect_uri={REDIRECT_URI}&state=teststate&code_challenge={CODE_CHALLENGE}&code_challenge_method=S256&scope=admin%20openid"
+    ))
+    .header("cookie", format!("sid={sid}"))
+    .body(axum::body::Body::empty())
+    .unwrap();
+    let response = app.request(req).await;
+    common::redirect_param(&response, "code")
+}
+
+/// Authorize the client with the session of `sid` and return the id token it
+/// receives for the code
+async fn id_token(app: &common::TestApp, sid: &str, client_id: &str) -> String {
+    let code = authorize(app, sid, client_id).await.unwrap();
+    let code: String = url::form_urlencoded::byte_serialize(code.as_bytes()).collect();
+    let req = Request::post("/oauth/token")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(format!(
+            "grant_type=authorization_code&code={code}&redirect_uri={REDIRECT_URI}&client_id={client_id}&code_verifier={CODE_VERIFIER}"
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    let tokens: serde_json::Value = serde_json::from_str(&body).unwrap();
+    tokens["id_token"].as_str().unwrap().to_string()
+}
+
+async fn logout(
+    app: &common::TestApp,
+    sid: Option<&str>,
+    query: &str,
+) -> http::Response<axum::body::Body> {
+    let mut req = Request::get(format!("/logout?{query}"));
+    if let Some(sid) = sid {
+        req = req.header("cookie", format!("sid={sid}"));
+    }
+    app.request(req.body(axum::body::Body::empty()).unwrap())
+        .await
+}
+
+fn location(response: &http::Response<axum::body::Body>) -> &str {
+    response.headers()["location"].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn test_logout_ends_session() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+    assert!(authorize(&app, &sid, "flecs").await.is_some());
+
+    let response = logout(&app, Some(&sid), "").await;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let cookie = response.headers()["set-cookie"].to_str().unwrap();
+    assert!(cookie.starts_with("sid=;"), "cookie: {cookie}");
+    assert!(cookie.contains("Max-Age=0"), "cookie: {cookie}");
+
+    assert!(authorize(&app, &sid, "flecs").await.is_none());
+
+    // Logging out without a session is fine as well
+    let response = logout(&app, None, "").await;
+    assert_eq!(response.status(), http::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_logout_via_post() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+    let id_token = id_token(&app, &sid, "flecs").await;
+
+    let req = Request::post("/logout")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("cookie", format!("sid={sid}"))
+        .body(axum::body::Body::from(format!(
+            "id_token_hint={id_token}&post_logout_redirect_uri=https%3A%2F%2Flocalhost%2F"
+        )))
+        .unwrap();
+    let response = app.request(req).await;
+    assert_eq!(location(&response), "https://localhost/");
+    assert!(authorize(&app, &sid, "flecs").await.is_none());
+}
+
+#[tokio::test]
+async fn test_post_logout_redirect_uri_must_be_registered() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    create_client(
+        &app,
+        &token,
+        r#", "post_logout_redirect_uris": ["https://app.local/bye"]"#,
+    )
+    .await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+    let id_token = id_token(&app, &sid, "app").await;
+
+    let response = logout(
+        &app,
+        None,
+        &format!("id_token_hint={id_token}&post_logout_redirect_uri=https://attacker.example/"),
+    )
+    .await;
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    let response = logout(
+        &app,
+        None,
+        &format!(
+            "id_token_hint={id_token}&post_logout_redirect_uri=https://app.local/bye&state=xyz"
+        ),
+    )
+    .await;
+    assert!(response.status().is_redirection());
+    assert_eq!(location(&response), "https://app.local/bye?state=xyz");
+}
+
+#[tokio::test]
+async fn test_post_logout_redirect_requires_id_token_hint() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    for query in [
+        "post_logout_redirect_uri=https://localhost/",
+        "client_id=flecs&post_logout_redirect_uri=https://localhost/",
+    ] {
+        let response = logout(&app, Some(&sid), query).await;
+        assert_eq!(
+            response.status(),
+            http::StatusCode::BAD_REQUEST,
+            "query: {query}"
+        );
+    }
+    assert!(authorize(&app, &sid, "flecs").await.is_some());
+
+    // The flecs client only returns to the configured hosts
+    let id_token = id_token(&app, &sid, "flecs").await;
+    for uri in ["http://localhost/", "https://attacker.example/"] {
+        let response = logout(
+            &app,
+            None,
+            &format!("id_token_hint={id_token}&post_logout_redirect_uri={uri}"),
+        )
+        .await;
+        assert_eq!(
+            response.status(),
+            http::StatusCode::BAD_REQUEST,
+            "uri: {uri}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_logout_with_id_token_hint() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+    let code = authorize(&app, &sid, "flecs").await.unwrap();
+    let (status, body) = app.exchange_code(&code, "").await;
+    assert_eq!(status, http::StatusCode::OK);
+    let tokens: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let id_token = tokens["id_token"].as_str().unwrap();
+
+    let response = logout(&app, None, "id_token_hint=invalid").await;
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    let response = logout(
+        &app,
+        Some(&sid),
+        &format!("id_token_hint={id_token}&client_id=other"),
+    )
+    .await;
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    assert!(authorize(&app, &sid, "flecs").await.is_some());
+
+    // The client is taken from the audience of the id token
+    let response = logout(
+        &app,
+        Some(&sid),
+        &format!("id_token_hint={id_token}&post_logout_redirect_uri=https://localhost/"),
+    )
+    .await;
+    assert_eq!(location(&response), "https://localhost/");
+    assert!(authorize(&app, &sid, "flecs").await.is_none());
+}
+
+#[tokio::test]
+async fn test_backchannel_logout_notifies_clients() {
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let stub = axum::Router::new().route(
+        "/backchannel",
+        axum::routing::post(
+            move |axum::Form(form): axum::Form<std::collections::HashMap<String, String>>| async move {
+                sender.send(form["logout_token"].clone()).unwrap();
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, stub).await.unwrap() });
+
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    create_client(
+        &app,
+        &token,
+        &format!(r#", "backchannel_logout_uri": "http://{address}/backchannel""#),
+    )
+    .await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+    assert!(authorize(&app, &sid, "app").await.is_some());
+    assert!(authorize(&app, &sid, "flecs").await.is_some());
+
+    let response = logout(&app, Some(&sid), "").await;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    // Only the client with a back-channel logout uri is notified
+    let logout_token = receiver.try_recv().unwrap();
+    assert!(receiver.try_recv().is_err());
+    let header = jsonwebtoken::decode_header(&logout_token).unwrap();
+    assert_eq!(header.typ.as_deref(), Some("logout+jwt"));
+    let payload = logout_token.split('.').nth(1).unwrap();
+    let claims: serde_json::Value = serde_json::from_slice(
+        &base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(payload)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(claims["aud"], "app");
+    assert_eq!(claims["sub"], "0");
+    assert!(claims["jti"].is_string());
+    assert!(claims["events"]["http://schemas.openid.net/event/backchannel-logout"].is_object());
+    assert!(claims.get("nonce").is_none());
+}
+
+#[tokio::test]
+async fn test_backchannel_logout_failure_does_not_prevent_logout() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    // Nothing listens on port 9 of localhost
+    create_client(
+        &app,
+        &token,
+        r#", "backchannel_logout_uri": "http://127.0.0.1:9/backchannel""#,
+    )
+    .await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+    assert!(authorize(&app, &sid, "app").await.is_some());
+
+    let response = logout(&app, Some(&sid), "").await;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert!(authorize(&app, &sid, "app").await.is_none());
+}
+
+#[tokio::test]
+async fn test_invalid_backchannel_logout_uri_is_rejected() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let req = Request::post("/oauth-clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"id": "app", "name": "App", "client_type": {{"type": "Public"}}, "redirect_uris": ["{REDIRECT_URI}"], "scope": "admin", "backchannel_logout_uri": "https://app.local/logout#now"}}"#
+        )))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_deleting_user_notifies_clients_of_their_sessions() {
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let stub = axum::Router::new().route(
+        "/backchannel",
+        axum::routing::post(
+            move |axum::Form(form): axum::Form<std::collections::HashMap<String, String>>| async move {
+                sender.send(form["logout_token"].clone()).unwrap();
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, stub).await.unwrap() });
+
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    create_client(
+        &app,
+        &token,
+        &format!(r#", "backchannel_logout_uri": "http://{address}/backchannel""#),
+    )
+    .await;
+    let req = Request::post("/users")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "second", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.admin"]}}"#
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {body}");
+    let uid: u16 = serde_json::from_str(&body).unwrap();
+    let sid = app.login("second", VALID_PASSWORD, None).await;
+    assert!(authorize(&app, &sid, "app").await.is_some());
+
+    let req = Request::delete(format!("/users/{uid}"))
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+
+    let logout_token = receiver.try_recv().unwrap();
+    let payload = logout_token.split('.').nth(1).unwrap();
+    let claims: serde_json::Value = serde_json::from_slice(
+        &base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(payload)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(claims["aud"], "app");
+    assert_eq!(claims["sub"], uid.to_string());
+}
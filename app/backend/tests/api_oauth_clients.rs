@@ -0,0 +1,290 @@
+mod common;
+
+use common::{CODE_CHALLENGE, CODE_VERIFIER, REDIRECT_URI};
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+async fn create_client(
+    app: &common::TestApp,
+    token: &str,
+    json: &str,
+) -> (http::StatusCode, serde_json::Value) {
+    let req = Request::post("/oauth-clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(json))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    (status, serde_json::from_str(&body).unwrap_or_default())
+}
+
+fn public_client_json(id: &str, redirect_uri: &str) -> String {
+    format!(
+        r#"{{"id": "{id}", "name": "{id}", "client_type": {{"type": "Public"}}, "redirect_uris": ["{redirect_uri}"], "scope": "admin", "first_party": true}}"#
+    )
+}
+
+/// Authorize as `admin` for the given client and redirect uri, returning
+/// whether an authorization code was issued
+async fn authorize_code(app: &common::TestApp, client_id: &str, redirect_uri: &str) -> bool {
+    let query = format!(
+        "response_type=code&client_id={client_id}&redirect_uri={redirect_uri}&state=teststate&code_challenge={CODE_CHALLENGE}&code_challenge_method=S256"
+    );
+    let response = app
+        .authorize_response("admin", VALID_PASSWORD, &query)
+        .await;
+    common::redirect_param(&response, "code").is_some()
+}
+
+#[tokio::test]
+async fn test_default_flecs_client_is_registered() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let req = Request::get("/oauth-clients/flecs")
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK);
+    let client: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(client["client_type"], "public");
+    assert_eq!(client["require_pkce"], true);
+    assert_eq!(client["scope"], "admin");
+}
+
+#[tokio::test]
+async fn test_flecs_client_rejects_foreign_redirect() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    assert!(authorize_code(&app, "flecs", REDIRECT_URI).await);
+    assert!(!authorize_code(&app, "flecs", "https://attacker.example/").await);
+    assert!(!authorize_code(&app, "flecs", "https://localhost/oauth/callback/evil").await);
+    assert!(!authorize_code(&app, "flecs", "http://localhost/oauth/callback").await);
+    assert!(!authorize_code(&app, "flecs", "https://localhost:8443/oauth/callback").await);
+}
+
+#[tokio::test]
+async fn test_flecs_client_redirects_to_configured_hosts() {
+    let app = common::TestApp::new_with_config(|config| {
+        config.auth.flecs_ui_hosts = vec!["flecs.local".to_string(), "10.0.0.1:8443".to_string()];
+    })
+    .await;
+    setup_admin(&app).await;
+
+    assert!(authorize_code(&app, "flecs", "https://flecs.local/oauth/callback").await);
+    assert!(authorize_code(&app, "flecs", "https://10.0.0.1:8443/oauth/callback").await);
+    assert!(!authorize_code(&app, "flecs", REDIRECT_URI).await);
+}
+
+#[tokio::test]
+async fn test_flecs_client_logs_in_under_configured_address() {
+    let app = common::TestApp::new_with_config(|config| {
+        config.auth.flecs_ui_hosts =
+            vec!["flecs.local".to_string(), "192.168.1.10:8443".to_string()];
+    })
+    .await;
+    setup_admin(&app).await;
+
+    let redirect_uri = "https://192.168.1.10:8443/oauth/callback";
+    let query = format!(
+        "response_type=code&client_id=flecs&redirect_uri={redirect_uri}&state=teststate&code_challenge={CODE_CHALLENGE}&code_challenge_method=S256"
+    );
+    let response = app
+        .authorize_response("admin", VALID_PASSWORD, &query)
+        .await;
+    let code = common::redirect_param(&response, "code").unwrap();
+    let code: String = url::form_urlencoded::byte_serialize(code.as_bytes()).collect();
+    let req = Request::post("/oauth/token")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(json_body(&format!(
+            "grant_type=authorization_code&code={code}&redirect_uri={redirect_uri}&client_id=flecs&code_verifier={CODE_VERIFIER}"
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    let tokens: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(tokens["access_token"].is_string());
+
+    assert!(!authorize_code(&app, "flecs", "http://192.168.1.10:8443/oauth/callback").await);
+    assert!(!authorize_code(&app, "flecs", "https://192.168.1.11:8443/oauth/callback").await);
+    assert!(!authorize_code(&app, "flecs", "https://attacker.example/oauth/callback").await);
+}
+
+#[tokio::test]
+async fn test_registered_client_redirect_uris() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let (status, client) = create_client(
+        &app,
+        &token,
+        &public_client_json("dashboard", "https://*.flecs.local/callback"),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    assert!(client.get("secret").is_none());
+
+    assert!(authorize_code(&app, "dashboard", "https://ui.flecs.local/callback").await);
+    assert!(!authorize_code(&app, "dashboard", "https://flecs.local.evil/callback").await);
+    assert!(!authorize_code(&app, "dashboard", REDIRECT_URI).await);
+}
+
+#[tokio::test]
+async fn test_create_confidential_client_returns_secret_once() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let (status, client) = create_client(
+        &app,
+        &token,
+        &format!(
+            r#"{{"id": "backend", "name": "Backend", "client_type": {{"type": "Confidential"}}, "redirect_uris": ["{REDIRECT_URI}"], "scope": "admin"}}"#
+        ),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    assert_eq!(client["client_type"], "confidential");
+    assert_eq!(client["require_pkce"], true);
+    assert!(client["secret"].is_string());
+
+    let req = Request::get("/oauth-clients/backend")
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (_, body) = app.request_body(req).await;
+    let client: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(client.get("secret").is_none());
+}
+
+#[tokio::test]
+async fn test_create_client_validation() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let (status, _) = create_client(&app, &token, &public_client_json("flecs", REDIRECT_URI)).await;
+    assert_eq!(status, http::StatusCode::CONFLICT);
+
+    for uri in [
+        "ftp://flecs.local/callback",
+        "https://flecs.local/callback#fragment",
+        "https://ui.*.local/callback",
+    ] {
+        let (status, _) = create_client(&app, &token, &public_client_json("other", uri)).await;
+        assert_eq!(status, http::StatusCode::BAD_REQUEST, "uri: {uri}");
+    }
+
+    let (status, _) = create_client(
+        &app,
+        &token,
+        r#"{"id": "other", "name": "other", "client_type": {"type": "Public"}, "redirect_uris": [], "scope": "admin"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_update_redirect_uris() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let req = Request::patch("/oauth-clients/flecs")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(
+            r#"{"redirect_uris": ["https://ui.flecs.local/oauth/callback"]}"#,
+        ))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+
+    assert!(!authorize_code(&app, "flecs", REDIRECT_URI).await);
+    assert!(authorize_code(&app, "flecs", "https://ui.flecs.local/oauth/callback").await);
+}
+
+#[tokio::test]
+async fn test_delete_client() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    create_client(&app, &token, &public_client_json("dashboard", REDIRECT_URI)).await;
+
+    let delete = || {
+        Request::delete("/oauth-clients/dashboard")
+            .header("authorization", format!("Bearer {token}"))
+            .body(axum::body::Body::empty())
+            .unwrap()
+    };
+    let (status, _) = app.request_body(delete()).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let (status, _) = app.request_body(delete()).await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+    assert!(!authorize_code(&app, "dashboard", REDIRECT_URI).await);
+}
+
+#[tokio::test]
+async fn test_clients_persist_across_restart() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    create_client(&app, &token, &public_client_json("dashboard", REDIRECT_URI)).await;
+    let (_, tempdir) = app.shutdown();
+
+    let app = common::TestApp::new_with_setup(|dir| {
+        for file in ["users.json", "oauth_clients.json"] {
+            std::fs::copy(tempdir.path().join(file), dir.join(file)).unwrap();
+        }
+    })
+    .await;
+    assert!(authorize_code(&app, "dashboard", REDIRECT_URI).await);
+    assert!(authorize_code(&app, "flecs", REDIRECT_URI).await);
+}
+
+#[tokio::test]
+async fn test_manage_clients_requires_permission() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let req = Request::post("/users")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "operator", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.operator"]}}"#
+        )))
+        .unwrap();
+    let (_, body) = app.request_body(req).await;
+    let uid: u16 = serde_json::from_str(&body).unwrap();
+    let operator_token = app.mint_token(uid);
+
+    let req = Request::get("/oauth-clients")
+        .header("authorization", format!("Bearer {operator_token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+
+    let (status, _) = create_client(
+        &app,
+        &operator_token,
+        &public_client_json("dashboard", REDIRECT_URI),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,197 @@
+mod common;
+
+use base64::Engine;
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+}
+
+async fn login_tokens(app: &common::TestApp, extra_query: &str) -> serde_json::Value {
+    let code = app.authorize("admin", VALID_PASSWORD, extra_query).await;
+    let (status, body) = app.exchange_code(&code, "").await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    serde_json::from_str(&body).unwrap()
+}
+
+async fn decode_id_token(app: &common::TestApp, id_token: &str) -> serde_json::Value {
+    let req = Request::get("/.well-known/openid-configuration")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (_, body) = app.request_body(req).await;
+    let metadata: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    let req = Request::get("/.well-known/jwks.json")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (_, body) = app.request_body(req).await;
+    let jwks: jsonwebtoken::jwk::JwkSet = serde_json::from_str(&body).unwrap();
+
+    let header = jsonwebtoken::decode_header(id_token).unwrap();
+    let jwk = jwks.find(header.kid.as_deref().unwrap()).unwrap();
+    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::RS256);
+    validation.set_audience(&["flecs"]);
+    validation.set_issuer(&[metadata["issuer"].as_str().unwrap()]);
+    jsonwebtoken::decode::<serde_json::Value>(
+        id_token,
+        &jsonwebtoken::DecodingKey::from_jwk(jwk).unwrap(),
+        &validation,
+    )
+    .unwrap()
+    .claims
+}
+
+fn expected_at_hash(access_token: &str) -> String {
+    let digest = openssl::sha::sha256(access_token.as_bytes());
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&digest[..16])
+}
+
+#[tokio::test]
+async fn test_id_token_issued_for_openid_scope() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let before = chrono::Utc::now().timestamp();
+
+    let tokens = login_tokens(&app, "&scope=openid&nonce=n-0S6_WzA2Mj").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+    let claims = decode_id_token(&app, tokens["id_token"].as_str().unwrap()).await;
+
+    assert_eq!(claims["sub"], "0");
+    assert_eq!(claims["aud"], "flecs");
+    assert_eq!(claims["azp"], "flecs");
+    assert_eq!(claims["nonce"], "n-0S6_WzA2Mj");
+    assert_eq!(claims["at_hash"], expected_at_hash(access_token));
+    assert!(claims["auth_time"].as_i64().unwrap() >= before);
+    assert!(claims.get("name").is_none());
+}
+
+#[tokio::test]
+async fn test_id_token_contains_profile_for_profile_scope() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let tokens = login_tokens(&app, "&scope=openid%20profile").await;
+    let claims = decode_id_token(&app, tokens["id_token"].as_str().unwrap()).await;
+    assert_eq!(claims["name"], "Super Admin");
+    assert_eq!(claims["preferred_username"], "admin");
+    assert!(claims.get("nonce").is_none());
+}
+
+#[tokio::test]
+async fn test_no_id_token_without_openid_scope() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let tokens = login_tokens(&app, "").await;
+    assert!(tokens["access_token"].is_string());
+    assert!(tokens.get("id_token").is_none());
+}
+
+#[tokio::test]
+async fn test_tokens_carry_no_email() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    // Users have no email address, so no token claims one, even when asked
+    let tokens = login_tokens(&app, "&scope=openid%20profile%20email").await;
+    let payload = tokens["access_token"]
+        .as_str()
+        .unwrap()
+        .split('.')
+        .nth(1)
+        .unwrap();
+    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .unwrap();
+    let claims: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+    assert!(claims.get("email").is_none());
+    assert_eq!(claims["preferred_username"], "admin");
+
+    let claims = decode_id_token(&app, tokens["id_token"].as_str().unwrap()).await;
+    assert!(claims.get("email").is_none());
+    assert_eq!(claims["preferred_username"], "admin");
+}
+
+#[tokio::test]
+async fn test_userinfo_returns_profile_claims() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let tokens = login_tokens(&app, "&scope=openid%20profile").await;
+
+    for method in [http::Method::GET, http::Method::POST] {
+        let req = Request::builder()
+            .method(method)
+            .uri("/userinfo")
+            .header(
+                "authorization",
+                format!("Bearer {}", tokens["access_token"].as_str().unwrap()),
+            )
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let (status, body) = app.request_body(req).await;
+        assert_eq!(status, http::StatusCode::OK);
+        let userinfo: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            userinfo,
+            serde_json::json!({
+                "sub": "0",
+                "name": "Super Admin",
+                "preferred_username": "admin"
+            })
+        );
+    }
+}
+
+async fn userinfo(app: &common::TestApp, token: &str) -> (http::StatusCode, serde_json::Value) {
+    let req = Request::get("/userinfo")
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    (status, serde_json::from_str(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_userinfo_claims_follow_scope() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let tokens = login_tokens(&app, "&scope=openid").await;
+    let (status, claims) = userinfo(&app, tokens["access_token"].as_str().unwrap()).await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(claims, serde_json::json!({"sub": "0"}));
+
+    // Users have no email address, asking for one yields no claim
+    let tokens = login_tokens(&app, "&scope=openid%20email").await;
+    let (status, claims) = userinfo(&app, tokens["access_token"].as_str().unwrap()).await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(claims, serde_json::json!({"sub": "0"}));
+
+    // Tokens of plain OAuth flows get no claims
+    let (status, _) = userinfo(&app, &app.mint_token(0)).await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_userinfo_requires_authentication() {
+    let app = common::TestApp::new().await;
+    let req = Request::get("/userinfo")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+}
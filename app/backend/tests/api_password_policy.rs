@@ -0,0 +1,141 @@
+mod common;
+
+use http::Request;
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+/// Send a JSON request and return the status and the parsed body
+async fn send(
+    app: &common::TestApp,
+    req: http::request::Builder,
+    token: Option<&str>,
+    json: &str,
+) -> (http::StatusCode, serde_json::Value) {
+    let mut req = req.header("content-type", "application/json");
+    if let Some(token) = token {
+        req = req.header("authorization", format!("Bearer {token}"));
+    }
+    let (status, body) = app.request_body(req.body(json_body(json)).unwrap()).await;
+    (status, serde_json::from_str(&body).unwrap_or_default())
+}
+
+fn violations(body: &serde_json::Value) -> Vec<&str> {
+    body["violations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|rule| rule.as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_password_policy_is_public() {
+    let app = common::TestApp::new().await;
+
+    let req = Request::get("/meta/password-policy")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK);
+    let policy: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(policy["len_min"], 12);
+    assert_eq!(policy["len_max"], 63);
+    assert_eq!(policy["need_lower"], true);
+    assert_eq!(policy["need_upper"], true);
+    assert_eq!(policy["need_digit"], true);
+    assert_eq!(policy["need_special"], false);
+}
+
+#[tokio::test]
+async fn test_weak_super_admin_password_is_rejected() {
+    let app = common::TestApp::new().await;
+
+    let (status, body) = send(
+        &app,
+        Request::post("/users/super-admin"),
+        None,
+        r#"{"name": "admin", "full_name": "Super Admin", "password": ""}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    assert_eq!(violations(&body), ["min_length", "lower", "upper", "digit"]);
+
+    let req = Request::get("/users/super-admin")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_weak_password_of_new_user_is_rejected() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let (status, body) = send(
+        &app,
+        Request::post("/users"),
+        Some(&token),
+        r#"{"name": "weak", "password": "alllowercaseletters", "groups": []}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    assert_eq!(violations(&body), ["upper", "digit"]);
+
+    let (status, _) = send(
+        &app,
+        Request::post("/users"),
+        Some(&token),
+        &format!(r#"{{"name": "strong", "password": "{VALID_PASSWORD}", "groups": []}}"#),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_weak_password_updates_are_rejected() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let too_long = format!("A1{}", "a".repeat(62));
+
+    for path in ["/users/0", "/users/self"] {
+        let (status, body) = send(
+            &app,
+            Request::patch(path),
+            Some(&token),
+            &format!(r#"{{"password": "{too_long}"}}"#),
+        )
+        .await;
+        assert_eq!(status, http::StatusCode::BAD_REQUEST, "{path}");
+        assert_eq!(violations(&body), ["max_length"], "{path}");
+    }
+
+    // Updates without a password are not affected
+    let (status, _) = send(
+        &app,
+        Request::patch("/users/0"),
+        Some(&token),
+        r#"{"full_name": "Admin"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+
+    app.login("admin", VALID_PASSWORD, None).await;
+}
@@ -0,0 +1,196 @@
+mod common;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use common::{CODE_CHALLENGE, CODE_VERIFIER, REDIRECT_URI};
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+}
+
+fn flecs_query(pkce: &str) -> String {
+    format!("response_type=code&client_id=flecs&redirect_uri={REDIRECT_URI}&state=teststate{pkce}")
+}
+
+/// Register a confidential client and return its secret
+async fn create_confidential_client(app: &common::TestApp, require_pkce: bool) -> String {
+    let req = Request::post("/oauth-clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {}", app.mint_token(0)))
+        .body(json_body(&format!(
+            r#"{{"id": "backend", "name": "Backend", "client_type": {{"type": "Confidential", "require_pkce": {require_pkce}}}, "redirect_uris": ["{REDIRECT_URI}"], "scope": "admin", "first_party": true}}"#
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {body}");
+    let resp: serde_json::Value = serde_json::from_str(&body).unwrap();
+    resp["secret"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_s256_challenge_is_accepted() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let code = app.authorize("admin", VALID_PASSWORD, "").await;
+    let (status, body) = app.exchange_code(&code, "").await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+}
+
+#[tokio::test]
+async fn test_public_client_without_challenge_is_rejected() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let response = app
+        .authorize_response("admin", VALID_PASSWORD, &flecs_query(""))
+        .await;
+    assert!(common::redirect_param(&response, "code").is_none());
+}
+
+#[tokio::test]
+async fn test_plain_challenge_is_rejected() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let query = flecs_query(&format!(
+        "&code_challenge={CODE_VERIFIER}&code_challenge_method=plain"
+    ));
+    let response = app
+        .authorize_response("admin", VALID_PASSWORD, &query)
+        .await;
+    assert!(common::redirect_param(&response, "code").is_none());
+
+    // `plain` is the default method if none is given
+    let query = flecs_query(&format!("&code_challenge={CODE_VERIFIER}"));
+    let response = app
+        .authorize_response("admin", VALID_PASSWORD, &query)
+        .await;
+    assert!(common::redirect_param(&response, "code").is_none());
+}
+
+#[tokio::test]
+async fn test_missing_verifier_is_rejected() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let code = app.authorize("admin", VALID_PASSWORD, "").await;
+    let (status, _) = app.exchange_code_with(&code, "").await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_wrong_verifier_is_rejected() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let code = app.authorize("admin", VALID_PASSWORD, "").await;
+    let (status, _) = app
+        .exchange_code_with(&code, &format!("&code_verifier={CODE_CHALLENGE}"))
+        .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_confidential_client_requires_pkce_by_default() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    create_confidential_client(&app, true).await;
+
+    let query =
+        format!("response_type=code&client_id=backend&redirect_uri={REDIRECT_URI}&state=teststate");
+    let response = app
+        .authorize_response("admin", VALID_PASSWORD, &query)
+        .await;
+    assert!(common::redirect_param(&response, "code").is_none());
+}
+
+#[tokio::test]
+async fn test_confidential_client_may_opt_out() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let secret = create_confidential_client(&app, false).await;
+
+    let query =
+        format!("response_type=code&client_id=backend&redirect_uri={REDIRECT_URI}&state=teststate");
+    let response = app
+        .authorize_response("admin", VALID_PASSWORD, &query)
+        .await;
+    let code = common::redirect_param(&response, "code").expect("code without PKCE");
+    let code: String = url::form_urlencoded::byte_serialize(code.as_bytes()).collect();
+
+    let req = Request::post("/oauth/token")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header(
+            "authorization",
+            format!("Basic {}", STANDARD.encode(format!("backend:{secret}"))),
+        )
+        .body(axum::body::Body::from(format!(
+            "grant_type=authorization_code&code={code}&redirect_uri={REDIRECT_URI}"
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+}
+
+#[tokio::test]
+async fn test_public_client_cannot_opt_out() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let req = Request::patch("/oauth-clients/flecs")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {}", app.mint_token(0)))
+        .body(json_body(r#"{"require_pkce": false}"#))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_confidential_client_opts_out_through_update() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    create_confidential_client(&app, true).await;
+    let query =
+        format!("response_type=code&client_id=backend&redirect_uri={REDIRECT_URI}&state=teststate");
+
+    let req = Request::patch("/oauth-clients/backend")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {}", app.mint_token(0)))
+        .body(json_body(r#"{"require_pkce": false}"#))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert!(status.is_success(), "body: {body}");
+    let response = app
+        .authorize_response("admin", VALID_PASSWORD, &query)
+        .await;
+    assert!(common::redirect_param(&response, "code").is_some());
+
+    let req = Request::patch("/oauth-clients/backend")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {}", app.mint_token(0)))
+        .body(json_body(r#"{"require_pkce": true}"#))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert!(status.is_success());
+    let response = app
+        .authorize_response("admin", VALID_PASSWORD, &query)
+        .await;
+    assert!(common::redirect_param(&response, "code").is_none());
+}
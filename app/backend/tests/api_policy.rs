@@ -0,0 +1,503 @@
+mod common;
+
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+/// Send a JSON request and return the status and the parsed body
+async fn send(
+    app: &common::TestApp,
+    req: http::request::Builder,
+    token: &str,
+    json: &str,
+) -> (http::StatusCode, serde_json::Value) {
+    let req = req
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(json))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    (status, serde_json::from_str(&body).unwrap_or_default())
+}
+
+async fn check(
+    app: &common::TestApp,
+    token: &str,
+    subject: &str,
+    path: &str,
+    method: &str,
+) -> (http::StatusCode, serde_json::Value) {
+    send(
+        app,
+        Request::post("/policy/check"),
+        token,
+        &format!(r#"{{"subject": {subject}, "path": "{path}", "method": "{method}"}}"#),
+    )
+    .await
+}
+
+async fn create_user(app: &common::TestApp, token: &str, name: &str, group: &str) -> u16 {
+    let (status, body) = send(
+        app,
+        Request::post("/users"),
+        token,
+        &format!(r#"{{"name": "{name}", "password": "{VALID_PASSWORD}", "groups": ["{group}"]}}"#),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    body.as_u64().unwrap() as u16
+}
+
+#[tokio::test]
+async fn test_check_explains_allowed_request() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let (status, body) = check(
+        &app,
+        &token,
+        r#"{"type": "User", "uid": 0}"#,
+        "/users/7",
+        "get",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["allowed"], true);
+    assert_eq!(
+        body["rule"],
+        serde_json::json!({"role": "tech.flecs.fence.list_users", "path": "/users/:uid", "method": "GET"})
+    );
+    assert_eq!(
+        body["role_chain"],
+        serde_json::json!([
+            "tech.flecs.admin",
+            "tech.flecs.fence.admin",
+            "tech.flecs.fence.list_users"
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_check_explains_denied_request() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let uid = create_user(&app, &token, "operator", "tech.flecs.operator").await;
+
+    let (status, body) = check(
+        &app,
+        &token,
+        &format!(r#"{{"type": "User", "uid": {uid}}}"#),
+        "/users",
+        "GET",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["allowed"], false);
+    assert!(body.get("rule").is_none());
+    assert_eq!(body["role_chain"], serde_json::json!([]));
+    assert_eq!(
+        body["roles"],
+        serde_json::json!(["tech.flecs.core.operator", "tech.flecs.operator"])
+    );
+
+    // Public endpoints are allowed for everybody
+    let (_, body) = check(
+        &app,
+        &token,
+        r#"{"type": "Roles", "roles": []}"#,
+        "/login",
+        "GET",
+    )
+    .await;
+    assert_eq!(body["allowed"], true);
+    assert_eq!(body["rule"]["role"], "*");
+    assert_eq!(body["role_chain"], serde_json::json!(["*"]));
+}
+
+#[tokio::test]
+async fn test_check_follows_custom_groups() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let (status, _) = send(
+        &app,
+        Request::post("/groups"),
+        &token,
+        r#"{"id": "site-admin", "name": "Site Admin", "sub_groups": ["tech.flecs.admin"]}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let uid = create_user(&app, &token, "alice", "site-admin").await;
+
+    let (_, body) = check(
+        &app,
+        &token,
+        &format!(r#"{{"type": "User", "uid": {uid}}}"#),
+        "/groups",
+        "POST",
+    )
+    .await;
+    assert_eq!(body["allowed"], true);
+    assert_eq!(
+        body["role_chain"],
+        serde_json::json!([
+            "site-admin",
+            "tech.flecs.admin",
+            "tech.flecs.fence.admin",
+            "tech.flecs.fence.create_group"
+        ])
+    );
+
+    // Raw roles are checked as a token carries them, without sub groups
+    let (_, body) = check(
+        &app,
+        &token,
+        r#"{"type": "Roles", "roles": ["site-admin"]}"#,
+        "/groups",
+        "POST",
+    )
+    .await;
+    assert_eq!(body["allowed"], false);
+}
+
+#[tokio::test]
+async fn test_check_client() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let (status, client) = send(
+        &app,
+        Request::post("/clients"),
+        &token,
+        r#"{"name": "line-monitor", "auth_method": {"type": "Secret"}, "groups": ["tech.flecs.admin"]}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let cid = client["id"].as_str().unwrap();
+
+    let (status, body) = check(
+        &app,
+        &token,
+        &format!(r#"{{"type": "Client", "cid": "{cid}"}}"#),
+        "/keys",
+        "GET",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["allowed"], true);
+    assert_eq!(body["rule"]["role"], "tech.flecs.fence.manage_keys");
+}
+
+#[tokio::test]
+async fn test_check_unknown_subject_and_permission() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let (status, _) = check(
+        &app,
+        &token,
+        r#"{"type": "User", "uid": 999}"#,
+        "/users",
+        "GET",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+    let (status, _) = check(
+        &app,
+        &token,
+        &format!(r#"{{"type": "Client", "cid": "{}"}}"#, uuid::Uuid::new_v4()),
+        "/users",
+        "GET",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+
+    let uid = create_user(&app, &token, "operator", "tech.flecs.operator").await;
+    let (status, _) = check(
+        &app,
+        &app.mint_token(uid),
+        r#"{"type": "User", "uid": 0}"#,
+        "/users",
+        "GET",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+}
+
+async fn get_users_status(app: &common::TestApp, token: &str) -> http::StatusCode {
+    let req = Request::get("/users")
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    app.request(req).await.status()
+}
+
+#[tokio::test]
+async fn test_rules_are_added_and_removed_at_runtime() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let uid = create_user(&app, &token, "operator", "tech.flecs.operator").await;
+    let operator_token = app.mint_token(uid);
+    assert_eq!(
+        get_users_status(&app, &operator_token).await,
+        http::StatusCode::FORBIDDEN
+    );
+
+    let rule = r#"{"role": "tech.flecs.operator", "path": "/users", "method": "get"}"#;
+    let (status, _) = send(&app, Request::post("/policy/rules"), &token, rule).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let (status, _) = send(&app, Request::post("/policy/rules"), &token, rule).await;
+    assert_eq!(status, http::StatusCode::CONFLICT);
+    assert_eq!(
+        get_users_status(&app, &operator_token).await,
+        http::StatusCode::OK
+    );
+    let (_, rules) = send(&app, Request::get("/policy/rules"), &token, "").await;
+    assert!(rules.as_array().unwrap().contains(
+        &serde_json::json!({"role": "tech.flecs.operator", "path": "/users", "method": "GET"})
+    ));
+
+    let (status, _) = send(&app, Request::delete("/policy/rules"), &token, rule).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Request::delete("/policy/rules"), &token, rule).await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+    assert_eq!(
+        get_users_status(&app, &operator_token).await,
+        http::StatusCode::FORBIDDEN
+    );
+
+    let (status, _) = send(
+        &app,
+        Request::post("/policy/rules"),
+        &token,
+        r#"{"role": "tech.flecs.operator", "path": "users", "method": "GET"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_inheritances_are_added_and_removed_at_runtime() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let uid = create_user(&app, &token, "operator", "tech.flecs.operator").await;
+    let operator_token = app.mint_token(uid);
+
+    let inheritance =
+        r#"{"role": "tech.flecs.operator", "inherited_role": "tech.flecs.fence.list_users"}"#;
+    let (status, _) = send(
+        &app,
+        Request::post("/policy/inheritances"),
+        &token,
+        inheritance,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    assert_eq!(
+        get_users_status(&app, &operator_token).await,
+        http::StatusCode::OK
+    );
+
+    let (_, inheritances) = send(&app, Request::get("/policy/inheritances"), &token, "").await;
+    assert!(
+        inheritances
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!(
+                {"role": "tech.flecs.operator", "inherited_role": "tech.flecs.fence.list_users"}
+            ))
+    );
+    let (status, _) = send(
+        &app,
+        Request::delete("/policy/inheritances"),
+        &token,
+        inheritance,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    assert_eq!(
+        get_users_status(&app, &operator_token).await,
+        http::StatusCode::FORBIDDEN
+    );
+
+    // Shipped inheritances can be removed as well
+    let (status, _) = send(
+        &app,
+        Request::delete("/policy/inheritances"),
+        &token,
+        r#"{"role": "tech.flecs.fence.admin", "inherited_role": "tech.flecs.fence.list_users"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    assert_eq!(
+        get_users_status(&app, &token).await,
+        http::StatusCode::FORBIDDEN
+    );
+
+    let (status, _) = send(
+        &app,
+        Request::post("/policy/inheritances"),
+        &token,
+        r#"{"role": "a", "inherited_role": "a"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_admin_keeps_access_to_policy() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    for (path, line) in [
+        (
+            "/policy/rules",
+            r#"{"role": "tech.flecs.fence.manage_policy", "path": "/policy/rules", "method": "DELETE"}"#,
+        ),
+        (
+            "/policy/inheritances",
+            r#"{"role": "tech.flecs.fence.admin", "inherited_role": "tech.flecs.fence.manage_policy"}"#,
+        ),
+        (
+            "/policy/inheritances",
+            r#"{"role": "tech.flecs.admin", "inherited_role": "tech.flecs.fence.admin"}"#,
+        ),
+    ] {
+        let (status, _) = send(&app, Request::delete(path), &token, line).await;
+        assert_eq!(status, http::StatusCode::BAD_REQUEST, "{line}");
+    }
+    let (_, rules) = send(&app, Request::get("/policy/rules"), &token, "").await;
+    assert!(rules.as_array().unwrap().contains(&serde_json::json!(
+        {"role": "tech.flecs.fence.manage_policy", "path": "/policy/rules", "method": "DELETE"}
+    )));
+
+    // Rules can go once the admin reaches the endpoint another way
+    let rule = r#"{"role": "tech.flecs.admin", "path": "/policy/rules", "method": "DELETE"}"#;
+    let (status, _) = send(&app, Request::post("/policy/rules"), &token, rule).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let (status, _) = send(
+        &app,
+        Request::delete("/policy/rules"),
+        &token,
+        r#"{"role": "tech.flecs.fence.manage_policy", "path": "/policy/rules", "method": "DELETE"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Request::delete("/policy/rules"), &token, rule).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_unsaved_changes_are_not_applied_later() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let uid = create_user(&app, &token, "operator", "tech.flecs.operator").await;
+    let operator = app.mint_token(uid);
+    assert_eq!(
+        get_users_status(&app, &operator).await,
+        http::StatusCode::FORBIDDEN
+    );
+
+    // A directory in place of the temporary file makes saving fail
+    let policy_path = app.users_path.with_file_name("policy.json");
+    let blocker = policy_path.with_extension("tmp");
+    std::fs::create_dir_all(blocker.join("blocker")).unwrap();
+    let rule = r#"{"role": "tech.flecs.operator", "path": "/users", "method": "GET"}"#;
+    let (status, _) = send(&app, Request::post("/policy/rules"), &token, rule).await;
+    assert_eq!(status, http::StatusCode::INTERNAL_SERVER_ERROR);
+    let inheritance =
+        r#"{"role": "tech.flecs.operator", "inherited_role": "tech.flecs.fence.list_users"}"#;
+    let (status, _) = send(
+        &app,
+        Request::post("/policy/inheritances"),
+        &token,
+        inheritance,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(
+        get_users_status(&app, &operator).await,
+        http::StatusCode::FORBIDDEN
+    );
+
+    // Neither the next successful save nor a reload picks the changes up
+    std::fs::remove_dir_all(&blocker).unwrap();
+    let other = r#"{"role": "tech.flecs.operator", "path": "/groups", "method": "GET"}"#;
+    let (status, _) = send(&app, Request::post("/policy/rules"), &token, other).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let (status, _) = send(&app, Request::post("/policy/reload"), &token, "").await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    assert_eq!(
+        get_users_status(&app, &operator).await,
+        http::StatusCode::FORBIDDEN
+    );
+    let stored: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&policy_path).unwrap()).unwrap();
+    assert_eq!(
+        stored["added_rules"],
+        serde_json::json!([{"role": "tech.flecs.operator", "path": "/groups", "method": "GET"}])
+    );
+    assert_eq!(stored["added_inheritances"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_changes_are_persisted_and_reloaded() {
+    let changes = serde_json::json!({
+        "version": "1",
+        "added_rules": [],
+        "removed_rules": [{"role": "tech.flecs.fence.list_users", "path": "/users", "method": "GET"}],
+        "added_inheritances": [],
+        "removed_inheritances": [],
+    });
+    let app = common::TestApp::new_with_setup(|dir| {
+        std::fs::write(dir.join("policy.json"), changes.to_string()).unwrap();
+    })
+    .await;
+    let token = setup_admin(&app).await;
+    assert_eq!(
+        get_users_status(&app, &token).await,
+        http::StatusCode::FORBIDDEN
+    );
+
+    let rule = r#"{"role": "tech.flecs.fence.list_users", "path": "/users", "method": "GET"}"#;
+    let (status, _) = send(&app, Request::post("/policy/rules"), &token, rule).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let policy_path = app.users_path.with_file_name("policy.json");
+    let stored: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&policy_path).unwrap()).unwrap();
+    assert_eq!(stored["removed_rules"], serde_json::json!([]));
+
+    // Edits on disk take effect with a reload
+    std::fs::write(&policy_path, changes.to_string()).unwrap();
+    assert_eq!(get_users_status(&app, &token).await, http::StatusCode::OK);
+    let (status, _) = send(&app, Request::post("/policy/reload"), &token, "").await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    assert_eq!(
+        get_users_status(&app, &token).await,
+        http::StatusCode::FORBIDDEN
+    );
+
+    // A broken file and backup are rejected and the running policy kept
+    std::fs::write(&policy_path, "not json").unwrap();
+    std::fs::write(policy_path.with_extension("bak"), "not json").unwrap();
+    app.state.reload_policy().await.unwrap_err();
+    assert_eq!(
+        get_users_status(&app, &token).await,
+        http::StatusCode::FORBIDDEN
+    );
+}
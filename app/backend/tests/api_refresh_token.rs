@@ -0,0 +1,175 @@
+mod common;
+
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+async fn create_user(app: &common::TestApp, token: &str, name: &str) -> u16 {
+    let req = Request::post("/users")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "{name}", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.operator"]}}"#
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    serde_json::from_str(&body).unwrap()
+}
+
+/// Run the authorization code flow and return the token response
+async fn login_tokens(app: &common::TestApp, username: &str) -> serde_json::Value {
+    let code = app.authorize(username, VALID_PASSWORD, "").await;
+    let (status, body) = app.exchange_code(&code, "").await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    serde_json::from_str(&body).unwrap()
+}
+
+async fn refresh(app: &common::TestApp, refresh_token: &str) -> (http::StatusCode, String) {
+    let req = Request::post("/oauth/token")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(format!(
+            "grant_type=refresh_token&refresh_token={refresh_token}&client_id=flecs"
+        )))
+        .unwrap();
+    app.request_body(req).await
+}
+
+#[tokio::test]
+async fn test_authorization_code_returns_refresh_token() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let tokens = login_tokens(&app, "admin").await;
+    assert!(tokens["access_token"].is_string());
+    assert!(tokens["refresh_token"].is_string());
+}
+
+#[tokio::test]
+async fn test_refresh_rotates_refresh_token() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let tokens = login_tokens(&app, "admin").await;
+    let first = tokens["refresh_token"].as_str().unwrap();
+
+    let (status, body) = refresh(&app, first).await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    let refreshed: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let second = refreshed["refresh_token"].as_str().unwrap();
+    assert_ne!(first, second);
+
+    // The new access token is accepted
+    let req = Request::get("/users")
+        .header(
+            "authorization",
+            format!("Bearer {}", refreshed["access_token"].as_str().unwrap()),
+        )
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK);
+
+    let (status, _) = refresh(&app, second).await;
+    assert_eq!(status, http::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_refresh_token_reuse_revokes_family() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let tokens = login_tokens(&app, "admin").await;
+    let first = tokens["refresh_token"].as_str().unwrap();
+
+    let (_, body) = refresh(&app, first).await;
+    let refreshed: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let second = refreshed["refresh_token"].as_str().unwrap();
+
+    // Reusing the first token is rejected and revokes the whole family
+    let (status, _) = refresh(&app, first).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    let (status, _) = refresh(&app, second).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_refresh_token_unknown() {
+    let app = common::TestApp::new().await;
+    let (status, _) = refresh(&app, "unknown").await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_refresh_token_revoked_on_password_change() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let uid = create_user(&app, &admin_token, "operator").await;
+    let tokens = login_tokens(&app, "operator").await;
+
+    let req = Request::patch(format!("/users/{uid}"))
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {admin_token}"))
+        .body(json_body(r#"{"password": "NewPassword456"}"#))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+
+    let (status, _) = refresh(&app, tokens["refresh_token"].as_str().unwrap()).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_refresh_token_revoked_on_user_deletion() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let uid = create_user(&app, &admin_token, "operator").await;
+    let tokens = login_tokens(&app, "operator").await;
+
+    let req = Request::delete(format!("/users/{uid}"))
+        .header("authorization", format!("Bearer {admin_token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+
+    // Even if a new user takes over the freed uid
+    create_user(&app, &admin_token, "successor").await;
+
+    let (status, _) = refresh(&app, tokens["refresh_token"].as_str().unwrap()).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_refresh_token_survives_restart() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let tokens = login_tokens(&app, "admin").await;
+
+    let (users_path, _tempdir) = app.shutdown();
+    let data_dir = users_path.parent().unwrap().to_path_buf();
+    let app = common::TestApp::new_with_setup(|path| {
+        for file in ["users.json", "signing_key.json", "refresh_tokens.json"] {
+            std::fs::copy(data_dir.join(file), path.join(file)).unwrap();
+        }
+    })
+    .await;
+
+    let (status, body) = refresh(&app, tokens["refresh_token"].as_str().unwrap()).await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+}
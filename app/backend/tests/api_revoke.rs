@@ -0,0 +1,187 @@
+mod common;
+
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+/// Revoke as the public `flecs` client, which all test tokens are issued to
+async fn revoke(app: &common::TestApp, form: &str) -> http::StatusCode {
+    revoke_as(app, &format!("client_id=flecs&{form}")).await
+}
+
+async fn revoke_as(app: &common::TestApp, form: &str) -> http::StatusCode {
+    let req = Request::post("/oauth/revoke")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(form.to_string()))
+        .unwrap();
+    app.request_body(req).await.0
+}
+
+/// Create a client with secret auth and return (client_id, client_secret).
+async fn create_secret_client(app: &common::TestApp, token: &str) -> (String, String) {
+    let req = Request::post("/clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(
+            r#"{"name": "monitor", "auth_method": {"type": "Secret"}, "groups": ["tech.flecs.operator"]}"#,
+        ))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {body}");
+    let resp: serde_json::Value = serde_json::from_str(&body).unwrap();
+    (
+        resp["id"].as_str().unwrap().to_string(),
+        resp["secret"].as_str().unwrap().to_string(),
+    )
+}
+
+async fn list_users(app: &common::TestApp, token: &str) -> http::StatusCode {
+    let req = Request::get("/users")
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    app.request_body(req).await.0
+}
+
+#[tokio::test]
+async fn test_revoked_access_token_is_rejected() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let other_token = app.mint_token(0);
+    assert_eq!(list_users(&app, &token).await, http::StatusCode::OK);
+
+    assert_eq!(
+        revoke(&app, &format!("token={token}&token_type_hint=access_token")).await,
+        http::StatusCode::OK
+    );
+    assert_eq!(
+        list_users(&app, &token).await,
+        http::StatusCode::UNAUTHORIZED
+    );
+    // Other tokens of the same user stay valid
+    assert_eq!(list_users(&app, &other_token).await, http::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_access_token_revoked_without_hint() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    assert_eq!(
+        revoke(&app, &format!("token={token}")).await,
+        http::StatusCode::OK
+    );
+    assert_eq!(
+        list_users(&app, &token).await,
+        http::StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_revoked_refresh_token_can_not_be_used() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let code = app.authorize("admin", VALID_PASSWORD, "").await;
+    let (_, body) = app.exchange_code(&code, "").await;
+    let tokens: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let refresh_token = tokens["refresh_token"].as_str().unwrap();
+
+    assert_eq!(
+        revoke(
+            &app,
+            &format!("token={refresh_token}&token_type_hint=refresh_token")
+        )
+        .await,
+        http::StatusCode::OK
+    );
+
+    let req = Request::post("/oauth/token")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(format!(
+            "grant_type=refresh_token&refresh_token={refresh_token}&client_id=flecs"
+        )))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_revoke_unknown_token_succeeds() {
+    let app = common::TestApp::new().await;
+    assert_eq!(revoke(&app, "token=unknown").await, http::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_revoke_without_token_fails() {
+    let app = common::TestApp::new().await;
+    assert!(revoke(&app, "").await.is_client_error());
+}
+
+#[tokio::test]
+async fn test_revoke_requires_client_authentication() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let (client_id, _) = create_secret_client(&app, &token).await;
+
+    assert!(
+        revoke_as(&app, &format!("token={token}"))
+            .await
+            .is_client_error()
+    );
+    assert_eq!(
+        revoke_as(
+            &app,
+            &format!("token={token}&client_id={client_id}&client_secret=wrong")
+        )
+        .await,
+        http::StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(list_users(&app, &token).await, http::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_tokens_of_other_clients_are_not_revoked() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let (client_id, client_secret) = create_secret_client(&app, &token).await;
+    let code = app.authorize("admin", VALID_PASSWORD, "").await;
+    let (_, body) = app.exchange_code(&code, "").await;
+    let tokens: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let refresh_token = tokens["refresh_token"].as_str().unwrap();
+
+    let credentials = format!("client_id={client_id}&client_secret={client_secret}");
+    assert_eq!(
+        revoke_as(&app, &format!("token={token}&{credentials}")).await,
+        http::StatusCode::OK
+    );
+    assert_eq!(
+        revoke_as(&app, &format!("token={refresh_token}&{credentials}")).await,
+        http::StatusCode::OK
+    );
+    assert_eq!(list_users(&app, &token).await, http::StatusCode::OK);
+    let req = Request::post("/oauth/token")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(format!(
+            "grant_type=refresh_token&refresh_token={refresh_token}&client_id=flecs"
+        )))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK);
+}
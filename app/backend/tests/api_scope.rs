@@ -0,0 +1,158 @@
+mod common;
+
+use base64::Engine;
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+fn token_claims(token: &str) -> serde_json::Value {
+    let payload = token.split('.').nth(1).unwrap();
+    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .unwrap();
+    serde_json::from_slice(&payload).unwrap()
+}
+
+fn roles(claims: &serde_json::Value) -> Vec<String> {
+    let mut roles: Vec<String> = claims["realm_access"]["roles"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r.as_str().unwrap().to_string())
+        .collect();
+    roles.sort();
+    roles
+}
+
+async fn authorized_tokens(app: &common::TestApp, extra_query: &str) -> serde_json::Value {
+    let code = app.authorize("admin", VALID_PASSWORD, extra_query).await;
+    let (status, body) = app.exchange_code(&code, "").await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    serde_json::from_str(&body).unwrap()
+}
+
+async fn client_credentials(
+    app: &common::TestApp,
+    token: &str,
+    extra_form: &str,
+) -> (http::StatusCode, String) {
+    let req = Request::post("/clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(
+            r#"{"name": "scoped", "auth_method": {"type": "Secret"}, "groups": ["tech.flecs.admin"]}"#,
+        ))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED, "body: {body}");
+    let client: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    let req = Request::post("/oauth/token")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(format!(
+            "grant_type=client_credentials&client_id={}&client_secret={}{extra_form}",
+            client["id"].as_str().unwrap(),
+            client["secret"].as_str().unwrap()
+        )))
+        .unwrap();
+    app.request_body(req).await
+}
+
+#[tokio::test]
+async fn test_default_scope_grants_all_roles() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let tokens = authorized_tokens(&app, "").await;
+    let claims = token_claims(tokens["access_token"].as_str().unwrap());
+    assert!(roles(&claims).contains(&"tech.flecs.admin".to_string()));
+    assert_eq!(claims["scope"], "admin");
+}
+
+#[tokio::test]
+async fn test_requested_role_restricts_token() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let tokens = authorized_tokens(&app, "&scope=tech.flecs.operator").await;
+    let claims = token_claims(tokens["access_token"].as_str().unwrap());
+    assert!(!roles(&claims).contains(&"tech.flecs.admin".to_string()));
+    assert!(roles(&claims).contains(&"tech.flecs.operator".to_string()));
+    assert_eq!(claims["scope"], "tech.flecs.operator");
+}
+
+#[tokio::test]
+async fn test_openid_only_request_keeps_default_roles() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let tokens = authorized_tokens(&app, "&scope=openid").await;
+    let claims = token_claims(tokens["access_token"].as_str().unwrap());
+    assert!(roles(&claims).contains(&"tech.flecs.admin".to_string()));
+    assert!(tokens["id_token"].is_string());
+}
+
+#[tokio::test]
+async fn test_role_not_held_is_not_granted() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let req = Request::post("/users")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "operator", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.operator"]}}"#
+        )))
+        .unwrap();
+    app.request(req).await;
+
+    let code = app
+        .authorize("operator", VALID_PASSWORD, "&scope=tech.flecs.admin")
+        .await;
+    let (status, body) = app.exchange_code(&code, "").await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    let tokens: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let claims = token_claims(tokens["access_token"].as_str().unwrap());
+    assert!(!roles(&claims).contains(&"tech.flecs.admin".to_string()));
+    assert!(roles(&claims).contains(&"tech.flecs.operator".to_string()));
+}
+
+#[tokio::test]
+async fn test_client_credentials_scope_restricts_roles() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let (status, body) = client_credentials(&app, &token, "&scope=tech.flecs.operator").await;
+    assert_eq!(status, http::StatusCode::OK, "body: {body}");
+    let resp: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(resp["scope"], "tech.flecs.operator");
+    let claims = token_claims(resp["access_token"].as_str().unwrap());
+    assert!(roles(&claims).contains(&"tech.flecs.operator".to_string()));
+    assert!(!roles(&claims).contains(&"tech.flecs.admin".to_string()));
+}
+
+#[tokio::test]
+async fn test_client_credentials_scope_beyond_client_groups_is_rejected() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+
+    let (status, _) = client_credentials(&app, &token, "&scope=unknown.group").await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use common::{CODE_CHALLENGE, REDIRECT_URI};
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+async fn create_operator(app: &common::TestApp, admin_token: &str) -> u16 {
+    let req = Request::post("/users")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {admin_token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "operator", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.operator"]}}"#
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    serde_json::from_str(&body).unwrap()
+}
+
+/// Log in from the given device and return the sid
+async fn login_from(
+    app: &common::TestApp,
+    username: &str,
+    user_agent: &str,
+    forwarded_for: &str,
+) -> String {
+    let req = Request::post("/login")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("user-agent", user_agent)
+        .header("x-forwarded-for", forwarded_for)
+        .body(axum::body::Body::from(format!(
+            "username={username}&password={VALID_PASSWORD}"
+        )))
+        .unwrap();
+    let response = app.request(req).await;
+    common::extract_sid(&response).unwrap()
+}
+
+async fn is_logged_in(app: &common::TestApp, sid: &str) -> bool {
+    let req = Request::get(format!(
+        "/oauth/authorize?response_type=code&client_id=flecs&redirect_uri={REDIRECT_URI}&state=teststate&code_challenge={CODE_CHALLENGE}&code_challenge_method=S256"
+    ))
+    .header("cookie", format!("sid={sid}"))
+    .body(axum::body::Body::empty())
+    .unwrap();
+    let response = app.request(req).await;
+    common::redirect_param(&response, "code").is_some()
+}
+
+async fn list_sessions(
+    app: &common::TestApp,
+    token: &str,
+    path: &str,
+) -> (http::StatusCode, serde_json::Value) {
+    let req = Request::get(path)
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    (status, serde_json::from_str(&body).unwrap_or_default())
+}
+
+async fn delete_session(app: &common::TestApp, token: &str, path: &str) -> http::StatusCode {
+    let req = Request::delete(path)
+        .header("authorization", format!("Bearer {token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    app.request(req).await.status()
+}
+
+#[tokio::test]
+async fn test_list_own_sessions() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    login_from(&app, "admin", "Laptop", "10.0.0.1").await;
+    login_from(&app, "admin", "Phone", "192.0.2.1, 10.0.0.2").await;
+
+    let (status, sessions) = list_sessions(&app, &token, "/users/self/sessions").await;
+    assert_eq!(status, http::StatusCode::OK);
+    let mut sessions = sessions.as_array().unwrap().clone();
+    sessions.sort_by_key(|session| session["user_agent"].as_str().unwrap().to_string());
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0]["user_agent"], "Laptop");
+    assert_eq!(sessions[0]["ip_address"], "10.0.0.1");
+    // Only the address added by the proxy is trusted
+    assert_eq!(sessions[1]["user_agent"], "Phone");
+    assert_eq!(sessions[1]["ip_address"], "10.0.0.2");
+    for session in &sessions {
+        assert!(session["sid"].is_string());
+        assert!(session["created_at"].is_string());
+        assert!(session["last_active"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn test_revoke_own_session() {
+    let app = common::TestApp::new().await;
+    let token = setup_admin(&app).await;
+    let laptop = login_from(&app, "admin", "Laptop", "10.0.0.1").await;
+    let phone = login_from(&app, "admin", "Phone", "10.0.0.2").await;
+
+    let (_, sessions) = list_sessions(&app, &token, "/users/self/sessions").await;
+    let phone_id = sessions
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|session| session["user_agent"] == "Phone")
+        .map(|session| session["sid"].as_str().unwrap().to_string())
+        .unwrap();
+    // The public id is not the secret sid
+    assert_ne!(phone_id, phone);
+
+    let path = format!("/users/self/sessions/{phone_id}");
+    assert_eq!(
+        delete_session(&app, &token, &path).await,
+        http::StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        delete_session(&app, &token, &path).await,
+        http::StatusCode::NOT_FOUND
+    );
+    assert!(!is_logged_in(&app, &phone).await);
+    assert!(is_logged_in(&app, &laptop).await);
+}
+
+#[tokio::test]
+async fn test_users_cannot_revoke_sessions_of_others() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let operator = create_operator(&app, &admin_token).await;
+    let operator_token = app.mint_token(operator);
+    let admin_sid = login_from(&app, "admin", "Laptop", "10.0.0.1").await;
+
+    let (_, sessions) = list_sessions(&app, &admin_token, "/users/self/sessions").await;
+    let admin_session = sessions[0]["sid"].as_str().unwrap().to_string();
+
+    let (_, sessions) = list_sessions(&app, &operator_token, "/users/self/sessions").await;
+    assert_eq!(sessions, serde_json::json!([]));
+    assert_eq!(
+        delete_session(
+            &app,
+            &operator_token,
+            &format!("/users/self/sessions/{admin_session}")
+        )
+        .await,
+        http::StatusCode::NOT_FOUND
+    );
+    assert!(is_logged_in(&app, &admin_sid).await);
+
+    let (status, _) = list_sessions(&app, &operator_token, "/users/0/sessions").await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+    assert_eq!(
+        delete_session(
+            &app,
+            &operator_token,
+            &format!("/users/0/sessions/{admin_session}")
+        )
+        .await,
+        http::StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn test_admin_manages_sessions_of_users() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let operator = create_operator(&app, &admin_token).await;
+    let operator_sid = login_from(&app, "operator", "Panel", "10.0.0.3").await;
+
+    let path = format!("/users/{operator}/sessions");
+    let (status, sessions) = list_sessions(&app, &admin_token, &path).await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(sessions.as_array().unwrap().len(), 1);
+    assert_eq!(sessions[0]["user_agent"], "Panel");
+    let session = sessions[0]["sid"].as_str().unwrap().to_string();
+
+    // Sessions are only found below the user they belong to
+    assert_eq!(
+        delete_session(&app, &admin_token, &format!("/users/0/sessions/{session}")).await,
+        http::StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        delete_session(&app, &admin_token, &format!("{path}/{session}")).await,
+        http::StatusCode::NO_CONTENT
+    );
+    assert!(!is_logged_in(&app, &operator_sid).await);
+
+    let (status, _) = list_sessions(&app, &admin_token, "/users/99/sessions").await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_deleting_user_ends_their_sessions() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let operator = create_operator(&app, &admin_token).await;
+    login_from(&app, "operator", "Panel", "10.0.0.3").await;
+
+    let req = Request::delete(format!("/users/{operator}"))
+        .header("authorization", format!("Bearer {admin_token}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    assert_eq!(
+        app.request(req).await.status(),
+        http::StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        app.state
+            .sessions
+            .lock()
+            .unwrap()
+            .query_by_user(operator)
+            .count(),
+        0
+    );
+}
@@ -0,0 +1,236 @@
+mod common;
+
+use common::{CODE_CHALLENGE, REDIRECT_URI};
+use http::Request;
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+async fn setup_admin(app: &common::TestApp) {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+}
+
+fn flecs_query(extra_query: &str) -> String {
+    format!(
+        "response_type=code&client_id=flecs&redirect_uri={REDIRECT_URI}&state=teststate&code_challenge={CODE_CHALLENGE}&code_challenge_method=S256{extra_query}"
+    )
+}
+
+async fn authorize(
+    app: &common::TestApp,
+    sid: Option<&str>,
+    query: &str,
+) -> http::Response<axum::body::Body> {
+    let mut req = Request::get(format!("/oauth/authorize?{query}"));
+    if let Some(sid) = sid {
+        req = req.header("cookie", format!("sid={sid}"));
+    }
+    app.request(req.body(axum::body::Body::empty()).unwrap())
+        .await
+}
+
+fn location(response: &http::Response<axum::body::Body>) -> &str {
+    response.headers()["location"].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn test_session_is_shared_by_authorizations() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    for _ in 0..3 {
+        let response = authorize(&app, Some(&sid), &flecs_query("")).await;
+        assert!(common::redirect_param(&response, "code").is_some());
+    }
+}
+
+#[tokio::test]
+async fn test_login_cookie_expires_with_session() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let req = Request::post("/login")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(format!(
+            "username=admin&password={VALID_PASSWORD}"
+        )))
+        .unwrap();
+    let response = app.request(req).await;
+    let cookie = response.headers()["set-cookie"].to_str().unwrap();
+    assert!(cookie.contains("Max-Age=43200"), "cookie: {cookie}");
+}
+
+#[tokio::test]
+async fn test_unexpired_sessions_survive_garbage_collection() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    app.state.remove_expired_sessions();
+    let response = authorize(&app, Some(&sid), &flecs_query("")).await;
+    assert!(common::redirect_param(&response, "code").is_some());
+}
+
+#[tokio::test]
+async fn test_prompt_none_without_session_returns_login_required() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+
+    let response = authorize(&app, None, &flecs_query("&prompt=none")).await;
+    assert!(response.status().is_redirection());
+    assert!(location(&response).starts_with(REDIRECT_URI));
+    assert_eq!(
+        common::redirect_param(&response, "error").as_deref(),
+        Some("login_required")
+    );
+    assert_eq!(
+        common::redirect_param(&response, "state").as_deref(),
+        Some("teststate")
+    );
+
+    // Errors are never sent to unregistered redirect uris
+    let query = flecs_query("&prompt=none").replace(REDIRECT_URI, "https://attacker.example/");
+    let response = authorize(&app, None, &query).await;
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_prompt_none_with_session_issues_code() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    let response = authorize(&app, Some(&sid), &flecs_query("&prompt=none")).await;
+    assert!(common::redirect_param(&response, "code").is_some());
+}
+
+#[tokio::test]
+async fn test_prompt_none_without_consent_returns_consent_required() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let token = app.mint_token(0);
+    let req = Request::post("/oauth-clients")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"id": "app", "name": "App", "client_type": {{"type": "Public"}}, "redirect_uris": ["{REDIRECT_URI}"], "scope": "admin"}}"#
+        )))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    let query = flecs_query("&prompt=none").replace("client_id=flecs", "client_id=app");
+    let response = authorize(&app, Some(&sid), &query).await;
+    assert_eq!(
+        common::redirect_param(&response, "error").as_deref(),
+        Some("consent_required")
+    );
+    assert!(common::redirect_param(&response, "code").is_none());
+}
+
+#[tokio::test]
+async fn test_prompt_login_requires_new_login() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    let response = authorize(&app, Some(&sid), &flecs_query("&prompt=login")).await;
+    assert_eq!(location(&response), "/login");
+    let login_sid = common::extract_sid(&response).unwrap();
+
+    // The login finishes the authorization without asking again
+    let req = Request::post("/login")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("cookie", format!("sid={login_sid}"))
+        .body(axum::body::Body::from(format!(
+            "username=admin&password={VALID_PASSWORD}"
+        )))
+        .unwrap();
+    let response = app.request(req).await;
+    let next = location(&response).to_string();
+    assert!(!next.contains("prompt=login"), "location: {next}");
+    let user_sid = common::extract_sid(&response).unwrap();
+    let response = app
+        .request(
+            Request::get(next)
+                .header("cookie", format!("sid={user_sid}"))
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await;
+    assert!(common::redirect_param(&response, "code").is_some());
+}
+
+#[tokio::test]
+async fn test_max_age_requires_recent_login() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    let response = authorize(&app, Some(&sid), &flecs_query("&max_age=3600")).await;
+    assert!(common::redirect_param(&response, "code").is_some());
+
+    let response = authorize(&app, Some(&sid), &flecs_query("&max_age=0")).await;
+    assert_eq!(location(&response), "/login");
+
+    let response = authorize(&app, Some(&sid), &flecs_query("&max_age=soon")).await;
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_sessions_and_codes_survive_restart() {
+    let app = common::TestApp::new().await;
+    setup_admin(&app).await;
+    let user_sid = app.login("admin", VALID_PASSWORD, None).await;
+    let code = app.authorize("admin", VALID_PASSWORD, "").await;
+    let response = authorize(&app, None, &flecs_query("")).await;
+    let login_sid = common::extract_sid(&response).unwrap();
+    let (_, tempdir) = app.shutdown();
+
+    // Only digests of the sids are stored
+    let sessions = std::fs::read_to_string(tempdir.path().join("sessions.json")).unwrap();
+    assert!(!sessions.contains(&user_sid));
+    assert!(!sessions.contains(&login_sid));
+
+    let app = common::TestApp::new_with_setup(|dir| {
+        for file in [
+            "users.json",
+            "signing_key.json",
+            "sessions.json",
+            "authorization_codes.json",
+        ] {
+            std::fs::copy(tempdir.path().join(file), dir.join(file)).unwrap();
+        }
+    })
+    .await;
+
+    let (status, _) = app.exchange_code(&code, "").await;
+    assert_eq!(status, http::StatusCode::OK);
+    let (status, _) = app.exchange_code(&code, "").await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+
+    let response = authorize(&app, Some(&user_sid), &flecs_query("")).await;
+    assert!(common::redirect_param(&response, "code").is_some());
+
+    let req = Request::post("/login")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("cookie", format!("sid={login_sid}"))
+        .body(axum::body::Body::from(format!(
+            "username=admin&password={VALID_PASSWORD}"
+        )))
+        .unwrap();
+    let response = app.request(req).await;
+    assert!(location(&response).starts_with("/oauth/authorize?"));
+}
@@ -0,0 +1,389 @@
+mod common;
+
+use http::Request;
+use user_manager::model::group::GroupId;
+use user_manager::model::totp::TotpSecret;
+
+fn json_body(json: &str) -> axum::body::Body {
+    axum::body::Body::from(json.to_string())
+}
+
+const VALID_PASSWORD: &str = "TestPassword123";
+
+fn super_admin_json() -> String {
+    format!(r#"{{"name": "admin", "full_name": "Super Admin", "password": "{VALID_PASSWORD}"}}"#)
+}
+
+async fn setup_admin(app: &common::TestApp) -> String {
+    let req = Request::post("/users/super-admin")
+        .header("content-type", "application/json")
+        .body(json_body(&super_admin_json()))
+        .unwrap();
+    app.request(req).await;
+    app.mint_token(0)
+}
+
+async fn create_user(app: &common::TestApp, token: &str, name: &str) -> u16 {
+    let req = Request::post("/users")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "{name}", "password": "{VALID_PASSWORD}", "groups": []}}"#
+        )))
+        .unwrap();
+    let (status, body) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    body.parse().unwrap()
+}
+
+/// Send a JSON request and return the status and the parsed body
+async fn send(
+    app: &common::TestApp,
+    req: http::request::Builder,
+    token: &str,
+    json: &str,
+) -> (http::StatusCode, serde_json::Value) {
+    let req = req
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"));
+    let (status, body) = app.request_body(req.body(json_body(json)).unwrap()).await;
+    (status, serde_json::from_str(&body).unwrap_or_default())
+}
+
+fn current_password() -> String {
+    format!(r#"{{"current_password": "{VALID_PASSWORD}"}}"#)
+}
+
+/// Code of the authenticator one time step ahead, so that it is never the
+/// code that was just accepted
+fn next_code(secret: &TotpSecret) -> String {
+    secret.code_at(chrono::Utc::now() + chrono::Duration::seconds(30))
+}
+
+/// Set up and confirm an authenticator, returns its secret and the
+/// recovery codes
+async fn enroll(app: &common::TestApp, token: &str) -> (TotpSecret, Vec<String>) {
+    let (status, enrollment) = send(
+        app,
+        Request::post("/users/self/totp"),
+        token,
+        &current_password(),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    let secret: TotpSecret = serde_json::from_value(enrollment["secret"].clone()).unwrap();
+
+    let code = secret.code_at(chrono::Utc::now());
+    let (status, body) = send(
+        app,
+        Request::post("/users/self/totp/confirm"),
+        token,
+        &format!(r#"{{"code": "{code}"}}"#),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    let recovery_codes = body["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_string())
+        .collect();
+    (secret, recovery_codes)
+}
+
+/// Enter the password, returns the response and the sid of its cookie
+async fn post_password(
+    app: &common::TestApp,
+    username: &str,
+    sid: Option<&str>,
+) -> (http::Response<axum::body::Body>, Option<String>) {
+    let mut req =
+        Request::post("/login").header("content-type", "application/x-www-form-urlencoded");
+    if let Some(sid) = sid {
+        req = req.header("cookie", format!("sid={sid}"));
+    }
+    let req = req
+        .body(axum::body::Body::from(format!(
+            "username={username}&password={VALID_PASSWORD}"
+        )))
+        .unwrap();
+    let response = app.request(req).await;
+    let sid = common::extract_sid(&response);
+    (response, sid)
+}
+
+async fn post_code(
+    app: &common::TestApp,
+    sid: &str,
+    code: &str,
+) -> http::Response<axum::body::Body> {
+    let req = Request::post("/login/totp")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("cookie", format!("sid={sid}"))
+        .body(axum::body::Body::from(format!("code={code}")))
+        .unwrap();
+    app.request(req).await
+}
+
+async fn body_text(response: http::Response<axum::body::Body>) -> String {
+    use http_body_util::BodyExt;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8_lossy(&body).to_string()
+}
+
+fn location(response: &http::Response<axum::body::Body>) -> &str {
+    response.headers()["location"].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn test_enrolled_user_logs_in_with_code() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let uid = create_user(&app, &admin_token, "alice").await;
+    let token = app.mint_token(uid);
+
+    let (status, body) = send(&app, Request::get("/users/self/totp"), &token, "").await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["enabled"], false);
+
+    let (status, _) = send(
+        &app,
+        Request::post("/users/self/totp"),
+        &token,
+        r#"{"current_password": "WrongPassword1"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+
+    let (secret, recovery_codes) = enroll(&app, &token).await;
+    assert_eq!(recovery_codes.len(), 10);
+    let (_, body) = send(&app, Request::get("/users/self/totp"), &token, "").await;
+    assert_eq!(body["enabled"], true);
+    assert_eq!(body["required"], false);
+    assert_eq!(body["recovery_codes_left"], 10);
+
+    let (status, _) = send(
+        &app,
+        Request::post("/users/self/totp"),
+        &token,
+        &current_password(),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CONFLICT);
+
+    /* the password alone does not log in */
+    let (response, sid) = post_password(&app, "alice", None).await;
+    assert_eq!(response.status(), http::StatusCode::SEE_OTHER);
+    assert_eq!(location(&response), "/login/totp");
+    let sid = sid.unwrap();
+    assert!(
+        app.state
+            .sessions
+            .lock()
+            .unwrap()
+            .user_session_mut(&sid)
+            .is_none()
+    );
+
+    let req = Request::get("/login/totp")
+        .header("cookie", format!("sid={sid}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, html) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert!(html.contains("Enter the code of your authenticator"));
+
+    let response = post_code(&app, &sid, "not a code").await;
+    assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+
+    let response = post_code(&app, &sid, &next_code(&secret)).await;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let user_sid = common::extract_sid(&response).unwrap();
+    assert!(body_text(response).await.contains("Login successful"));
+    assert_eq!(
+        app.state
+            .sessions
+            .lock()
+            .unwrap()
+            .user_session_mut(&user_sid)
+            .unwrap()
+            .get_uid(),
+        uid
+    );
+
+    /* the two-factor session is used up */
+    let response = post_code(&app, &sid, &next_code(&secret)).await;
+    assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_recovery_code_continues_authorization_once() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let uid = create_user(&app, &admin_token, "alice").await;
+    let (_, recovery_codes) = enroll(&app, &app.mint_token(uid)).await;
+
+    let query = format!(
+        "response_type=code&client_id=flecs&redirect_uri={}&state=teststate&code_challenge={}&code_challenge_method=S256",
+        common::REDIRECT_URI,
+        common::CODE_CHALLENGE
+    );
+    for expected in [http::StatusCode::SEE_OTHER, http::StatusCode::FORBIDDEN] {
+        let req = Request::get(format!("/oauth/authorize?{query}"))
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.request(req).await;
+        let login_sid = common::extract_sid(&response).unwrap();
+
+        let (_, sid) = post_password(&app, "alice", Some(&login_sid)).await;
+        let response = post_code(&app, &sid.unwrap(), &recovery_codes[0]).await;
+        assert_eq!(response.status(), expected);
+        if expected == http::StatusCode::SEE_OTHER {
+            assert!(location(&response).starts_with("/oauth/authorize?response_type=code"));
+        }
+    }
+
+    let (_, body) = send(
+        &app,
+        Request::get("/users/self/totp"),
+        &app.mint_token(uid),
+        "",
+    )
+    .await;
+    assert_eq!(body["recovery_codes_left"], 9);
+}
+
+#[tokio::test]
+async fn test_required_group_enrolls_at_login() {
+    let app = common::TestApp::new_with_config(|config| {
+        config.auth.totp_required_groups = vec![GroupId::admin()];
+    })
+    .await;
+    let admin_token = setup_admin(&app).await;
+
+    let (response, sid) = post_password(&app, "admin", None).await;
+    assert_eq!(location(&response), "/login/totp");
+    let sid = sid.unwrap();
+
+    let req = Request::get("/login/totp")
+        .header("cookie", format!("sid={sid}"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let (status, html) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert!(html.contains("otpauth://totp/FLECS:admin?secret="));
+    let secret = html
+        .split(r#"<code id="secret">"#)
+        .nth(1)
+        .and_then(|rest| rest.split("</code>").next())
+        .unwrap();
+    let secret: TotpSecret = serde_json::from_value(secret.into()).unwrap();
+
+    let response = post_code(&app, &sid, &secret.code_at(chrono::Utc::now())).await;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert!(common::extract_sid(&response).is_some());
+    assert!(body_text(response).await.contains("recoveryCodes"));
+
+    let (_, body) = send(&app, Request::get("/users/self/totp"), &admin_token, "").await;
+    assert_eq!(body["enabled"], true);
+    assert_eq!(body["required"], true);
+    assert_eq!(body["recovery_codes_left"], 10);
+}
+
+#[tokio::test]
+async fn test_wrong_codes_lock_out() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let uid = create_user(&app, &admin_token, "alice").await;
+    let (secret, _) = enroll(&app, &app.mint_token(uid)).await;
+
+    let (_, sid) = post_password(&app, "alice", None).await;
+    let sid = sid.unwrap();
+    for _ in 0..5 {
+        let response = post_code(&app, &sid, "000000x").await;
+        assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+    }
+    let response = post_code(&app, &sid, &next_code(&secret)).await;
+    assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+
+    /* the correct password does not reset the failures */
+    let (response, _) = post_password(&app, "alice", None).await;
+    assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_disable_and_admin_reset() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let uid = create_user(&app, &admin_token, "alice").await;
+    let token = app.mint_token(uid);
+
+    enroll(&app, &token).await;
+    let (status, _) = send(
+        &app,
+        Request::delete("/users/self/totp"),
+        &token,
+        r#"{"current_password": "WrongPassword1"}"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &app,
+        Request::delete("/users/self/totp"),
+        &token,
+        &current_password(),
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let (response, _) = post_password(&app, "alice", None).await;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    enroll(&app, &token).await;
+    let (status, _) = send(
+        &app,
+        Request::delete(format!("/users/{uid}/totp")),
+        &token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &app,
+        Request::delete(format!("/users/{uid}/totp")),
+        &admin_token,
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Request::delete("/users/999/totp"), &admin_token, "").await;
+    assert_eq!(status, http::StatusCode::NOT_FOUND);
+
+    let (response, _) = post_password(&app, "alice", None).await;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let (_, body) = send(&app, Request::get("/users/self/totp"), &token, "").await;
+    assert_eq!(body["enabled"], false);
+}
+
+#[tokio::test]
+async fn test_totp_survives_restart() {
+    let app = common::TestApp::new().await;
+    let admin_token = setup_admin(&app).await;
+    let uid = create_user(&app, &admin_token, "alice").await;
+    enroll(&app, &app.mint_token(uid)).await;
+
+    let (users_path, _tempdir) = app.shutdown();
+    let content = std::fs::read_to_string(users_path).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(json["version"], "2");
+    let alice = json["users"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|user| user["name"] == "alice")
+        .unwrap();
+    assert!(alice["totp"]["confirmed_at"].is_string());
+    assert_eq!(
+        alice["totp"]["recovery_codes"].as_array().unwrap().len(),
+        10
+    );
+}
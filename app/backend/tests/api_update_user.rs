@@ -197,7 +197,9 @@ async fn test_patch_self_update_name() {
     let req = Request::patch("/users/self")
         .header("content-type", "application/json")
         .header("authorization", format!("Bearer {token}"))
-        .body(json_body(r#"{"name": "newadmin"}"#))
+        .body(json_body(&format!(
+            r#"{{"name": "newadmin", "current_password": "{VALID_PASSWORD}"}}"#
+        )))
         .unwrap();
     let (status, _) = app.request_body(req).await;
     assert_eq!(status, http::StatusCode::NO_CONTENT);
@@ -214,7 +216,9 @@ async fn test_patch_self_update_password() {
     let req = Request::patch("/users/self")
         .header("content-type", "application/json")
         .header("authorization", format!("Bearer {token}"))
-        .body(json_body(r#"{"password": "NewPassword456"}"#))
+        .body(json_body(&format!(
+            r#"{{"password": "NewPassword456", "current_password": "{VALID_PASSWORD}"}}"#
+        )))
         .unwrap();
     let (status, _) = app.request_body(req).await;
     assert_eq!(status, http::StatusCode::NO_CONTENT);
@@ -230,7 +234,9 @@ async fn test_patch_self_duplicate_name() {
     let req = Request::patch("/users/self")
         .header("content-type", "application/json")
         .header("authorization", format!("Bearer {token}"))
-        .body(json_body(r#"{"name": "other"}"#))
+        .body(json_body(&format!(
+            r#"{{"name": "other", "current_password": "{VALID_PASSWORD}"}}"#
+        )))
         .unwrap();
     let (status, _) = app.request_body(req).await;
     assert_eq!(status, http::StatusCode::CONFLICT);
@@ -252,3 +258,102 @@ async fn test_patch_self_as_regular_user() {
     let (status, _) = app.request_body(req).await;
     assert_eq!(status, http::StatusCode::NO_CONTENT);
 }
+
+#[tokio::test]
+async fn test_patch_self_requires_current_password() {
+    let app = common::TestApp::new().await;
+    let token = setup_with_admin(&app).await;
+
+    for json in [
+        r#"{"password": "NewPassword456"}"#,
+        r#"{"name": "newadmin"}"#,
+    ] {
+        let req = Request::patch("/users/self")
+            .header("content-type", "application/json")
+            .header("authorization", format!("Bearer {token}"))
+            .body(json_body(json))
+            .unwrap();
+        let (status, _) = app.request_body(req).await;
+        assert_eq!(status, http::StatusCode::BAD_REQUEST, "{json}");
+    }
+
+    let req = Request::patch("/users/self")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(
+            r#"{"password": "NewPassword456", "current_password": "WrongPassword1"}"#,
+        ))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+
+    // Nothing changed, the old password still works
+    let user = get_user(&app, &token, 0).await;
+    assert_eq!(user["name"], "admin");
+    app.login("admin", VALID_PASSWORD, None).await;
+}
+
+#[tokio::test]
+async fn test_patch_self_wrong_current_password_counts_as_failed_attempt() {
+    let app = common::TestApp::new().await;
+    let token = setup_with_admin(&app).await;
+
+    let patch = |current_password: &str| {
+        Request::patch("/users/self")
+            .header("content-type", "application/json")
+            .header("authorization", format!("Bearer {token}"))
+            .body(json_body(&format!(
+                r#"{{"password": "NewPassword456", "current_password": "{current_password}"}}"#
+            )))
+            .unwrap()
+    };
+    for _ in 0..5 {
+        let (status, _) = app.request_body(patch("WrongPassword1")).await;
+        assert_eq!(status, http::StatusCode::FORBIDDEN);
+    }
+    let (status, _) = app.request_body(patch(VALID_PASSWORD)).await;
+    assert_eq!(status, http::StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_patch_self_password_ends_other_sessions_and_refresh_tokens() {
+    let app = common::TestApp::new().await;
+    let token = setup_with_admin(&app).await;
+
+    let code = app.authorize("admin", VALID_PASSWORD, "").await;
+    let (status, body) = app.exchange_code(&code, "").await;
+    assert_eq!(status, http::StatusCode::OK);
+    let tokens: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let refresh_token = tokens["refresh_token"].as_str().unwrap().to_string();
+
+    let current_sid = app.login("admin", VALID_PASSWORD, None).await;
+    let other_sid = app.login("admin", VALID_PASSWORD, None).await;
+
+    let req = Request::patch("/users/self")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .header("cookie", format!("sid={current_sid}"))
+        .body(json_body(&format!(
+            r#"{{"password": "NewPassword456", "current_password": "{VALID_PASSWORD}"}}"#
+        )))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+
+    {
+        let mut sessions = app.state.sessions.lock().unwrap();
+        assert!(sessions.user_session_mut(&current_sid).is_some());
+        assert!(sessions.user_session_mut(&other_sid).is_none());
+    }
+
+    let req = Request::post("/oauth/token")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(format!(
+            "grant_type=refresh_token&refresh_token={refresh_token}&client_id=flecs"
+        )))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+
+    app.login("admin", "NewPassword456", None).await;
+}
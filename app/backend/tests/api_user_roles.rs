@@ -412,3 +412,215 @@ async fn test_delete_role_success() {
     let roles = get_roles(&app, &token, uid).await;
     assert_eq!(roles, vec!["tech.flecs.operator"]);
 }
+
+// ── Validation and privilege escalation ─────────────────────────────
+
+/// Helper: send a role assignment and return the status and body.
+async fn put_roles(
+    app: &common::TestApp,
+    token: &str,
+    path: &str,
+    json: &str,
+) -> (http::StatusCode, String) {
+    let req = Request::put(path)
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(json))
+        .unwrap();
+    app.request_body(req).await
+}
+
+/// Helper: start an app where members of the custom group `user-managers`
+/// may create users and assign roles, returning the admin token.
+async fn setup_with_user_managers() -> (common::TestApp, String) {
+    let app = common::TestApp::new_with_setup(|dir| {
+        let path = dir.join("casbin_policy.csv");
+        let mut policy = std::fs::read_to_string(&path).unwrap();
+        policy.push_str("g,user-managers,tech.flecs.fence.assign_roles\n");
+        policy.push_str("g,user-managers,tech.flecs.fence.create_user\n");
+        std::fs::write(path, policy).unwrap();
+    })
+    .await;
+    let token = setup_with_admin(&app).await;
+    let req = Request::post("/groups")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(
+            r#"{"id": "user-managers", "name": "User Managers"}"#,
+        ))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::CREATED);
+    (app, token)
+}
+
+#[tokio::test]
+async fn test_unknown_roles_are_rejected() {
+    let app = common::TestApp::new().await;
+    let token = setup_with_admin(&app).await;
+    let uid = create_user(&app, &token, "testuser").await;
+
+    let (status, body) = put_roles(
+        &app,
+        &token,
+        &format!("/users/{uid}/roles"),
+        r#"["tech.flecs.operator", "tech.flecs.oprator"]"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    assert!(body.contains("tech.flecs.oprator"), "body: {body}");
+
+    let (status, body) = put_roles(
+        &app,
+        &token,
+        &format!("/users/{uid}/roles/tech.flecs.oprator"),
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    assert!(body.contains("tech.flecs.oprator"), "body: {body}");
+
+    let req = Request::post("/users")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "other", "password": "{VALID_PASSWORD}", "groups": ["unknown"]}}"#
+        )))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+
+    assert!(get_roles(&app, &token, uid).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_roles_not_held_cannot_be_assigned() {
+    let (app, token) = setup_with_user_managers().await;
+    let manager = create_user(&app, &token, "manager").await;
+    let (status, _) = put_roles(
+        &app,
+        &token,
+        &format!("/users/{manager}/roles/user-managers"),
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let manager_token = app.mint_token(manager);
+    let uid = create_user(&app, &manager_token, "testuser").await;
+
+    for role in ["tech.flecs.admin", "tech.flecs.operator"] {
+        let (status, body) = put_roles(
+            &app,
+            &manager_token,
+            &format!("/users/{uid}/roles/{role}"),
+            "",
+        )
+        .await;
+        assert_eq!(status, http::StatusCode::FORBIDDEN, "{role}");
+        assert!(body.contains(role), "body: {body}");
+    }
+    let (status, _) = put_roles(
+        &app,
+        &manager_token,
+        &format!("/users/{manager}/roles"),
+        r#"["user-managers", "tech.flecs.admin"]"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+    let req = Request::post("/users")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {manager_token}"))
+        .body(json_body(&format!(
+            r#"{{"name": "other", "password": "{VALID_PASSWORD}", "groups": ["tech.flecs.admin"]}}"#
+        )))
+        .unwrap();
+    let (status, _) = app.request_body(req).await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+
+    // Roles the caller holds may be handed out
+    let (status, _) = put_roles(
+        &app,
+        &manager_token,
+        &format!("/users/{uid}/roles/user-managers"),
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    assert_eq!(get_roles(&app, &token, uid).await, vec!["user-managers"]);
+    assert_eq!(
+        get_roles(&app, &token, manager).await,
+        vec!["user-managers"]
+    );
+}
+
+#[tokio::test]
+async fn test_roles_not_held_may_be_kept_or_removed() {
+    let (app, token) = setup_with_user_managers().await;
+    let manager = create_user(&app, &token, "manager").await;
+    put_roles(
+        &app,
+        &token,
+        &format!("/users/{manager}/roles/user-managers"),
+        "",
+    )
+    .await;
+    let manager_token = app.mint_token(manager);
+    let uid = create_user(&app, &token, "testuser").await;
+    put_roles(
+        &app,
+        &token,
+        &format!("/users/{uid}/roles"),
+        r#"["tech.flecs.operator"]"#,
+    )
+    .await;
+
+    let (status, _) = put_roles(
+        &app,
+        &manager_token,
+        &format!("/users/{uid}/roles"),
+        r#"["tech.flecs.operator", "user-managers"]"#,
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    let mut roles = get_roles(&app, &token, uid).await;
+    roles.sort();
+    assert_eq!(roles, vec!["tech.flecs.operator", "user-managers"]);
+
+    let (status, _) = put_roles(&app, &manager_token, &format!("/users/{uid}/roles"), "[]").await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+    assert!(get_roles(&app, &token, uid).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_custom_roles_require_the_roles_they_grant() {
+    let (app, token) = setup_with_user_managers().await;
+    let manager = create_user(&app, &token, "manager").await;
+    put_roles(
+        &app,
+        &token,
+        &format!("/users/{manager}/roles/user-managers"),
+        "",
+    )
+    .await;
+    let manager_token = app.mint_token(manager);
+    let uid = create_user(&app, &token, "testuser").await;
+    let req = Request::post("/groups")
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {token}"))
+        .body(json_body(
+            r#"{"id": "site-admin", "name": "Site Admin", "sub_groups": ["tech.flecs.admin"]}"#,
+        ))
+        .unwrap();
+    app.request(req).await;
+
+    let (status, _) = put_roles(
+        &app,
+        &manager_token,
+        &format!("/users/{uid}/roles/site-admin"),
+        "",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::FORBIDDEN);
+    let (status, _) = put_roles(&app, &token, &format!("/users/{uid}/roles/site-admin"), "").await;
+    assert_eq!(status, http::StatusCode::NO_CONTENT);
+}
@@ -10,7 +10,7 @@ fn json_body(json: &str) -> axum::body::Body {
 fn load_users_from_disk(path: &Path) -> Vec<serde_json::Value> {
     let content = std::fs::read_to_string(path).unwrap();
     let db: serde_json::Value = serde_json::from_str(&content).unwrap();
-    assert_eq!(db["version"], "1");
+    assert_eq!(db["version"], "2");
     db["users"].as_array().unwrap().clone()
 }
 
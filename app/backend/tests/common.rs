@@ -26,6 +26,18 @@ impl TestApp {
     /// Create a TestApp with a setup closure that runs before the app starts.
     /// The closure receives the tempdir path so it can pre-populate files.
     pub async fn new_with_setup(setup: impl FnOnce(&std::path::Path)) -> Self {
+        Self::build(setup, |_| {}).await
+    }
+
+    /// Create a TestApp whose configuration is adjusted by the closure
+    pub async fn new_with_config(configure: impl FnOnce(&mut Config)) -> Self {
+        Self::build(|_| {}, configure).await
+    }
+
+    async fn build(
+        setup: impl FnOnce(&std::path::Path),
+        configure: impl FnOnce(&mut Config),
+    ) -> Self {
         let tempdir = TempDir::new().unwrap();
 
         let casbin_source = format!(
@@ -39,19 +51,48 @@ impl TestApp {
 
         setup(tempdir.path());
 
-        let config = Config {
+        let mut config = Config {
             database: user_manager::config::Database {
                 users_path: tempdir.path().join("users.json"),
                 groups_path: tempdir.path().join("groups.json"),
+                authorization_codes_path: tempdir.path().join("authorization_codes.json"),
                 clients_path: tempdir.path().join("clients.json"),
                 ro_clients_path: tempdir.path().join("ro_clients.json"),
+                consents_path: tempdir.path().join("consents.json"),
+                oauth_clients_path: tempdir.path().join("oauth_clients.json"),
+                initial_access_tokens_path: tempdir.path().join("initial_access_tokens.json"),
+                refresh_tokens_path: tempdir.path().join("refresh_tokens.json"),
+                revoked_tokens_path: tempdir.path().join("revoked_tokens.json"),
+                sessions_path: tempdir.path().join("sessions.json"),
+                failed_attempts_path: tempdir.path().join("failed_attempts.json"),
+                policy_path: tempdir.path().join("policy.json"),
             },
             auth: user_manager::config::Auth {
                 issuer_url: url::Url::parse("http://localhost").unwrap(),
+                signing_key_path: tempdir.path().join("signing_key.json"),
+                signing_key_rotation_days: 30,
+                refresh_token_lifetime_days: 30,
+                session_idle_timeout_minutes: 60,
+                session_max_age_hours: 12,
+                trust_forwarded_for: true,
+                lockout_threshold: 5,
+                ip_lockout_threshold: 20,
+                lockout_minutes: 15,
+                // Tests log in right after failed attempts, lockouts still apply
+                login_backoff_seconds: 0,
+                password_min_length: 12,
+                password_max_length: 63,
+                password_need_lower: true,
+                password_need_upper: true,
+                password_need_digit: true,
+                password_need_special: false,
+                totp_required_groups: Vec::new(),
+                flecs_ui_hosts: vec!["localhost".to_string()],
                 casbin_model_path: model_path,
                 casbin_policy_path: policy_path,
             },
         };
+        configure(&mut config);
 
         let enforcer = state::construct_enforcer(
             config.auth.casbin_model_path.clone(),
@@ -59,7 +100,7 @@ impl TestApp {
         )
         .await
         .unwrap();
-        let app_state = AppState::new(enforcer, &config);
+        let app_state = AppState::new(enforcer, &config).unwrap();
         let router = user_manager::router::build_router(app_state.clone());
 
         let users_path = config.database.users_path.clone();
@@ -104,6 +145,7 @@ impl TestApp {
     pub fn mint_token(&self, user_id: UserId) -> String {
         use oxide_auth::primitives::grant::Grant;
         let issuer = self.state.issuer.lock().unwrap();
+        let key = issuer.signing_key();
         let grant = Grant {
             owner_id: user_id.to_string(),
             client_id: "flecs".to_string(),
@@ -115,11 +157,124 @@ impl TestApp {
         let token = user_manager::token::issue(
             grant,
             issuer.url.clone(),
-            issuer.jwk.common.key_id.clone(),
-            &issuer.encoding_key,
+            Some(key.kid().to_string()),
+            key.encoding_key(),
             self.state.db.clone(),
         )
         .unwrap();
         token.token
     }
+
+    /// Log in through the login form and return the `sid` of the user session.
+    /// If `sid` is given, it is sent along as the cookie of a pending login session.
+    pub async fn login(&self, username: &str, password: &str, sid: Option<&str>) -> String {
+        let mut req =
+            Request::post("/login").header("content-type", "application/x-www-form-urlencoded");
+        if let Some(sid) = sid {
+            req = req.header("cookie", format!("sid={sid}"));
+        }
+        let req = req
+            .body(axum::body::Body::from(format!(
+                "username={username}&password={password}"
+            )))
+            .unwrap();
+        let response = self.request(req).await;
+        assert!(
+            response.status().is_success() || response.status().is_redirection(),
+            "login failed: {}",
+            response.status()
+        );
+        extract_sid(&response).expect("login should set a session cookie")
+    }
+
+    /// Run the authorization code flow for the built-in `flecs` client with
+    /// an S256 PKCE challenge for [`CODE_VERIFIER`] and return the
+    /// authorization code.
+    pub async fn authorize(&self, username: &str, password: &str, extra_query: &str) -> String {
+        let query = format!(
+            "response_type=code&client_id=flecs&redirect_uri={REDIRECT_URI}&state=teststate&code_challenge={CODE_CHALLENGE}&code_challenge_method=S256{extra_query}"
+        );
+        let response = self.authorize_response(username, password, &query).await;
+        assert!(
+            response.status().is_redirection(),
+            "authorize failed: {}",
+            response.status()
+        );
+        redirect_param(&response, "code").expect("redirect should carry an authorization code")
+    }
+
+    /// Log in while authorizing with the given query and return the response
+    /// of the authorization endpoint to the logged in user.
+    pub async fn authorize_response(
+        &self,
+        username: &str,
+        password: &str,
+        query: &str,
+    ) -> http::Response<axum::body::Body> {
+        let req = Request::get(format!("/oauth/authorize?{query}"))
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = self.request(req).await;
+        assert!(response.status().is_redirection());
+        let login_sid = extract_sid(&response).expect("authorize should start a login session");
+
+        let user_sid = self.login(username, password, Some(&login_sid)).await;
+
+        let req = Request::get(format!("/oauth/authorize?{query}"))
+            .header("cookie", format!("sid={user_sid}"))
+            .body(axum::body::Body::empty())
+            .unwrap();
+        self.request(req).await
+    }
+
+    /// Exchange an authorization code of the `flecs` client at the token
+    /// endpoint, presenting [`CODE_VERIFIER`]
+    pub async fn exchange_code(&self, code: &str, extra_form: &str) -> (http::StatusCode, String) {
+        self.exchange_code_with(code, &format!("&code_verifier={CODE_VERIFIER}{extra_form}"))
+            .await
+    }
+
+    /// Exchange an authorization code of the `flecs` client at the token
+    /// endpoint without adding a PKCE verifier
+    pub async fn exchange_code_with(
+        &self,
+        code: &str,
+        extra_form: &str,
+    ) -> (http::StatusCode, String) {
+        let code: String = url::form_urlencoded::byte_serialize(code.as_bytes()).collect();
+        let req = Request::post("/oauth/token")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(axum::body::Body::from(format!(
+                "grant_type=authorization_code&code={code}&redirect_uri={REDIRECT_URI}&client_id=flecs{extra_form}"
+            )))
+            .unwrap();
+        self.request_body(req).await
+    }
+}
+
+pub const REDIRECT_URI: &str = "https://localhost/oauth/callback";
+
+/// PKCE verifier and its S256 challenge, taken from RFC 7636, Appendix B
+pub const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+pub const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+/// Value of a query parameter of the `location` a response redirects to
+pub fn redirect_param(response: &http::Response<axum::body::Body>, name: &str) -> Option<String> {
+    let location = response.headers().get("location")?.to_str().ok()?;
+    let location = url::Url::parse(location).ok()?;
+    location
+        .query_pairs()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.to_string())
+}
+
+pub fn extract_sid(response: &http::Response<axum::body::Body>) -> Option<String> {
+    response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .filter_map(|v| cookie::Cookie::parse(v.to_string()).ok())
+        .find(|c| c.name() == "sid")
+        .map(|c| c.value().to_string())
 }